@@ -0,0 +1,114 @@
+class Lazy:
+    def __init__(self):
+        self.loaded = 'already there'
+    def __getattr__(self, name):
+        print('loading', name)
+        object.__setattr__(self, name, name)
+        return name
+
+lazy = Lazy()
+print(lazy.loaded)
+print(lazy.thing)
+print(lazy.thing)
+
+class Proxy:
+    def __init__(self, target):
+        object.__setattr__(self, 'target', target)
+    def __getattribute__(self, name):
+        print('get', name)
+        target = object.__getattribute__(self, 'target')
+        return getattr(target, name)
+    def __setattr__(self, name, value):
+        print('set', name)
+        setattr(object.__getattribute__(self, 'target'), name, value)
+    def __delattr__(self, name):
+        print('del', name)
+        delattr(object.__getattribute__(self, 'target'), name)
+
+class Target:
+    def __init__(self):
+        self.x = 'x of target'
+    def method(self):
+        return 'method of target'
+
+target = Target()
+proxy = Proxy(target)
+print(proxy.x)
+print(proxy.method())
+proxy.y = 'y set through proxy'
+print(target.y)
+del proxy.y
+print(hasattr(target, 'y'))
+
+class Plain:
+    pass
+
+p = Plain()
+p.a = 'a'
+print(p.a)
+del p.a
+try:
+    p.a
+except AttributeError:
+    print('AttributeError after del')
+try:
+    del p.a
+except AttributeError:
+    print('AttributeError on second del')
+try:
+    Plain.missing
+except AttributeError:
+    print('AttributeError on class')
+
+print(getattr(p, 'missing', 'default'))
+setattr(p, 'b', 'b')
+print(getattr(p, 'b'))
+print(hasattr(p, 'b'))
+print(hasattr(p, 'c'))
+
+try:
+    (1).attr = 2
+except AttributeError:
+    print('AttributeError on int')
+
+class WithProperty:
+    def get(self):
+        return 'value'
+    def delete(self):
+        print('deleter called')
+    value = property(get, None, delete)
+    readonly = property(get)
+
+w = WithProperty()
+del w.value
+try:
+    del w.readonly
+except AttributeError:
+    print("AttributeError: can't delete attribute")
+
+class Logged:
+    def __setattr__(self, name, value):
+        print('setting', name)
+        super().__setattr__(name, value)
+
+l = Logged()
+l.z = 'z'
+print(l.z)
+
+class Fallback:
+    def __getattribute__(self, name):
+        if name == 'missing':
+            raise AttributeError(name)
+        if name == 'broken':
+            raise KeyError(name)
+        return f'found {name}'
+    def __getattr__(self, name):
+        return f'fallback {name}'
+
+fallback = Fallback()
+print(fallback.present)
+print(fallback.missing)
+try:
+    fallback.broken
+except KeyError:
+    print('KeyError')
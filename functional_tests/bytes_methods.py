@@ -0,0 +1,190 @@
+print(repr(b'abc'))
+print(repr(b''))
+print(repr(b"it's"))
+print(repr(b'both \' and "'))
+print(repr(b'\x00\t\n\r\\\x7f\xff'))
+print(repr(bytes(3)))
+print(repr(bytes((104, 105))))
+print(repr(bytes('héllo', 'utf-8')))
+print(repr(bytes('abc', encoding='ascii')))
+try:
+    bytes('abc')
+except TypeError:
+    print('TypeError')
+try:
+    bytes((1, 256))
+except ValueError:
+    print('ValueError')
+try:
+    bytes(('a',))
+except TypeError:
+    print('TypeError')
+try:
+    bytes('é', 'ascii')
+except UnicodeEncodeError:
+    print('UnicodeEncodeError')
+
+b = b'hello'
+print(b[0])
+print(b[4])
+try:
+    b[5]
+except IndexError:
+    print('IndexError')
+try:
+    b['x']
+except TypeError:
+    print('TypeError')
+print(len(b))
+for byte in b'ab':
+    print(byte)
+print(b == b'hello')
+print(b == bytearray(b'hello'))
+print(b == 'hello')
+print(b'ell' in b)
+print(104 in b)
+print(b'z' in b)
+try:
+    'h' in b
+except TypeError:
+    print('TypeError')
+print(hash(b'abc') == hash(b'abc'))
+
+print(b'caf\xc3\xa9'.decode() == 'café')
+print(b'abc'.decode('ascii'))
+print(b'\xe9t\xe9'.decode('latin-1') == 'été')
+try:
+    b'\xff'.decode()
+except UnicodeDecodeError:
+    print('UnicodeDecodeError')
+try:
+    b'a\x80'.decode('ascii')
+except UnicodeDecodeError:
+    print('UnicodeDecodeError')
+print(b'a\xffb'.decode('utf-8', 'replace') == 'a�b')
+print(b'a\xffb'.decode('utf-8', 'ignore'))
+print('été'.encode() == b'\xc3\xa9t\xc3\xa9')
+print(repr('été'.encode('latin-1')))
+try:
+    'été'.encode('ascii')
+except UnicodeEncodeError:
+    print('UnicodeEncodeError')
+print(repr('été'.encode('ascii', 'replace')))
+print(repr('été'.encode('ascii', 'ignore')))
+
+print(b'\x00\x01\xfe\xff'.hex())
+print(repr(bytes.fromhex('00 01 fe FF')))
+print(repr(bytearray.fromhex('deadbeef')))
+try:
+    bytes.fromhex('0g')
+except ValueError:
+    print('ValueError')
+
+print(repr(b'-'.join((b'a', b'b', bytearray(b'c')))))
+try:
+    b'-'.join((b'a', 'b'))
+except TypeError:
+    print('TypeError')
+print(repr(b'a b  c '.split()))
+print(repr(b'a,b,,c'.split(b',')))
+print(repr(b'a,b,,c'.split(b',', 1)))
+print(repr(b'a,b,,c'.rsplit(b',', 1)))
+print(repr(b' a b c '.rsplit(None, 1)))
+try:
+    b'abc'.split(b'')
+except ValueError:
+    print('ValueError')
+print(repr(b'  x \n'.strip()))
+print(repr(b'xxhixyx'.strip(b'xy')))
+print(repr(b'xxhix'.lstrip(b'x')))
+print(repr(b'xxhix'.rstrip(b'x')))
+
+print(b'hello'.find(b'l'))
+print(b'hello'.rfind(b'l'))
+print(b'hello'.find(108))
+print(b'hello'.find(b'l', 3))
+print(b'hello'.find(b'z') == -1)
+print(b'abc'.find(b'c', 0, -1))
+print(b'abc'.rfind(b'a', -2))
+print(b'hello'.index(b'lo'))
+try:
+    b'hello'.index(b'z')
+except ValueError:
+    print('ValueError')
+print(b'hello'.count(b'l'))
+print(b'hello'.count(b''))
+print(b'hello'.startswith(b'he'))
+print(b'hello'.startswith((b'x', b'h')))
+print(b'abc'.startswith(b'c', -1))
+print(b'abc'.endswith(b'a', -5, -2))
+print(b'abc'.count(b'b', -9))
+print(b'hello'.endswith(b'll', 0, 4))
+try:
+    b'hello'.startswith('h')
+except TypeError:
+    print('TypeError')
+print(repr(b'a-b-c'.replace(b'-', b'+')))
+print(repr(b'a-b-c'.replace(b'-', b'', 1)))
+print(repr(b'ab'.replace(b'', b'.')))
+print(repr(b'Hello'.upper()))
+print(repr(b'Hello'.lower()))
+print(b'123'.isdigit(), b'abc'.isalpha(), b'a1'.isalnum(), b' \t'.isspace())
+print(b'ABC1'.isupper(), b'abc1'.islower(), b''.isdigit())
+print(repr(b'|'.join(b'key=value=x'.partition(b'='))))
+print(repr(b'|'.join(b'key=value=x'.rpartition(b'='))))
+print(repr(b'|'.join(b'key'.partition(b'='))))
+
+a = bytearray(b'abc')
+print(repr(a))
+print(repr(bytearray()))
+print(repr(bytearray(2)))
+print(repr(bytearray('hé', 'utf-8')))
+a.append(100)
+a.extend(b'ef')
+a.extend((103, 104))
+a.insert(0, 65)
+a.insert(-1, 66)
+print(repr(a))
+print(a.pop())
+print(a.pop(0))
+a.remove(66)
+print(repr(a))
+try:
+    a.remove(122)
+except ValueError:
+    print('ValueError')
+try:
+    a.append(256)
+except ValueError:
+    print('ValueError')
+try:
+    a.append('x')
+except TypeError:
+    print('TypeError')
+a[0] = 120
+print(repr(a))
+try:
+    a[0] = 300
+except ValueError:
+    print('ValueError')
+del a[0]
+print(repr(a))
+b2 = a.copy()
+a.reverse()
+print(repr(a))
+print(repr(b2))
+print(repr(a.upper()))
+print(repr(a.split(b'd')))
+print(repr(a.decode()))
+a.clear()
+print(repr(a))
+try:
+    a.pop()
+except IndexError:
+    print('IndexError')
+try:
+    hash(a)
+except TypeError:
+    print('TypeError')
+print(repr(bytes(bytearray(b'xy'))))
+print(isinstance(bytearray(), bytearray), isinstance(b'', bytes))
@@ -0,0 +1,72 @@
+class A:
+    pass
+
+class B(A):
+    pass
+
+class C:
+    pass
+
+print(isinstance(B(), (C, A)))
+print(isinstance(C(), (A, B)))
+print(isinstance(B(), (C, (int, (A,)))))
+print(isinstance(1, ()))
+print(issubclass(B, (C, A)))
+print(issubclass(C, (A, (B, int))))
+print(issubclass(B, (C, (str, (A,)))))
+
+try:
+    isinstance(1, 2)
+except TypeError:
+    print('isinstance TypeError')
+try:
+    issubclass(1, A)
+except TypeError:
+    print('issubclass arg 1 TypeError')
+try:
+    issubclass(A, 'A')
+except TypeError:
+    print('issubclass arg 2 TypeError')
+
+class Meta(type):
+    def __instancecheck__(cls, instance):
+        print('__instancecheck__')
+        return instance == 'special'
+    def __subclasscheck__(cls, subclass):
+        print('__subclasscheck__')
+        return subclass is int
+
+class Special(metaclass=Meta):
+    pass
+
+print(isinstance('special', Special))
+print(isinstance('other', Special))
+print(isinstance(Special(), Special))
+print(issubclass(int, Special))
+print(issubclass(str, (A, Special)))
+
+try:
+    raise KeyError('key')
+except (IndexError, KeyError):
+    print('caught KeyError')
+
+try:
+    raise IndexError('index')
+except (TypeError, LookupError):
+    print('caught subclass')
+
+try:
+    try:
+        raise KeyError('key')
+    except (TypeError, IndexError):
+        print('not reached')
+except KeyError:
+    print('not matched by tuple')
+
+try:
+    try:
+        raise KeyError('key')
+    except 'not a class':
+        print('not reached')
+except TypeError:
+    print('bad except clause')
@@ -0,0 +1,88 @@
+xs = ['a', '', 'b', '', 'c']
+print(repr([x for x in xs]))
+print(repr([x for x in xs if x]))
+print(repr([(x, y) for x in 'ab' for y in 'xy']))
+print(repr([[y for y in x] for x in ('ab', 'cd')]))
+print(repr([x for x in xs if x if x != 'b']))
+print(repr([x for x in ()]))
+
+print(repr({x for x in (1, 2, 1, 3, 2)}))
+print(repr({x for x in (1, 2, 3) if x != 2}))
+print(repr({x for x in ()}))
+
+print(repr({x: len(x) for x in xs}))
+print(repr({x: None for x in xs if x}))
+print(repr({k: v for k in 'ab' for v in (1, 2)}))
+print(repr({x: x for x in ()}))
+pairs = [('a', 1), ('b', 2)]
+print(repr({k: v for k, v in pairs}))
+print(repr([(v, k) for k, v in pairs]))
+
+# Generator expressions are evaluated lazily
+print(repr(list(x for x in xs)))
+print(repr(tuple(x for x in xs if x)))
+print(repr(set(x for x in (1, 3, 1))))
+print(repr(dict((x, len(x)) for x in xs)))
+print(repr(list((x, y) for x in 'ab' for y in 'xy')))
+print(repr(list(x for x in ())))
+def noisy(values):
+    for value in values:
+        print('yield', value)
+        yield value
+gen = (x for x in noisy('ab'))
+print(type(gen) is type(x for x in ()))
+print(next(gen))
+print(next(gen))
+try:
+    next(gen)
+except StopIteration:
+    print('StopIteration')
+print(repr(list(gen)))
+print(repr([y for y in (x for x in noisy('cd'))]))
+for x in noisy(''):
+    print('unreachable')
+
+# Unpacking checks the number of items
+a, b = 'xy'
+print(a)
+print(b)
+values = [(1, 2), 3]
+(a, b), c = values
+print(a)
+print(c)
+try:
+    values = (1, 2, 3)
+    a, b = values
+except ValueError:
+    print('ValueError')
+try:
+    values = [1]
+    a, b = values
+except ValueError:
+    print('ValueError')
+try:
+    values = 1
+    a, b = values
+except TypeError:
+    print('TypeError')
+
+# Comprehensions have their own scope but see enclosing variables
+x = 'outer'
+suffix = '!'
+print(repr([(x, suffix) for x in ('a', 'b')]))
+print(x)
+def nested(words):
+    return [[(word, letter) for letter in word] for word in words]
+print(repr(nested(('', 'a', 'bc'))))
+
+# The iterable is evaluated once, in the enclosing scope
+def items():
+    print('items')
+    return (1, 2)
+print(repr([[y for y in items()] for x in 'ab']))
+
+# Displays are built from their items
+print(repr([1, 'two', (3,)]))
+print(repr([]))
+print(repr({1, 3, 1}))
+print(type({1}) is set)
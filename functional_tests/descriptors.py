@@ -0,0 +1,69 @@
+class Foo:
+    @property
+    def bar(self):
+        print('getting bar')
+        return self._bar
+
+    @bar.setter
+    def bar(self, value):
+        print('setting bar')
+        self._bar = value
+
+    @bar.deleter
+    def bar(self):
+        print('deleting bar')
+
+    @property
+    def readonly(self):
+        return 'read-only value'
+
+    @staticmethod
+    def static(value):
+        print(value)
+
+    @classmethod
+    def klass(cls, value):
+        print(cls == Foo)
+        print(value)
+
+    def method(self, value):
+        print(self._bar)
+        print(value)
+
+f = Foo()
+f.bar = 'baz'
+print(f.bar)
+print(f.readonly)
+try:
+    f.readonly = 'qux'
+except AttributeError:
+    print('read-only property')
+
+print('---')
+print(isinstance(Foo.bar, property))
+print(isinstance(Foo.bar.fget, property))
+print(Foo.readonly.fset == None)
+
+print('---')
+Foo.static('static from class')
+f.static('static from instance')
+Foo.klass('classmethod from class')
+f.klass('classmethod from instance')
+f.method('method')
+m = f.method
+m('bound method')
+
+class Documented:
+    @property
+    def size(self):
+        'The size.'
+        return 1
+
+    def get_color(self):
+        return 'red'
+    color = property(get_color, doc='The color.')
+    shape = property(lambda self: 'square')
+
+print(Documented.size.__doc__)
+print(Documented.color.__doc__)
+print(Documented.shape.__doc__ is None)
@@ -0,0 +1,174 @@
+d = {'one': 1, 'two': 2}
+d['three'] = 3
+d[4] = 'four'
+d['one'] = 'ONE'
+print(repr(d))
+print(d['two'])
+print(len(d))
+print('three' in d)
+print('five' in d)
+print(d.get('two'))
+print(d.get('five') is None)
+print(d.get('five', 5))
+print(d.setdefault('two', 'other'))
+print(d.setdefault('six', 6))
+print(repr(d))
+print(d.pop('six'))
+print(d.pop('six', 'gone'))
+try:
+    d.pop('six')
+except KeyError:
+    print('KeyError')
+del d[4]
+print(repr(d))
+try:
+    d['missing']
+except KeyError:
+    print('KeyError')
+try:
+    del d['missing']
+except KeyError:
+    print('KeyError')
+
+print(repr(d.keys()))
+print(repr(d.values()))
+print(repr(d.items()))
+for key in d:
+    print(key)
+for value in d.values():
+    print(value)
+for item in d.items():
+    print(repr(item))
+print('one' in d.keys())
+print(3 in d.values())
+print(('two', 2) in d.items())
+print(('two', 3) in d.items())
+keys = d.keys()
+d['seven'] = 7
+print(len(keys))
+print(repr(keys))
+
+print(repr(d.popitem()))
+print(repr(d))
+e = d.copy()
+e['copied'] = True
+print(repr(d))
+print(repr(e))
+print(d == e)
+e.clear()
+print(repr(e))
+print(len(e))
+try:
+    e.popitem()
+except KeyError:
+    print('KeyError')
+
+f = {}
+f.update({'a': 1}, b=2)
+f.update((('c', 3), ('a', 'A')))
+print(repr(f))
+try:
+    f.update((('too', 'many', 'items'),))
+except ValueError:
+    print('ValueError')
+try:
+    f.update((1,))
+except TypeError:
+    print('TypeError')
+print(repr(dict(f, z=26)))
+print(repr(dict(x=1, y=2)))
+print(repr(dict((('p', 1), ('q', 2)))))
+print(repr(dict.fromkeys('abc')))
+print(repr(dict.fromkeys(('x', 'y'), 0)))
+
+# Keys are compared with __hash__ and __eq__
+class Key:
+    def __init__(self, name):
+        self.name = name
+    def __hash__(self):
+        return 1
+    def __eq__(self, other):
+        return self.name == other.name
+
+g = {}
+g[Key('a')] = 1
+g[Key('b')] = 2
+g[Key('a')] = 3
+print(len(g))
+print(g[Key('a')])
+print(g[Key('b')])
+print(Key('c') in g)
+print(1 in {True: 'yes'})
+print({1: 'a'} == {1: 'a'})
+print({1: 'a'} == {1: 'b'})
+try:
+    g[[]]
+except TypeError:
+    print('TypeError')
+try:
+    g[{}] = 1
+except TypeError:
+    print('TypeError')
+
+class Counter(dict):
+    def __missing__(self, key):
+        return 0
+
+c = Counter()
+c['x'] = 5
+print(c['x'])
+print(c['y'])
+print(len(c))
+
+class Ordered(dict):
+    pass
+o = Ordered.fromkeys('xy', 1)
+print(type(o) is Ordered)
+print(repr(o))
+
+# Changing the size of a dict while iterating over it is an error,
+# but replacing values is not
+growing = {'a': 1}
+try:
+    for key in growing:
+        growing['new'] = 1
+except RuntimeError:
+    print('RuntimeError')
+replaced = {'a': 1, 'b': 2}
+for key in replaced:
+    replaced[key] = 'replaced'
+print(repr(replaced))
+
+# Removed keys leave no trace
+h = {}
+for i in 'abcdefghijklmnop':
+    h[i] = i
+for i in 'abcdefghijklmno':
+    del h[i]
+print(repr(h))
+h['q'] = 'q'
+print(repr(h))
+
+# Exceptions raised by the interpreter can be handled within except clauses
+d = {}
+unhashable = []
+try:
+    d['x']
+except KeyError:
+    try:
+        d[unhashable]
+    except TypeError:
+        print('TypeError')
+    print('KeyError')
+try:
+    try:
+        d['x']
+    except TypeError:
+        print('not reached')
+except KeyError:
+    print('KeyError')
+for key in 'ab':
+    try:
+        d[key]
+    except KeyError:
+        print('KeyError', key)
@@ -3,3 +3,31 @@ for n in mytuple:
     print(n)
 
 print('end')
+
+def loop_then_raise():
+    for n in mytuple:
+        for c in 'ab':
+            pass
+    print('after loop')
+    next(iter(()))
+    print('not reached')
+
+try:
+    loop_then_raise()
+except StopIteration:
+    print('StopIteration propagated')
+
+class Countdown:
+    def __init__(self):
+        self.values = iter((3, 2, 1))
+    def __iter__(self):
+        return self
+    def __next__(self):
+        return next(self.values)
+
+for n in Countdown():
+    for c in Countdown():
+        print(c == n)
+for n in Countdown():
+    print(n)
+print('end')
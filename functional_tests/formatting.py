@@ -0,0 +1,139 @@
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+    def __repr__(self):
+        return 'Point(%d, %d)' % (self.x, self.y)
+
+class Money:
+    def __format__(self, spec):
+        return 'money[%s]' % spec
+
+class Named:
+    def __str__(self):
+        return 'named'
+
+name = 'world'
+n = 42
+p = Point(1, 2)
+x = 3.14159
+
+# f-strings
+print(f'hello {name}!')
+print(f'{n} {n:5} {n:<5}| {n:^6}| {n:05} {n:+}')
+print(f'{n:x} {n:X} {n:o} {n:b} {n:#x} {n:#o} {n:#b}')
+print(f'{1234567:,} {1234567:_} {255:_x} {65535:_b}')
+print(f'{n:e} {n:.2e} {12345:.2E} {n:f} {n:.1f} {n:%} {n:.0%}')
+print(f'{n:g} {1234567:g} {1234567:.3g} {n:#g} {n:.0e}')
+print(f'{name:>10}|{name:*^11}|{name:.3}|{name!r}|{p}|{p!s}')
+print(f'{Named()} {p!r:.4} {Money():abc}')
+width = 8
+print(f'[{name:>{width}}]')
+print(f'{True} {True:d} {False:>6}')
+print(f'{65:c}{66:c}')
+print(f'{x} {x:.2f} {x:8.3f}| {x:<8.1f}| {x:+.1e} {x:.3g} {x:.0f} {x:.2%}')
+print(f'{-3.14159:.2f} {-3.14159:09.2f} {1234567.891:,.2f} {0.00001234:g} {1e20:g} {1e20:G}')
+print(f'{2.5:.0f} {0.125:.2f} {1.0:.3} {1234.5:.2} {100.0:.3} {10.0:.2} {99.5:.3} {0.0001:.2} {3.0:#.0f} {2.0:#g}')
+print(f'{float("inf"):f} {float("-inf"):>6} {float("nan"):F} {-0.0:.1f}')
+print(f'{-42} {-42:5} {-42:05} {-255:x} {-255:#x} {-1234567:,}')
+print(f'{"é"!a}')
+
+# str.format
+print('{} and {}'.format('a', 'b'))
+print('{1} and {0} and {1}'.format('a', 'b'))
+print('{x}-{y}'.format(x=1, y='two'))
+print('{0.x},{0.y}'.format(p))
+print('{0[1]}'.format(('a', 'b')))
+print('{d[k]}'.format(d={'k': 'v'}))
+print('{{literal}} {}'.format(3))
+print('{:>{}}|'.format('ab', 5))
+print('{!r:>10}'.format('ab'))
+print('{:>6}|{:<6}|{:^7.2f}'.format(1.5, 1.5, 1.5))
+def check_value_error(template):
+    try:
+        template.format(1, 2)
+    except ValueError:
+        print('ValueError')
+
+check_value_error('{0} {}')
+check_value_error('{} {0}')
+try:
+    '{2}'.format(1)
+except IndexError:
+    print('IndexError')
+try:
+    '{missing}'.format(1)
+except KeyError:
+    print('KeyError')
+try:
+    'a } b'.format()
+except ValueError:
+    print('ValueError')
+
+# format() and __format__
+print(format(n))
+print(format(n, '08b'))
+print(format(1.5, '.2f'))
+print(format(1.5))
+print((2.5).__format__('>5'))
+print(format('abc', '^7'))
+print(format(p))
+print(format(Money(), 'xyz'))
+print(n.__format__('>4'))
+print('ab'.__format__('<4'), '|')
+try:
+    format(p, '>10')
+except TypeError:
+    print('TypeError')
+try:
+    format('abc', 'd')
+except ValueError:
+    print('ValueError')
+try:
+    format(3, '.2d')
+except ValueError:
+    print('ValueError')
+try:
+    format(3, '+c')
+except ValueError:
+    print('ValueError')
+try:
+    format(1.5, 'd')
+except ValueError:
+    print('ValueError')
+
+# printf-style
+print('%s is %d years old' % ('Bob', 30))
+print('%5s|%-5s|%.2s' % ('ab', 'ab', 'abc'))
+print('%05d|%-5d|%+d|% d|%.3d' % (42, 42, 42, 42, 7))
+print('%x %X %o %#x %#o %c %c' % (255, 255, 8, 255, 8, 65, 'z'))
+print('%r %s %a' % ('x', p, 'é'))
+print('%e %.2f %g' % (3, 3, 3))
+print('%.2f %e %g %G %f' % (x, 1.5, 1e-05, 1e20, float('inf')))
+print('%+08.3f|%-8.1f|%#.0f|%10.3e|%5.1f%%' % (-1.5, 2.25, 3.0, 12345.678, 99.45))
+print('%d %i %d %x' % (2.7, -2.7, -5, -255))
+print('%*d|%-*d|%.*s' % (5, 1, 4, 2, 2, 'abcdef'))
+print('100%% %s' % 'sure')
+print('%(a)s-%(b)s' % {'a': 1, 'b': 2})
+print('%s' % p)
+print('%s' % (p,))
+print('%s' % 'single')
+def check_type_error(template, values):
+    try:
+        template % values
+    except TypeError:
+        print('TypeError')
+
+check_type_error('%s %s', (1,))
+check_type_error('%s', (1, 2))
+check_type_error('%d', 'x')
+check_type_error('%f', 'x')
+check_type_error('%x', 1.5)
+try:
+    '%q' % 1
+except ValueError:
+    print('ValueError')
+try:
+    None % 3
+except TypeError:
+    print('TypeError')
@@ -0,0 +1,90 @@
+def f(a, b=2, *args, c, d=4, **kwargs):
+    """Docstring of f."""
+    x = 1
+    return a
+
+print(f.__name__)
+print(f.__qualname__)
+print(f.__module__)
+print(f.__doc__)
+print(len(f.__defaults__))
+print(f.__defaults__[0])
+print(len(f.__kwdefaults__))
+print(f.__kwdefaults__['d'])
+print(f.__closure__ is None)
+print(len(f.__annotations__))
+print('f' in f.__globals__)
+
+code = f.__code__
+print(code.co_name)
+print(code.co_argcount)
+print(code.co_kwonlyargcount)
+print(len(code.co_varnames))
+for name in code.co_varnames:
+    print(name)
+print(code.co_filename)
+print(code.co_firstlineno)
+print(code.co_flags)
+print(code.co_consts[0])
+print(len(code.co_names))
+
+def no_doc():
+    return 'value'
+
+print(no_doc.__doc__ is None)
+print(no_doc.__defaults__ is None)
+print(no_doc.__kwdefaults__ is None)
+
+def annotated(x: int, *, y: str = 'y') -> bool:
+    return True
+
+print(annotated.__annotations__['x'] is int)
+print(annotated.__annotations__['y'] is str)
+print(annotated.__annotations__['return'] is bool)
+
+def outer():
+    value = 'closed over'
+    def inner():
+        return value
+    return inner
+
+inner = outer()
+print(inner.__qualname__)
+print(len(inner.__closure__))
+print(inner.__closure__[0].cell_contents)
+print(inner.__code__.co_freevars[0])
+
+class C:
+    def method(self):
+        pass
+
+print(C.method.__qualname__)
+print(C.method.__name__)
+
+def defaults(a, b='b', c='c'):
+    print(a, b, c)
+
+defaults('a')
+defaults('a', 'B')
+defaults('a', c='C')
+defaults(b='B', a='A')
+try:
+    defaults()
+except TypeError:
+    print('missing argument')
+try:
+    defaults('a', 'b', 'c', 'd')
+except TypeError:
+    print('too many arguments')
+try:
+    defaults('a', a='a')
+except TypeError:
+    print('multiple values')
+try:
+    f(1)
+except TypeError:
+    print('missing keyword-only argument')
+
+lam = lambda: 'doc?'
+print(lam.__name__)
+print(lam.__doc__ is None)
@@ -0,0 +1,51 @@
+class Base:
+    kind = 'base'
+    def __init__(self, name):
+        self.name = name
+    def greet(self):
+        return 'hello'
+    def describe(self):
+        return self.kind
+
+class Middle(Base):
+    def greet(self):
+        return 'hi'
+
+class Leaf(Middle):
+    kind = 'leaf'
+    @classmethod
+    def make(cls):
+        return cls('made')
+    @property
+    def upper(self):
+        return self.name
+
+b = Base('b')
+m = Middle('m')
+l = Leaf('l')
+print(b.greet())
+print(m.greet())
+print(l.greet())
+print(m.describe())
+print(l.describe())
+
+Base.kind = 'changed'
+print(m.describe())
+print(b.describe())
+
+Base.extra = 'added later'
+print(l.extra)
+Base.shout = lambda self: self.name
+print(l.shout())
+
+x = Leaf.make()
+print(isinstance(x, Leaf))
+print(isinstance(x, Base))
+print(x.name)
+print(l.upper)
+
+m.kind = 'own'
+print(m.describe())
+print(Middle.kind)
+print(issubclass(Leaf, Base))
+print(issubclass(Base, Leaf))
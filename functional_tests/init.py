@@ -0,0 +1,112 @@
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+p = Point(1, 2)
+print(p.x)
+print(p.y)
+
+print('---')
+
+class Base:
+    def __init__(self, name):
+        print('Base.__init__')
+        self.name = name
+
+class Derived(Base):
+    pass
+
+print(Derived('derived').name)
+
+print('---')
+
+class WithNew:
+    def __new__(cls, value):
+        print('WithNew.__new__')
+        instance = object.__new__(cls)
+        instance.value = value
+        return instance
+
+    def __init__(self, value):
+        print('WithNew.__init__')
+
+print(WithNew(3).value)
+
+class Other:
+    pass
+
+class ReturnsOther:
+    def __new__(cls):
+        return Other()
+
+    def __init__(self):
+        print('not called')
+
+print(isinstance(ReturnsOther(), Other))
+
+print('---')
+
+class BadInit:
+    def __init__(self):
+        return 5
+
+try:
+    BadInit()
+except TypeError:
+    print('__init__ returned a value')
+
+class NoInit:
+    pass
+
+try:
+    NoInit(1)
+except TypeError:
+    print('object() takes no parameters')
+
+try:
+    object(1)
+except TypeError:
+    print('object() takes no parameters')
+
+print('---')
+
+print(int())
+print(int(5))
+print(int(True))
+print(str())
+print(str(5))
+print(str('abc'))
+print(bool())
+print(bool(0))
+print(bool(3))
+print(bool(''))
+print(bool('a'))
+print(tuple((1, 2))[0])
+print(list((1, 2))[1])
+print(isinstance(list(), list))
+print(isinstance(dict(), dict))
+print(NoInit.__name__)
+
+class MyList(list):
+    pass
+
+l = MyList((4, 5))
+print(isinstance(l, list))
+print(l[1])
+
+print('---')
+
+e = Exception('foo', 'bar')
+print(e.args[0])
+print(e.args[1])
+
+class MyError(Exception):
+    def __init__(self, code):
+        self.code = code
+
+try:
+    raise MyError(42)
+except MyError:
+    print('caught MyError')
+print(MyError(43).code)
@@ -0,0 +1,79 @@
+for c in 'hé!':
+    print(c == 'é')
+
+for b in b'AB':
+    print(b)
+
+for key in {'a': 1, 'b': 2}:
+    print(key)
+
+for x in {1, 2, 3}:
+    print(x)
+
+it = iter((1, 2))
+print(next(it))
+print(next(it))
+print(next(it, 'done'))
+try:
+    next(it)
+except StopIteration:
+    print('StopIteration')
+print(iter(it) is it)
+
+class Container:
+    def __iter__(self):
+        print('__iter__')
+        return iter(('x', 'y'))
+
+for x in Container():
+    print(x)
+
+class Sequence:
+    def __getitem__(self, index):
+        if index == 3:
+            raise IndexError
+        return index
+
+for x in Sequence():
+    print(x)
+print(2 in Sequence())
+it = iter(Sequence())
+print(next(it))
+print(next(it))
+
+class Counter:
+    def __init__(self):
+        self.values = iter(('one', 'two'))
+    def __iter__(self):
+        return self
+    def __next__(self):
+        return next(self.values)
+
+for x in Counter():
+    print(x)
+print(next(Counter()))
+
+try:
+    iter(5)
+except TypeError:
+    print('not iterable')
+try:
+    for x in 5:
+        print(x)
+except TypeError:
+    print('not iterable in for')
+try:
+    next((1, 2))
+except TypeError:
+    print('not an iterator')
+
+class BadIter:
+    def __iter__(self):
+        return 5
+
+try:
+    iter(BadIter())
+except TypeError:
+    print('non-iterator')
+
+print('é' in 'hé!')
@@ -0,0 +1,248 @@
+# Ranges are lazy sequences
+r = range(2, 11, 3)
+print(repr(r))
+print(repr(range(5)))
+print(repr(range(1, 5)))
+print(repr(list(r)))
+print(repr(list(range(5))))
+print(repr(list(range(5, 2))))
+print(len(r))
+print(len(range(3, 3)))
+print(len(range(0, 10, 4)))
+print(r[0])
+print(r[2])
+try:
+    r[3]
+except IndexError:
+    print('IndexError')
+try:
+    r['a']
+except TypeError:
+    print('TypeError')
+print(5 in r)
+print(6 in r)
+print(11 in r)
+print(True in range(1, 3))
+print('a' in r)
+print(range(0) == range(4, 2))
+print(range(0, 3) == range(0, 3, 1))
+print(range(1, 2, 5) == range(1, 3, 7))
+print(range(0, 3) == range(0, 4))
+
+print(type(r) is range)
+try:
+    range()
+except TypeError:
+    print('TypeError')
+try:
+    range('a')
+except TypeError:
+    print('TypeError')
+try:
+    range(1, 2, 0)
+except ValueError:
+    print('ValueError')
+try:
+    range(1, 2, 3, 4)
+except TypeError:
+    print('TypeError')
+try:
+    range(stop=4)
+except TypeError:
+    print('TypeError')
+for i in range(3):
+    print(i)
+print(repr([i for i in range(2) for j in range(2)]))
+if range(0):
+    print('empty range is true')
+if range(1):
+    print('range is true')
+
+# Negative steps count down
+down = range(10, 0, -3)
+print(repr(down))
+print(repr(list(down)))
+print(repr(list(range(0, 5, -1))))
+print(repr(list(range(-3, 3))))
+print(len(down))
+print(down[1])
+print(7 in down)
+print(6 in down)
+print(0 in down)
+print(10 in down)
+print(repr(list(reversed(range(1, 10, 4)))))
+print(repr(list(reversed(down))))
+print(range(3, 0, -1) == range(3, 0, -1))
+print(range(3, 0, -1) == range(3, 0, -2))
+for i in range(2, -1, -1):
+    print(i)
+
+# Iterators wrapping other iterables
+print(repr(list(enumerate('abc'))))
+print(repr(list(enumerate('ab', 10))))
+print(repr(list(enumerate(('x',), start=5))))
+try:
+    enumerate(3)
+except TypeError:
+    print('TypeError')
+print(repr(list(zip('abc', range(10), (True, False, None)))))
+print(repr(list(zip())))
+print(repr(list(zip('abc'))))
+try:
+    zip('a', 3)
+except TypeError:
+    print('TypeError')
+print(repr(list(map(len, ('', 'a', 'bb')))))
+print(repr(list(map(lambda x, y: (y, x), 'ab', 'xyz'))))
+try:
+    map(len)
+except TypeError:
+    print('TypeError')
+print(repr(list(filter(None, (0, 1, '', 'a', (), (0,), None)))))
+print(repr(list(filter(lambda x: x != 'b', 'abc'))))
+try:
+    filter(None)
+except TypeError:
+    print('TypeError')
+print(repr(list(reversed((1, 2, 3)))))
+print(repr(list(reversed('abc'))))
+print(repr(list(reversed(range(1, 8, 3)))))
+print(repr(list(reversed([]))))
+try:
+    reversed({1})
+except TypeError:
+    print('TypeError')
+try:
+    reversed({})
+except TypeError:
+    print('TypeError')
+try:
+    reversed(3)
+except TypeError:
+    print('TypeError')
+m = map(len, ('a', 'bb'))
+print(iter(m) is m)
+print(next(m))
+print(next(m))
+print(next(m, 'done'))
+try:
+    next(m)
+except StopIteration:
+    print('StopIteration')
+z = zip(range(3), 'ab')
+print(repr(next(z)))
+print(repr(list(z)))
+print(repr(dict(zip('ab', range(2)))))
+print(repr(dict(enumerate('xy'))))
+
+# Reversing a list sees it shrink
+l = [1, 2, 3, 4]
+rev = reversed(l)
+print(next(rev))
+l.pop()
+l.pop()
+print(repr(list(rev)))
+
+# Wrapped iterators are advanced lazily
+class Counter:
+    def __init__(self, limit):
+        self.limit = limit
+        self.values = list(range(limit))
+    def __iter__(self):
+        return self
+    def __next__(self):
+        if self.values:
+            value = self.values.pop()
+            print('next', value)
+            return value
+        raise StopIteration
+c = Counter(3)
+f = filter(None, c)
+print('created')
+print(next(f))
+print(repr(list(f)))
+
+# Sequences with __len__ and __getitem__, and __reversed__
+class Seq:
+    def __len__(self):
+        return 3
+    def __getitem__(self, index):
+        return ('a', 'b', 'c')[index]
+print(repr(list(reversed(Seq()))))
+class Rev:
+    def __reversed__(self):
+        return iter('reversed')
+print(repr(list(reversed(Rev()))))
+
+# Truth values use __bool__ and __len__
+class Truthy:
+    def __init__(self, value):
+        self.value = value
+    def __bool__(self):
+        return self.value
+class Sized:
+    def __init__(self, size):
+        self.size = size
+    def __len__(self):
+        return self.size
+print(any((0, '', Truthy(True))))
+print(any((0, '', Truthy(False))))
+print(any(()))
+print(all((1, 'a', Sized(1))))
+print(all((1, 'a', Sized(0))))
+print(all(()))
+print(len(list(filter(None, (Truthy(False), Sized(0), Sized(2))))))
+if Truthy(True):
+    print('true')
+if Sized(0):
+    print('empty is true')
+class BadBool:
+    def __bool__(self):
+        return 1
+try:
+    if BadBool():
+        pass
+except TypeError:
+    print('TypeError')
+
+# sorted and sum
+print(repr(sorted((3, 1, 2))))
+print(repr(sorted('bca', reverse=True)))
+print(repr(sorted(('bb', 'a', 'ccc'), key=len)))
+try:
+    sorted((1,), None)
+except TypeError:
+    print('TypeError')
+print(sum(range(5)))
+print(sum((1, 2), 10))
+print(sum(()))
+print(sum((True, True)))
+class Money:
+    def __init__(self, amount):
+        self.amount = amount
+    def __radd__(self, other):
+        return Money(sum((other, self.amount)))
+    def __add__(self, other):
+        return Money(sum((self.amount, other.amount)))
+print(sum((Money(1), Money(2))).amount)
+print(sum((Money(1), Money(2)), Money(3)).amount)
+try:
+    sum(('a',))
+except TypeError:
+    print('TypeError')
+try:
+    sum(('a',), '')
+except TypeError:
+    print('TypeError')
+try:
+    sum((b'a',), b'')
+except TypeError:
+    print('TypeError')
+try:
+    sum(3)
+except TypeError:
+    print('TypeError')
+try:
+    sum((), start=1)
+except TypeError:
+    print('TypeError')
@@ -0,0 +1,122 @@
+l = []
+l.append(1)
+l.append('two')
+l.extend((3, 4))
+l.extend('ab')
+print(repr(l))
+l.insert(0, 'first')
+l.insert(100, 'last')
+l.insert(-1, 'before last')
+print(repr(l))
+print(l.pop())
+print(l.pop(0))
+print(l.pop(-2))
+print(repr(l))
+l.remove('two')
+print(repr(l))
+try:
+    l.remove('two')
+except ValueError:
+    print('ValueError')
+print(l.index(3))
+print(l.index('a', 2))
+try:
+    l.index('z')
+except ValueError:
+    print('ValueError')
+try:
+    l.index(1, 1)
+except ValueError:
+    print('ValueError')
+print(l.count(3))
+l.append(3)
+print(l.count(3))
+c = l.copy()
+l.reverse()
+print(repr(l))
+print(repr(c))
+print(c == l)
+l.clear()
+print(repr(l))
+print(len(l))
+try:
+    l.pop()
+except IndexError:
+    print('IndexError')
+try:
+    c.pop(10)
+except IndexError:
+    print('IndexError')
+
+numbers = [5, 2, 8, 1, 9, 3]
+numbers.sort()
+print(repr(numbers))
+numbers.sort(reverse=True)
+print(repr(numbers))
+words = ['banana', 'Apple', 'cherry', 'date', 'fig', 'egg']
+words.sort()
+print(repr(words))
+words.sort(key=len)
+print(repr(words))
+words.sort(key=len, reverse=True)
+print(repr(words))
+words.sort(key=str.lower)
+print(repr(words))
+pairs = [(2, 'b'), (1, 'z'), (2, 'a'), (1, 'y')]
+pairs.sort()
+print(repr(pairs))
+
+class Item:
+    def __init__(self, rank, name):
+        self.rank = rank
+        self.name = name
+    def __lt__(self, other):
+        if self.rank == 1:
+            return other.rank == 2
+        return False
+
+def names(items):
+    print(' '.join([item.name for item in items]))
+
+def rank(item):
+    return item.rank
+
+items = [Item(2, 'a'), Item(1, 'b'), Item(2, 'c'), Item(1, 'd')]
+items.sort()
+names(items)
+items.sort(reverse=True)
+names(items)
+items.sort(key=lambda item: item.name, reverse=True)
+names(items)
+items.sort(key=rank)
+names(items)
+
+try:
+    [1, 'a'].sort()
+except TypeError:
+    print('TypeError')
+try:
+    [2, 1].sort(len)
+except TypeError:
+    print('TypeError')
+try:
+    [2, 1].sort(key=len)
+except TypeError:
+    print('TypeError')
+
+victim = [3, 2, 1]
+def mutating_key(x):
+    victim.append(x)
+    return x
+try:
+    victim.sort(key=mutating_key)
+except ValueError:
+    print('ValueError')
+
+seen = []
+growing = [1, 2, 3]
+for x in growing:
+    seen.append(x)
+    if len(growing) != 5:
+        growing.append(x)
+print(repr(seen))
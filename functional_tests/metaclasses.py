@@ -0,0 +1,141 @@
+class Meta(type):
+    def __new__(mcls, name, bases, namespace, **kwargs):
+        print('Meta.__new__', name)
+        cls = super().__new__(mcls, name, bases, namespace)
+        cls.created_by_meta = True
+        return cls
+    def __init__(cls, name, bases, namespace, **kwargs):
+        print('Meta.__init__', name)
+        super().__init__(name, bases, namespace)
+    def hello(cls):
+        return 'hello from the metaclass'
+
+class WithMeta(metaclass=Meta):
+    def method(self):
+        return 'method'
+
+print(WithMeta.created_by_meta)
+print(WithMeta.hello())
+print(isinstance(WithMeta, Meta))
+print(WithMeta().method())
+
+class InheritsMeta(WithMeta):
+    pass
+print(isinstance(InheritsMeta, Meta))
+
+class Prepared(type):
+    @classmethod
+    def __prepare__(mcls, name, bases, **kwargs):
+        print('__prepare__', name)
+        return {'injected': 'from prepare'}
+
+class UsesPrepared(metaclass=Prepared):
+    pass
+print(UsesPrepared.injected)
+
+def function_metaclass(name, bases, namespace):
+    print('function_metaclass', name)
+    return 'not a class'
+
+class NotAClass(metaclass=function_metaclass):
+    x = 1
+print(NotAClass)
+
+Dynamic = type('Dynamic', (WithMeta,), {'attr': 'dynamic'})
+print(Dynamic.attr)
+print(Dynamic().method())
+
+registry = {}
+
+class Plugin:
+    def __init_subclass__(cls, *, name, **kwargs):
+        super().__init_subclass__(**kwargs)
+        print('registering', name)
+        cls.plugin_name = name
+
+class First(Plugin, name='first'):
+    pass
+
+class Second(Plugin, name='second'):
+    pass
+
+options = {'name': 'third'}
+class Third(Plugin, **options):
+    pass
+
+print(First.plugin_name)
+print(Second.plugin_name)
+print(Third.plugin_name)
+
+try:
+    class Bad(name='bad'):
+        pass
+except TypeError:
+    print('TypeError: object.__init_subclass__ takes no keyword arguments')
+
+class Field:
+    def __set_name__(self, owner, name):
+        print('__set_name__', owner.__name__, name)
+        self.name = name
+
+class Model:
+    title = Field()
+
+print(Model.title.name)
+
+class Meta2(type):
+    pass
+try:
+    class Conflict(WithMeta, metaclass=Meta2):
+        pass
+except TypeError:
+    print('TypeError: metaclass conflict')
+
+class UsesClassCell:
+    def get_class(self):
+        return __class__
+print(UsesClassCell().get_class() == UsesClassCell)
+
+class Ordered(type):
+    def __new__(mcls, name, bases, namespace):
+        print(' '.join(namespace))
+        return super().__new__(mcls, name, bases, namespace)
+
+class DefinitionOrder(metaclass=Ordered):
+    zebra = 1
+    def apple(self):
+        pass
+    mango = 2
+
+class Recorder:
+    def __init__(self):
+        self.names = []
+        self.values = {}
+    def __getitem__(self, key):
+        return self.values[key]
+    def __setitem__(self, key, value):
+        self.names.append(key)
+        self.values[key] = value
+    def __delitem__(self, key):
+        del self.values[key]
+
+class Recording(type):
+    @classmethod
+    def __prepare__(mcls, name, bases):
+        return Recorder()
+    def __new__(mcls, name, bases, namespace):
+        print(type(namespace).__name__)
+        print(' '.join(namespace.names))
+        return super().__new__(mcls, name, bases, namespace.values)
+
+class Recorded(metaclass=Recording):
+    first = 1
+    second = first
+    temporary = 2
+    del temporary
+    try:
+        del temporary
+    except NameError:
+        print('NameError')
+print(Recorded.second)
+print(hasattr(Recorded, 'temporary'))
@@ -0,0 +1,19 @@
+"""Module docstring."""
+
+print(__name__)
+print(__file__)
+print(__doc__)
+print(__spec__ is None)
+
+if __name__ == '__main__':
+    print('main')
+
+class C:
+    pass
+
+print(C.__module__)
+
+def f():
+    return __name__
+
+print(f())
@@ -0,0 +1,96 @@
+x = 'global'
+
+def read_global():
+    return x
+
+print(read_global())
+
+def shadow_builtin():
+    return isinstance
+
+isinstance = 'shadowed'
+print(shadow_builtin())
+del isinstance
+print(shadow_builtin()(1, int))
+
+def set_global():
+    global y
+    y = 'set'
+
+set_global()
+print(y)
+
+def del_global():
+    global y
+    del y
+
+del_global()
+try:
+    print(y)
+except NameError:
+    print('NameError')
+
+def unbound():
+    print(z)
+    z = 1
+
+try:
+    unbound()
+except UnboundLocalError:
+    print('UnboundLocalError')
+
+def delete_local():
+    a = 1
+    del a
+    try:
+        print(a)
+    except UnboundLocalError:
+        print('deleted')
+
+delete_local()
+
+def delete_unbound():
+    del b
+    b = 1
+
+try:
+    delete_unbound()
+except UnboundLocalError:
+    print('cannot delete unbound')
+
+class C:
+    x = 'class'
+    print(x)
+    del x
+    print(x)
+
+def free_unbound():
+    def inner():
+        return v
+    try:
+        inner()
+    except NameError:
+        print('free variable')
+    v = 1
+
+free_unbound()
+
+def cell_unbound():
+    print(w)
+    w = 1
+    def inner():
+        return w
+
+try:
+    cell_unbound()
+except UnboundLocalError:
+    print('cell UnboundLocalError')
+
+try:
+    undefined_name
+except NameError:
+    print('undefined')
+try:
+    del undefined_name
+except NameError:
+    print('cannot delete undefined')
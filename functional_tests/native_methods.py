@@ -0,0 +1,41 @@
+print((5).bit_length())
+f = (255).bit_length
+print(f.__self__)
+print(f())
+s = set((1,))
+init = s.__init__
+print(init.__self__ is s)
+set.__init__(s, (4, 5, 6))
+print(len(s))
+init((7,))
+print(len(s))
+print(str.__new__(str, 5))
+print((1).__new__ is int.__new__)
+
+class MySet(set):
+    pass
+
+m = MySet()
+set.__init__(m, (1, 2))
+print(len(m))
+
+try:
+    set.__init__(5)
+except TypeError:
+    print('TypeError')
+try:
+    dict.__init__(s)
+except TypeError:
+    print('TypeError')
+
+class Base(Exception):
+    def __init__(self, x):
+        super().__init__(x)
+        print('init', x)
+
+Base(3)
+print(True.bit_length())
+try:
+    int.bit_length('a')
+except TypeError:
+    print('TypeError')
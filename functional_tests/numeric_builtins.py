@@ -0,0 +1,412 @@
+class Number:
+    def __init__(self, value):
+        self.value = value
+    def __repr__(self):
+        return 'Number({!r})'.format(self.value)
+    def __str__(self):
+        return 'number {}'.format(self.value)
+    def __int__(self):
+        return self.value
+    def __float__(self):
+        return 1.5
+    def __index__(self):
+        return self.value
+    def __bool__(self):
+        return self.value != 0
+    def __abs__(self):
+        return 'abs'
+    def __divmod__(self, other):
+        return ('divmod', other)
+    def __rdivmod__(self, other):
+        return ('rdivmod', other)
+    def __pow__(self, other, modulo=None):
+        return ('pow', other, modulo)
+    def __rpow__(self, other):
+        return ('rpow', other)
+    def __round__(self, ndigits=None):
+        return ('round', ndigits)
+    def __lt__(self, other):
+        return self.value < other.value
+
+class BadInt:
+    def __int__(self):
+        return 'a'
+    def __index__(self):
+        return 'a'
+    def __float__(self):
+        return 1
+
+# int()
+print(int())
+print(int(7))
+print(int(True))
+print(int(2.9))
+print(int('42'))
+print(int(' +17 '))
+print(int(b'12'))
+print(int('ff', 16))
+print(int('0x_1f', 0))
+print(int('0b101', 2))
+print(int('0o17', 0))
+print(int('1_000'))
+print(int('z', 36))
+print(int('0', 0))
+print(int(x='12'))
+print(int(Number(5)))
+print(int('-5'))
+print(int(-2.7))
+print(int('-0x10', 16))
+try:
+    int('x')
+except ValueError:
+    print('ValueError')
+try:
+    int('010', 0)
+except ValueError:
+    print('ValueError')
+try:
+    int('1__0')
+except ValueError:
+    print('ValueError')
+try:
+    int('_1')
+except ValueError:
+    print('ValueError')
+try:
+    int('')
+except ValueError:
+    print('ValueError')
+try:
+    int('12', 1)
+except ValueError:
+    print('ValueError')
+try:
+    int('12', 37)
+except ValueError:
+    print('ValueError')
+try:
+    int(12, 10)
+except TypeError:
+    print('TypeError')
+try:
+    int(base=10)
+except TypeError:
+    print('TypeError')
+try:
+    int([])
+except TypeError:
+    print('TypeError')
+try:
+    int(BadInt())
+except TypeError:
+    print('TypeError')
+try:
+    int(float('nan'))
+except ValueError:
+    print('ValueError')
+try:
+    int(float('inf'))
+except OverflowError:
+    print('OverflowError')
+
+# float()
+print(repr(float()))
+print(repr(float(3)))
+print(repr(float(' 1e3 ')))
+print(repr(float('-inf')))
+print(repr(float('nan')))
+print(repr(float('5.')))
+print(repr(float('infinity')))
+print(repr(float(Number(0))))
+print(repr(float(True)))
+print(repr(1.5))
+print(repr(0.1))
+print(repr(100.0))
+print(repr(1e16))
+print(repr(1.5e-05))
+print(repr(0.0001))
+print(repr(1e22))
+print(repr(123456789012345.6))
+print(repr(-0.0))
+print(type(2.5) is float)
+print(2.0 == 2)
+print(1.5 < 2)
+print(hash(2.0) == hash(2))
+try:
+    float('abc')
+except ValueError:
+    print('ValueError')
+try:
+    float([])
+except TypeError:
+    print('TypeError')
+try:
+    float(BadInt())
+except TypeError:
+    print('TypeError')
+print(repr(float(x=1)))
+
+# bool(), str() and repr()
+print(bool())
+print(bool(0))
+print(bool('a'))
+print(bool([]))
+print(bool(0.0))
+print(bool(Number(0)))
+print(bool(Number(3)))
+print(str())
+print(str(12))
+print(str(Number(3)))
+print(repr('a'))
+print(repr(Number(3)))
+
+# abs()
+print(abs(4))
+print(abs(-3))
+print(repr(abs(-2.5)))
+print(abs(Number(1)))
+try:
+    abs('a')
+except TypeError:
+    print('TypeError')
+
+# divmod()
+print(repr(divmod(7, 2)))
+print(repr(divmod(-7, 2)))
+print(repr(divmod(7, -2)))
+print(repr(divmod(-6, 3)))
+print(repr(divmod(7.5, 2)))
+print(repr(divmod(-7.5, 2)))
+print(repr(divmod(7, 2.0)))
+print(repr(divmod(Number(1), 3)))
+print(repr(divmod(3, Number(1))))
+try:
+    divmod(1, 0)
+except ZeroDivisionError:
+    print('ZeroDivisionError')
+try:
+    divmod(1.0, 0)
+except ZeroDivisionError:
+    print('ZeroDivisionError')
+try:
+    divmod('a', 1)
+except TypeError:
+    print('TypeError')
+
+# pow()
+print(pow(2, 10))
+print(pow(3, 4, 5))
+print(pow(7, 0, 1))
+print(pow(-3, 3))
+print(pow(-2, 3, 5))
+print(pow(3, 2, -5))
+print(repr(pow(2, -1)))
+print(repr(pow(2.0, 3)))
+print(repr(pow(4, 0.5)))
+print(repr(pow(Number(1), 2)))
+print(repr(pow(Number(1), 2, 3)))
+print(repr(pow(2, Number(1))))
+try:
+    pow(2, 3, 0)
+except ValueError:
+    print('ValueError')
+try:
+    pow(2.0, 3, 4)
+except TypeError:
+    print('TypeError')
+try:
+    pow('a', 2, 3)
+except TypeError:
+    print('TypeError')
+try:
+    pow('a', 2)
+except TypeError:
+    print('TypeError')
+try:
+    pow(0.0, -1.0)
+except ZeroDivisionError:
+    print('ZeroDivisionError')
+try:
+    pow(10.0, 400)
+except OverflowError:
+    print('OverflowError')
+try:
+    pow(2, 3, z=4)
+except TypeError:
+    print('TypeError')
+try:
+    pow(2, -1, 5)
+except ValueError:
+    print('ValueError')
+
+# round()
+print(round(2.5))
+print(round(3.5))
+print(round(2.4))
+print(round(5, 2))
+print(round(-2.5))
+print(round(1234, -2))
+print(round(1250, -2))
+print(round(-1350, -2))
+print(repr(round(1234.5, -2)))
+print(round(1.5, None))
+print(repr(round(2.675, 2)))
+print(repr(round(1.23456, 3)))
+print(repr(round(Number(1))))
+print(repr(round(Number(1), 2)))
+try:
+    round('a')
+except TypeError:
+    print('TypeError')
+try:
+    round(float('nan'))
+except ValueError:
+    print('ValueError')
+try:
+    round(float('inf'))
+except OverflowError:
+    print('OverflowError')
+
+# min() and max()
+print(min(3, 1, 2))
+print(max(3, 1, 2))
+print(min([4, 6, 5]))
+print(max('abc'))
+print(min([], default='empty'))
+print(max([1, 2, 3], key={1: 3, 2: 1, 3: 2}.get))
+print(min(['bb', 'a', 'ccc'], key=len))
+print(repr(min(Number(3), Number(2), Number(4))))
+print(repr(max(Number(3), Number(2), Number(4))))
+print(repr(max([Number(1), Number(1)]).value))
+print(repr(min(1, 2.5)))
+print(repr(max(1, 2.5)))
+try:
+    min()
+except TypeError:
+    print('TypeError')
+try:
+    max([])
+except ValueError:
+    print('ValueError')
+try:
+    min(1, 2, default=3)
+except TypeError:
+    print('TypeError')
+try:
+    min([1], foo=2)
+except TypeError:
+    print('TypeError')
+try:
+    max(1, 'a')
+except TypeError:
+    print('TypeError')
+
+# chr(), ord(), hex(), oct() and bin()
+print(chr(97))
+print(ord(chr(233)))
+print(ord('a'))
+print(ord('é'))
+print(ord(b'a'))
+print(hex(255))
+print(oct(8))
+print(bin(5))
+print(hex(-255))
+print(bin(-5))
+print(hex(Number(16)))
+try:
+    chr(1114112)
+except ValueError:
+    print('ValueError')
+try:
+    chr('a')
+except TypeError:
+    print('TypeError')
+try:
+    ord('ab')
+except TypeError:
+    print('TypeError')
+try:
+    ord(b'ab')
+except TypeError:
+    print('TypeError')
+try:
+    ord(1)
+except TypeError:
+    print('TypeError')
+try:
+    hex(1.5)
+except TypeError:
+    print('TypeError')
+try:
+    hex(BadInt())
+except TypeError:
+    print('TypeError')
+
+# hash() and format()
+print(hash(5))
+print(hash(-3))
+print(hash(-3.0) == hash(-3))
+print(format(5, '03'))
+print(format(-5, '+d'))
+print(format(-42, '06'))
+print(format(-255, '#x'))
+
+# Negative ints as indexes count from the end
+last = -1
+first = -5
+print(repr('hello'[last]))
+print('hello'[first])
+print((1, 2, 3)[last])
+print([1, 2, 3][last])
+print(b'xyz'[last])
+print(bytearray(b'xyz')[last])
+print(range(5)[last])
+print(range(0, 10, 3)[last])
+l = [1, 2, 3]
+l[last] = 9
+print(repr(l))
+del l[last]
+print(repr(l))
+b = bytearray(b'abc')
+b[last] = 100
+print(repr(b))
+del b[last]
+print(repr(b))
+try:
+    'hi'[first]
+except IndexError:
+    print('IndexError')
+try:
+    range(3)[first]
+except IndexError:
+    print('IndexError')
+try:
+    l[first] = 1
+except IndexError:
+    print('IndexError')
+
+# Floats, None and bytes are printed through str()
+print(sum([1.5, 2]))
+print(sum([1, 2.5], 0.5))
+half = 0.5
+print(half - 2, 2 - half, half.__add__(1), half.__radd__(1), half.__mul__(3), half.__rmul__(3))
+print(half.__add__("x"))
+print(None)
+print(b'x')
+print(hash(-1), hash(-2))
+
+# Ints subtract and combine bitwise natively
+a = 12
+b = 10
+print(a - b, b - a, a & b, a ^ b, a | b)
+print(a - half, half - a)
+print(True & True, True | False, True ^ True, True & 3)
+a -= 2
+a |= 1
+a ^= 8
+a &= 7
+print(a)
+try:
+    a - 'x'
+except TypeError:
+    print('TypeError')
@@ -0,0 +1,193 @@
+class Sequence:
+    def __init__(self, *items):
+        self.items = items
+    def __len__(self):
+        return len(self.items)
+    def __getitem__(self, index):
+        return self.items[index]
+    def __contains__(self, item):
+        print('contains', item)
+        return item in self.items
+
+s = Sequence(1, 2, 3)
+print(len(s))
+print(s[1])
+print(2 in s)
+print(5 in s)
+print(5 not in s)
+try:
+    s[5]
+except IndexError:
+    print('IndexError')
+
+class Mapping:
+    def __init__(self):
+        self.data = {}
+    def __setitem__(self, key, value):
+        print('set', key, value)
+        self.data[key] = value
+    def __getitem__(self, key):
+        return self.data[key]
+    def __delitem__(self, key):
+        print('del', key)
+        del self.data[key]
+
+m = Mapping()
+m['a'] = 1
+m['b'] = 2
+m['a'] = 3
+print(m['a'])
+print(len(m.data))
+del m['a']
+print('a' in m.data)
+print('b' in m.data)
+try:
+    m['a']
+except KeyError:
+    print('KeyError')
+
+class Countdown:
+    def __init__(self, start):
+        self.current = start
+        self.next_values = {3: 2, 2: 1, 1: 0}
+    def __iter__(self):
+        return self
+    def __next__(self):
+        if self.current == 0:
+            raise StopIteration
+        value = self.current
+        self.current = self.next_values[value]
+        return value
+
+for i in Countdown(3):
+    print(i)
+print(2 in Countdown(3))
+print(5 in Countdown(3))
+
+class Adder:
+    def __init__(self, name):
+        self.name = name
+    def __call__(self, *args, **kwargs):
+        print(self.name, 'called with', len(args), len(kwargs))
+        return 'result'
+
+a = Adder('adder')
+print(a(1, 2, x=3))
+try:
+    s()
+except TypeError:
+    print('not callable')
+
+class Meta(type):
+    def __call__(cls, *args):
+        print('creating', cls.__name__)
+        return super().__call__(*args)
+
+class WithMeta(metaclass=Meta):
+    def __init__(self, x):
+        print('init', x)
+
+w = WithMeta(5)
+print(isinstance(w, WithMeta))
+
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+    def __eq__(self, other):
+        if self.x == other.x:
+            return self.y == other.y
+        return False
+
+print(Point(1, 2) == Point(1, 2))
+print(Point(1, 2) == Point(1, 3))
+print(Point(1, 2) != Point(1, 3))
+print(Point(1, 2) in (Point(0, 0), Point(1, 2)))
+print((1, Point(1, 2)) == (1, Point(1, 2)))
+try:
+    hash(Point(1, 2))
+except TypeError:
+    print('unhashable')
+
+class Hashable:
+    def __hash__(self):
+        return 42
+
+print(hash(Hashable()))
+print(hash(5))
+print(hash((1, 'a')) == hash((1, 'a')))
+print(hash('abc') == hash('abc'))
+try:
+    hash((1, {}))
+except TypeError:
+    print('unhashable dict')
+
+print('bc' in 'abcd')
+print('e' in 'abcd')
+print(w is w)
+print(w is not w)
+try:
+    raise KeyError
+except KeyError:
+    print('raised a class')
+
+class Meters:
+    def __init__(self, value):
+        self.value = value
+    def __eq__(self, other):
+        if isinstance(other, Meters):
+            return self.value == other.value
+        return NotImplemented
+    def __sub__(self, other):
+        return NotImplemented
+    def __rsub__(self, other):
+        return 'Meters.__rsub__'
+
+class Feet:
+    def __eq__(self, other):
+        return NotImplemented
+    def __sub__(self, other):
+        return NotImplemented
+
+class AlwaysEqual:
+    def __eq__(self, other):
+        return True
+
+m = Meters(3)
+print(m == Meters(3))
+print(m == 3)
+print(m != 3)
+print(m == m)
+print(m == AlwaysEqual())
+print(Feet() == m)
+print(Feet() - m)
+try:
+    m - Feet()
+except TypeError:
+    print('TypeError')
+print(repr(NotImplemented))
+print(type(NotImplemented).__name__)
+print(set([1]).__or__(5) is NotImplemented)
+try:
+    set([1]) | 5
+except TypeError:
+    print('TypeError')
+
+# __len__ must not return a negative length
+class Negative:
+    def __len__(self):
+        return -1
+    def __getitem__(self, index):
+        return index
+try:
+    len(Negative())
+except ValueError:
+    print('ValueError')
+try:
+    reversed(Negative())
+except ValueError:
+    print('ValueError')
+try:
+    bool(Negative())
+except ValueError:
+    print('ValueError')
@@ -0,0 +1,147 @@
+s = set((1, 2, 2, 3, 1))
+print(repr(s))
+print(len(s))
+print(2 in s)
+print(4 in s)
+print(4 not in s)
+s.add(4)
+s.add(4)
+print(repr(s))
+s.discard(1)
+s.discard(10)
+print(repr(s))
+s.remove(2)
+try:
+    s.remove(2)
+except KeyError:
+    print('KeyError')
+print(repr(s))
+print(s.pop())
+print(repr(s))
+s.clear()
+print(repr(s))
+try:
+    s.pop()
+except KeyError:
+    print('KeyError')
+try:
+    s.add(list())
+except TypeError:
+    print('TypeError')
+print(len(set('mississippi')))
+print(set() == set(()))
+
+a = set((1, 2, 3))
+b = set((3, 4, 5))
+print(repr(a.union(b)))
+print(repr(a.union(b, (6, 7))))
+print(repr(a.intersection(b)))
+print(repr(a.intersection((2, 3), (3,))))
+print(repr(a.difference(b)))
+print(repr(a.difference((1,), (2,))))
+print(repr(a.symmetric_difference(b)))
+print(repr(a | b))
+print(repr(a & b))
+print(repr(a - b))
+print(repr(a ^ b))
+print(repr(a))
+print(repr(b))
+print(a.isdisjoint(b))
+print(a.isdisjoint((7, 8)))
+print(set((1, 2)).issubset(a))
+print(a.issubset((1, 2)))
+print(a.issuperset((1, 2)))
+print(set((1, 2)) <= a)
+print(a <= a)
+print(a < a)
+print(set((1,)) < a)
+print(a >= set((2, 3)))
+print(a > set((2, 3)))
+print(a > b)
+print(a == set((3, 2, 1)))
+print(a != b)
+
+try:
+    a | (4,)
+except TypeError:
+    print('TypeError')
+try:
+    a <= (4,)
+except TypeError:
+    print('TypeError')
+
+c = a.copy()
+c.update((10, 11), set((12,)))
+print(repr(c))
+c.intersection_update((1, 10, 11, 12, 20))
+print(repr(c))
+c.difference_update((11,))
+print(repr(c))
+c.symmetric_difference_update((12, 13))
+print(repr(c))
+c |= set((14,))
+print(repr(c))
+c &= set((1, 13, 14))
+print(repr(c))
+c -= set((13,))
+print(repr(c))
+c ^= set((1, 15))
+print(repr(c))
+d = c
+d |= set((6,))
+print(d is c)
+
+# frozensets are immutable and hashable
+f = frozenset((1, 2, 3))
+print(repr(f))
+print(repr(frozenset()))
+print(f == a)
+print(repr(f | b))
+print(repr(f & b))
+print(type(f - b) is frozenset)
+print(type(a - f) is set)
+g = f
+g |= set((4,))
+print(g is f)
+print(repr(f))
+print(repr(g))
+print(hash(f) == hash(frozenset((3, 2, 1))))
+e = dict()
+e[f] = 'frozen'
+print(e[frozenset((2, 1, 3))])
+try:
+    hash(a)
+except TypeError:
+    print('TypeError')
+print(frozenset((1, 2)) in set((frozenset((2, 1)),)))
+print('x' in frozenset('xyz'))
+
+# Items are compared with __hash__ and __eq__
+class Point:
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+    def __hash__(self):
+        return hash((self.x, self.y))
+    def __eq__(self, other):
+        return (self.x, self.y) == (other.x, other.y)
+
+points = set((Point(1, 2), Point(1, 2), Point(2, 1)))
+print(len(points))
+print(Point(2, 1) in points)
+print(Point(2, 2) in points)
+
+# Changing the size of a set while iterating over it is an error
+def grow(s):
+    for item in s:
+        s.add(3)
+try:
+    grow(set((1, 20)))
+except RuntimeError:
+    print('RuntimeError')
+
+# Sets of constants are built by the compiler
+def check(x):
+    return x in {'a', 'b', 'c'}
+print(check('b'))
+print(check('d'))
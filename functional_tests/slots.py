@@ -0,0 +1,95 @@
+class Point:
+    __slots__ = ('x', 'y')
+    def __init__(self, x, y):
+        self.x = x
+        self.y = y
+
+p = Point(1, 2)
+print(p.x)
+print(p.y)
+p.x = 3
+print(p.x)
+try:
+    p.z = 4
+except AttributeError:
+    print('AttributeError: no z')
+try:
+    p.__dict__
+except AttributeError:
+    print('AttributeError: no __dict__')
+
+del p.y
+try:
+    p.y
+except AttributeError:
+    print('AttributeError: y deleted')
+try:
+    del p.y
+except AttributeError:
+    print('AttributeError: y already deleted')
+p.y = 5
+print(p.y)
+
+print(isinstance(Point.x, object))
+
+class Point3D(Point):
+    __slots__ = 'z'
+    def __init__(self, x, y, z):
+        super().__init__(x, y)
+        self.z = z
+
+q = Point3D(1, 2, 3)
+print(q.x)
+print(q.y)
+print(q.z)
+try:
+    q.w = 4
+except AttributeError:
+    print('AttributeError: no w')
+
+class WithDict(Point):
+    pass
+
+r = WithDict(1, 2)
+r.extra = 'extra'
+print(r.extra)
+print(r.x)
+
+class SlotsAndDict:
+    __slots__ = ('a', '__dict__')
+
+s = SlotsAndDict()
+s.a = 'a'
+s.b = 'b'
+print(s.a)
+print(s.b)
+
+class Empty:
+    __slots__ = ()
+
+try:
+    Empty().attr = 1
+except AttributeError:
+    print('AttributeError: empty slots')
+
+class Other:
+    __slots__ = ('o',)
+
+try:
+    class Conflict(Point, Other):
+        pass
+except TypeError:
+    print('TypeError: lay-out conflict')
+
+try:
+    object().attr = 1
+except AttributeError:
+    print('AttributeError: object instance')
+
+class Unset:
+    __slots__ = ('value',)
+
+try:
+    Unset().value
+except AttributeError:
+    print('AttributeError: unset slot')
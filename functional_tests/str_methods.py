@@ -0,0 +1,116 @@
+print('-'.join(('a', 'b', 'c')))
+print(''.join('xyz'))
+try:
+    ','.join(('a', 1))
+except TypeError:
+    print('TypeError')
+
+print(repr('a b  c '.split()))
+print(repr('  a b  c '.split(None, 1)))
+print(repr('a,b,,c'.split(',')))
+print(repr('a,b,,c'.split(',', 2)))
+print(repr('a,b,,c'.split(sep=',', maxsplit=1)))
+print(repr('a b  c '.rsplit(None, 1)))
+print(repr('a,b,,c'.rsplit(',', 1)))
+print(repr(''.split()))
+print(repr(''.split(',')))
+try:
+    'abc'.split('')
+except ValueError:
+    print('ValueError')
+
+print(repr('  x y \n'.strip()))
+print(repr('  x y \n'.lstrip()))
+print(repr('  x y \n'.rstrip()))
+print('xxhixyx'.strip('xy'))
+print('xxhixyx'.lstrip('x'))
+print('xxhixyx'.rstrip('xy'))
+
+print('hello'.startswith('he'))
+print('hello'.startswith('lo'))
+print('hello'.endswith('lo'))
+print('hello'.startswith(('x', 'h')))
+print('hello'.startswith('ll', 2))
+print('hello'.endswith('ll', 0, 4))
+print('hello'.startswith('lo', -2))
+print('hello'.endswith('he', -9, -3))
+
+print('hello'.find('l'))
+print('hello'.rfind('l'))
+print('hello'.find('l', 3))
+print('hello'.find('z') == -1)
+print('hello'.index('lo'))
+print('hello'.rindex('l'))
+try:
+    'hello'.index('z')
+except ValueError:
+    print('ValueError')
+print('hello'.count('l'))
+print('hello'.count(''))
+print('aaaa'.count('aa'))
+print('abc'.count('a', -5))
+print('abc'.find('c', 0, -1))
+print('abc'.rfind('a', -2))
+print('abc'.find('', 4))
+print(repr('a,b,c'.split(',', -2)))
+
+print('a-b-c'.replace('-', '+'))
+print('a-b-c'.replace('-', '', 1))
+print('a-b-c'.replace('-', '', -1))
+print('abc'.replace('', '.'))
+
+print('Hello World'.upper())
+print('Hello World'.lower())
+print('Hello World'.casefold())
+
+print('|'.join('a=b=c'.partition('=')))
+print('|'.join('a=b=c'.rpartition('=')))
+print('|'.join('abc'.partition('=')))
+print('|'.join('abc'.rpartition('=')))
+
+print(repr('a\nb\r\nc\rd'.splitlines()))
+print(repr('a\nb\n'.splitlines(True)))
+print(repr(''.splitlines()))
+
+print('123'.isdigit(), '12a'.isdigit(), ''.isdigit())
+print('123'.isdecimal(), '123'.isnumeric())
+print('abc'.isalpha(), 'ab1'.isalpha(), 'ab1'.isalnum())
+print(' \t\n'.isspace(), ' a'.isspace())
+print('ABC1'.isupper(), 'AbC'.isupper(), '123'.isupper())
+print('abc1'.islower(), 'aBc'.islower())
+
+print('42'.zfill(5))
+print('-42'.zfill(5))
+print('12345'.zfill(3))
+print('ab'.zfill(-1))
+print(repr('ab'.center(5)))
+print(repr('ab'.center(6, '*')))
+print(repr('abc'.center(6, '*')))
+print(repr('ab'.ljust(5, '.')))
+print(repr('ab'.rjust(5)))
+print(repr('ab'.center(-1)))
+print(repr('ab'.ljust(-3, '*')))
+try:
+    'ab'.center(5, '**')
+except TypeError:
+    print('TypeError')
+
+s = 'héllo wörld'
+print(len(s))
+print(s[1] == 'é')
+print(s[4])
+print(s.find('w') == 6)
+print(s.upper() == 'HÉLLO WÖRLD')
+print('Straße'.upper() == 'STRASSE')
+print('Straße'.casefold() == 'strasse')
+print(s.center(13, '*').find('h'))
+try:
+    s[11]
+except IndexError:
+    print('IndexError')
+print('abc'[0], 'abc'[2])
+
+class MyStr(str):
+    pass
+
+print(MyStr('a b').split()[1])
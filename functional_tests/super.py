@@ -0,0 +1,102 @@
+class A:
+    def who(self):
+        return 'A'
+    def greet(self):
+        return 'A.greet'
+
+class B(A):
+    def who(self):
+        print('B')
+        return super().who()
+
+class C(A):
+    def who(self):
+        print('C')
+        return super().who()
+
+class D(B, C):
+    def who(self):
+        print('D')
+        return super().who()
+
+print(D().who())
+for cls in D.__mro__:
+    print(cls.__name__)
+for cls in D.mro():
+    print(cls.__name__)
+print(D.mro() == list(D.__mro__))
+
+class Base:
+    def __init__(self, x):
+        self.x = x
+
+class Child(Base):
+    def __init__(self, x, y):
+        super().__init__(x)
+        self.y = y
+
+c = Child(1, 2)
+print(c.x)
+print(c.y)
+
+class Explicit(Child):
+    def __init__(self):
+        super(Explicit, self).__init__(3, 4)
+        print(super(Child, self).__init__ == None)
+
+e = Explicit()
+print(e.x)
+print(e.y)
+
+class Factory:
+    @classmethod
+    def make(cls):
+        return 'Factory.make'
+    @property
+    def value(self):
+        return 'Factory.value'
+
+class SubFactory(Factory):
+    @classmethod
+    def make(cls):
+        print('SubFactory.make')
+        return super().make()
+    @property
+    def value(self):
+        return super().value
+
+print(SubFactory.make())
+print(SubFactory().value)
+print(super(SubFactory, SubFactory).make())
+
+class X: pass
+class Y: pass
+class XY(X, Y): pass
+class YX(Y, X): pass
+try:
+    class Bad(XY, YX): pass
+except TypeError:
+    print('TypeError: inconsistent MRO')
+try:
+    class Dup(X, X): pass
+except TypeError:
+    print('TypeError: duplicate base')
+
+try:
+    super(D, 1)
+except TypeError:
+    print('TypeError: not an instance')
+
+def no_class():
+    return super()
+try:
+    no_class()
+except RuntimeError:
+    print('RuntimeError: no arguments')
+
+def outer():
+    value = 'from closure'
+    def inner():
+        return value
+    return inner
+print(outer()())
@@ -0,0 +1,49 @@
+class A:
+    def get(self):
+        return self.v
+
+class Meta(type):
+    pass
+
+class WithMeta(metaclass=Meta):
+    pass
+
+print(type(1) is int)
+print(type('a') is str)
+print(type(A()) is A)
+print(type(A) is type)
+print(type(WithMeta) is Meta)
+print(type(type) is type)
+
+X = type('X', (A,), {'v': 5})
+print(X.__name__)
+print(X.v)
+print(X().get())
+print(issubclass(X, A))
+print(type(X()) is X)
+
+Y = Meta('Y', (), {})
+print(type(Y) is Meta)
+Z = type('Z', (WithMeta,), {})
+print(type(Z) is Meta)
+
+print(A().__class__ is A)
+print((1).__class__ is int)
+print(A.__class__ is type)
+print(WithMeta.__class__ is Meta)
+
+try:
+    type('X', (A,))
+except TypeError:
+    print('TypeError')
+
+class InitMeta(type):
+    def __init__(cls, name, bases, namespace):
+        print('InitMeta.__init__', name)
+        super().__init__(name, bases, namespace)
+
+class WithInitMeta(metaclass=InitMeta):
+    pass
+
+print(type(WithInitMeta) is InitMeta)
+print(type(WithInitMeta()) is WithInitMeta)
@@ -1,5 +1,42 @@
+object = __primitives__.object
+type = __primitives__.type
+int = __primitives__.int
+float = __primitives__.float
+bool = __primitives__.bool
+str = __primitives__.str
+bytes = __primitives__.bytes
+bytearray = __primitives__.bytearray
+tuple = __primitives__.tuple
+list = __primitives__.list
+set = __primitives__.set
+frozenset = __primitives__.frozenset
+dict = __primitives__.dict
+range = __primitives__.range
+reversed = __primitives__.reversed
+enumerate = __primitives__.enumerate
+zip = __primitives__.zip
+map = __primitives__.map
+filter = __primitives__.filter
+NotImplemented = __primitives__.NotImplemented
+
 BaseException = __primitives__.BaseException
 RuntimeError = __primitives__.RuntimeError
+Exception = __primitives__.Exception
+NameError = __primitives__.NameError
+UnboundLocalError = __primitives__.UnboundLocalError
+AttributeError = __primitives__.AttributeError
+TypeError = __primitives__.TypeError
+StopIteration = __primitives__.StopIteration
+LookupError = __primitives__.LookupError
+KeyError = __primitives__.KeyError
+IndexError = __primitives__.IndexError
+ValueError = __primitives__.ValueError
+ArithmeticError = __primitives__.ArithmeticError
+OverflowError = __primitives__.OverflowError
+ZeroDivisionError = __primitives__.ZeroDivisionError
+UnicodeError = __primitives__.UnicodeError
+UnicodeDecodeError = __primitives__.UnicodeDecodeError
+UnicodeEncodeError = __primitives__.UnicodeEncodeError
 
 def print(*values, sep=' ', end='\n'):
     first = True
@@ -8,11 +45,77 @@ def print(*values, sep=' ', end='\n'):
             first = False
         else:
             __primitives__.write_stdout(sep)
-        __primitives__.write_stdout(value)
+        __primitives__.write_stdout(str(value))
 
     __primitives__.write_stdout(end)
 
 __build__class__ = __primitives__.build_class
 issubclass = __primitives__.issubclass
 isinstance = __primitives__.isinstance
+len = __primitives__.len
+iter = __primitives__.iter
+
+def next(iterator, *default):
+    if default == ():
+        return __primitives__.next(iterator)
+    try:
+        return __primitives__.next(iterator)
+    except StopIteration:
+        return default[0]
+
+sum = __primitives__.sum
+
+def any(iterable):
+    for item in iterable:
+        if item:
+            return True
+    return False
+
+def all(iterable):
+    for item in iterable:
+        if not item:
+            return False
+    return True
+
+def sorted(iterable, *, key=None, reverse=False):
+    items = list(iterable)
+    items.sort(key=key, reverse=reverse)
+    return items
+
+hash = __primitives__.hash
+format = __primitives__.format
+repr = __primitives__.repr
+abs = __primitives__.abs
+min = __primitives__.min
+max = __primitives__.max
+divmod = __primitives__.divmod
+pow = __primitives__.pow
+round = __primitives__.round
+chr = __primitives__.chr
+ord = __primitives__.ord
+hex = __primitives__.hex
+oct = __primitives__.oct
+bin = __primitives__.bin
+
+def getattr(obj, name, *default):
+    if default == ():
+        return __primitives__.getattr(obj, name)
+    try:
+        return __primitives__.getattr(obj, name)
+    except AttributeError:
+        return default[0]
+
+def hasattr(obj, name):
+    try:
+        __primitives__.getattr(obj, name)
+    except AttributeError:
+        return False
+    return True
+
+setattr = __primitives__.setattr
+delattr = __primitives__.delattr
 
+property = __primitives__.property
+staticmethod = __primitives__.staticmethod
+classmethod = __primitives__.classmethod
+super = __primitives__.super
@@ -40,7 +40,7 @@ pub fn run_file<R: io::Read, EP: sandbox::EnvProxy>(reader: &mut R, envproxy: EP
     let mut store = objects::ObjectStore::new();
     let primitive_objects = objects::PrimitiveObjects::new(&mut store);
     let module = try!(marshal::read_object(reader, &mut store, &primitive_objects).map_err(InterpreterError::Unmarshal));
-    let mut state = State { envproxy: envproxy, store: store, primitive_functions: primitives::get_default_primitives(), primitive_objects: primitive_objects, modules: HashMap::new(), };
+    let mut state = State { envproxy: envproxy, store: store, primitive_functions: primitives::get_default_primitives(), primitive_objects: primitive_objects, modules: HashMap::new(), generator_frames: HashMap::new(), };
     let result = call_main_code(&mut state, module);
     Ok((state, result))
 }
@@ -2,6 +2,7 @@ use std::fmt;
 use std::io;
 
 use super::super::objects::{Code, ObjectContent, ObjectRef, ObjectStore, PrimitiveObjects};
+use super::super::primitives::sets::constant_set;
 
 #[derive(Debug)]
 pub enum UnmarshalError {
@@ -123,6 +124,23 @@ macro_rules! build_container {
     }}
 }
 
+/// Read the items of a set or frozenset and build it.
+/// Like build_container, if the flag is true, add it to the vector of objects first.
+fn read_set<R: io::Read>(reader: &mut R, store: &mut ObjectStore, primitive_objects: &PrimitiveObjects, references: &mut Vec<ObjectRef>, size: usize, flag: bool, frozen: bool) -> Result<ObjectRef, UnmarshalError> {
+    let obj_ref = ObjectRef::new();
+    if flag {
+        references.push(obj_ref.clone());
+    }
+    let objects = try!(read_objects(reader, store, primitive_objects, references, size));
+    let content = match constant_set(store, objects) {
+        Some(content) => content,
+        None => return Err(UnmarshalError::UnexpectedCode("unhashable item in a set".to_string())),
+    };
+    let obj = if frozen { primitive_objects.new_frozenset(content) } else { primitive_objects.new_set(content) };
+    store.allocate_at(obj_ref.clone(), obj);
+    Ok(obj_ref)
+}
+
 /// Read an tmporary marshal object, whose type is known from the first byte.
 /// If it is a container, read its content too.
 /// If the first bit is 1 and the marshal protocol allows the type to be referenced,
@@ -137,7 +155,16 @@ pub fn read_object<R: io::Read>(reader: &mut R, store: &mut ObjectStore, primiti
         'F' => Ok(primitive_objects.false_obj.clone()),
         'T' => Ok(primitive_objects.true_obj.clone()),
         'i' => {
-            let obj_ref = store.allocate(primitive_objects.new_int(try!(read_long(reader))));
+            let obj_ref = store.allocate(primitive_objects.new_int(try!(read_long(reader)) as i32 as i64));
+            if flag {
+                references.push(obj_ref.clone());
+            }
+            Ok(obj_ref)
+        },
+        'g' => { // “binary float”
+            let low = try!(read_long(reader)) as u64;
+            let high = try!(read_long(reader)) as u64;
+            let obj_ref = store.allocate(primitive_objects.new_float(f64::from_bits(low + (high << 32))));
             if flag {
                 references.push(obj_ref.clone());
             }
@@ -187,11 +214,11 @@ pub fn read_object<R: io::Read>(reader: &mut R, store: &mut ObjectStore, primiti
         }
         '<' => { // “set”
             let size = try!(read_long(reader)) as usize; // TODO: overflow check if usize is smaller than u32
-            build_container!(reader, store, references, primitive_objects, new_set, size, flag)
+            read_set(reader, store, primitive_objects, references, size, flag, false)
         }
         '>' => { // “frozenset”
             let size = try!(read_long(reader)) as usize; // TODO: overflow check if usize is smaller than u32
-            build_container!(reader, store, references, primitive_objects, new_frozenset, size, false)
+            read_set(reader, store, primitive_objects, references, size, flag, true)
         }
         'r' => {
             let index = try!(read_long(reader));
@@ -294,6 +321,17 @@ fn test_int() {
     assert_unmarshal!(ObjectContent::Int(5), store, b"\xe9\x05\x00\x00\x00");
 
     assert_unmarshal!(ObjectContent::Int(1000), store, b"\xe9\xe8\x03\x00\x00");
+
+    assert_unmarshal!(ObjectContent::Int(-3), store, b"\xe9\xfd\xff\xff\xff");
+}
+
+#[test]
+fn test_float() {
+    let mut store;
+
+    assert_unmarshal!(ObjectContent::Float(1.5f64.to_bits()), store, b"\xe7\x00\x00\x00\x00\x00\x00\xf8?");
+
+    assert_unmarshal!(ObjectContent::Float((-0.25f64).to_bits()), store, b"\xe7\x00\x00\x00\x00\x00\x00\xd0\xbf");
 }
 
 #[test]
@@ -336,6 +374,24 @@ fn test_references() {
 }
 
 
+#[test]
+fn test_frozenset_reference() {
+    let mut store = ObjectStore::new();
+    // f = frozenset(('ab',)); (f, f)
+    let ref obj = get_obj!(store, b"\xa9\x02\xbe\x01\x00\x00\x00\xda\x02abr\x01\x00\x00\x00");
+    match *obj {
+        ObjectContent::Tuple(ref v) => {
+            assert_eq!(v.len(), 2);
+            assert!(v[0].is(&v[1]));
+            match store.deref(&v[0]).content {
+                ObjectContent::FrozenSet(ref s) => assert_eq!(s.len(), 1),
+                _ => panic!("Not frozenset."),
+            }
+        },
+        _ => panic!("Not tuple."),
+    }
+}
+
 #[test]
 fn test_recursive_reference() {
     let mut store = ObjectStore::new();
@@ -0,0 +1,118 @@
+//! Content of dicts: an insertion-ordered hash table.
+//!
+//! Hashing and comparing keys may run Python code (`__hash__` and `__eq__`),
+//! so the table does neither: callers give the hash of keys, get the positions
+//! of the entries with the same hash, and compare the keys themselves.
+
+use std::collections::HashMap;
+use super::ObjectRef;
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+pub struct DictEntry {
+    pub hash: u32,
+    pub key: ObjectRef,
+    pub value: ObjectRef,
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+pub struct DictContent {
+    entries: Vec<Option<DictEntry>>, // In insertion order; removed entries leave a hole
+    buckets: HashMap<u32, Vec<usize>>, // Positions in `entries`, by hash
+    len: usize,
+}
+
+impl DictContent {
+    pub fn new() -> DictContent {
+        DictContent { entries: Vec::new(), buckets: HashMap::new(), len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Positions of the entries whose key has this hash, in insertion order.
+    pub fn positions(&self, hash: u32) -> Vec<usize> {
+        self.buckets.get(&hash).cloned().unwrap_or(vec![])
+    }
+
+    pub fn get(&self, position: usize) -> Option<&DictEntry> {
+        self.entries.get(position).and_then(Option::as_ref)
+    }
+
+    /// The first entry at or after this position, and its position.
+    /// Used by iterators, which remember positions.
+    pub fn next_entry(&self, position: usize) -> Option<(usize, &DictEntry)> {
+        self.entries.iter().enumerate().skip(position).filter_map(|(i, entry)| entry.as_ref().map(|entry| (i, entry))).next()
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item=&'a DictEntry> + 'a {
+        self.entries.iter().filter_map(Option::as_ref)
+    }
+
+    pub fn keys(&self) -> Vec<ObjectRef> {
+        self.iter().map(|entry| entry.key.clone()).collect()
+    }
+
+    pub fn items(&self) -> Vec<(ObjectRef, ObjectRef)> {
+        self.iter().map(|entry| (entry.key.clone(), entry.value.clone())).collect()
+    }
+
+    pub fn set_value(&mut self, position: usize, value: ObjectRef) {
+        if let Some(ref mut entry) = self.entries[position] {
+            entry.value = value
+        }
+    }
+
+    /// Adds a key which is not in the table yet.
+    pub fn push(&mut self, hash: u32, key: ObjectRef, value: ObjectRef) {
+        self.buckets.entry(hash).or_default().push(self.entries.len());
+        self.entries.push(Some(DictEntry { hash, key, value }));
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, position: usize) -> Option<DictEntry> {
+        let entry = self.entries.get_mut(position)?.take()?;
+        let now_empty = {
+            let bucket = self.buckets.get_mut(&entry.hash).unwrap();
+            bucket.retain(|&i| i != position);
+            bucket.is_empty()
+        };
+        if now_empty {
+            self.buckets.remove(&entry.hash);
+        }
+        self.len -= 1;
+        while let Some(&None) = self.entries.last() {
+            self.entries.pop();
+        }
+        if self.entries.len() > 8 && self.entries.len() > 2*self.len {
+            self.compact();
+        }
+        Some(entry)
+    }
+
+    /// Removes and returns the last entry.
+    pub fn pop_last(&mut self) -> Option<DictEntry> {
+        let position = self.entries.len().checked_sub(1)?;
+        self.remove(position)
+    }
+
+    pub fn clear(&mut self) {
+        *self = DictContent::new()
+    }
+
+    // Removes the holes left by removed entries; this moves entries.
+    fn compact(&mut self) {
+        let entries: Vec<DictEntry> = self.entries.drain(..).flatten().collect();
+        self.buckets.clear();
+        self.len = 0;
+        for entry in entries {
+            self.push(entry.hash, entry.key, entry.value);
+        }
+    }
+}
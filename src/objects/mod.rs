@@ -6,8 +6,9 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use std::fmt;
 use self::itertools::Itertools;
-use super::state::State;
-use super::sandbox::EnvProxy;
+
+mod dict;
+pub use self::dict::DictContent;
 
 #[derive(Debug)]
 #[derive(Clone)]
@@ -38,6 +39,9 @@ impl Code {
     pub fn co_varkwargs(&self) -> bool {
         self.flags & 0x8 != 0
     }
+    pub fn co_generator(&self) -> bool {
+        self.flags & 0x20 != 0
+    }
     pub fn get_varargs_name(&self) -> Option<&String> {
         if self.co_varargs() {
             Some(self.varnames.get(self.argcount+self.kwonlyargcount).unwrap())
@@ -49,7 +53,7 @@ impl Code {
     pub fn get_varkwargs_name(&self) -> Option<&String> {
         if self.co_varkwargs() {
             let mut index = self.argcount+self.kwonlyargcount;
-            if self.co_varkwargs() {
+            if self.co_varargs() {
                 index += 1;
             }
             Some(self.varnames.get(index).unwrap())
@@ -67,29 +71,61 @@ impl Code {
     }
 }
 
+/// Which part of the items of a dict an iterator returns.
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+pub enum DictView {
+    Keys,
+    Values,
+    Items,
+}
+
 #[derive(Debug)]
 #[derive(Clone)]
 #[derive(PartialEq)]
 #[derive(Eq)]
 pub enum ObjectContent {
     None,
+    NotImplemented,
     True,
     False,
-    Int(u32),
+    Int(i64),
+    Float(u64), // bits of an f64, so that contents can be compared with ==
     String(::std::string::String),
     Tuple(Vec<ObjectRef>),
     List(Vec<ObjectRef>),
     Code(Box<Code>),
-    Set(Vec<ObjectRef>),
-    Dict(Vec<(ObjectRef, ObjectRef)>),
-    FrozenSet(Vec<ObjectRef>),
+    Set(DictContent), // items are the keys of the table, and their own values
+    Dict(DictContent),
+    FrozenSet(DictContent), // same as Set
     Bytes(Vec<u8>),
-    Function(String, ObjectRef, HashMap<String, ObjectRef>), // module, code, default arguments
+    ByteArray(Vec<u8>),
+    Function(String, ObjectRef, HashMap<String, ObjectRef>, Vec<ObjectRef>), // module, code, default arguments, closure cells
     Module(ObjectRef),
     PrimitiveNamespace, // __primitives__
     PrimitiveFunction(String),
+    BoundMethod(ObjectRef, ObjectRef), // function, instance
+    Property(ObjectRef, ObjectRef, ObjectRef, ObjectRef), // fget, fset, fdel, doc
+    StaticMethod(ObjectRef),
+    ClassMethod(ObjectRef),
+    Super(ObjectRef, ObjectRef), // class, instance (or subclass)
+    Cell(Option<ObjectRef>),
+    MemberDescriptor(String, usize), // name and index of a slot
     Class,
-    RandomAccessIterator(ObjectRef, usize, u64), // container, index, container version
+    RandomAccessIterator(ObjectRef, usize, u64), // container, index (in bytes for str), container version
+    DictIterator(ObjectRef, usize, u64, DictView), // dict, position, dict version, returned part of the items
+    DictView(ObjectRef, DictView), // dict, viewed part of the items
+    SequenceIterator(ObjectRef, usize), // object with a __getitem__ method, index
+    Range(i64, i64, i64), // start, stop, step
+    ReversedIterator(ObjectRef, usize), // sequence, number of items left
+    Enumerate(ObjectRef, i64), // iterator, next count
+    Zip(Vec<ObjectRef>), // iterators
+    Map(ObjectRef, Vec<ObjectRef>), // function, iterators
+    Filter(ObjectRef, ObjectRef), // function (or None), iterator
+    Generator, // the state keeps its frame while it is suspended
     OtherObject,
 }
 
@@ -103,13 +139,19 @@ pub struct Object {
     pub content: ObjectContent,
     pub class: ObjectRef,
     pub bases: Option<Vec<ObjectRef>>, // superclasses
+    pub mro: Option<Vec<ObjectRef>>, // method resolution order of classes, starting with themselves
     pub attributes: Option<Rc<RefCell<HashMap<String, ObjectRef>>>>,
+    pub slots: Vec<Option<ObjectRef>>, // values of attributes declared in __slots__
 }
 
 impl Object {
     fn new_version() -> u64 {
         CURRENT_VERSION.fetch_add(1, Ordering::SeqCst) as u64 // TODO: avoid cast
     }
+    /// Marks the content of the object as changed, so iterators on it can notice.
+    pub fn bump_version(&mut self) {
+        self.version = Object::new_version();
+    }
     pub fn new_instance(name: Option<String>, class: ObjectRef, content: ObjectContent) -> Object {
         Object {
             version: Object::new_version(),
@@ -117,10 +159,27 @@ impl Object {
             content: content,
             class: class,
             bases: None,
+            mro: None,
             attributes: Some(Rc::new(RefCell::new(HashMap::new()))),
+            slots: Vec::new(),
+        }
+    }
+
+    /// Instance of a builtin type, which has no __dict__.
+    pub fn new_builtin_instance(class: ObjectRef, content: ObjectContent) -> Object {
+        Object {
+            version: Object::new_version(),
+            name: None,
+            content: content,
+            class: class,
+            bases: None,
+            mro: None,
+            attributes: None,
+            slots: Vec::new(),
         }
     }
 
+    /// A class; its MRO is set once the class is allocated.
     pub fn new_class(name: String, attributes: Option<Rc<RefCell<HashMap<String, ObjectRef>>>>, metaclass: ObjectRef, bases: Vec<ObjectRef>) -> Object {
         Object {
             version: Object::new_version(),
@@ -128,7 +187,9 @@ impl Object {
             content: ObjectContent::Class,
             class: metaclass,
             bases: Some(bases),
+            mro: None,
             attributes: attributes,
+            slots: Vec::new(),
         }
     }
 }
@@ -144,12 +205,100 @@ pub struct ObjectRef {
     id: usize,
 }
 
+/// Python representation of a string literal, with the same choice of quotes
+/// and the same escapes as CPython.
+pub fn string_repr(s: &str) -> String {
+    let quote = if s.contains('\'') && !s.contains('"') { '"' } else { '\'' };
+    let mut repr = String::with_capacity(s.len() + 2);
+    repr.push(quote);
+    for c in s.chars() {
+        match c {
+            '\\' => repr.push_str("\\\\"),
+            '\n' => repr.push_str("\\n"),
+            '\r' => repr.push_str("\\r"),
+            '\t' => repr.push_str("\\t"),
+            _ if c == quote => { repr.push('\\'); repr.push(c) },
+            // TODO: use Unicode's definition of printable characters
+            _ if c.is_control() || (c.is_whitespace() && c != ' ') => {
+                let code = c as u32;
+                if code < 0x100 { repr.push_str(&format!("\\x{:02x}", code)) }
+                else if code < 0x10000 { repr.push_str(&format!("\\u{:04x}", code)) }
+                else { repr.push_str(&format!("\\U{:08x}", code)) }
+            },
+            _ => repr.push(c),
+        }
+    }
+    repr.push(quote);
+    repr
+}
+
+/// Shortest representation of a float which reads back as the same value,
+/// in the same notation as CPython.
+pub fn float_repr(f: f64) -> String {
+    if f.is_nan() {
+        return "nan".to_string()
+    }
+    if f.is_infinite() {
+        return if f > 0. { "inf".to_string() } else { "-inf".to_string() }
+    }
+    // Rust already finds the shortest digits; only the notation differs.
+    let scientific = format!("{:e}", f.abs());
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+    let digits = mantissa.replace(".", "");
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let sign = if f.is_sign_negative() { "-" } else { "" };
+    if !(-4..16).contains(&exponent) {
+        let fraction = if digits.len() > 1 { format!(".{}", &digits[1..]) } else { "".to_string() };
+        let exponent_sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}{}{}e{}{:02}", sign, &digits[..1], fraction, exponent_sign, exponent.abs())
+    }
+    else if exponent < 0 {
+        let zeros: String = ::std::iter::repeat('0').take((-exponent - 1) as usize).collect();
+        format!("{}0.{}{}", sign, zeros, digits)
+    }
+    else {
+        let nb_integer_digits = exponent as usize + 1;
+        if digits.len() > nb_integer_digits {
+            format!("{}{}.{}", sign, &digits[..nb_integer_digits], &digits[nb_integer_digits..])
+        }
+        else {
+            let zeros: String = ::std::iter::repeat('0').take(nb_integer_digits - digits.len()).collect();
+            format!("{}{}{}.0", sign, digits, zeros)
+        }
+    }
+}
+
+pub fn bytes_repr(b: &[u8]) -> String {
+    let quote = if b.contains(&b'\'') && !b.contains(&b'"') { b'"' } else { b'\'' };
+    let mut repr = String::with_capacity(b.len() + 3);
+    repr.push('b');
+    repr.push(quote as char);
+    for &c in b {
+        match c {
+            b'\\' => repr.push_str("\\\\"),
+            b'\n' => repr.push_str("\\n"),
+            b'\r' => repr.push_str("\\r"),
+            b'\t' => repr.push_str("\\t"),
+            _ if c == quote => { repr.push('\\'); repr.push(c as char) },
+            0x20..=0x7e => repr.push(c as char),
+            _ => repr.push_str(&format!("\\x{:02x}", c)),
+        }
+    }
+    repr.push(quote as char);
+    repr
+}
+
 impl ObjectRef {
     // TODO: make it private
     pub fn new() -> ObjectRef {
         ObjectRef { id: CURRENT_REF_ID.fetch_add(1, Ordering::SeqCst) }
     }
 
+    /// Like Python's id(): unique for the lifetime of the object
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
     /// Like Python's is operator: reference equality
     pub fn is(&self, other: &ObjectRef) -> bool {
         return self.id == other.id
@@ -163,18 +312,24 @@ impl ObjectRef {
         let obj = store.deref(self);
         match obj.content {
             ObjectContent::None => "None".to_string(),
+            ObjectContent::NotImplemented => "NotImplemented".to_string(),
             ObjectContent::True => "True".to_string(),
             ObjectContent::False => "False".to_string(),
             ObjectContent::Int(ref i) => i.to_string(),
-            ObjectContent::Bytes(ref s) => "<bytes>".to_string(), // TODO
-            ObjectContent::String(ref s) => format!("'{}'", s), // TODO: escape
-            ObjectContent::Dict(ref l) => format!("{{{}}}", l.iter().map(|arg| { let (ref k, ref v) = *arg; format!("{}: {}", k.repr(store), v.repr(store))}).join(", ")),
-            ObjectContent::Tuple(ref l) => format!("tuple({})", ObjectRef::repr_vec(l, store)),
+            ObjectContent::Float(bits) => float_repr(f64::from_bits(bits)),
+            ObjectContent::Bytes(ref b) => bytes_repr(b),
+            ObjectContent::ByteArray(ref b) => format!("bytearray({})", bytes_repr(b)),
+            ObjectContent::String(ref s) => string_repr(s),
+            ObjectContent::Dict(ref d) => format!("{{{}}}", d.iter().map(|entry| format!("{}: {}", entry.key.repr(store), entry.value.repr(store))).join(", ")),
+            ObjectContent::Tuple(ref l) if l.len() == 1 => format!("({},)", l[0].repr(store)),
+            ObjectContent::Tuple(ref l) => format!("({})", ObjectRef::repr_vec(l, store)),
             ObjectContent::List(ref l) => format!("[{}]", ObjectRef::repr_vec(l, store)),
             ObjectContent::Code(_) => "<code object>".to_string(),
-            ObjectContent::Set(ref l) => format!("set({})", ObjectRef::repr_vec(l, store)),
-            ObjectContent::FrozenSet(ref l) => format!("frozenset({})", ObjectRef::repr_vec(l, store)),
-            ObjectContent::Function(ref module, ref _code, ref _defaults) => {
+            ObjectContent::Set(ref s) if s.len() == 0 => "set()".to_string(),
+            ObjectContent::Set(ref s) => format!("{{{}}}", ObjectRef::repr_vec(&s.keys(), store)),
+            ObjectContent::FrozenSet(ref s) if s.len() == 0 => "frozenset()".to_string(),
+            ObjectContent::FrozenSet(ref s) => format!("frozenset({{{}}})", ObjectRef::repr_vec(&s.keys(), store)),
+            ObjectContent::Function(ref module, ref _code, ref _defaults, ref _closure) => {
                 match obj.name {
                     None => format!("<anonymous function in module {}>", module),
                     Some(ref s) => format!("<function {} in module {}>", s, module),
@@ -182,6 +337,14 @@ impl ObjectRef {
             },
             ObjectContent::PrimitiveNamespace => "__primitives__".to_string(),
             ObjectContent::PrimitiveFunction(ref s) => format!("__primitives__.{}", s),
+            ObjectContent::BoundMethod(ref func, ref instance) => format!("<bound method {} of {}>", func.repr(store), instance.repr(store)),
+            ObjectContent::Property(..) => "<property object>".to_string(),
+            ObjectContent::StaticMethod(ref func) => format!("<staticmethod of {}>", func.repr(store)),
+            ObjectContent::ClassMethod(ref func) => format!("<classmethod of {}>", func.repr(store)),
+            ObjectContent::Super(ref class, ref instance) => format!("<super: {}, {}>", class.repr(store), instance.repr(store)),
+            ObjectContent::Cell(None) => "<cell: empty>".to_string(),
+            ObjectContent::Cell(Some(ref value)) => format!("<cell: {}>", value.repr(store)),
+            ObjectContent::MemberDescriptor(ref name, _) => format!("<member '{}'>", name),
             ObjectContent::Class => {
                 match obj.name {
                     None => "<anonymous class>".to_string(),
@@ -191,12 +354,39 @@ impl ObjectRef {
             ObjectContent::Module(ref _code) => {
                 match obj.name {
                     None => "<anonymous module>".to_string(),
-                    Some(ref s) => format!("<module {}", s),
+                    Some(ref s) => format!("<module '{}'>", s),
                 }
             },
             ObjectContent::RandomAccessIterator(ref container, ref index, ref version) => {
                 format!("<iterator on {} at index {} and version {}>", store.deref(container).class.repr(store), index, version)
             }
+            ObjectContent::DictIterator(_, ref index, ref version, ref view) => {
+                format!("<dict {:?} iterator at index {} and version {}>", view, index, version)
+            }
+            ObjectContent::DictView(ref dict, ref view) => {
+                let items = match store.deref(dict).content {
+                    ObjectContent::Dict(ref d) => d.iter().map(|entry| match *view {
+                        DictView::Keys => entry.key.repr(store),
+                        DictView::Values => entry.value.repr(store),
+                        DictView::Items => format!("({}, {})", entry.key.repr(store), entry.value.repr(store)),
+                    }).join(", "),
+                    _ => "?".to_string(),
+                };
+                format!("{}([{}])", store.deref(&obj.class).name.clone().unwrap_or("?".to_string()), items)
+            }
+            ObjectContent::SequenceIterator(ref container, ref index) => {
+                format!("<iterator on {} at index {}>", store.deref(container).class.repr(store), index)
+            }
+            ObjectContent::Range(start, stop, 1) => format!("range({}, {})", start, stop),
+            ObjectContent::Range(start, stop, step) => format!("range({}, {}, {})", start, stop, step),
+            ObjectContent::ReversedIterator(ref sequence, ref left) => {
+                format!("<reversed iterator on {} with {} items left>", store.deref(sequence).class.repr(store), left)
+            }
+            ObjectContent::Enumerate(_, ref count) => format!("<enumerate object at count {}>", count),
+            ObjectContent::Zip(..) => "<zip object>".to_string(),
+            ObjectContent::Map(ref func, _) => format!("<map object of {}>", func.repr(store)),
+            ObjectContent::Filter(ref func, _) => format!("<filter object of {}>", func.repr(store)),
+            ObjectContent::Generator => format!("<generator object {}>", obj.name.clone().unwrap_or("?".to_string())),
             ObjectContent::OtherObject => format!("<{} instance>", obj.class.repr(store)),
         }
     }
@@ -205,35 +395,26 @@ impl ObjectRef {
         let func = store.deref(self);
         let ref name = func.name;
         match func.content {
-            ObjectContent::Function(ref module_name, ref _code, ref _defaults) => module_name.clone(),
+            ObjectContent::Function(ref module_name, ref _code, ref _defaults, ref _closure) => module_name.clone(),
             ObjectContent::Module(ref _code) => name.clone().unwrap(),
             _ => panic!(format!("Not a function/module: {:?}", func)),
         }
     }
 
-    pub fn iter<EP: EnvProxy>(&self, state: &mut State<EP>) -> ObjectRef {
-        // TODO: check it's a list or a tuple
-        let obj_version = state.store.deref(self).version;
-        let iterator = Object::new_instance(None, state.primitive_objects.iterator_type.clone(), ObjectContent::RandomAccessIterator(self.clone(), 0, obj_version));
-        state.store.allocate(iterator)
-    }
-
-    pub fn setattr(&self, store: &mut ObjectStore, name: String, value: ObjectRef) {
+    /// Sets an attribute in the object's __dict__. Returns false if it has none.
+    pub fn setattr(&self, store: &mut ObjectStore, name: String, value: ObjectRef) -> bool {
         match store.deref(self).attributes {
-            Some(ref attributes) => attributes.borrow_mut().insert(name, value),
-            None => panic!("{}'s attributes are not settable.", self.repr(store)),
-        };
+            Some(ref attributes) => { attributes.borrow_mut().insert(name, value); true },
+            None => false,
+        }
     }
 
-    /// __new__
-    pub fn new_instance(&self, store: &mut ObjectStore, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> ObjectRef {
-        let mut obj = Object::new_instance(None, self.clone(), ObjectContent::OtherObject);
-        if let Some(ref attributes) = store.deref(self).attributes {
-            obj.attributes = Some(Rc::new(RefCell::new(attributes.borrow().clone())))
+    /// Removes an attribute from the object's __dict__, and returns it.
+    pub fn delattr(&self, store: &mut ObjectStore, name: &str) -> Option<ObjectRef> {
+        match store.deref(self).attributes {
+            Some(ref attributes) => attributes.borrow_mut().remove(name),
+            None => None,
         }
-        let obj_ref = store.allocate(obj);
-        // TODO: call its __init__
-        obj_ref
     }
 }
 
@@ -286,9 +467,12 @@ pub struct PrimitiveObjects {
 
     pub none_type: ObjectRef,
     pub none: ObjectRef,
+    pub not_implemented_type: ObjectRef,
+    pub not_implemented: ObjectRef,
 
     pub int_type: ObjectRef,
     pub bool_type: ObjectRef,
+    pub float_type: ObjectRef,
     pub true_obj: ObjectRef,
     pub false_obj: ObjectRef,
 
@@ -298,28 +482,57 @@ pub struct PrimitiveObjects {
     pub set_type: ObjectRef,
     pub frozenset_type: ObjectRef,
     pub dict_type: ObjectRef,
+    pub dict_keys_type: ObjectRef,
+    pub dict_values_type: ObjectRef,
+    pub dict_items_type: ObjectRef,
 
     pub bytes_type: ObjectRef,
+    pub bytearray_type: ObjectRef,
     pub str_type: ObjectRef,
 
     pub iterator_type: ObjectRef,
+    pub generator_type: ObjectRef,
+    pub range_type: ObjectRef,
+    pub reversed_type: ObjectRef,
+    pub enumerate_type: ObjectRef,
+    pub zip_type: ObjectRef,
+    pub map_type: ObjectRef,
+    pub filter_type: ObjectRef,
 
     pub function_type: ObjectRef,
+    pub method_type: ObjectRef,
     pub code_type: ObjectRef,
 
+    pub property_type: ObjectRef,
+    pub staticmethod_type: ObjectRef,
+    pub classmethod_type: ObjectRef,
+    pub super_type: ObjectRef,
+    pub cell_type: ObjectRef,
+    pub member_descriptor_type: ObjectRef,
+
     pub module: ObjectRef,
 
     pub baseexception: ObjectRef,
     pub processorerror: ObjectRef,
     pub exception: ObjectRef,
 
+    pub runtimeerror: ObjectRef,
     pub nameerror: ObjectRef,
+    pub unboundlocalerror: ObjectRef,
     pub attributeerror: ObjectRef,
     pub typeerror: ObjectRef,
     pub stopiteration: ObjectRef,
 
     pub lookuperror: ObjectRef,
     pub keyerror: ObjectRef,
+    pub indexerror: ObjectRef,
+    pub valueerror: ObjectRef,
+    pub arithmeticerror: ObjectRef,
+    pub overflowerror: ObjectRef,
+    pub zerodivisionerror: ObjectRef,
+    pub unicodeerror: ObjectRef,
+    pub unicodedecodeerror: ObjectRef,
+    pub unicodeencodeerror: ObjectRef,
 
     pub names_map: HashMap<String, ObjectRef>,
 }
@@ -331,27 +544,34 @@ impl PrimitiveObjects {
         let obj = Object {
             version: Object::new_version(),
             name: Some("object".to_string()),
-            content: ObjectContent::OtherObject,
+            content: ObjectContent::Class,
             bases: Some(vec![]),
+            mro: None,
             class: type_ref.clone(),
             attributes: None,
+            slots: Vec::new(),
         };
         let type_ = Object {
             version: Object::new_version(),
             name: Some("type".to_string()),
-            content: ObjectContent::OtherObject,
+            content: ObjectContent::Class,
             bases: Some(vec![obj_ref.clone()]),
+            mro: None,
             class: type_ref.clone(),
             attributes: None,
+            slots: Vec::new(),
         };
         store.allocate_at(obj_ref.clone(), obj);
         store.allocate_at(type_ref.clone(), type_);
 
         let none_type = store.allocate(Object::new_class("nonetype".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
         let none = store.allocate(Object::new_instance(Some("None".to_string()), none_type.clone(), ObjectContent::None));
+        let not_implemented_type = store.allocate(Object::new_class("NotImplementedType".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let not_implemented = store.allocate(Object::new_instance(Some("NotImplemented".to_string()), not_implemented_type.clone(), ObjectContent::NotImplemented));
 
         let int_type = store.allocate(Object::new_class("int".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
         let bool_type = store.allocate(Object::new_class("bool".to_string(), None, type_ref.clone(), vec![int_type.clone()]));
+        let float_type = store.allocate(Object::new_class("float".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
         let true_obj = store.allocate(Object::new_instance(Some("True".to_string()), bool_type.clone(), ObjectContent::True));
         let false_obj = store.allocate(Object::new_instance(Some("False".to_string()), bool_type.clone(), ObjectContent::False));
 
@@ -360,46 +580,139 @@ impl PrimitiveObjects {
         let set_type = store.allocate(Object::new_class("set".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
         let frozenset_type = store.allocate(Object::new_class("frozenset".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
         let dict_type = store.allocate(Object::new_class("dict".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let dict_keys_type = store.allocate(Object::new_class("dict_keys".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let dict_values_type = store.allocate(Object::new_class("dict_values".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let dict_items_type = store.allocate(Object::new_class("dict_items".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
         let bytes_type = store.allocate(Object::new_class("bytes".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let bytearray_type = store.allocate(Object::new_class("bytearray".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
         let str_type = store.allocate(Object::new_class("str".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
         let iterator_type = store.allocate(Object::new_class("iterator".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let generator_type = store.allocate(Object::new_class("generator".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let range_type = store.allocate(Object::new_class("range".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let reversed_type = store.allocate(Object::new_class("reversed".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let enumerate_type = store.allocate(Object::new_class("enumerate".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let zip_type = store.allocate(Object::new_class("zip".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let map_type = store.allocate(Object::new_class("map".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let filter_type = store.allocate(Object::new_class("filter".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
 
         let function_type = store.allocate(Object::new_class("function".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let method_type = store.allocate(Object::new_class("method".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
         let code_type = store.allocate(Object::new_class("code".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
 
+        let property_type = store.allocate(Object::new_class("property".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let staticmethod_type = store.allocate(Object::new_class("staticmethod".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let classmethod_type = store.allocate(Object::new_class("classmethod".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let super_type = store.allocate(Object::new_class("super".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let cell_type = store.allocate(Object::new_class("cell".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+        let member_descriptor_type = store.allocate(Object::new_class("member_descriptor".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
+
         let module = store.allocate(Object::new_class("module".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
 
         let baseexception = store.allocate(Object::new_class("BaseException".to_string(), None, type_ref.clone(), vec![obj_ref.clone()]));
         let processorerror = store.allocate(Object::new_class("ProcessorError".to_string(), None, type_ref.clone(), vec![baseexception.clone()]));
         let exception = store.allocate(Object::new_class("Exception".to_string(), None, type_ref.clone(), vec![baseexception.clone()]));
 
+        let runtimeerror = store.allocate(Object::new_class("RuntimeError".to_string(), None, type_ref.clone(), vec![exception.clone()]));
         let nameerror = store.allocate(Object::new_class("NameError".to_string(), None, type_ref.clone(), vec![exception.clone()]));
+        let unboundlocalerror = store.allocate(Object::new_class("UnboundLocalError".to_string(), None, type_ref.clone(), vec![nameerror.clone()]));
         let attributeerror = store.allocate(Object::new_class("AttributeError".to_string(), None, type_ref.clone(), vec![exception.clone()]));
         let typeerror = store.allocate(Object::new_class("TypeError".to_string(), None, type_ref.clone(), vec![exception.clone()]));
         let stopiteration = store.allocate(Object::new_class("StopIteration".to_string(), None, type_ref.clone(), vec![exception.clone()]));
 
         let lookuperror = store.allocate(Object::new_class("LookupError".to_string(), None, type_ref.clone(), vec![exception.clone()]));
         let keyerror = store.allocate(Object::new_class("KeyError".to_string(), None, type_ref.clone(), vec![lookuperror.clone()]));
+        let indexerror = store.allocate(Object::new_class("IndexError".to_string(), None, type_ref.clone(), vec![lookuperror.clone()]));
+        let valueerror = store.allocate(Object::new_class("ValueError".to_string(), None, type_ref.clone(), vec![exception.clone()]));
+        let arithmeticerror = store.allocate(Object::new_class("ArithmeticError".to_string(), None, type_ref.clone(), vec![exception.clone()]));
+        let overflowerror = store.allocate(Object::new_class("OverflowError".to_string(), None, type_ref.clone(), vec![arithmeticerror.clone()]));
+        let zerodivisionerror = store.allocate(Object::new_class("ZeroDivisionError".to_string(), None, type_ref.clone(), vec![arithmeticerror.clone()]));
+        let unicodeerror = store.allocate(Object::new_class("UnicodeError".to_string(), None, type_ref.clone(), vec![valueerror.clone()]));
+        let unicodedecodeerror = store.allocate(Object::new_class("UnicodeDecodeError".to_string(), None, type_ref.clone(), vec![unicodeerror.clone()]));
+        let unicodeencodeerror = store.allocate(Object::new_class("UnicodeEncodeError".to_string(), None, type_ref.clone(), vec![unicodeerror.clone()]));
+
+        {
+            let mut add_methods = |class: &ObjectRef, methods: &[&str]| {
+                PrimitiveObjects::add_native_methods(store, class, &function_type, &staticmethod_type, &classmethod_type, methods)
+            };
+            add_methods(&obj_ref, &["__new__", "__init__", "__init_subclass__", "__getattribute__", "__setattr__", "__delattr__", "__format__"]);
+            add_methods(&type_ref, &["__new__", "__init__", "__prepare__", "__call__", "mro"]);
+            add_methods(&none_type, &["__new__"]);
+            add_methods(&int_type, &["__new__", "__format__", "bit_length", "__sub__", "__rsub__", "__and__", "__rand__", "__xor__", "__rxor__", "__or__", "__ror__"]);
+            add_methods(&bool_type, &["__new__"]);
+            add_methods(&float_type, &["__new__", "__format__", "__add__", "__radd__", "__sub__", "__rsub__", "__mul__", "__rmul__"]);
+            add_methods(&tuple_type, &["__new__"]);
+            add_methods(&list_type, &["__new__", "__init__", "append", "extend", "insert", "pop", "remove", "index", "count", "clear", "copy", "reverse", "sort"]);
+            add_methods(&set_type, &["__new__", "__init__", "add", "discard", "remove", "pop", "clear", "update", "intersection_update", "difference_update", "symmetric_difference_update", "__ior__", "__iand__", "__isub__", "__ixor__", "copy", "union", "intersection", "difference", "symmetric_difference", "issubset", "issuperset", "isdisjoint", "__or__", "__and__", "__sub__", "__xor__", "__le__", "__lt__", "__ge__", "__gt__"]);
+            add_methods(&frozenset_type, &["__new__", "copy", "union", "intersection", "difference", "symmetric_difference", "issubset", "issuperset", "isdisjoint", "__or__", "__and__", "__sub__", "__xor__", "__le__", "__lt__", "__ge__", "__gt__"]);
+            add_methods(&dict_type, &["__new__", "__init__", "get", "setdefault", "pop", "popitem", "update", "keys", "values", "items", "fromkeys", "copy", "clear"]);
+            add_methods(&bytes_type, &["__new__", "decode", "hex", "fromhex", "join", "split", "rsplit", "strip", "lstrip", "rstrip", "find", "rfind", "index", "rindex", "count", "startswith", "endswith", "replace", "upper", "lower", "isdigit", "isalpha", "isalnum", "isspace", "isupper", "islower", "partition", "rpartition"]);
+            add_methods(&bytearray_type, &["__new__", "__init__", "decode", "hex", "fromhex", "join", "split", "rsplit", "strip", "lstrip", "rstrip", "find", "rfind", "index", "rindex", "count", "startswith", "endswith", "replace", "upper", "lower", "isdigit", "isalpha", "isalnum", "isspace", "isupper", "islower", "partition", "rpartition", "append", "extend", "insert", "pop", "remove", "clear", "copy", "reverse"]);
+            add_methods(&str_type, &["__new__", "__format__", "__mod__", "format", "join", "split", "rsplit", "strip", "lstrip", "rstrip", "find", "rfind", "index", "rindex", "count", "startswith", "endswith", "replace", "upper", "lower", "casefold", "isdigit", "isdecimal", "isnumeric", "isalpha", "isalnum", "isspace", "isupper", "islower", "partition", "rpartition", "splitlines", "center", "ljust", "rjust", "zfill", "encode"]);
+            add_methods(&range_type, &["__new__"]);
+            add_methods(&reversed_type, &["__new__"]);
+            add_methods(&enumerate_type, &["__new__"]);
+            add_methods(&zip_type, &["__new__"]);
+            add_methods(&map_type, &["__new__"]);
+            add_methods(&filter_type, &["__new__"]);
+            add_methods(&property_type, &["__new__", "getter", "setter", "deleter"]);
+            add_methods(&staticmethod_type, &["__new__"]);
+            add_methods(&classmethod_type, &["__new__"]);
+            add_methods(&super_type, &["__new__"]);
+            add_methods(&baseexception, &["__new__", "__init__"]);
+        }
 
+        // Builtin classes have at most one base, so their MRO is the chain of their bases.
+        let classes: Vec<ObjectRef> = store.all_objects.iter()
+            .filter(|&(_, obj)| obj.bases.is_some())
+            .map(|(&id, _)| ObjectRef { id: id })
+            .collect();
+        for class in classes {
+            let mut mro = vec![class.clone()];
+            while let Some(base) = store.deref(mro.last().unwrap()).bases.as_ref().and_then(|bases| bases.first()).cloned() {
+                mro.push(base);
+            }
+            store.deref_mut(&class).mro = Some(mro);
+        }
 
         let mut map = HashMap::new();
         map.insert("object".to_string(), obj_ref.clone());
-        map.insert("tuple".to_string(), type_ref.clone());
+        map.insert("type".to_string(), type_ref.clone());
         map.insert("nonetype".to_string(), none_type.clone());
         map.insert("None".to_string(), none.clone());
+        map.insert("NotImplementedType".to_string(), not_implemented_type.clone());
+        map.insert("NotImplemented".to_string(), not_implemented.clone());
         map.insert("True".to_string(), true_obj.clone());
         map.insert("False".to_string(), false_obj.clone());
         map.insert("int".to_string(), int_type.clone());
         map.insert("bool".to_string(), bool_type.clone());
+        map.insert("float".to_string(), float_type.clone());
         map.insert("tuple".to_string(), tuple_type.clone());
         map.insert("list".to_string(), list_type.clone());
         map.insert("set".to_string(), set_type.clone());
         map.insert("frozenset".to_string(), frozenset_type.clone());
         map.insert("dict".to_string(), dict_type.clone());
+        map.insert("dict_keys".to_string(), dict_keys_type.clone());
+        map.insert("dict_values".to_string(), dict_values_type.clone());
+        map.insert("dict_items".to_string(), dict_items_type.clone());
         map.insert("bytes".to_string(), bytes_type.clone());
+        map.insert("bytearray".to_string(), bytearray_type.clone());
         map.insert("str".to_string(), str_type.clone());
+        map.insert("range".to_string(), range_type.clone());
+        map.insert("reversed".to_string(), reversed_type.clone());
+        map.insert("enumerate".to_string(), enumerate_type.clone());
+        map.insert("zip".to_string(), zip_type.clone());
+        map.insert("map".to_string(), map_type.clone());
+        map.insert("filter".to_string(), filter_type.clone());
+        map.insert("generator".to_string(), generator_type.clone());
         map.insert("function".to_string(), function_type.clone());
+        map.insert("method".to_string(), method_type.clone());
         map.insert("code".to_string(), code_type.clone());
+        map.insert("property".to_string(), property_type.clone());
+        map.insert("staticmethod".to_string(), staticmethod_type.clone());
+        map.insert("classmethod".to_string(), classmethod_type.clone());
+        map.insert("super".to_string(), super_type.clone());
+        map.insert("cell".to_string(), cell_type.clone());
+        map.insert("member_descriptor".to_string(), member_descriptor_type.clone());
         map.insert("module".to_string(), module.clone());
 
         // Base classes
@@ -407,60 +720,110 @@ impl PrimitiveObjects {
         map.insert("ProcessorError".to_string(), processorerror.clone());
         map.insert("Exception".to_string(), exception.clone());
 
+        map.insert("RuntimeError".to_string(), runtimeerror.clone());
         map.insert("NameError".to_string(), nameerror.clone());
+        map.insert("UnboundLocalError".to_string(), unboundlocalerror.clone());
         map.insert("AttributeError".to_string(), attributeerror.clone());
         map.insert("TypeError".to_string(), typeerror.clone());
         map.insert("StopIteration".to_string(), stopiteration.clone());
 
         map.insert("LookupError".to_string(), lookuperror.clone());
         map.insert("KeyError".to_string(), keyerror.clone());
+        map.insert("IndexError".to_string(), indexerror.clone());
+        map.insert("ValueError".to_string(), valueerror.clone());
+        map.insert("ArithmeticError".to_string(), arithmeticerror.clone());
+        map.insert("OverflowError".to_string(), overflowerror.clone());
+        map.insert("ZeroDivisionError".to_string(), zerodivisionerror.clone());
+        map.insert("UnicodeError".to_string(), unicodeerror.clone());
+        map.insert("UnicodeDecodeError".to_string(), unicodedecodeerror.clone());
+        map.insert("UnicodeEncodeError".to_string(), unicodeencodeerror.clone());
 
         PrimitiveObjects {
             object: obj_ref, type_: type_ref,
             none_type: none_type, none: none,
-            int_type: int_type, bool_type: bool_type, true_obj: true_obj, false_obj: false_obj,
+            not_implemented_type: not_implemented_type, not_implemented: not_implemented,
+            int_type: int_type, bool_type: bool_type, float_type: float_type, true_obj: true_obj, false_obj: false_obj,
             tuple_type: tuple_type, list_type: list_type,
             set_type: set_type, frozenset_type: frozenset_type, dict_type: dict_type,
-            bytes_type: bytes_type, str_type: str_type,
-            iterator_type: iterator_type,
-            function_type: function_type, code_type: code_type,
+            dict_keys_type: dict_keys_type, dict_values_type: dict_values_type, dict_items_type: dict_items_type,
+            bytes_type: bytes_type, bytearray_type: bytearray_type, str_type: str_type,
+            iterator_type: iterator_type, generator_type: generator_type, range_type: range_type, reversed_type: reversed_type,
+            enumerate_type: enumerate_type, zip_type: zip_type, map_type: map_type, filter_type: filter_type,
+            function_type: function_type, method_type: method_type, code_type: code_type,
+            property_type: property_type, staticmethod_type: staticmethod_type, classmethod_type: classmethod_type,
+            super_type: super_type, cell_type: cell_type, member_descriptor_type: member_descriptor_type,
             baseexception: baseexception, processorerror: processorerror, exception: exception,
-            nameerror: nameerror, attributeerror: attributeerror, typeerror: typeerror, stopiteration: stopiteration,
-            lookuperror: lookuperror, keyerror: keyerror,
+            runtimeerror: runtimeerror,
+            nameerror: nameerror, unboundlocalerror: unboundlocalerror, attributeerror: attributeerror, typeerror: typeerror, stopiteration: stopiteration,
+            lookuperror: lookuperror, keyerror: keyerror, indexerror: indexerror,
+            valueerror: valueerror,
+            arithmeticerror: arithmeticerror, overflowerror: overflowerror, zerodivisionerror: zerodivisionerror,
+            unicodeerror: unicodeerror, unicodedecodeerror: unicodedecodeerror, unicodeencodeerror: unicodeencodeerror,
             module: module,
             names_map: map,
         }
     }
 
-    pub fn new_int(&self, i: u32) -> Object {
-        Object::new_instance(None, self.int_type.clone(), ObjectContent::Int(i))
+    /// Adds methods implemented by primitive functions named `<class>.<method>` to a builtin class.
+    /// `__new__` is wrapped in a staticmethod, and `__init_subclass__` and `__prepare__`
+    /// in a classmethod, like CPython does (as well as the alternate constructors `fromhex` and `fromkeys`).
+    fn add_native_methods(store: &mut ObjectStore, class: &ObjectRef, function_type: &ObjectRef, staticmethod_type: &ObjectRef, classmethod_type: &ObjectRef, methods: &[&str]) {
+        let class_name = store.deref(class).name.clone().unwrap();
+        let mut attributes = HashMap::new();
+        for method_name in methods {
+            let primitive_name = format!("{}.{}", class_name, method_name);
+            let func = store.allocate(Object::new_instance(Some(method_name.to_string()), function_type.clone(), ObjectContent::PrimitiveFunction(primitive_name)));
+            let func = match *method_name {
+                "__new__" => store.allocate(Object::new_instance(None, staticmethod_type.clone(), ObjectContent::StaticMethod(func))),
+                "__init_subclass__" | "__prepare__" | "fromhex" | "fromkeys" => store.allocate(Object::new_instance(None, classmethod_type.clone(), ObjectContent::ClassMethod(func))),
+                _ => func,
+            };
+            attributes.insert(method_name.to_string(), func);
+        }
+        store.deref_mut(class).attributes = Some(Rc::new(RefCell::new(attributes)));
+    }
+
+    pub fn new_int(&self, i: i64) -> Object {
+        Object::new_builtin_instance(self.int_type.clone(), ObjectContent::Int(i))
+    }
+    pub fn new_float(&self, f: f64) -> Object {
+        Object::new_builtin_instance(self.float_type.clone(), ObjectContent::Float(f.to_bits()))
     }
     pub fn new_string(&self, s: String) -> Object {
-        Object::new_instance(None, self.str_type.clone(), ObjectContent::String(s))
+        Object::new_builtin_instance(self.str_type.clone(), ObjectContent::String(s))
     }
     pub fn new_bytes(&self, b: Vec<u8>) -> Object {
-        Object::new_instance(None, self.bytes_type.clone(), ObjectContent::Bytes(b))
+        Object::new_builtin_instance(self.bytes_type.clone(), ObjectContent::Bytes(b))
+    }
+    pub fn new_bytearray(&self, b: Vec<u8>) -> Object {
+        Object::new_builtin_instance(self.bytearray_type.clone(), ObjectContent::ByteArray(b))
     }
     pub fn new_tuple(&self, v: Vec<ObjectRef>) -> Object {
-        Object::new_instance(None, self.tuple_type.clone(), ObjectContent::Tuple(v))
+        Object::new_builtin_instance(self.tuple_type.clone(), ObjectContent::Tuple(v))
     }
     pub fn new_list(&self, v: Vec<ObjectRef>) -> Object {
-        Object::new_instance(None, self.list_type.clone(), ObjectContent::List(v))
+        Object::new_builtin_instance(self.list_type.clone(), ObjectContent::List(v))
     }
-    pub fn new_set(&self, v: Vec<ObjectRef>) -> Object {
-        Object::new_instance(None, self.set_type.clone(), ObjectContent::Set(v))
+    pub fn new_set(&self, v: DictContent) -> Object {
+        Object::new_builtin_instance(self.set_type.clone(), ObjectContent::Set(v))
     }
-    pub fn new_dict(&self, v: Vec<(ObjectRef, ObjectRef)>) -> Object {
-        Object::new_instance(None, self.dict_type.clone(), ObjectContent::Dict(v))
+    pub fn new_dict(&self, d: DictContent) -> Object {
+        Object::new_builtin_instance(self.dict_type.clone(), ObjectContent::Dict(d))
     }
-    pub fn new_frozenset(&self, v: Vec<ObjectRef>) -> Object {
-        Object::new_instance(None, self.frozenset_type.clone(), ObjectContent::FrozenSet(v))
+    pub fn new_frozenset(&self, v: DictContent) -> Object {
+        Object::new_builtin_instance(self.frozenset_type.clone(), ObjectContent::FrozenSet(v))
     }
     pub fn new_code(&self, c: Code) -> Object {
-        Object::new_instance(None, self.code_type.clone(), ObjectContent::Code(Box::new(c)))
+        Object::new_builtin_instance(self.code_type.clone(), ObjectContent::Code(Box::new(c)))
+    }
+    pub fn new_function(&self, name: String, module_name: String, code: ObjectRef, defaults: HashMap<String, ObjectRef>, closure: Vec<ObjectRef>) -> Object {
+        Object::new_instance(Some(name), self.function_type.clone(), ObjectContent::Function(module_name, code, defaults, closure))
+    }
+    pub fn new_bound_method(&self, func: ObjectRef, instance: ObjectRef) -> Object {
+        Object::new_builtin_instance(self.method_type.clone(), ObjectContent::BoundMethod(func, instance))
     }
-    pub fn new_function(&self, name: String, module_name: String, code: ObjectRef, defaults: HashMap<String, ObjectRef>) -> Object {
-        Object::new_instance(Some(name), self.function_type.clone(), ObjectContent::Function(module_name, code, defaults))
+    pub fn new_cell(&self, value: Option<ObjectRef>) -> Object {
+        Object::new_builtin_instance(self.cell_type.clone(), ObjectContent::Cell(value))
     }
     pub fn new_module(&self, name: String, code: ObjectRef) -> Object {
         Object::new_instance(Some(name), self.module.clone(), ObjectContent::Module(code))
@@ -0,0 +1,1014 @@
+//! Native methods of `bytes` and `bytearray`, and text encodings.
+//!
+//! Both types share their methods, which return objects of the type of the
+//! receiver; indices and lengths are in bytes.
+
+use super::super::sandbox::EnvProxy;
+use super::super::state::{State, raise, return_value};
+use super::super::objects::{ObjectRef, ObjectContent, Object};
+use super::super::processor::frame::Frame;
+use super::super::processor;
+use super::{bind_arguments, container_items, native_issubclass, signed_index, slice_index};
+use super::strings::{int_argument, limit_argument, optional_string_argument, return_bool, raise_empty_separator};
+
+/// ASCII whitespace, as understood by the methods of bytes
+const WHITESPACE: &[u8] = b" \t\n\r\x0b\x0c";
+
+/// Content of the receiver of a method, and whether it is a bytearray;
+/// method descriptors already checked its type.
+fn receiver<EP: EnvProxy>(state: &State<EP>, args: &mut Vec<ObjectRef>) -> (Vec<u8>, bool) {
+    let receiver = args.remove(0);
+    match state.store.deref(&receiver).content {
+        ObjectContent::Bytes(ref b) => (b.clone(), false),
+        ObjectContent::ByteArray(ref b) => (b.clone(), true),
+        _ => panic!("bytes method called on a non-bytes object"),
+    }
+}
+
+/// Like receiver, for the methods that mutate a bytearray.
+fn bytearray_receiver<EP: EnvProxy>(state: &State<EP>, args: &mut Vec<ObjectRef>) -> (ObjectRef, Vec<u8>) {
+    let receiver = args.remove(0);
+    let content = match state.store.deref(&receiver).content {
+        ObjectContent::ByteArray(ref b) => b.clone(),
+        _ => panic!("bytearray method called on a non-bytearray object"),
+    };
+    (receiver, content)
+}
+
+fn set_bytearray<EP: EnvProxy>(state: &mut State<EP>, obj_ref: &ObjectRef, b: Vec<u8>) {
+    let obj = state.store.deref_mut(obj_ref);
+    obj.content = ObjectContent::ByteArray(b);
+    obj.bump_version();
+}
+
+/// Content of a bytes-like argument (bytes or bytearray); raises a TypeError
+/// and returns None if it is not one.
+fn bytes_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<Vec<u8>> {
+    match state.store.deref(obj_ref).content {
+        ObjectContent::Bytes(ref b) | ObjectContent::ByteArray(ref b) => return Some(b.clone()),
+        _ => (),
+    }
+    let exc = state.primitive_objects.typeerror.clone();
+    let name = processor::type_name(&state.store, obj_ref);
+    raise(state, call_stack, exc, format!("a bytes-like object is required, not '{}'", name));
+    None
+}
+
+/// Like bytes_argument, but None is the same as a missing argument.
+fn optional_bytes_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: Option<ObjectRef>) -> Option<Option<Vec<u8>>> {
+    match obj_ref {
+        Some(ref obj_ref) if !obj_ref.is(&state.primitive_objects.none) => bytes_argument(state, call_stack, obj_ref).map(Some),
+        _ => Some(None),
+    }
+}
+
+/// Value of an int argument that must fit in a byte.
+fn byte_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<u8> {
+    match state.store.deref(obj_ref).content {
+        ObjectContent::Int(i) if (0..256).contains(&i) => return Some(i as u8),
+        ObjectContent::True => return Some(1),
+        ObjectContent::False => return Some(0),
+        ObjectContent::Int(_) => {
+            let exc = state.primitive_objects.valueerror.clone();
+            raise(state, call_stack, exc, "byte must be in range(0, 256)".to_string());
+            return None
+        },
+        _ => (),
+    }
+    let exc = state.primitive_objects.typeerror.clone();
+    let name = processor::type_name(&state.store, obj_ref);
+    raise(state, call_stack, exc, format!("an integer is required (got type {})", name));
+    None
+}
+
+/// The subsequence searched by find, count, partition, ...: either bytes-like or a single byte.
+fn sub_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<Vec<u8>> {
+    match state.store.deref(obj_ref).content {
+        ObjectContent::Int(_) | ObjectContent::True | ObjectContent::False => (),
+        _ => return bytes_argument(state, call_stack, obj_ref),
+    }
+    byte_argument(state, call_stack, obj_ref).map(|b| vec![b])
+}
+
+fn no_arguments<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, args: &[ObjectRef], kwargs: &[(ObjectRef, ObjectRef)]) -> Option<()> {
+    if !kwargs.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() takes no keyword arguments", funcname));
+        return None
+    }
+    if !args.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() takes no arguments ({} given)", funcname, args.len()));
+        return None
+    }
+    Some(())
+}
+
+fn new_bytes_object<EP: EnvProxy>(state: &State<EP>, b: Vec<u8>, bytearray: bool) -> Object {
+    if bytearray { state.primitive_objects.new_bytearray(b) } else { state.primitive_objects.new_bytes(b) }
+}
+
+fn return_bytes<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, b: Vec<u8>, bytearray: bool) {
+    let obj_ref = state.store.allocate(new_bytes_object(state, b, bytearray));
+    return_value(call_stack, obj_ref)
+}
+
+fn return_bytes_list<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, parts: Vec<Vec<u8>>, bytearray: bool) {
+    let items = parts.into_iter().map(|b| state.store.allocate(new_bytes_object(state, b, bytearray))).collect();
+    let list = state.store.allocate(state.primitive_objects.new_list(items));
+    return_value(call_stack, list)
+}
+
+fn return_int<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, i: usize) {
+    let obj_ref = state.store.allocate(state.primitive_objects.new_int(i as i64));
+    return_value(call_stack, obj_ref)
+}
+
+fn find_in(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.len() > haystack.len() {
+        return None
+    }
+    (0..haystack.len()-needle.len()+1).find(|&i| &haystack[i..i+needle.len()] == needle)
+}
+
+fn rfind_in(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.len() > haystack.len() {
+        return None
+    }
+    (0..haystack.len()-needle.len()+1).rev().find(|&i| &haystack[i..i+needle.len()] == needle)
+}
+
+fn trim_start<'a>(b: &'a [u8], chars: &[u8]) -> &'a [u8] {
+    let start = b.iter().position(|c| !chars.contains(c)).unwrap_or(b.len());
+    &b[start..]
+}
+
+fn trim_end<'a>(b: &'a [u8], chars: &[u8]) -> &'a [u8] {
+    let end = b.iter().rposition(|c| !chars.contains(c)).map(|i| i+1).unwrap_or(0);
+    &b[..end]
+}
+
+#[derive(Clone, Copy)]
+pub enum Codec {
+    Utf8,
+    Ascii,
+    Latin1,
+}
+
+impl Codec {
+    fn name(&self) -> &'static str {
+        match *self {
+            Codec::Utf8 => "utf-8",
+            Codec::Ascii => "ascii",
+            Codec::Latin1 => "latin-1",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ErrorHandler {
+    Strict,
+    Replace,
+    Ignore,
+}
+
+/// Parses the `encoding` and `errors` arguments of str.encode, bytes.decode, bytes(), ...
+pub fn codec_arguments<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, encoding: Option<ObjectRef>, errors: Option<ObjectRef>) -> Option<(Codec, ErrorHandler)> {
+    let encoding = optional_string_argument(state, call_stack, encoding)?.unwrap_or("utf-8".to_string());
+    let errors = optional_string_argument(state, call_stack, errors)?.unwrap_or("strict".to_string());
+    let codec = match encoding.to_lowercase().replace('_', "-").as_str() {
+        "utf-8" | "utf8" | "u8" => Codec::Utf8,
+        "ascii" | "us-ascii" | "646" => Codec::Ascii,
+        "latin-1" | "latin1" | "l1" | "iso-8859-1" | "iso8859-1" => Codec::Latin1,
+        _ => {
+            let exc = state.primitive_objects.lookuperror.clone();
+            raise(state, call_stack, exc, format!("unknown encoding: {}", encoding));
+            return None
+        },
+    };
+    let errors = match errors.as_str() {
+        "strict" => ErrorHandler::Strict,
+        "replace" => ErrorHandler::Replace,
+        "ignore" => ErrorHandler::Ignore,
+        _ => {
+            let exc = state.primitive_objects.lookuperror.clone();
+            raise(state, call_stack, exc, format!("unknown error handler name '{}'", errors));
+            return None
+        },
+    };
+    Some((codec, errors))
+}
+
+fn decode_error(codec: Codec, b: &[u8], start: usize, end: usize, reason: &str) -> String {
+    if end - start == 1 {
+        format!("'{}' codec can't decode byte 0x{:02x} in position {}: {}", codec.name(), b[start], start, reason)
+    }
+    else {
+        format!("'{}' codec can't decode bytes in position {}-{}: {}", codec.name(), start, end-1, reason)
+    }
+}
+
+/// Decodes bytes; returns the message of the UnicodeDecodeError on failure.
+pub fn decode_bytes(b: &[u8], codec: Codec, errors: ErrorHandler) -> Result<String, String> {
+    let mut result = String::with_capacity(b.len());
+    let mut position = 0;
+    while position < b.len() {
+        let (end, reason) = match codec {
+            Codec::Utf8 => {
+                match ::std::str::from_utf8(&b[position..]) {
+                    Ok(s) => {
+                        result.push_str(s);
+                        break
+                    },
+                    Err(e) => {
+                        let start = position + e.valid_up_to();
+                        result.push_str(::std::str::from_utf8(&b[position..start]).unwrap());
+                        position = start;
+                        match e.error_len() {
+                            None => (b.len(), "unexpected end of data"),
+                            Some(length) => {
+                                let reason = match b[start] {
+                                    0x80..=0xc1 | 0xf5..=0xff => "invalid start byte",
+                                    _ => "invalid continuation byte",
+                                };
+                                (start + length, reason)
+                            },
+                        }
+                    },
+                }
+            },
+            Codec::Ascii if b[position] >= 0x80 => (position + 1, "ordinal not in range(128)"),
+            Codec::Ascii | Codec::Latin1 => {
+                result.push(b[position] as char);
+                position += 1;
+                continue
+            },
+        };
+        match errors {
+            ErrorHandler::Strict => return Err(decode_error(codec, b, position, end, reason)),
+            ErrorHandler::Replace => result.push('\u{fffd}'),
+            ErrorHandler::Ignore => (),
+        }
+        position = end;
+    }
+    Ok(result)
+}
+
+/// Encodes a str; returns the message of the UnicodeEncodeError on failure.
+pub fn encode_str(s: &str, codec: Codec, errors: ErrorHandler) -> Result<Vec<u8>, String> {
+    let limit = match codec {
+        Codec::Utf8 => return Ok(s.as_bytes().to_vec()),
+        Codec::Ascii => 128,
+        Codec::Latin1 => 256,
+    };
+    let mut result = Vec::with_capacity(s.len());
+    for (position, c) in s.chars().enumerate() {
+        let code = c as u32;
+        if code < limit {
+            result.push(code as u8);
+            continue
+        }
+        match errors {
+            ErrorHandler::Strict => {
+                let escaped = if code < 0x100 { format!("\\x{:02x}", code) } else if code < 0x10000 { format!("\\u{:04x}", code) } else { format!("\\U{:08x}", code) };
+                return Err(format!("'{}' codec can't encode character '{}' in position {}: ordinal not in range({})", codec.name(), escaped, position, limit))
+            },
+            ErrorHandler::Replace => result.push(b'?'),
+            ErrorHandler::Ignore => (),
+        }
+    }
+    Ok(result)
+}
+
+/// Content of a new bytes or bytearray object, from the arguments of their constructor.
+pub fn source_bytes<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, source: Option<ObjectRef>, encoding: Option<ObjectRef>, errors: Option<ObjectRef>) -> Option<Vec<u8>> {
+    let source = match source {
+        Some(source) => source,
+        None if encoding.is_some() || errors.is_some() => {
+            let exc = state.primitive_objects.typeerror.clone();
+            raise(state, call_stack, exc, "encoding or errors without sequence argument".to_string());
+            return None
+        },
+        None => return Some(vec![]),
+    };
+    let content = state.store.deref(&source).content.clone(); // TODO: copy only if needed
+    match content {
+        ObjectContent::String(s) => {
+            if encoding.is_none() {
+                let exc = state.primitive_objects.typeerror.clone();
+                raise(state, call_stack, exc, "string argument without an encoding".to_string());
+                return None
+            }
+            let (codec, errors) = codec_arguments(state, call_stack, encoding, errors)?;
+            match encode_str(&s, codec, errors) {
+                Ok(b) => Some(b),
+                Err(message) => {
+                    let exc = state.primitive_objects.unicodeencodeerror.clone();
+                    raise(state, call_stack, exc, message);
+                    None
+                },
+            }
+        },
+        _ if encoding.is_some() || errors.is_some() => {
+            let exc = state.primitive_objects.typeerror.clone();
+            let message = if encoding.is_some() { "encoding without a string argument" } else { "errors without a string argument" };
+            raise(state, call_stack, exc, message.to_string());
+            None
+        },
+        ObjectContent::Bytes(b) | ObjectContent::ByteArray(b) => Some(b),
+        ObjectContent::Int(size) if size < 0 => {
+            let exc = state.primitive_objects.valueerror.clone();
+            raise(state, call_stack, exc, "negative count".to_string());
+            None
+        },
+        ObjectContent::Int(size) => Some(vec![0; size as usize]),
+        _ => {
+            let items = container_items(state, call_stack, &source)?;
+            let mut bytes = Vec::with_capacity(items.len());
+            for item in items {
+                match state.store.deref(&item).content {
+                    ObjectContent::Int(i) if (0..256).contains(&i) => bytes.push(i as u8),
+                    ObjectContent::Int(_) => {
+                        let exc = state.primitive_objects.valueerror.clone();
+                        raise(state, call_stack, exc, "bytes must be in range(0, 256)".to_string());
+                        return None
+                    },
+                    _ => {
+                        let exc = state.primitive_objects.typeerror.clone();
+                        let name = processor::type_name(&state.store, &item);
+                        raise(state, call_stack, exc, format!("'{}' object cannot be interpreted as an integer", name));
+                        return None
+                    },
+                }
+            }
+            Some(bytes)
+        },
+    }
+}
+
+pub fn decode<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (b, _) = receiver(state, &mut args);
+    let mut values = match bind_arguments(state, call_stack, "decode", &["encoding", "errors"], 0, args, kwargs) {
+        Some(values) => values,
+        None => return,
+    };
+    let (codec, errors) = match codec_arguments(state, call_stack, values.remove(0), values.remove(0)) {
+        Some(arguments) => arguments,
+        None => return,
+    };
+    match decode_bytes(&b, codec, errors) {
+        Ok(s) => {
+            let obj_ref = state.store.allocate(state.primitive_objects.new_string(s));
+            return_value(call_stack, obj_ref)
+        },
+        Err(message) => {
+            let exc = state.primitive_objects.unicodedecodeerror.clone();
+            raise(state, call_stack, exc, message)
+        },
+    }
+}
+
+pub fn hex<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (b, _) = receiver(state, &mut args);
+    if no_arguments(state, call_stack, "hex", &args, &kwargs).is_none() {
+        return
+    }
+    let s = b.iter().map(|c| format!("{:02x}", c)).collect();
+    let obj_ref = state.store.allocate(state.primitive_objects.new_string(s));
+    return_value(call_stack, obj_ref)
+}
+
+/// Class method building bytes (or a bytearray) from pairs of hexadecimal digits,
+/// which may be separated by whitespace.
+pub fn fromhex<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("fromhex", state, call_stack, args);
+    check_no_kwargs!("fromhex", state, call_stack, kwargs);
+    let string = match bind_arguments(state, call_stack, "fromhex", &["string"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let s = match state.store.deref(&string).content {
+        ObjectContent::String(ref s) => s.clone(),
+        _ => {
+            let exc = state.primitive_objects.typeerror.clone();
+            let name = processor::type_name(&state.store, &string);
+            return raise(state, call_stack, exc, format!("fromhex() argument must be str, not {}", name))
+        },
+    };
+    let chars: Vec<char> = s.chars().collect();
+    let mut b = Vec::with_capacity(chars.len() / 2);
+    let mut position = 0;
+    while position < chars.len() {
+        if chars[position].is_ascii_whitespace() {
+            position += 1;
+            continue
+        }
+        let high = chars[position].to_digit(16);
+        let low = chars.get(position+1).and_then(|c| c.to_digit(16));
+        match (high, low) {
+            (Some(high), Some(low)) => b.push((high * 16 + low) as u8),
+            _ => {
+                let exc = state.primitive_objects.valueerror.clone();
+                let position = if high.is_some() { position + 1 } else { position };
+                return raise(state, call_stack, exc, format!("non-hexadecimal number found in fromhex() arg at position {}", position))
+            },
+        }
+        position += 2;
+    }
+    let content = if native_issubclass(&state.store, &cls, &state.primitive_objects.bytearray_type) {
+        ObjectContent::ByteArray(b)
+    }
+    else {
+        ObjectContent::Bytes(b)
+    };
+    let obj_ref = state.store.allocate(Object::new_instance(None, cls, content));
+    return_value(call_stack, obj_ref)
+}
+
+pub fn join<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (b, bytearray) = receiver(state, &mut args);
+    check_no_kwargs!("join", state, call_stack, kwargs);
+    let iterable = match bind_arguments(state, call_stack, "join", &["iterable_of_bytes"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let items = match container_items(state, call_stack, &iterable) {
+        Some(items) => items,
+        None => return,
+    };
+    let mut parts = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        match state.store.deref(item).content {
+            ObjectContent::Bytes(ref item) | ObjectContent::ByteArray(ref item) => parts.push(item.clone()),
+            _ => {
+                let exc = state.primitive_objects.typeerror.clone();
+                let name = processor::type_name(&state.store, item);
+                return raise(state, call_stack, exc, format!("sequence item {}: expected a bytes-like object, {} found", i, name))
+            },
+        }
+    }
+    return_bytes(state, call_stack, parts.join(&b[..]), bytearray)
+}
+
+// Splits on runs of whitespace, ignoring leading and trailing whitespace.
+fn split_whitespace(b: &[u8], maxsplit: usize) -> Vec<Vec<u8>> {
+    let mut parts = Vec::new();
+    let mut rest = trim_start(b, WHITESPACE);
+    while !rest.is_empty() {
+        if parts.len() == maxsplit {
+            parts.push(rest.to_vec());
+            break
+        }
+        match rest.iter().position(|c| WHITESPACE.contains(c)) {
+            Some(end) => {
+                parts.push(rest[..end].to_vec());
+                rest = trim_start(&rest[end..], WHITESPACE);
+            },
+            None => {
+                parts.push(rest.to_vec());
+                break
+            },
+        }
+    }
+    parts
+}
+
+fn rsplit_whitespace(b: &[u8], maxsplit: usize) -> Vec<Vec<u8>> {
+    let mut parts = Vec::new();
+    let mut rest = trim_end(b, WHITESPACE);
+    while !rest.is_empty() {
+        if parts.len() == maxsplit {
+            parts.push(rest.to_vec());
+            break
+        }
+        match rest.iter().rposition(|c| WHITESPACE.contains(c)) {
+            Some(start) => {
+                parts.push(rest[start+1..].to_vec());
+                rest = trim_end(&rest[..start], WHITESPACE);
+            },
+            None => {
+                parts.push(rest.to_vec());
+                break
+            },
+        }
+    }
+    parts.reverse();
+    parts
+}
+
+fn split_on(b: &[u8], sep: &[u8], maxsplit: usize, reverse: bool) -> Vec<Vec<u8>> {
+    let mut parts = Vec::new();
+    let mut rest = b;
+    while parts.len() < maxsplit {
+        if reverse {
+            match rfind_in(rest, sep) {
+                Some(start) => {
+                    parts.push(rest[start+sep.len()..].to_vec());
+                    rest = &rest[..start];
+                },
+                None => break,
+            }
+        }
+        else {
+            match find_in(rest, sep) {
+                Some(start) => {
+                    parts.push(rest[..start].to_vec());
+                    rest = &rest[start+sep.len()..];
+                },
+                None => break,
+            }
+        }
+    }
+    parts.push(rest.to_vec());
+    if reverse {
+        parts.reverse();
+    }
+    parts
+}
+
+fn split_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, reverse: bool) {
+    let (b, bytearray) = receiver(state, &mut args);
+    let mut values = match bind_arguments(state, call_stack, funcname, &["sep", "maxsplit"], 0, args, kwargs) {
+        Some(values) => values,
+        None => return,
+    };
+    let sep = match optional_bytes_argument(state, call_stack, values.remove(0)) {
+        Some(sep) => sep,
+        None => return,
+    };
+    let maxsplit = match limit_argument(state, call_stack, values.remove(0)) {
+        Some(maxsplit) => maxsplit,
+        None => return,
+    };
+    let parts = match sep {
+        None if reverse => rsplit_whitespace(&b, maxsplit),
+        None => split_whitespace(&b, maxsplit),
+        Some(ref sep) if sep.is_empty() => return raise_empty_separator(state, call_stack),
+        Some(ref sep) => split_on(&b, sep, maxsplit, reverse),
+    };
+    return_bytes_list(state, call_stack, parts, bytearray)
+}
+
+pub fn split<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    split_impl(state, call_stack, "split", args, kwargs, false)
+}
+
+pub fn rsplit<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    split_impl(state, call_stack, "rsplit", args, kwargs, true)
+}
+
+fn strip_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, left: bool, right: bool) {
+    let (b, bytearray) = receiver(state, &mut args);
+    check_no_kwargs!(funcname, state, call_stack, kwargs);
+    let chars = match bind_arguments(state, call_stack, funcname, &["bytes"], 0, args, vec![]) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let chars = match optional_bytes_argument(state, call_stack, chars) {
+        Some(chars) => chars.unwrap_or(WHITESPACE.to_vec()),
+        None => return,
+    };
+    let mut stripped = &b[..];
+    if left {
+        stripped = trim_start(stripped, &chars);
+    }
+    if right {
+        stripped = trim_end(stripped, &chars);
+    }
+    let stripped = stripped.to_vec();
+    return_bytes(state, call_stack, stripped, bytearray)
+}
+
+pub fn strip<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    strip_impl(state, call_stack, "strip", args, kwargs, true, true)
+}
+
+pub fn lstrip<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    strip_impl(state, call_stack, "lstrip", args, kwargs, true, false)
+}
+
+pub fn rstrip<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    strip_impl(state, call_stack, "rstrip", args, kwargs, false, true)
+}
+
+/// Parses the `sub[, start[, end]]` arguments of find, count, startswith, ...
+/// Returns the subject, the part of the bytes between start and end, and start;
+/// or None if the start is past the end, or Err if an exception was raised.
+fn substring_arguments<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, b: &[u8], args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> Result<Option<(ObjectRef, Vec<u8>, usize)>, ()> {
+    if !kwargs.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() takes no keyword arguments", funcname));
+        return Err(())
+    }
+    let mut values = bind_arguments(state, call_stack, funcname, &["sub", "start", "end"], 1, args, vec![]).ok_or(())?;
+    let sub = values.remove(0).unwrap();
+    let start = slice_index(int_argument(state, call_stack, values.remove(0), 0).ok_or(())?, b.len());
+    let end = slice_index(int_argument(state, call_stack, values.remove(0), b.len() as i64).ok_or(())?, b.len());
+    if start > b.len() {
+        return Ok(None)
+    }
+    let end = if end > b.len() { b.len() } else { end };
+    let window = if end > start { b[start..end].to_vec() } else { vec![] };
+    Ok(Some((sub, window, start)))
+}
+
+fn find_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, reverse: bool, raise_if_missing: bool) {
+    let (b, _) = receiver(state, &mut args);
+    let position = match substring_arguments(state, call_stack, funcname, &b, args, kwargs) {
+        Err(()) => return,
+        Ok(None) => None,
+        Ok(Some((sub, window, start))) => {
+            let sub = match sub_argument(state, call_stack, &sub) {
+                Some(sub) => sub,
+                None => return,
+            };
+            let offset = if reverse { rfind_in(&window, &sub) } else { find_in(&window, &sub) };
+            offset.map(|offset| start + offset)
+        },
+    };
+    match position {
+        Some(position) => return_int(state, call_stack, position),
+        None if raise_if_missing => {
+            let exc = state.primitive_objects.valueerror.clone();
+            raise(state, call_stack, exc, "subsection not found".to_string())
+        },
+        None => {
+            let obj_ref = state.store.allocate(state.primitive_objects.new_int(-1));
+            return_value(call_stack, obj_ref)
+        },
+    }
+}
+
+pub fn find<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    find_impl(state, call_stack, "find", args, kwargs, false, false)
+}
+
+pub fn rfind<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    find_impl(state, call_stack, "rfind", args, kwargs, true, false)
+}
+
+pub fn index<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    find_impl(state, call_stack, "index", args, kwargs, false, true)
+}
+
+pub fn rindex<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    find_impl(state, call_stack, "rindex", args, kwargs, true, true)
+}
+
+pub fn count<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (b, _) = receiver(state, &mut args);
+    let (sub, window) = match substring_arguments(state, call_stack, "count", &b, args, kwargs) {
+        Err(()) => return,
+        Ok(None) => return return_int(state, call_stack, 0),
+        Ok(Some((sub, window, _))) => (sub, window),
+    };
+    let sub = match sub_argument(state, call_stack, &sub) {
+        Some(sub) => sub,
+        None => return,
+    };
+    if sub.is_empty() {
+        return return_int(state, call_stack, window.len() + 1)
+    }
+    let mut count = 0;
+    let mut rest = &window[..];
+    while let Some(start) = find_in(rest, &sub) {
+        count += 1;
+        rest = &rest[start+sub.len()..];
+    }
+    return_int(state, call_stack, count)
+}
+
+fn affix_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, suffix: bool) {
+    let (b, _) = receiver(state, &mut args);
+    let (affix, window) = match substring_arguments(state, call_stack, funcname, &b, args, kwargs) {
+        Err(()) => return,
+        Ok(None) => return return_bool(state, call_stack, false),
+        Ok(Some((affix, window, _))) => (affix, window),
+    };
+    // The prefix (or suffix) may be a tuple of candidates
+    let candidates = match state.store.deref(&affix).content {
+        ObjectContent::Tuple(ref v) => v.clone(),
+        _ => vec![affix.clone()],
+    };
+    for candidate in candidates {
+        let candidate = match state.store.deref(&candidate).content {
+            ObjectContent::Bytes(ref candidate) | ObjectContent::ByteArray(ref candidate) => candidate.clone(),
+            _ => {
+                let exc = state.primitive_objects.typeerror.clone();
+                let name = processor::type_name(&state.store, &candidate);
+                return raise(state, call_stack, exc, format!("{} first arg must be bytes or a tuple of bytes, not {}", funcname, name))
+            },
+        };
+        let found = if suffix { window.ends_with(&candidate) } else { window.starts_with(&candidate) };
+        if found {
+            return return_bool(state, call_stack, true)
+        }
+    }
+    return_bool(state, call_stack, false)
+}
+
+pub fn startswith<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    affix_impl(state, call_stack, "startswith", args, kwargs, false)
+}
+
+pub fn endswith<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    affix_impl(state, call_stack, "endswith", args, kwargs, true)
+}
+
+pub fn replace<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (b, bytearray) = receiver(state, &mut args);
+    check_no_kwargs!("replace", state, call_stack, kwargs);
+    let mut values = match bind_arguments(state, call_stack, "replace", &["old", "new", "count"], 2, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let old = match bytes_argument(state, call_stack, &values.remove(0).unwrap()) {
+        Some(old) => old,
+        None => return,
+    };
+    let new = match bytes_argument(state, call_stack, &values.remove(0).unwrap()) {
+        Some(new) => new,
+        None => return,
+    };
+    let count = match limit_argument(state, call_stack, values.remove(0)) {
+        Some(count) => count,
+        None => return,
+    };
+    let mut result = Vec::with_capacity(b.len());
+    let mut replaced = 0;
+    if old.is_empty() {
+        // The empty subsequence matches before each byte, and at the end.
+        for &c in b.iter() {
+            if replaced < count {
+                result.extend_from_slice(&new);
+                replaced += 1;
+            }
+            result.push(c);
+        }
+        if replaced < count {
+            result.extend_from_slice(&new);
+        }
+    }
+    else {
+        let mut rest = &b[..];
+        while replaced < count {
+            match find_in(rest, &old) {
+                Some(start) => {
+                    result.extend_from_slice(&rest[..start]);
+                    result.extend_from_slice(&new);
+                    rest = &rest[start+old.len()..];
+                    replaced += 1;
+                },
+                None => break,
+            }
+        }
+        result.extend_from_slice(rest);
+    }
+    return_bytes(state, call_stack, result, bytearray)
+}
+
+pub fn upper<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (b, bytearray) = receiver(state, &mut args);
+    if no_arguments(state, call_stack, "upper", &args, &kwargs).is_some() {
+        return_bytes(state, call_stack, b.to_ascii_uppercase(), bytearray)
+    }
+}
+
+pub fn lower<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (b, bytearray) = receiver(state, &mut args);
+    if no_arguments(state, call_stack, "lower", &args, &kwargs).is_some() {
+        return_bytes(state, call_stack, b.to_ascii_lowercase(), bytearray)
+    }
+}
+
+fn predicate_impl<EP: EnvProxy, F: Fn(&[u8]) -> bool>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, predicate: F) {
+    let (b, _) = receiver(state, &mut args);
+    if no_arguments(state, call_stack, funcname, &args, &kwargs).is_some() {
+        return_bool(state, call_stack, predicate(&b))
+    }
+}
+
+fn all_bytes<F: Fn(&u8) -> bool>(b: &[u8], predicate: F) -> bool {
+    !b.is_empty() && b.iter().all(predicate)
+}
+
+// Like str, true if there is at least one cased byte and all of them have the given case.
+fn is_case<F: Fn(&u8) -> bool, G: Fn(&u8) -> bool>(b: &[u8], has_case: F, has_other_case: G) -> bool {
+    b.iter().any(&has_case) && !b.iter().any(has_other_case)
+}
+
+pub fn isdigit<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "isdigit", args, kwargs, |b| all_bytes(b, u8::is_ascii_digit))
+}
+
+pub fn isalpha<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "isalpha", args, kwargs, |b| all_bytes(b, u8::is_ascii_alphabetic))
+}
+
+pub fn isalnum<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "isalnum", args, kwargs, |b| all_bytes(b, u8::is_ascii_alphanumeric))
+}
+
+pub fn isspace<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "isspace", args, kwargs, |b| all_bytes(b, |c| WHITESPACE.contains(c)))
+}
+
+pub fn isupper<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "isupper", args, kwargs, |b| is_case(b, u8::is_ascii_uppercase, u8::is_ascii_lowercase))
+}
+
+pub fn islower<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "islower", args, kwargs, |b| is_case(b, u8::is_ascii_lowercase, u8::is_ascii_uppercase))
+}
+
+fn partition_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, reverse: bool) {
+    let (b, bytearray) = receiver(state, &mut args);
+    check_no_kwargs!(funcname, state, call_stack, kwargs);
+    let sep = match bind_arguments(state, call_stack, funcname, &["sep"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let sep = match bytes_argument(state, call_stack, &sep) {
+        Some(sep) => sep,
+        None => return,
+    };
+    if sep.is_empty() {
+        return raise_empty_separator(state, call_stack)
+    }
+    let offset = if reverse { rfind_in(&b, &sep) } else { find_in(&b, &sep) };
+    let parts = match offset {
+        Some(offset) => vec![b[..offset].to_vec(), sep.clone(), b[offset+sep.len()..].to_vec()],
+        None if reverse => vec![vec![], vec![], b.clone()],
+        None => vec![b.clone(), vec![], vec![]],
+    };
+    let items = parts.into_iter().map(|b| state.store.allocate(new_bytes_object(state, b, bytearray))).collect();
+    let tuple = state.store.allocate(state.primitive_objects.new_tuple(items));
+    return_value(call_stack, tuple)
+}
+
+pub fn partition<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    partition_impl(state, call_stack, "partition", args, kwargs, false)
+}
+
+pub fn rpartition<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    partition_impl(state, call_stack, "rpartition", args, kwargs, true)
+}
+
+pub fn bytearray_init<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let bytearray = args.remove(0);
+    let mut values = match bind_arguments(state, call_stack, "bytearray", &["source", "encoding", "errors"], 0, args, kwargs) {
+        Some(values) => values,
+        None => return,
+    };
+    let b = match source_bytes(state, call_stack, values.remove(0), values.remove(0), values.remove(0)) {
+        Some(b) => b,
+        None => return,
+    };
+    set_bytearray(state, &bytearray, b);
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+pub fn append<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (bytearray, mut b) = bytearray_receiver(state, &mut args);
+    check_no_kwargs!("append", state, call_stack, kwargs);
+    let item = match bind_arguments(state, call_stack, "append", &["item"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let item = match byte_argument(state, call_stack, &item) {
+        Some(item) => item,
+        None => return,
+    };
+    b.push(item);
+    set_bytearray(state, &bytearray, b);
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+pub fn extend<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (bytearray, mut b) = bytearray_receiver(state, &mut args);
+    check_no_kwargs!("extend", state, call_stack, kwargs);
+    let iterable = match bind_arguments(state, call_stack, "extend", &["iterable_of_ints"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let extension = match state.store.deref(&iterable).content {
+        ObjectContent::Bytes(ref other) | ObjectContent::ByteArray(ref other) => Some(other.clone()),
+        _ => None,
+    };
+    match extension {
+        Some(other) => b.extend(other),
+        None => {
+            let items = match container_items(state, call_stack, &iterable) {
+                Some(items) => items,
+                None => return,
+            };
+            for item in items {
+                match byte_argument(state, call_stack, &item) {
+                    Some(item) => b.push(item),
+                    None => return,
+                }
+            }
+        },
+    }
+    set_bytearray(state, &bytearray, b);
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+pub fn insert<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (bytearray, mut b) = bytearray_receiver(state, &mut args);
+    check_no_kwargs!("insert", state, call_stack, kwargs);
+    let mut values = match bind_arguments(state, call_stack, "insert", &["index", "item"], 2, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let index = match int_argument(state, call_stack, values.remove(0), 0) {
+        Some(index) => index,
+        None => return,
+    };
+    let item = match byte_argument(state, call_stack, &values.remove(0).unwrap()) {
+        Some(item) => item,
+        None => return,
+    };
+    // Out-of-range indices insert at the beginning or at the end, like list.insert
+    let index = match signed_index(index, b.len()) {
+        Some(index) if index > b.len() => b.len(),
+        Some(index) => index,
+        None => 0,
+    };
+    b.insert(index, item);
+    set_bytearray(state, &bytearray, b);
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+pub fn pop<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (bytearray, mut b) = bytearray_receiver(state, &mut args);
+    check_no_kwargs!("pop", state, call_stack, kwargs);
+    let index = match bind_arguments(state, call_stack, "pop", &["index"], 0, args, vec![]) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let index = match int_argument(state, call_stack, index, -1) {
+        Some(index) => index,
+        None => return,
+    };
+    if b.is_empty() {
+        let exc = state.primitive_objects.indexerror.clone();
+        return raise(state, call_stack, exc, "pop from empty bytearray".to_string())
+    }
+    let index = match signed_index(index, b.len()) {
+        Some(index) if index < b.len() => index,
+        _ => {
+            let exc = state.primitive_objects.indexerror.clone();
+            return raise(state, call_stack, exc, "pop index out of range".to_string())
+        },
+    };
+    let item = b.remove(index);
+    set_bytearray(state, &bytearray, b);
+    return_int(state, call_stack, item as usize)
+}
+
+pub fn remove<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (bytearray, mut b) = bytearray_receiver(state, &mut args);
+    check_no_kwargs!("remove", state, call_stack, kwargs);
+    let value = match bind_arguments(state, call_stack, "remove", &["value"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let value = match byte_argument(state, call_stack, &value) {
+        Some(value) => value,
+        None => return,
+    };
+    match b.iter().position(|&c| c == value) {
+        Some(index) => { b.remove(index); },
+        None => {
+            let exc = state.primitive_objects.valueerror.clone();
+            return raise(state, call_stack, exc, "value not found in bytearray".to_string())
+        },
+    }
+    set_bytearray(state, &bytearray, b);
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+pub fn clear<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (bytearray, _) = bytearray_receiver(state, &mut args);
+    if no_arguments(state, call_stack, "clear", &args, &kwargs).is_some() {
+        set_bytearray(state, &bytearray, vec![]);
+        return_value(call_stack, state.primitive_objects.none.clone())
+    }
+}
+
+pub fn copy<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (_, b) = bytearray_receiver(state, &mut args);
+    if no_arguments(state, call_stack, "copy", &args, &kwargs).is_some() {
+        return_bytes(state, call_stack, b, true)
+    }
+}
+
+pub fn reverse<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (bytearray, mut b) = bytearray_receiver(state, &mut args);
+    if no_arguments(state, call_stack, "reverse", &args, &kwargs).is_some() {
+        b.reverse();
+        set_bytearray(state, &bytearray, b);
+        return_value(call_stack, state.primitive_objects.none.clone())
+    }
+}
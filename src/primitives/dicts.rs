@@ -0,0 +1,400 @@
+//! Lookups in dicts, and native methods of `dict`.
+//!
+//! Keys are found with their `__hash__` and `__eq__` methods. Dicts bump their
+//! version when a key is added or removed, but not when a value is replaced,
+//! so iterators only fail when the size of the dict changes, like in CPython.
+
+use super::super::sandbox::EnvProxy;
+use super::super::state::{State, PyResult, raise, return_value};
+use super::super::objects::{ObjectRef, ObjectContent, Object, DictContent, DictView};
+use super::super::processor::frame::Frame;
+use super::super::processor;
+use super::super::processor::call_function_sync;
+use super::{bind_arguments, container_items, object_hash, hash_bytes};
+
+/// Copy of the content of a dict object.
+fn dict_content<EP: EnvProxy>(state: &State<EP>, dict_ref: &ObjectRef) -> DictContent {
+    match state.store.deref(dict_ref).content {
+        ObjectContent::Dict(ref d) => d.clone(),
+        _ => panic!("dict operation on a non-dict object"),
+    }
+}
+
+// Applies a change to the content of a dict; bumps its version if the change
+// adds or removes keys.
+fn update_content<EP: EnvProxy, T, F: FnOnce(&mut DictContent) -> T>(state: &mut State<EP>, dict_ref: &ObjectRef, change: F) -> T {
+    let obj = state.store.deref_mut(dict_ref);
+    let (result, resized) = match obj.content {
+        ObjectContent::Dict(ref mut d) => {
+            let len = d.len();
+            let result = change(d);
+            (result, d.len() != len)
+        },
+        _ => panic!("dict operation on a non-dict object"),
+    };
+    if resized {
+        obj.bump_version();
+    }
+    result
+}
+
+/// Hash of a key and, if the key is in the dict, the position of its entry.
+/// Returns None if an exception was raised.
+pub fn lookup<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, dict: &DictContent, key: &ObjectRef) -> Option<(u32, Option<usize>)> {
+    let hash = object_hash(state, call_stack, key)?;
+    lookup_hashed(state, call_stack, dict, hash, key).map(|position| (hash, position))
+}
+
+/// Like lookup, for a key whose hash is already known.
+pub fn lookup_hashed<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, dict: &DictContent, hash: u32, key: &ObjectRef) -> Option<Option<usize>> {
+    find_key(state, call_stack, candidates(dict, hash), key)
+}
+
+/// Like lookup, in the table of a dict, set or frozenset object.
+pub fn lookup_in<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef, key: &ObjectRef) -> Option<(u32, Option<usize>)> {
+    let hash = object_hash(state, call_stack, key)?;
+    let candidates = match state.store.deref(obj_ref).content {
+        ObjectContent::Dict(ref d) | ObjectContent::Set(ref d) | ObjectContent::FrozenSet(ref d) => candidates(d, hash),
+        _ => panic!("lookup in an object which is not a dict or a set"),
+    };
+    find_key(state, call_stack, candidates, key).map(|position| (hash, position))
+}
+
+// Positions and keys of the entries whose key has this hash. They are copied
+// out of the table, because comparing keys may run Python code.
+fn candidates(dict: &DictContent, hash: u32) -> Vec<(usize, ObjectRef)> {
+    dict.positions(hash).into_iter().map(|position| (position, dict.get(position).unwrap().key.clone())).collect()
+}
+
+// Position of the first candidate whose key is equal to this key.
+fn find_key<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, candidates: Vec<(usize, ObjectRef)>, key: &ObjectRef) -> Option<Option<usize>> {
+    for (position, candidate) in candidates {
+        if candidate.is(key) || processor::objects_equal(state, call_stack, &candidate, key)? {
+            return Some(Some(position))
+        }
+    }
+    Some(None)
+}
+
+/// Value of a key in a dict object; the inner option is None if the key is missing.
+pub fn get_item<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, dict_ref: &ObjectRef, key: &ObjectRef) -> Option<Option<ObjectRef>> {
+    let (_, position) = lookup_in(state, call_stack, dict_ref, key)?;
+    match state.store.deref(dict_ref).content {
+        ObjectContent::Dict(ref d) => Some(position.and_then(|position| d.get(position)).map(|entry| entry.value.clone())),
+        _ => panic!("dict operation on a non-dict object"),
+    }
+}
+
+pub fn set_item<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, dict_ref: &ObjectRef, key: ObjectRef, value: ObjectRef) -> Option<()> {
+    let (hash, position) = lookup_in(state, call_stack, dict_ref, &key)?;
+    update_content(state, dict_ref, |d| match position {
+        Some(position) => d.set_value(position, value),
+        None => d.push(hash, key, value),
+    });
+    Some(())
+}
+
+/// Removes a key from a dict object and returns its value; the inner option is
+/// None if the key is missing.
+pub fn remove_item<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, dict_ref: &ObjectRef, key: &ObjectRef) -> Option<Option<ObjectRef>> {
+    let (_, position) = lookup_in(state, call_stack, dict_ref, key)?;
+    Some(position.and_then(|position| update_content(state, dict_ref, |d| d.remove(position))).map(|entry| entry.value))
+}
+
+/// Content of a dict whose keys are distinct strs, built without running Python code
+/// (used for namespaces and keyword arguments).
+pub fn string_keyed_dict<EP: EnvProxy>(state: &State<EP>, items: Vec<(ObjectRef, ObjectRef)>) -> DictContent {
+    let mut dict = DictContent::new();
+    for (key, value) in items {
+        let hash = match state.store.deref(&key).content {
+            ObjectContent::String(ref s) => hash_bytes(s.as_bytes()),
+            _ => panic!("string_keyed_dict with a non-str key"),
+        };
+        dict.push(hash, key, value);
+    }
+    dict
+}
+
+pub fn new_string_dict<EP: EnvProxy>(state: &mut State<EP>, items: Vec<(String, ObjectRef)>) -> ObjectRef {
+    let items = items.into_iter().map(|(key, value)| (state.store.allocate(state.primitive_objects.new_string(key)), value)).collect();
+    let dict = string_keyed_dict(state, items);
+    state.store.allocate(state.primitive_objects.new_dict(dict))
+}
+
+/// Content of a dict with these items; later values of equal keys replace earlier ones.
+pub fn dict_from_pairs<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, pairs: Vec<(ObjectRef, ObjectRef)>) -> Option<DictContent> {
+    let mut dict = DictContent::new();
+    for (key, value) in pairs {
+        match lookup(state, call_stack, &dict, &key)? {
+            (_, Some(position)) => dict.set_value(position, value),
+            (hash, None) => dict.push(hash, key, value),
+        }
+    }
+    Some(dict)
+}
+
+/// Items of the argument of dict() and dict.update(): either a mapping (a dict,
+/// or an object with a keys() method), or an iterable of pairs.
+fn update_pairs<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, source: &ObjectRef) -> Option<Vec<(ObjectRef, ObjectRef)>> {
+    if let ObjectContent::Dict(ref d) = state.store.deref(source).content {
+        return Some(d.items())
+    }
+    if let Some(keys_method) = processor::lookup_special_method(state, source, "keys") {
+        let keys = match call_function_sync(state, call_stack, &keys_method, vec![], vec![]) {
+            PyResult::Return(keys) => keys,
+            PyResult::Raised => return None,
+        };
+        let mut pairs = Vec::new();
+        for key in container_items(state, call_stack, &keys)? {
+            match processor::getitem_sync(state, call_stack, source.clone(), key.clone()) {
+                PyResult::Return(value) => pairs.push((key, value)),
+                PyResult::Raised => return None,
+            }
+        }
+        return Some(pairs)
+    }
+    let mut pairs = Vec::new();
+    for (i, item) in container_items(state, call_stack, source)?.into_iter().enumerate() {
+        let is_iterable = match state.store.deref(&item).content {
+            ObjectContent::Tuple(_) | ObjectContent::List(_) => true,
+            _ => processor::lookup_special_method(state, &item, "__iter__").is_some(),
+        };
+        if !is_iterable {
+            let exc = state.primitive_objects.typeerror.clone();
+            raise(state, call_stack, exc, format!("cannot convert dictionary update sequence element #{} to a sequence", i));
+            return None
+        }
+        let mut pair = container_items(state, call_stack, &item)?;
+        if pair.len() != 2 {
+            let exc = state.primitive_objects.valueerror.clone();
+            raise(state, call_stack, exc, format!("dictionary update sequence element #{} has length {}; 2 is required", i, pair.len()));
+            return None
+        }
+        let value = pair.pop().unwrap();
+        let key = pair.pop().unwrap();
+        pairs.push((key, value));
+    }
+    Some(pairs)
+}
+
+// Common part of dict() and dict.update()
+fn update_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, dict_ref: &ObjectRef, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> Option<()> {
+    if args.len() > 1 {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{} expected at most 1 arguments, got {}", funcname, args.len()));
+        return None
+    }
+    let mut pairs = match args.pop() {
+        Some(source) => update_pairs(state, call_stack, &source)?,
+        None => vec![],
+    };
+    pairs.extend(kwargs);
+    for (key, value) in pairs {
+        set_item(state, call_stack, dict_ref, key, value)?;
+    }
+    Some(())
+}
+
+pub fn dict_init<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let dict = args.remove(0);
+    if update_impl(state, call_stack, "dict", &dict, args, kwargs).is_some() {
+        return_value(call_stack, state.primitive_objects.none.clone())
+    }
+}
+
+pub fn update<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let dict = args.remove(0);
+    if update_impl(state, call_stack, "update", &dict, args, kwargs).is_some() {
+        return_value(call_stack, state.primitive_objects.none.clone())
+    }
+}
+
+fn raise_key_error<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, key: &ObjectRef) {
+    let exc = state.primitive_objects.keyerror.clone();
+    let repr = key.repr(&state.store);
+    raise(state, call_stack, exc, repr)
+}
+
+pub fn get<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let dict = args.remove(0);
+    check_no_kwargs!("get", state, call_stack, kwargs);
+    let mut values = match bind_arguments(state, call_stack, "get", &["key", "default"], 1, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let key = values.remove(0).unwrap();
+    let default = values.remove(0).unwrap_or(state.primitive_objects.none.clone());
+    if let Some(value) = get_item(state, call_stack, &dict, &key) {
+        return_value(call_stack, value.unwrap_or(default))
+    }
+}
+
+pub fn setdefault<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let dict = args.remove(0);
+    check_no_kwargs!("setdefault", state, call_stack, kwargs);
+    let mut values = match bind_arguments(state, call_stack, "setdefault", &["key", "default"], 1, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let key = values.remove(0).unwrap();
+    let default = values.remove(0).unwrap_or(state.primitive_objects.none.clone());
+    match get_item(state, call_stack, &dict, &key) {
+        None => (),
+        Some(Some(value)) => return_value(call_stack, value),
+        Some(None) => {
+            if set_item(state, call_stack, &dict, key, default.clone()).is_none() {
+                return
+            }
+            return_value(call_stack, default)
+        },
+    }
+}
+
+pub fn pop<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let dict = args.remove(0);
+    check_no_kwargs!("pop", state, call_stack, kwargs);
+    let mut values = match bind_arguments(state, call_stack, "pop", &["key", "default"], 1, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let key = values.remove(0).unwrap();
+    let default = values.remove(0);
+    match (remove_item(state, call_stack, &dict, &key), default) {
+        (None, _) => (),
+        (Some(Some(value)), _) | (Some(None), Some(value)) => return_value(call_stack, value),
+        (Some(None), None) => raise_key_error(state, call_stack, &key),
+    }
+}
+
+pub fn popitem<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let dict = args.remove(0);
+    check_no_kwargs!("popitem", state, call_stack, kwargs);
+    let _ = match bind_arguments(state, call_stack, "popitem", &[], 0, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    match update_content(state, &dict, |d| d.pop_last()) {
+        Some(entry) => {
+            let item = state.store.allocate(state.primitive_objects.new_tuple(vec![entry.key, entry.value]));
+            return_value(call_stack, item)
+        },
+        None => {
+            let exc = state.primitive_objects.keyerror.clone();
+            raise(state, call_stack, exc, "'popitem(): dictionary is empty'".to_string())
+        },
+    }
+}
+
+fn view_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, view: DictView) {
+    let dict = args.remove(0);
+    check_no_kwargs!(funcname, state, call_stack, kwargs);
+    let _ = match bind_arguments(state, call_stack, funcname, &[], 0, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let class = match view {
+        DictView::Keys => state.primitive_objects.dict_keys_type.clone(),
+        DictView::Values => state.primitive_objects.dict_values_type.clone(),
+        DictView::Items => state.primitive_objects.dict_items_type.clone(),
+    };
+    let view = state.store.allocate(Object::new_instance(None, class, ObjectContent::DictView(dict, view)));
+    return_value(call_stack, view)
+}
+
+pub fn keys<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    view_impl(state, call_stack, "keys", args, kwargs, DictView::Keys)
+}
+
+pub fn values<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    view_impl(state, call_stack, "values", args, kwargs, DictView::Values)
+}
+
+pub fn items<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    view_impl(state, call_stack, "items", args, kwargs, DictView::Items)
+}
+
+/// Class method building a dict from keys, which all have the same value.
+pub fn fromkeys<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("fromkeys", state, call_stack, args);
+    check_no_kwargs!("fromkeys", state, call_stack, kwargs);
+    let mut values = match bind_arguments(state, call_stack, "fromkeys", &["iterable", "value"], 1, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let iterable = values.remove(0).unwrap();
+    let value = values.remove(0).unwrap_or(state.primitive_objects.none.clone());
+    let keys = match container_items(state, call_stack, &iterable) {
+        Some(keys) => keys,
+        None => return,
+    };
+    let dict = match call_function_sync(state, call_stack, &cls, vec![], vec![]) {
+        PyResult::Return(dict) => dict,
+        PyResult::Raised => return,
+    };
+    let setitem = processor::lookup_special_method(state, &dict, "__setitem__");
+    for key in keys {
+        let res = match setitem {
+            Some(ref method) => match call_function_sync(state, call_stack, method, vec![key, value.clone()], vec![]) {
+                PyResult::Return(_) => Some(()),
+                PyResult::Raised => None,
+            },
+            None => match state.store.deref(&dict).content {
+                ObjectContent::Dict(_) => set_item(state, call_stack, &dict, key, value.clone()),
+                _ => {
+                    let exc = state.primitive_objects.typeerror.clone();
+                    let name = processor::type_name(&state.store, &dict);
+                    raise(state, call_stack, exc, format!("'{}' object does not support item assignment", name));
+                    None
+                },
+            },
+        };
+        if res.is_none() {
+            return
+        }
+    }
+    return_value(call_stack, dict)
+}
+
+pub fn copy<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let dict = args.remove(0);
+    check_no_kwargs!("copy", state, call_stack, kwargs);
+    let _ = match bind_arguments(state, call_stack, "copy", &[], 0, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let content = dict_content(state, &dict);
+    let copy = state.store.allocate(state.primitive_objects.new_dict(content));
+    return_value(call_stack, copy)
+}
+
+pub fn clear<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let dict = args.remove(0);
+    check_no_kwargs!("clear", state, call_stack, kwargs);
+    let _ = match bind_arguments(state, call_stack, "clear", &[], 0, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    update_content(state, &dict, |d| d.clear());
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+/// Whether a view of a dict contains an item (a key, a value, or a (key, value) pair).
+pub fn view_contains<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, dict_ref: &ObjectRef, view: DictView, item: &ObjectRef) -> Option<bool> {
+    match view {
+        DictView::Keys => get_item(state, call_stack, dict_ref, item).map(|value| value.is_some()),
+        DictView::Values => {
+            let values: Vec<ObjectRef> = dict_content(state, dict_ref).iter().map(|entry| entry.value.clone()).collect();
+            processor::position_in(state, call_stack, values.iter(), item).map(|position| position.is_some())
+        },
+        DictView::Items => {
+            let pair = match state.store.deref(item).content {
+                ObjectContent::Tuple(ref v) if v.len() == 2 => (v[0].clone(), v[1].clone()),
+                _ => return Some(false),
+            };
+            match get_item(state, call_stack, dict_ref, &pair.0)? {
+                Some(value) => processor::objects_equal(state, call_stack, &value, &pair.1),
+                None => Some(false),
+            }
+        },
+    }
+}
@@ -0,0 +1,954 @@
+//! String formatting: the format specification mini-language, shared by
+//! `format()`, `__format__`, `str.format` and f-strings; and printf-style
+//! formatting with the `%` operator.
+
+use super::super::sandbox::EnvProxy;
+use super::super::state::{State, PyResult, raise, return_value};
+use super::super::objects::{ObjectRef, ObjectContent, float_repr};
+use super::super::processor::frame::Frame;
+use super::super::processor;
+use super::super::processor::call_function_sync;
+use super::{bind_arguments, numbers};
+
+/// A parsed format specification:
+/// `[[fill]align][sign][#][0][width][grouping][.precision][type]`
+#[derive(Debug)]
+pub struct FormatSpec {
+    pub fill: char,
+    pub align: Option<char>,
+    pub sign: Option<char>,
+    pub alternate: bool,
+    pub width: usize,
+    pub grouping: Option<char>,
+    pub precision: Option<usize>,
+    pub type_: Option<char>,
+}
+
+impl FormatSpec {
+    pub fn parse(spec: &str) -> Result<FormatSpec, String> {
+        let chars: Vec<char> = spec.chars().collect();
+        let is_align = |c: char| c == '<' || c == '>' || c == '=' || c == '^';
+        let mut res = FormatSpec { fill: ' ', align: None, sign: None, alternate: false, width: 0, grouping: None, precision: None, type_: None };
+        let mut fill_given = false;
+        let mut i = 0;
+        if chars.len() >= 2 && is_align(chars[1]) {
+            res.fill = chars[0];
+            res.align = Some(chars[1]);
+            fill_given = true;
+            i = 2;
+        }
+        else if !chars.is_empty() && is_align(chars[0]) {
+            res.align = Some(chars[0]);
+            i = 1;
+        }
+        if i < chars.len() && (chars[i] == '+' || chars[i] == '-' || chars[i] == ' ') {
+            res.sign = Some(chars[i]);
+            i += 1;
+        }
+        if i < chars.len() && chars[i] == '#' {
+            res.alternate = true;
+            i += 1;
+        }
+        if i < chars.len() && chars[i] == '0' {
+            // Zero-padding, unless an explicit fill character was given
+            if !fill_given {
+                res.fill = '0';
+                if res.align.is_none() {
+                    res.align = Some('=');
+                }
+            }
+            i += 1;
+        }
+        let (width, next) = parse_number(&chars, i);
+        res.width = width.unwrap_or(0);
+        i = next;
+        if i < chars.len() && (chars[i] == ',' || chars[i] == '_') {
+            res.grouping = Some(chars[i]);
+            i += 1;
+        }
+        if i < chars.len() && chars[i] == '.' {
+            let (precision, next) = parse_number(&chars, i+1);
+            if precision.is_none() {
+                return Err("Format specifier missing precision".to_string())
+            }
+            res.precision = precision;
+            i = next;
+        }
+        match chars.len() - i {
+            0 => (),
+            1 => res.type_ = Some(chars[i]),
+            _ => return Err("Invalid format specifier".to_string()),
+        }
+        Ok(res)
+    }
+
+    fn pad(&self, prefix: &str, body: &str, default_align: char) -> String {
+        let length = prefix.chars().count() + body.chars().count();
+        let padding = self.width.saturating_sub(length);
+        let fill = |n: usize| self.fill.to_string().repeat(n);
+        match self.align.unwrap_or(default_align) {
+            '<' => format!("{}{}{}", prefix, body, fill(padding)),
+            '^' => format!("{}{}{}{}", fill(padding / 2), prefix, body, fill(padding - padding / 2)),
+            '=' => format!("{}{}{}", prefix, fill(padding), body),
+            _ => format!("{}{}{}", fill(padding), prefix, body),
+        }
+    }
+}
+
+// Parses a decimal number at position i, returns it and the position after it.
+fn parse_number(chars: &[char], mut i: usize) -> (Option<usize>, usize) {
+    let mut number = None;
+    while i < chars.len() {
+        match chars[i].to_digit(10) {
+            Some(digit) => number = Some(number.unwrap_or(0usize).saturating_mul(10).saturating_add(digit as usize)),
+            None => break,
+        }
+        i += 1;
+    }
+    (number, i)
+}
+
+// Inserts a separator between groups of digits.
+fn group_digits(digits: &str, separator: char, group_size: usize) -> String {
+    let mut res = String::with_capacity(digits.len() * 2);
+    for (i, c) in digits.chars().enumerate() {
+        if i != 0 && (digits.len() - i).is_multiple_of(group_size) {
+            res.push(separator);
+        }
+        res.push(c);
+    }
+    res
+}
+
+// Scientific notation with `precision` digits after the point, rounded half to even.
+fn scientific(value: f64, precision: usize, strip_zeros: bool, alternate: bool) -> String {
+    // Rust writes the exponent without sign or padding: 1.5e3, 1.5e-7
+    let formatted = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = formatted.split_at(formatted.find('e').unwrap());
+    let exponent: i32 = exponent[1..].parse().unwrap();
+    let mantissa = if strip_zeros && mantissa.contains('.') { mantissa.trim_end_matches('0').trim_end_matches('.') } else { mantissa };
+    let point = if alternate && !mantissa.contains('.') { "." } else { "" };
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}{}e{}{:02}", mantissa, point, exponent_sign, exponent.abs())
+}
+
+// Fixed-point notation with `precision` digits after the point, rounded half to even.
+fn fixed(value: f64, precision: usize, alternate: bool) -> String {
+    let point = if precision == 0 && alternate { "." } else { "" };
+    format!("{:.*}{}", precision, value, point)
+}
+
+// General format ('g'): fixed-point or scientific notation depending on the
+// exponent, with `precision` significant digits. Without the type, fixed-point
+// numbers keep at least one digit after the point, which must fit in the precision.
+fn general(value: f64, precision: usize, alternate: bool, keep_point: bool) -> String {
+    let precision = if precision == 0 { 1 } else { precision };
+    // The exponent after rounding to the significant digits
+    let formatted = format!("{:.*e}", precision - 1, value);
+    let exponent: i32 = formatted[formatted.find('e').unwrap()+1..].parse().unwrap();
+    let max_exponent = if keep_point { precision as i32 - 1 } else { precision as i32 };
+    if exponent < -4 || exponent >= max_exponent {
+        return scientific(value, precision - 1, !alternate, alternate)
+    }
+    let res = fixed(value, (precision as i32 - 1 - exponent) as usize, alternate);
+    if alternate {
+        res
+    }
+    else if res.contains('.') {
+        let res = res.trim_end_matches('0');
+        if res.ends_with('.') {
+            if keep_point { format!("{}0", res) } else { res.trim_end_matches('.').to_string() }
+        }
+        else {
+            res.to_string()
+        }
+    }
+    else if keep_point {
+        format!("{}.0", res)
+    }
+    else {
+        res
+    }
+}
+
+// Body of a finite, positive float formatted with a presentation type
+// ('e', 'f', 'g', 'n', '%' or none).
+fn float_body(value: f64, type_: Option<char>, precision: Option<usize>, alternate: bool) -> String {
+    match (type_, precision) {
+        (None, None) => float_repr(value),
+        (None, Some(precision)) => general(value, precision, alternate, true),
+        (Some('e'), _) => scientific(value, precision.unwrap_or(6), false, alternate),
+        (Some('f'), _) => fixed(value, precision.unwrap_or(6), alternate),
+        (Some('%'), _) => format!("{}%", fixed(value * 100., precision.unwrap_or(6), alternate)),
+        _ => general(value, precision.unwrap_or(6), alternate, false), // 'g' and 'n'
+    }
+}
+
+/// Formats a float according to a format specification.
+pub fn format_float(value: f64, spec: &FormatSpec) -> Result<String, String> {
+    match spec.type_ {
+        None | Some('e') | Some('E') | Some('f') | Some('F') | Some('g') | Some('G') | Some('n') | Some('%') => (),
+        Some(type_) => return Err(format!("Unknown format code '{}' for object of type 'float'", type_)),
+    }
+    let lower_type = spec.type_.map(|type_| type_.to_lowercase().next().unwrap());
+    let body = if value.is_nan() {
+        "nan".to_string()
+    }
+    else if value.is_infinite() {
+        if lower_type == Some('%') { "inf%".to_string() } else { "inf".to_string() }
+    }
+    else {
+        float_body(value.abs(), lower_type, spec.precision, spec.alternate)
+    };
+    let body = match spec.grouping {
+        Some(separator) if spec.type_ == Some('n') => {
+            return Err(format!("Cannot specify '{}' with 'n'.", separator))
+        },
+        Some(separator) if value.is_finite() => {
+            // Only the integer part is grouped
+            let end = body.find(|c: char| !c.is_ascii_digit()).unwrap_or(body.len());
+            format!("{}{}", group_digits(&body[..end], separator, 3), &body[end..])
+        },
+        _ => body,
+    };
+    let body = if matches!(spec.type_, Some('E') | Some('F') | Some('G')) { body.to_uppercase() } else { body };
+    let negative = value.is_sign_negative() && !value.is_nan();
+    Ok(spec.pad(sign(negative, spec.sign), &body, '>'))
+}
+
+/// Formats an integer according to a format specification.
+pub fn format_int(value: i64, spec: &FormatSpec, type_name: &str) -> Result<String, String> {
+    let type_ = spec.type_.unwrap_or('d');
+    let unknown = || Err(format!("Unknown format code '{}' for object of type '{}'", type_, type_name));
+    let is_float_type = matches!(type_, 'e' | 'E' | 'f' | 'F' | 'g' | 'G' | '%');
+    if spec.precision.is_some() && !is_float_type {
+        return Err("Precision not allowed in integer format specifier".to_string())
+    }
+    if type_ == 'c' {
+        if spec.sign.is_some() {
+            return Err("Sign not allowed with integer format specifier 'c'".to_string())
+        }
+        if spec.alternate {
+            return Err("Alternate form (#) not allowed with integer format specifier 'c'".to_string())
+        }
+    }
+    let magnitude = value.unsigned_abs();
+    let (prefix, body, group_size) = match type_ {
+        'd' | 'n' => ("", magnitude.to_string(), 3),
+        'b' => ("0b", format!("{:b}", magnitude), 4),
+        'o' => ("0o", format!("{:o}", magnitude), 4),
+        'x' => ("0x", format!("{:x}", magnitude), 4),
+        'X' => ("0X", format!("{:X}", magnitude), 4),
+        'c' => {
+            match char_of(value) {
+                Some(c) => ("", c.to_string(), 0),
+                None => return Err("%c arg not in range(0x110000)".to_string()),
+            }
+        },
+        // Like CPython, ints are converted to floats for the float presentation types.
+        _ if is_float_type => return format_float(value as f64, spec),
+        _ => return unknown(),
+    };
+    let body = match spec.grouping {
+        None => body,
+        Some(separator) if type_ == 'c' || (group_size == 4 && separator == ',') || type_ == 'n' => {
+            return Err(format!("Cannot specify '{}' with '{}'.", separator, type_))
+        },
+        Some(separator) => group_digits(&body, separator, group_size),
+    };
+    let prefix = if spec.alternate { prefix } else { "" };
+    Ok(spec.pad(&format!("{}{}", sign(value < 0, spec.sign), prefix), &body, '>'))
+}
+
+// Sign of a formatted number, given by the sign option if it is not negative.
+fn sign(negative: bool, option: Option<char>) -> &'static str {
+    match option {
+        _ if negative => "-",
+        Some('+') => "+",
+        Some(' ') => " ",
+        _ => "",
+    }
+}
+
+// Character of a code point, or None if it is not a valid one.
+fn char_of(code: i64) -> Option<char> {
+    if code >= 0 && code <= u32::MAX as i64 { ::std::char::from_u32(code as u32) } else { None }
+}
+
+/// Formats a string according to a format specification.
+pub fn format_str(s: &str, spec: &FormatSpec) -> Result<String, String> {
+    match spec.type_ {
+        None | Some('s') => (),
+        Some(type_) => return Err(format!("Unknown format code '{}' for object of type 'str'", type_)),
+    }
+    if spec.sign.is_some() {
+        return Err("Sign not allowed in string format specifier".to_string())
+    }
+    if spec.alternate {
+        return Err("Alternate form (#) not allowed in string format specifier".to_string())
+    }
+    if let Some(separator) = spec.grouping {
+        return Err(format!("Cannot specify '{}' with 's'.", separator))
+    }
+    if spec.align == Some('=') {
+        return Err("'=' alignment not allowed in string format specifier".to_string())
+    }
+    let s = match spec.precision {
+        Some(precision) => s.chars().take(precision).collect(),
+        None => s.to_string(),
+    };
+    Ok(spec.pad("", &s, '<'))
+}
+
+/// Escapes the non-ASCII characters of a repr, like ascii() does.
+pub fn ascii_escape(repr: &str) -> String {
+    let mut res = String::with_capacity(repr.len());
+    for c in repr.chars() {
+        let code = c as u32;
+        if code < 0x80 { res.push(c) }
+        else if code < 0x100 { res.push_str(&format!("\\x{:02x}", code)) }
+        else if code < 0x10000 { res.push_str(&format!("\\u{:04x}", code)) }
+        else { res.push_str(&format!("\\U{:08x}", code)) }
+    }
+    res
+}
+
+/// Applies a conversion (`!s`, `!r` or `!a`) to an object.
+/// Returns None if an exception was raised.
+pub fn convert<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef, conversion: char) -> Option<String> {
+    match conversion {
+        's' => processor::str_of(state, call_stack, obj_ref),
+        'r' => processor::repr_of(state, call_stack, obj_ref),
+        'a' => processor::repr_of(state, call_stack, obj_ref).map(|repr| ascii_escape(&repr)),
+        _ => {
+            let exc = state.primitive_objects.valueerror.clone();
+            raise(state, call_stack, exc, format!("Unknown conversion specifier {}", conversion));
+            None
+        },
+    }
+}
+
+/// format(value, spec) from native code: calls the __format__ method of the value.
+/// Returns None if an exception was raised.
+pub fn format_value<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, value: &ObjectRef, spec: &str) -> Option<String> {
+    let method = processor::lookup_special_method(state, value, "__format__").unwrap(); // object.__format__ always exists
+    let spec = state.store.allocate(state.primitive_objects.new_string(spec.to_string()));
+    let res = match call_function_sync(state, call_stack, &method, vec![spec], vec![]) {
+        PyResult::Return(res) => res,
+        PyResult::Raised => return None,
+    };
+    if let ObjectContent::String(ref s) = state.store.deref(&res).content {
+        return Some(s.clone())
+    }
+    let exc = state.primitive_objects.typeerror.clone();
+    let name = processor::type_name(&state.store, &res);
+    raise(state, call_stack, exc, format!("__format__ must return a str, not {}", name));
+    None
+}
+
+fn raise_value_error<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, message: String) {
+    let exc = state.primitive_objects.valueerror.clone();
+    raise(state, call_stack, exc, message)
+}
+
+fn return_string<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, s: String) {
+    let obj_ref = state.store.allocate(state.primitive_objects.new_string(s));
+    return_value(call_stack, obj_ref)
+}
+
+// Content of the format_spec argument of format() and __format__
+fn spec_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, spec: Option<ObjectRef>) -> Option<String> {
+    let spec = match spec {
+        None => return Some(String::new()),
+        Some(spec) => spec,
+    };
+    if let ObjectContent::String(ref s) = state.store.deref(&spec).content {
+        return Some(s.clone())
+    }
+    let exc = state.primitive_objects.typeerror.clone();
+    let name = processor::type_name(&state.store, &spec);
+    raise(state, call_stack, exc, format!("{}() argument 2 must be str, not {}", funcname, name));
+    None
+}
+
+pub fn format<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let mut values = match bind_arguments(state, call_stack, "format", &["value", "format_spec"], 1, args, kwargs) {
+        Some(values) => values,
+        None => return,
+    };
+    let value = values.remove(0).unwrap();
+    let spec = match spec_argument(state, call_stack, "format", values.remove(0)) {
+        Some(spec) => spec,
+        None => return,
+    };
+    if let Some(s) = format_value(state, call_stack, &value, &spec) {
+        return_string(state, call_stack, s)
+    }
+}
+
+pub fn object_format<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let obj_ref = args.remove(0);
+    let spec = match bind_arguments(state, call_stack, "__format__", &["format_spec"], 1, args, kwargs) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let spec = match spec_argument(state, call_stack, "__format__", spec) {
+        Some(spec) => spec,
+        None => return,
+    };
+    if !spec.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        let name = processor::type_name(&state.store, &obj_ref);
+        return raise(state, call_stack, exc, format!("unsupported format string passed to {}.__format__", name))
+    }
+    if let Some(s) = processor::str_of(state, call_stack, &obj_ref) {
+        return_string(state, call_stack, s)
+    }
+}
+
+pub fn int_format<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let obj_ref = args.remove(0);
+    let spec = match bind_arguments(state, call_stack, "__format__", &["format_spec"], 1, args, kwargs) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let spec = match spec_argument(state, call_stack, "__format__", spec) {
+        Some(spec) => spec,
+        None => return,
+    };
+    if spec.is_empty() {
+        // Like str(), so booleans are formatted as True and False
+        if let Some(s) = processor::str_of(state, call_stack, &obj_ref) {
+            return_string(state, call_stack, s)
+        }
+        return
+    }
+    let value = match state.store.deref(&obj_ref).content {
+        ObjectContent::Int(i) => i,
+        ObjectContent::True => 1,
+        ObjectContent::False => 0,
+        ref content => panic!("int.__format__ called on {:?}", content),
+    };
+    let name = processor::type_name(&state.store, &obj_ref);
+    match FormatSpec::parse(&spec).and_then(|spec| format_int(value, &spec, &name)) {
+        Ok(s) => return_string(state, call_stack, s),
+        Err(message) => raise_value_error(state, call_stack, message),
+    }
+}
+
+pub fn float_format<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let obj_ref = args.remove(0);
+    let spec = match bind_arguments(state, call_stack, "__format__", &["format_spec"], 1, args, kwargs) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let spec = match spec_argument(state, call_stack, "__format__", spec) {
+        Some(spec) => spec,
+        None => return,
+    };
+    if spec.is_empty() {
+        if let Some(s) = processor::str_of(state, call_stack, &obj_ref) {
+            return_string(state, call_stack, s)
+        }
+        return
+    }
+    let value = match state.store.deref(&obj_ref).content {
+        ObjectContent::Float(bits) => f64::from_bits(bits),
+        ref content => panic!("float.__format__ called on {:?}", content),
+    };
+    match FormatSpec::parse(&spec).and_then(|spec| format_float(value, &spec)) {
+        Ok(s) => return_string(state, call_stack, s),
+        Err(message) => raise_value_error(state, call_stack, message),
+    }
+}
+
+pub fn str_format<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let obj_ref = args.remove(0);
+    let spec = match bind_arguments(state, call_stack, "__format__", &["format_spec"], 1, args, kwargs) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let spec = match spec_argument(state, call_stack, "__format__", spec) {
+        Some(spec) => spec,
+        None => return,
+    };
+    let s = match state.store.deref(&obj_ref).content {
+        ObjectContent::String(ref s) => s.clone(),
+        ref content => panic!("str.__format__ called on {:?}", content),
+    };
+    match FormatSpec::parse(&spec).and_then(|spec| format_str(&s, &spec)) {
+        Ok(s) => return_string(state, call_stack, s),
+        Err(message) => raise_value_error(state, call_stack, message),
+    }
+}
+
+// How the fields of a str.format template are numbered
+#[derive(PartialEq)]
+enum FieldNumbering {
+    Unknown,
+    Automatic(usize),
+    Manual,
+}
+
+struct Formatter<'a> {
+    args: &'a [ObjectRef],
+    kwargs: &'a [(ObjectRef, ObjectRef)],
+    numbering: FieldNumbering,
+}
+
+impl<'a> Formatter<'a> {
+    /// Expands the replacement fields of a template.
+    /// Returns None if an exception was raised.
+    fn expand<EP: EnvProxy>(&mut self, state: &mut State<EP>, call_stack: &mut Vec<Frame>, template: &str, recursion_depth: usize) -> Option<String> {
+        if recursion_depth == 0 {
+            raise_value_error(state, call_stack, "Max string recursion exceeded".to_string());
+            return None
+        }
+        let mut res = String::with_capacity(template.len());
+        let mut chars = template.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().map(|&(_, c)| c) == Some('{') => { chars.next(); res.push('{') },
+                '}' if chars.peek().map(|&(_, c)| c) == Some('}') => { chars.next(); res.push('}') },
+                '}' => {
+                    raise_value_error(state, call_stack, "Single '}' encountered in format string".to_string());
+                    return None
+                },
+                '{' => {
+                    // Find the matching closing brace; the format spec may contain nested fields.
+                    let mut depth = 1;
+                    let mut end = None;
+                    for (j, c) in chars.by_ref() {
+                        match c {
+                            '{' => depth += 1,
+                            '}' => {
+                                depth -= 1;
+                                if depth == 0 {
+                                    end = Some(j);
+                                    break
+                                }
+                            },
+                            _ => (),
+                        }
+                    }
+                    let end = match end {
+                        Some(end) => end,
+                        None => {
+                            raise_value_error(state, call_stack, "expected '}' before end of string".to_string());
+                            return None
+                        },
+                    };
+                    let field = self.replace_field(state, call_stack, &template[i+1..end], recursion_depth)?;
+                    res.push_str(&field);
+                },
+                _ => res.push(c),
+            }
+        }
+        Some(res)
+    }
+
+    // Formats a replacement field: `field_name[!conversion][:format_spec]`
+    fn replace_field<EP: EnvProxy>(&mut self, state: &mut State<EP>, call_stack: &mut Vec<Frame>, field: &str, recursion_depth: usize) -> Option<String> {
+        // The field name ends at the first ! or : that is not in an index
+        let mut in_index = false;
+        let name_end = field.char_indices().find(|&(_, c)| {
+            match c {
+                '[' => in_index = true,
+                ']' => in_index = false,
+                _ => (),
+            }
+            !in_index && (c == '!' || c == ':')
+        }).map(|(i, _)| i).unwrap_or(field.len());
+        let (name, rest) = field.split_at(name_end);
+        let (conversion, spec) = if let Some(rest) = rest.strip_prefix('!') {
+            let mut rest_chars = rest.chars();
+            let conversion = rest_chars.next();
+            let spec = rest_chars.as_str();
+            match conversion {
+                Some(conversion) if spec.is_empty() || spec.starts_with(':') => (Some(conversion), spec.get(1..).unwrap_or("")),
+                Some(_) => {
+                    raise_value_error(state, call_stack, "expected ':' after conversion specifier".to_string());
+                    return None
+                },
+                None => {
+                    raise_value_error(state, call_stack, "end of string while looking for conversion specifier".to_string());
+                    return None
+                },
+            }
+        }
+        else {
+            (None, rest.get(1..).unwrap_or(""))
+        };
+        let value = self.get_field(state, call_stack, name)?;
+        let value = match conversion {
+            Some(conversion) => {
+                let s = convert(state, call_stack, &value, conversion)?;
+                state.store.allocate(state.primitive_objects.new_string(s))
+            },
+            None => value,
+        };
+        if spec.contains('{') {
+            let spec = self.expand(state, call_stack, spec, recursion_depth - 1)?;
+            return format_value(state, call_stack, &value, &spec)
+        }
+        format_value(state, call_stack, &value, spec)
+    }
+
+    // Gets the object designated by a field name: `arg_name("." attribute | "[" index "]")*`
+    fn get_field<EP: EnvProxy>(&mut self, state: &mut State<EP>, call_stack: &mut Vec<Frame>, name: &str) -> Option<ObjectRef> {
+        let first_end = name.find(['.', '[']).unwrap_or(name.len());
+        let (arg_name, mut accessors) = name.split_at(first_end);
+        let mut value = if arg_name.is_empty() || arg_name.chars().all(|c| c.is_ascii_digit()) {
+            let index = if arg_name.is_empty() {
+                match self.numbering {
+                    FieldNumbering::Manual => {
+                        raise_value_error(state, call_stack, "cannot switch from manual field specification to automatic field numbering".to_string());
+                        return None
+                    },
+                    FieldNumbering::Automatic(next) => next,
+                    FieldNumbering::Unknown => 0,
+                }
+            }
+            else {
+                if let FieldNumbering::Automatic(_) = self.numbering {
+                    raise_value_error(state, call_stack, "cannot switch from automatic field numbering to manual field specification".to_string());
+                    return None
+                }
+                arg_name.parse().unwrap()
+            };
+            self.numbering = if arg_name.is_empty() { FieldNumbering::Automatic(index + 1) } else { FieldNumbering::Manual };
+            match self.args.get(index) {
+                Some(value) => value.clone(),
+                None => {
+                    let exc = state.primitive_objects.indexerror.clone();
+                    raise(state, call_stack, exc, "tuple index out of range".to_string());
+                    return None
+                },
+            }
+        }
+        else {
+            let found = self.kwargs.iter().find(|(key, _)| {
+                match state.store.deref(key).content {
+                    ObjectContent::String(ref key) => key == arg_name,
+                    _ => false,
+                }
+            }).map(|(_, value)| value.clone());
+            match found {
+                Some(value) => value,
+                None => {
+                    let exc = state.primitive_objects.keyerror.clone();
+                    raise(state, call_stack, exc, ::objects::string_repr(arg_name));
+                    return None
+                },
+            }
+        };
+        while !accessors.is_empty() {
+            if accessors.starts_with('.') {
+                let end = accessors[1..].find(['.', '[']).map(|i| i + 1).unwrap_or(accessors.len());
+                let attribute = state.store.allocate(state.primitive_objects.new_string(accessors[1..end].to_string()));
+                let getattr = processor::primitive_function(state, "getattr");
+                value = match call_function_sync(state, call_stack, &getattr, vec![value, attribute], vec![]) {
+                    PyResult::Return(value) => value,
+                    PyResult::Raised => return None,
+                };
+                accessors = &accessors[end..];
+            }
+            else {
+                let end = match accessors.find(']') {
+                    Some(end) => end,
+                    None => {
+                        raise_value_error(state, call_stack, "Missing ']' in format string".to_string());
+                        return None
+                    },
+                };
+                let key = &accessors[1..end];
+                // Like CPython, indices that look like integers are integers.
+                let key = match key.parse() {
+                    Ok(i) => state.primitive_objects.new_int(i),
+                    Err(_) => state.primitive_objects.new_string(key.to_string()),
+                };
+                let key = state.store.allocate(key);
+                value = match processor::getitem_sync(state, call_stack, value, key) {
+                    PyResult::Return(value) => value,
+                    PyResult::Raised => return None,
+                };
+                accessors = &accessors[end+1..];
+                if !accessors.is_empty() && !accessors.starts_with('.') && !accessors.starts_with('[') {
+                    raise_value_error(state, call_stack, "Only '.' or '[' may follow ']' in format field specifier".to_string());
+                    return None
+                }
+            }
+        }
+        Some(value)
+    }
+}
+
+pub fn format_method<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let template = match state.store.deref(&args.remove(0)).content {
+        ObjectContent::String(ref s) => s.clone(),
+        ref content => panic!("str.format called on {:?}", content),
+    };
+    let res = {
+        let mut formatter = Formatter { args: &args, kwargs: &kwargs, numbering: FieldNumbering::Unknown };
+        formatter.expand(state, call_stack, &template, 2)
+    };
+    if let Some(res) = res {
+        return_string(state, call_stack, res)
+    }
+}
+
+// Width or precision of printf-style formatting, either a number or * to take it
+// from the values. Returns None if an exception was raised.
+fn star_or_number<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, chars: &[char], i: &mut usize, values_iter: &mut ::std::slice::Iter<ObjectRef>) -> Option<Option<usize>> {
+    if *i >= chars.len() || chars[*i] != '*' {
+        let (number, next) = parse_number(chars, *i);
+        *i = next;
+        return Some(number)
+    }
+    *i += 1;
+    let typeerror = state.primitive_objects.typeerror.clone();
+    let value = match values_iter.next() {
+        Some(value) => value.clone(),
+        None => {
+            raise(state, call_stack, typeerror, "not enough arguments for format string".to_string());
+            return None
+        },
+    };
+    let number = match state.store.deref(&value).content {
+        ObjectContent::Int(n) => n as usize,
+        _ => {
+            raise(state, call_stack, typeerror, "* wants int".to_string());
+            return None
+        },
+    };
+    Some(Some(number))
+}
+
+// Integer value of an argument of printf-style formatting
+fn percent_int_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, value: &ObjectRef, conversion: char) -> Option<i64> {
+    match state.store.deref(value).content {
+        ObjectContent::Int(i) => return Some(i),
+        ObjectContent::True => return Some(1),
+        ObjectContent::False => return Some(0),
+        // Floats are truncated by %d, but not accepted by %x and %o
+        ObjectContent::Float(bits) if conversion == 'd' || conversion == 'i' || conversion == 'u' => {
+            return numbers::float_to_int(state, call_stack, f64::from_bits(bits))
+        },
+        _ => (),
+    }
+    let exc = state.primitive_objects.typeerror.clone();
+    let name = processor::type_name(&state.store, value);
+    let expected = match conversion { 'd' | 'i' | 'u' => "a number", _ => "an integer" };
+    raise(state, call_stack, exc, format!("%{} format: {} is required, not {}", conversion, expected, name));
+    None
+}
+
+// Float value of an argument of printf-style formatting
+fn percent_float_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, value: &ObjectRef) -> Option<f64> {
+    match state.store.deref(value).content {
+        ObjectContent::Float(bits) => return Some(f64::from_bits(bits)),
+        ObjectContent::Int(i) => return Some(i as f64),
+        ObjectContent::True => return Some(1.),
+        ObjectContent::False => return Some(0.),
+        _ => (),
+    }
+    let exc = state.primitive_objects.typeerror.clone();
+    let name = processor::type_name(&state.store, value);
+    raise(state, call_stack, exc, format!("must be real number, not {}", name));
+    None
+}
+
+/// printf-style formatting: `template % values`.
+/// Returns None if an exception was raised.
+pub fn percent_format<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, template: &str, values: &ObjectRef) -> Option<String> {
+    let typeerror = state.primitive_objects.typeerror.clone();
+    // Like CPython, any subscriptable object other than tuples and strings is a
+    // mapping for %(key)s; it is also the only value.
+    let (values, is_mapping) = match state.store.deref(values).content {
+        ObjectContent::Tuple(ref v) => (v.clone(), false),
+        ObjectContent::String(_) => (vec![values.clone()], false),
+        ObjectContent::Dict(_) | ObjectContent::List(_) => (vec![values.clone()], true),
+        _ => (vec![values.clone()], processor::lookup_special_method(state, values, "__getitem__").is_some()),
+    };
+    let mapping = if is_mapping { Some(values[0].clone()) } else { None };
+    let mut values_iter = values.iter();
+    let mut res = String::with_capacity(template.len());
+    let chars: Vec<char> = template.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '%' {
+            res.push(chars[i]);
+            i += 1;
+            continue
+        }
+        i += 1;
+        // Mapping key
+        let mut key_value = None;
+        if i < chars.len() && chars[i] == '(' {
+            let mapping = match mapping {
+                Some(ref mapping) => mapping.clone(),
+                None => {
+                    raise(state, call_stack, typeerror, "format requires a mapping".to_string());
+                    return None
+                },
+            };
+            let mut depth = 1;
+            let key_start = i + 1;
+            while depth != 0 {
+                i += 1;
+                if i >= chars.len() {
+                    raise_value_error(state, call_stack, "incomplete format key".to_string());
+                    return None
+                }
+                match chars[i] {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => (),
+                }
+            }
+            let key: String = chars[key_start..i].iter().cloned().collect();
+            let key = state.store.allocate(state.primitive_objects.new_string(key));
+            key_value = match processor::getitem_sync(state, call_stack, mapping, key) {
+                PyResult::Return(value) => Some(value),
+                PyResult::Raised => return None,
+            };
+            i += 1;
+        }
+        // Flags
+        let (mut left, mut zero, mut sign, mut alternate) = (false, false, None, false);
+        while i < chars.len() {
+            match chars[i] {
+                '-' => left = true,
+                '0' => zero = true,
+                '+' => sign = Some('+'),
+                ' ' => if sign.is_none() { sign = Some(' ') },
+                '#' => alternate = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        // Width and precision, which may be given by arguments with *
+        let width = star_or_number(state, call_stack, &chars, &mut i, &mut values_iter)?;
+        let precision = if i < chars.len() && chars[i] == '.' {
+            i += 1;
+            Some(star_or_number(state, call_stack, &chars, &mut i, &mut values_iter)?.unwrap_or(0))
+        }
+        else {
+            None
+        };
+        // Length modifiers are ignored, like in CPython
+        while i < chars.len() && (chars[i] == 'h' || chars[i] == 'l' || chars[i] == 'L') {
+            i += 1;
+        }
+        let conversion = match chars.get(i) {
+            Some(&c) => c,
+            None => {
+                raise_value_error(state, call_stack, "incomplete format".to_string());
+                return None
+            },
+        };
+        i += 1;
+        if conversion == '%' {
+            res.push('%');
+            continue
+        }
+        let value = match key_value {
+            Some(value) => value,
+            None => match values_iter.next() {
+                Some(value) => value.clone(),
+                None => {
+                    raise(state, call_stack, typeerror, "not enough arguments for format string".to_string());
+                    return None
+                },
+            },
+        };
+        let spec = FormatSpec {
+            fill: if zero && !left { '0' } else { ' ' },
+            align: Some(if left { '<' } else if zero { '=' } else { '>' }),
+            sign,
+            alternate,
+            width: width.unwrap_or(0),
+            grouping: None,
+            precision: None,
+            type_: None,
+        };
+        let formatted = match conversion {
+            's' | 'r' | 'a' => {
+                let s = convert(state, call_stack, &value, conversion)?;
+                let s = match precision {
+                    Some(precision) => s.chars().take(precision).collect(),
+                    None => s,
+                };
+                FormatSpec { fill: ' ', align: Some(if left { '<' } else { '>' }), ..spec }.pad("", &s, '>')
+            },
+            'c' => {
+                let c = match state.store.deref(&value).content {
+                    ObjectContent::Int(i) => char_of(i),
+                    ObjectContent::String(ref s) if s.chars().count() == 1 => s.chars().next(),
+                    _ => {
+                        raise(state, call_stack, typeerror, "%c requires int or char".to_string());
+                        return None
+                    },
+                };
+                match c {
+                    Some(c) => FormatSpec { fill: ' ', align: Some(if left { '<' } else { '>' }), ..spec }.pad("", &c.to_string(), '>'),
+                    None => {
+                        raise_value_error(state, call_stack, "%c arg not in range(0x110000)".to_string());
+                        return None
+                    },
+                }
+            },
+            'd' | 'i' | 'u' | 'o' | 'x' | 'X' => {
+                let n = percent_int_argument(state, call_stack, &value, conversion)?;
+                let magnitude = n.unsigned_abs();
+                let (prefix, digits) = match conversion {
+                    'o' => ("0o", format!("{:o}", magnitude)),
+                    'x' => ("0x", format!("{:x}", magnitude)),
+                    'X' => ("0X", format!("{:X}", magnitude)),
+                    _ => ("", magnitude.to_string()),
+                };
+                // The precision is the minimum number of digits
+                let zeros = "0".repeat(precision.unwrap_or(0).saturating_sub(digits.len()));
+                let prefix = format!("{}{}", self::sign(n < 0, sign), if alternate { prefix } else { "" });
+                spec.pad(&prefix, &format!("{}{}", zeros, digits), '>')
+            },
+            'e' | 'E' | 'f' | 'F' | 'g' | 'G' => {
+                let f = percent_float_argument(state, call_stack, &value)?;
+                let spec = FormatSpec { precision, type_: Some(conversion), ..spec };
+                match format_float(f, &spec) {
+                    Ok(s) => s,
+                    Err(message) => {
+                        raise_value_error(state, call_stack, message);
+                        return None
+                    },
+                }
+            },
+            _ => {
+                let message = format!("unsupported format character '{}' (0x{:x}) at index {}", conversion, conversion as u32, i - 1);
+                raise_value_error(state, call_stack, message);
+                return None
+            },
+        };
+        res.push_str(&formatted);
+    }
+    if values_iter.next().is_some() && mapping.is_none() {
+        raise(state, call_stack, typeerror, "not all arguments converted during string formatting".to_string());
+        return None
+    }
+    Some(res)
+}
+
+pub fn str_mod<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let template = match state.store.deref(&args.remove(0)).content {
+        ObjectContent::String(ref s) => s.clone(),
+        ref content => panic!("str.__mod__ called on {:?}", content),
+    };
+    check_no_kwargs!("__mod__", state, call_stack, kwargs);
+    let values = match bind_arguments(state, call_stack, "__mod__", &["value"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    if let Some(res) = percent_format(state, call_stack, &template, &values) {
+        return_string(state, call_stack, res)
+    }
+}
@@ -0,0 +1,307 @@
+//! Native `range`, and the lazy iterators returned by `reversed`, `enumerate`,
+//! `zip`, `map` and `filter`.
+//!
+//! Ranges are iterated like other sequences, by a `RandomAccessIterator`.
+//! The other iterators advance the iterators they wrap only when they are
+//! advanced themselves.
+
+use super::super::sandbox::EnvProxy;
+use super::super::state::{State, PyResult, raise, return_value};
+use super::super::objects::{ObjectRef, ObjectContent, Object};
+use super::super::processor::frame::Frame;
+use super::super::processor;
+use super::super::processor::call_function_sync;
+use super::{bind_arguments, native_len};
+
+/// Number of items of a range.
+pub fn range_len(start: i64, stop: i64, step: i64) -> i64 {
+    // Computed on i128, as stop - start overflows for some ranges
+    let (start, stop, step) = (start as i128, stop as i128, step as i128);
+    let len = if step > 0 && stop > start {
+        (stop - start - 1) / step + 1
+    }
+    else if step < 0 && stop < start {
+        (start - stop - 1) / -step + 1
+    }
+    else {
+        0
+    };
+    len.min(i64::MAX as i128) as i64
+}
+
+/// Whether an int is one of the items of a range.
+pub fn range_contains(start: i64, stop: i64, step: i64, i: i64) -> bool {
+    let in_bounds = if step > 0 { start <= i && i < stop } else { stop < i && i <= start };
+    in_bounds && (i as i128 - start as i128) % step as i128 == 0
+}
+
+// Value of an argument which must be an int.
+fn int_value<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<i64> {
+    let value = match state.store.deref(obj_ref).content {
+        ObjectContent::Int(i) => Some(i),
+        ObjectContent::True => Some(1),
+        ObjectContent::False => Some(0),
+        _ => None,
+    };
+    if value.is_none() {
+        let exc = state.primitive_objects.typeerror.clone();
+        let name = processor::type_name(&state.store, obj_ref);
+        raise(state, call_stack, exc, format!("'{}' object cannot be interpreted as an integer", name));
+    }
+    value
+}
+
+fn no_kwargs<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, kwargs: &[(ObjectRef, ObjectRef)]) -> Option<()> {
+    if !kwargs.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() does not take keyword arguments", funcname));
+        return None
+    }
+    Some(())
+}
+
+// Iterators on the given iterables.
+fn iterators<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, iterables: Vec<ObjectRef>) -> Option<Vec<ObjectRef>> {
+    let iter = processor::primitive_function(state, "iter");
+    let mut iterators = Vec::with_capacity(iterables.len());
+    for iterable in iterables {
+        match call_function_sync(state, call_stack, &iter, vec![iterable], vec![]) {
+            PyResult::Return(iterator) => iterators.push(iterator),
+            PyResult::Raised => return None,
+        }
+    }
+    Some(iterators)
+}
+
+fn return_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, cls: ObjectRef, content: ObjectContent) {
+    let obj = state.store.allocate(Object::new_instance(None, cls, content));
+    return_value(call_stack, obj)
+}
+
+pub fn range_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("range.__new__", state, call_stack, args);
+    if no_kwargs(state, call_stack, "range", &kwargs).is_none() {
+        return
+    }
+    let exc = state.primitive_objects.typeerror.clone();
+    match args.len() {
+        0 => return raise(state, call_stack, exc, "range expected 1 arguments, got 0".to_string()),
+        1..=3 => (),
+        n => return raise(state, call_stack, exc, format!("range expected at most 3 arguments, got {}", n)),
+    }
+    let mut values = Vec::with_capacity(3);
+    for arg in args.iter() {
+        match int_value(state, call_stack, arg) {
+            Some(value) => values.push(value),
+            None => return,
+        }
+    }
+    let (start, stop, step) = match values[..] {
+        [stop] => (0, stop, 1),
+        [start, stop] => (start, stop, 1),
+        [start, stop, step] => (start, stop, step),
+        _ => unreachable!(),
+    };
+    if step == 0 {
+        let exc = state.primitive_objects.valueerror.clone();
+        return raise(state, call_stack, exc, "range() arg 3 must not be zero".to_string())
+    }
+    return_new(state, call_stack, cls, ObjectContent::Range(start, stop, step))
+}
+
+pub fn reversed_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("reversed.__new__", state, call_stack, args);
+    if no_kwargs(state, call_stack, "reversed", &kwargs).is_none() {
+        return
+    }
+    let sequence = match bind_arguments(state, call_stack, "reversed", &["sequence"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    if let Some(method) = processor::lookup_special_method(state, &sequence, "__reversed__") {
+        return processor::call_function(state, call_stack, &method, vec![], vec![])
+    }
+    let len = match state.store.deref(&sequence).content {
+        ObjectContent::Set(_) | ObjectContent::FrozenSet(_) | ObjectContent::Dict(_) | ObjectContent::DictView(..) => None,
+        _ => native_len(&state.store, &sequence),
+    };
+    let len = match len {
+        Some(len) => len,
+        None => {
+            let is_sequence = processor::lookup_special_method(state, &sequence, "__len__").is_some() &&
+                processor::lookup_special_method(state, &sequence, "__getitem__").is_some();
+            if !is_sequence {
+                let exc = state.primitive_objects.typeerror.clone();
+                let name = processor::type_name(&state.store, &sequence);
+                return raise(state, call_stack, exc, format!("'{}' object is not reversible", name))
+            }
+            let len_function = processor::primitive_function(state, "len");
+            let len = match call_function_sync(state, call_stack, &len_function, vec![sequence.clone()], vec![]) {
+                PyResult::Return(len) => len,
+                PyResult::Raised => return,
+            };
+            match state.store.deref(&len).content {
+                ObjectContent::Int(i) => i as usize,
+                _ => panic!("len() returned a non-int"),
+            }
+        },
+    };
+    return_new(state, call_stack, cls, ObjectContent::ReversedIterator(sequence, len))
+}
+
+pub fn enumerate_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("enumerate.__new__", state, call_stack, args);
+    let (iterable, start) = match bind_arguments(state, call_stack, "enumerate", &["iterable", "start"], 1, args, kwargs) {
+        Some(mut values) => (values.remove(0).unwrap(), values.remove(0)),
+        None => return,
+    };
+    let start = match start {
+        None => 0,
+        Some(start) => match int_value(state, call_stack, &start) {
+            Some(start) => start,
+            None => return,
+        },
+    };
+    if let Some(mut iterators) = iterators(state, call_stack, vec![iterable]) {
+        return_new(state, call_stack, cls, ObjectContent::Enumerate(iterators.remove(0), start))
+    }
+}
+
+pub fn zip_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("zip.__new__", state, call_stack, args);
+    if no_kwargs(state, call_stack, "zip", &kwargs).is_none() {
+        return
+    }
+    if let Some(iterators) = iterators(state, call_stack, args) {
+        return_new(state, call_stack, cls, ObjectContent::Zip(iterators))
+    }
+}
+
+pub fn map_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("map.__new__", state, call_stack, args);
+    if no_kwargs(state, call_stack, "map", &kwargs).is_none() {
+        return
+    }
+    if args.len() < 2 {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, "map() must have at least two arguments.".to_string())
+    }
+    let function = args.remove(0);
+    if let Some(iterators) = iterators(state, call_stack, args) {
+        return_new(state, call_stack, cls, ObjectContent::Map(function, iterators))
+    }
+}
+
+pub fn filter_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("filter.__new__", state, call_stack, args);
+    if no_kwargs(state, call_stack, "filter", &kwargs).is_none() {
+        return
+    }
+    if args.len() != 2 {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, format!("filter expected 2 arguments, got {}", args.len()))
+    }
+    let function = args.remove(0);
+    if let Some(mut iterators) = iterators(state, call_stack, args) {
+        return_new(state, call_stack, cls, ObjectContent::Filter(function, iterators.remove(0)))
+    }
+}
+
+// Next item of an iterator; None if an exception was raised, and Some(None) if it is exhausted.
+fn next_item<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, iterator: &ObjectRef) -> Option<Option<ObjectRef>> {
+    match processor::iterator_next_sync(state, call_stack, iterator) {
+        None => Some(None),
+        Some(PyResult::Return(item)) => Some(Some(item)),
+        Some(PyResult::Raised) => None,
+    }
+}
+
+/// Advance a reversed, enumerate, zip, map or filter iterator.
+/// Returns None if an exception was raised, and Some(None) if the iterator is exhausted.
+pub fn wrapper_next<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, iterator_ref: &ObjectRef, content: ObjectContent) -> Option<Option<ObjectRef>> {
+    match content {
+        ObjectContent::ReversedIterator(_, 0) => Some(None),
+        ObjectContent::ReversedIterator(sequence, left) => {
+            let index = left - 1;
+            // Like CPython, stop if a list shrank below the next index.
+            let in_bounds = native_len(&state.store, &sequence).is_none_or(|len| index < len);
+            let item = if in_bounds {
+                let index_ref = state.store.allocate(state.primitive_objects.new_int(index as i64));
+                let indexerror = state.primitive_objects.indexerror.clone();
+                match processor::lookup_special_method(state, &sequence, "__getitem__") {
+                    Some(getitem) => match processor::call_function_catching(state, call_stack, &getitem, vec![index_ref], vec![], &indexerror) {
+                        None => None,
+                        Some(PyResult::Return(item)) => Some(item),
+                        Some(PyResult::Raised) => return None,
+                    },
+                    None => match processor::getitem_sync(state, call_stack, sequence.clone(), index_ref) {
+                        PyResult::Return(item) => Some(item),
+                        PyResult::Raised => return None,
+                    },
+                }
+            }
+            else {
+                None
+            };
+            let left = if item.is_some() { index } else { 0 };
+            state.store.deref_mut(iterator_ref).content = ObjectContent::ReversedIterator(sequence, left);
+            Some(item)
+        },
+        ObjectContent::Enumerate(iterator, count) => {
+            let item = match next_item(state, call_stack, &iterator)? {
+                Some(item) => item,
+                None => return Some(None),
+            };
+            state.store.deref_mut(iterator_ref).content = ObjectContent::Enumerate(iterator, count + 1); // TODO: overflow check
+            let count = state.store.allocate(state.primitive_objects.new_int(count));
+            Some(Some(state.store.allocate(state.primitive_objects.new_tuple(vec![count, item]))))
+        },
+        ObjectContent::Zip(iterators) => {
+            if iterators.is_empty() {
+                return Some(None)
+            }
+            let mut items = Vec::with_capacity(iterators.len());
+            for iterator in iterators.iter() {
+                match next_item(state, call_stack, iterator)? {
+                    Some(item) => items.push(item),
+                    None => return Some(None),
+                }
+            }
+            Some(Some(state.store.allocate(state.primitive_objects.new_tuple(items))))
+        },
+        ObjectContent::Map(function, iterators) => {
+            let mut args = Vec::with_capacity(iterators.len());
+            for iterator in iterators.iter() {
+                match next_item(state, call_stack, iterator)? {
+                    Some(item) => args.push(item),
+                    None => return Some(None),
+                }
+            }
+            match call_function_sync(state, call_stack, &function, args, vec![]) {
+                PyResult::Return(value) => Some(Some(value)),
+                PyResult::Raised => None,
+            }
+        },
+        ObjectContent::Filter(function, iterator) => {
+            loop {
+                let item = match next_item(state, call_stack, &iterator)? {
+                    Some(item) => item,
+                    None => return Some(None),
+                };
+                let value = if function.is(&state.primitive_objects.none) {
+                    item.clone()
+                }
+                else {
+                    match call_function_sync(state, call_stack, &function, vec![item.clone()], vec![]) {
+                        PyResult::Return(value) => value,
+                        PyResult::Raised => return None,
+                    }
+                };
+                if processor::truth_value(state, call_stack, &value)? {
+                    return Some(Some(item))
+                }
+            }
+        },
+        _ => panic!("wrapper_next called on {:?}", content),
+    }
+}
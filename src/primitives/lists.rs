@@ -0,0 +1,312 @@
+//! Native methods of `list`.
+//!
+//! Methods which mutate a list bump its version.
+
+use super::super::sandbox::EnvProxy;
+use super::super::state::{State, PyResult, raise, return_value};
+use super::super::objects::{ObjectRef, ObjectContent};
+use super::super::processor::frame::Frame;
+use super::super::processor;
+use super::super::processor::call_function_sync;
+use super::{bind_arguments, container_items, native_bool, signed_index, slice_index};
+use super::strings::int_argument;
+
+/// A copy of the items of a list, for methods which may run Python code (which
+/// may mutate the list) while going through them.
+fn items_of<EP: EnvProxy>(state: &State<EP>, list: &ObjectRef) -> Vec<ObjectRef> {
+    match state.store.deref(list).content {
+        ObjectContent::List(ref v) => v.clone(),
+        _ => panic!("list method called on a non-list object"),
+    }
+}
+
+fn len_of<EP: EnvProxy>(state: &State<EP>, list: &ObjectRef) -> usize {
+    match state.store.deref(list).content {
+        ObjectContent::List(ref v) => v.len(),
+        _ => panic!("list method called on a non-list object"),
+    }
+}
+
+/// Applies a change to the items of a list in place, and bumps its version.
+/// Method descriptors already checked the receiver is a list.
+fn update_items<EP: EnvProxy, T, F: FnOnce(&mut Vec<ObjectRef>) -> T>(state: &mut State<EP>, list: &ObjectRef, change: F) -> T {
+    let obj = state.store.deref_mut(list);
+    let result = match obj.content {
+        ObjectContent::List(ref mut v) => change(v),
+        _ => panic!("list method called on a non-list object"),
+    };
+    obj.bump_version();
+    result
+}
+
+fn no_arguments<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, args: &[ObjectRef], kwargs: &[(ObjectRef, ObjectRef)]) -> Option<()> {
+    if !kwargs.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() takes no keyword arguments", funcname));
+        return None
+    }
+    if !args.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() takes no arguments ({} given)", funcname, args.len()));
+        return None
+    }
+    Some(())
+}
+
+fn return_none<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) {
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+fn return_int<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, i: usize) {
+    let obj_ref = state.store.allocate(state.primitive_objects.new_int(i as i64));
+    return_value(call_stack, obj_ref)
+}
+
+pub fn append<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let list = args.remove(0);
+    check_no_kwargs!("append", state, call_stack, kwargs);
+    let item = match bind_arguments(state, call_stack, "append", &["object"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    update_items(state, &list, |items| items.push(item));
+    return_none(state, call_stack)
+}
+
+pub fn extend<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let list = args.remove(0);
+    check_no_kwargs!("extend", state, call_stack, kwargs);
+    let iterable = match bind_arguments(state, call_stack, "extend", &["iterable"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    // Consuming the iterable may run Python code, which may mutate the list.
+    let new_items = match container_items(state, call_stack, &iterable) {
+        Some(new_items) => new_items,
+        None => return,
+    };
+    update_items(state, &list, |items| items.extend(new_items));
+    return_none(state, call_stack)
+}
+
+pub fn insert<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let list = args.remove(0);
+    check_no_kwargs!("insert", state, call_stack, kwargs);
+    let mut values = match bind_arguments(state, call_stack, "insert", &["index", "object"], 2, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let index = match int_argument(state, call_stack, values.remove(0), 0) {
+        Some(index) => index,
+        None => return,
+    };
+    let item = values.remove(0).unwrap();
+    update_items(state, &list, |items| {
+        // Out-of-range indices insert at the beginning or at the end.
+        let index = match signed_index(index, items.len()) {
+            Some(index) if index > items.len() => items.len(),
+            Some(index) => index,
+            None => 0,
+        };
+        items.insert(index, item)
+    });
+    return_none(state, call_stack)
+}
+
+pub fn pop<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let list = args.remove(0);
+    check_no_kwargs!("pop", state, call_stack, kwargs);
+    let index = match bind_arguments(state, call_stack, "pop", &["index"], 0, args, vec![]) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let index = match int_argument(state, call_stack, index, -1) {
+        Some(index) => index,
+        None => return,
+    };
+    let len = len_of(state, &list);
+    if len == 0 {
+        let exc = state.primitive_objects.indexerror.clone();
+        return raise(state, call_stack, exc, "pop from empty list".to_string())
+    }
+    let index = match signed_index(index, len) {
+        Some(index) if index < len => index,
+        _ => {
+            let exc = state.primitive_objects.indexerror.clone();
+            return raise(state, call_stack, exc, "pop index out of range".to_string())
+        },
+    };
+    let item = update_items(state, &list, |items| items.remove(index));
+    return_value(call_stack, item)
+}
+
+pub fn remove<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let list = args.remove(0);
+    check_no_kwargs!("remove", state, call_stack, kwargs);
+    let value = match bind_arguments(state, call_stack, "remove", &["value"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let items = items_of(state, &list);
+    let position = match processor::position_in(state, call_stack, items.iter(), &value) {
+        Some(position) => position,
+        None => return,
+    };
+    match position {
+        Some(index) => {
+            // Comparing items may run Python code, which may mutate the list.
+            update_items(state, &list, |items| if index < items.len() { items.remove(index); });
+            return_none(state, call_stack)
+        },
+        None => {
+            let exc = state.primitive_objects.valueerror.clone();
+            raise(state, call_stack, exc, "list.remove(x): x not in list".to_string())
+        },
+    }
+}
+
+pub fn index<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let items = items_of(state, &args.remove(0));
+    check_no_kwargs!("index", state, call_stack, kwargs);
+    let mut values = match bind_arguments(state, call_stack, "index", &["value", "start", "stop"], 1, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let value = values.remove(0).unwrap();
+    let start = match int_argument(state, call_stack, values.remove(0), 0) {
+        Some(start) => slice_index(start, items.len()),
+        None => return,
+    };
+    let stop = match int_argument(state, call_stack, values.remove(0), items.len() as i64) {
+        Some(stop) => slice_index(stop, items.len()),
+        None => return,
+    };
+    if start < stop && start < items.len() {
+        let stop = if stop > items.len() { items.len() } else { stop };
+        match processor::position_in(state, call_stack, items[start..stop].iter(), &value) {
+            None => return,
+            Some(Some(position)) => return return_int(state, call_stack, start + position),
+            Some(None) => (),
+        }
+    }
+    let repr = match processor::repr_of(state, call_stack, &value) {
+        Some(repr) => repr,
+        None => return,
+    };
+    let exc = state.primitive_objects.valueerror.clone();
+    raise(state, call_stack, exc, format!("{} is not in list", repr))
+}
+
+pub fn count<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let items = items_of(state, &args.remove(0));
+    check_no_kwargs!("count", state, call_stack, kwargs);
+    let value = match bind_arguments(state, call_stack, "count", &["value"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let mut count = 0;
+    for item in items.iter() {
+        match processor::objects_equal(state, call_stack, item, &value) {
+            Some(true) => count += 1,
+            Some(false) => (),
+            None => return,
+        }
+    }
+    return_int(state, call_stack, count)
+}
+
+pub fn clear<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let list = args.remove(0);
+    if no_arguments(state, call_stack, "clear", &args, &kwargs).is_some() {
+        update_items(state, &list, |items| items.clear());
+        return_none(state, call_stack)
+    }
+}
+
+pub fn copy<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let list = args.remove(0);
+    if no_arguments(state, call_stack, "copy", &args, &kwargs).is_some() {
+        let items = items_of(state, &list);
+        let copy = state.store.allocate(state.primitive_objects.new_list(items));
+        return_value(call_stack, copy)
+    }
+}
+
+pub fn reverse<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let list = args.remove(0);
+    if no_arguments(state, call_stack, "reverse", &args, &kwargs).is_some() {
+        update_items(state, &list, |items| items.reverse());
+        return_none(state, call_stack)
+    }
+}
+
+/// Stable merge sort of (key, item) pairs, comparing the keys with `<`.
+/// With `reverse`, items with equal keys still keep their relative order.
+/// Returns None if a comparison raised an exception.
+fn merge_sort<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut pairs: Vec<(ObjectRef, ObjectRef)>, reverse: bool) -> Option<Vec<(ObjectRef, ObjectRef)>> {
+    if pairs.len() <= 1 {
+        return Some(pairs)
+    }
+    let right = pairs.split_off(pairs.len() / 2);
+    let left = merge_sort(state, call_stack, pairs, reverse)?;
+    let right = merge_sort(state, call_stack, right, reverse)?;
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    loop {
+        // Take from the right half only if it is strictly before, for stability.
+        let take_right = match (left.peek(), right.peek()) {
+            (Some((left_key, _)), Some((right_key, _))) => {
+                if reverse {
+                    processor::less_than(state, call_stack, left_key, right_key)?
+                }
+                else {
+                    processor::less_than(state, call_stack, right_key, left_key)?
+                }
+            },
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (None, None) => break,
+        };
+        merged.push(if take_right { right.next().unwrap() } else { left.next().unwrap() });
+    }
+    Some(merged)
+}
+
+pub fn sort<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let list = args.remove(0);
+    if !args.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, "must use keyword argument for key function".to_string())
+    }
+    let mut values = match bind_arguments(state, call_stack, "sort", &["key", "reverse"], 0, vec![], kwargs) {
+        Some(values) => values,
+        None => return,
+    };
+    let key = values.remove(0).and_then(|key| if key.is(&state.primitive_objects.none) { None } else { Some(key) });
+    let reverse = values.remove(0).map(|reverse| native_bool(&state.store, &reverse)).unwrap_or(false);
+    let items = items_of(state, &list);
+    let version = state.store.deref(&list).version;
+    let mut pairs = Vec::with_capacity(items.len());
+    for item in items {
+        let key = match key {
+            Some(ref key) => match call_function_sync(state, call_stack, key, vec![item.clone()], vec![]) {
+                PyResult::Return(key) => key,
+                PyResult::Raised => return,
+            },
+            None => item.clone(),
+        };
+        pairs.push((key, item));
+    }
+    let pairs = match merge_sort(state, call_stack, pairs, reverse) {
+        Some(pairs) => pairs,
+        None => return,
+    };
+    // Key functions and comparisons may run Python code
+    if state.store.deref(&list).version != version {
+        let exc = state.primitive_objects.valueerror.clone();
+        return raise(state, call_stack, exc, "list modified during sort".to_string())
+    }
+    update_items(state, &list, |items| *items = pairs.into_iter().map(|(_, item)| item).collect());
+    return_none(state, call_stack)
+}
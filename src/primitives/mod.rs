@@ -1,15 +1,14 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::io::Write;
-use std::collections::linked_list::LinkedList;
 use std::rc::Rc;
 use std::cell::RefCell;
 use std::iter::IntoIterator;
 use super::sandbox::EnvProxy;
-use super::state::{State, PyFunction, raise, return_value};
-use super::objects::{ObjectRef, ObjectContent, Object, ObjectStore};
+use super::state::{State, PyFunction, PyResult, raise, return_value};
+use super::objects::{ObjectRef, ObjectContent, Object, ObjectStore, DictContent, DictView};
 use super::processor::frame::Frame;
-use super::processor::instructions::{Instruction, InstructionDecoder};
-use super::varstack::VectorVarStack;
+use super::processor;
+use super::processor::{call_function_sync, run_frame_sync, bind_attribute};
 
 macro_rules! parse_first_arguments {
     ( $funcname:expr, $store:expr, $args:ident, $args_iter:ident, $( $argname:tt $argexpected:tt : { $($argpattern:pat => $argcode:block,)* } ),* ) => {{
@@ -37,7 +36,77 @@ macro_rules! parse_arguments {
     }};
 }
 
-fn write_stdout<EP: EnvProxy>(processor: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>) {
+/// Raises a TypeError if keyword arguments were given to a primitive that takes none.
+macro_rules! check_no_kwargs {
+    ( $funcname:expr, $state:expr, $call_stack:expr, $kwargs:expr ) => {
+        if $kwargs.len() != 0 {
+            let exc = $state.primitive_objects.typeerror.clone();
+            return raise($state, $call_stack, exc, format!("{}() takes no keyword arguments", $funcname))
+        }
+    }
+}
+
+/// Checks the first argument of __new__ is a class, and returns it.
+macro_rules! pop_cls {
+    ( $funcname:expr, $state:expr, $call_stack:expr, $args:expr ) => {{
+        let is_class = match $args.first() {
+            Some(cls) => $state.store.deref(cls).bases.is_some(),
+            None => false,
+        };
+        if !is_class {
+            let exc = $state.primitive_objects.typeerror.clone();
+            return raise($state, $call_stack, exc, format!("{}(X): X is not a type object", $funcname))
+        }
+        $args.remove(0)
+    }};
+}
+
+mod strings;
+mod bytes;
+mod lists;
+pub mod dicts;
+pub mod sets;
+pub mod iterators;
+mod numbers;
+pub mod formatting;
+
+/// Matches positional and keyword arguments with the names of the parameters of a
+/// primitive function; the first `nb_required` parameters are mandatory.
+/// Raises a TypeError and returns None if they do not match.
+fn bind_arguments<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, params: &[&str], nb_required: usize, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> Option<Vec<Option<ObjectRef>>> {
+    let exc = state.primitive_objects.typeerror.clone();
+    if args.len() > params.len() {
+        raise(state, call_stack, exc, format!("{}() takes at most {} arguments ({} given)", funcname, params.len(), args.len()));
+        return None
+    }
+    let mut values: Vec<Option<ObjectRef>> = args.into_iter().map(Some).collect();
+    values.resize(params.len(), None);
+    for (key, value) in kwargs {
+        let key = match state.store.deref(&key).content {
+            ObjectContent::String(ref s) => s.clone(),
+            _ => panic!("Keyword names should be strings."),
+        };
+        match params.iter().position(|param| *param == key) {
+            Some(i) if values[i].is_none() => values[i] = Some(value),
+            Some(_) => {
+                raise(state, call_stack, exc, format!("{}() got multiple values for argument '{}'", funcname, key));
+                return None
+            },
+            None => {
+                raise(state, call_stack, exc, format!("{}() got an unexpected keyword argument '{}'", funcname, key));
+                return None
+            },
+        }
+    }
+    if let Some(i) = values[..nb_required].iter().position(Option::is_none) {
+        raise(state, call_stack, exc, format!("{}() missing required argument '{}'", funcname, params[i]));
+        return None
+    }
+    Some(values)
+}
+
+fn write_stdout<EP: EnvProxy>(processor: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("__primitives__.write_stdout", processor, call_stack, kwargs);
     parse_arguments!("__primitives__.write_stdout", processor.store, args,
         "value" "a string, boolean, or integer": {
             ObjectContent::String(ref s) => {
@@ -57,17 +126,20 @@ fn write_stdout<EP: EnvProxy>(processor: &mut State<EP>, call_stack: &mut Vec<Fr
     return_value(call_stack, processor.primitive_objects.none.clone())
 }
 
-fn build_class<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>) {
+fn build_class<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
     let name;
     let code;
+    let closure;
+    let func = args.get(0).unwrap().clone();
     let mut args_iter = args.into_iter();
     parse_first_arguments!("__primitives__.build_class", state.store, args, args_iter,
         "func" "a function": {
-            ObjectContent::Function(_, ref code_arg, _) => {
+            ObjectContent::Function(_, ref code_arg, _, ref closure_arg) => {
                 match state.store.deref(code_arg).content {
                     ObjectContent::Code(ref code_) => code = code_.clone(),
                     _ => panic!("__build_class__'s function argument has a code that is not code.")
                 }
+                closure = closure_arg.clone();
             },
         },
         "name" "a string": {
@@ -75,6 +147,159 @@ fn build_class<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>,
         }
     );
     let bases: Vec<ObjectRef> = args_iter.collect();
+
+    // The metaclass is either given explicitly, or the most derived metaclass of the bases.
+    let mut metaclass = None;
+    let kwargs: Vec<(ObjectRef, ObjectRef)> = kwargs.into_iter().filter(|&(ref key, ref value)| {
+        match state.store.deref(key).content {
+            ObjectContent::String(ref s) if s == "metaclass" => { metaclass = Some(value.clone()); false },
+            _ => true,
+        }
+    }).collect();
+    let metaclass = match metaclass {
+        Some(metaclass) => metaclass,
+        None => match bases.first() {
+            Some(base) => state.store.deref(base).class.clone(),
+            None => state.primitive_objects.type_.clone(),
+        },
+    };
+    let metaclass = if state.store.deref(&metaclass).bases.is_some() {
+        match most_derived_metaclass(state, call_stack, metaclass, &bases) {
+            Some(metaclass) => metaclass,
+            None => return,
+        }
+    }
+    else {
+        metaclass // Not a class, it is only called.
+    };
+
+    let name = state.store.allocate(state.primitive_objects.new_string(name));
+    let bases = state.store.allocate(state.primitive_objects.new_tuple(bases));
+
+    // Get the namespace from __prepare__; it may be any mapping.
+    let prepare = if state.store.deref(&metaclass).bases.is_some() {
+        lookup_class_attribute(&state.store, &metaclass, "__prepare__")
+    }
+    else {
+        None
+    };
+    let namespace = match prepare {
+        Some(prepare) => {
+            let prepare = bind_attribute(state, prepare, None, metaclass.clone());
+            match call_function_sync(state, call_stack, &prepare, vec![name.clone(), bases.clone()], kwargs.clone()) {
+                PyResult::Return(prepared) => prepared,
+                PyResult::Raised => return,
+            }
+        },
+        None => state.store.allocate(state.primitive_objects.new_dict(DictContent::new())),
+    };
+    let is_mapping = match state.store.deref(&namespace).content {
+        ObjectContent::Dict(_) => true,
+        _ => processor::lookup_special_method(state, &namespace, "__getitem__").is_some(),
+    };
+    if !is_mapping {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, "__prepare__() must return a mapping".to_string())
+    }
+
+    // Run the body of the class in this namespace.
+    let mut cells = Vec::with_capacity(code.cellvars.len() + closure.len());
+    for _ in code.cellvars.iter() {
+        cells.push(state.store.allocate(state.primitive_objects.new_cell(None)));
+    }
+    cells.extend(closure);
+    let mut frame = Frame::new(func, *code, Rc::new(RefCell::new(HashMap::new())));
+    frame.namespace = Some(namespace.clone());
+    frame.cells = cells;
+    if let PyResult::Raised = run_frame_sync(state, call_stack, frame) {
+        return
+    }
+
+    // Then create the class with the metaclass.
+    match call_function_sync(state, call_stack, &metaclass, vec![name, bases, namespace], kwargs) {
+        PyResult::Return(cls) => return_value(call_stack, cls),
+        PyResult::Raised => (),
+    }
+}
+
+/// The metaclass of a new class: the most derived of the given metaclass and of the
+/// metaclasses of the bases. Raises a TypeError and returns None if there is none.
+fn most_derived_metaclass<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, metaclass: ObjectRef, bases: &[ObjectRef]) -> Option<ObjectRef> {
+    let mut winner = metaclass;
+    for base in bases.iter() {
+        let base_metaclass = state.store.deref(base).class.clone();
+        if native_issubclass(&state.store, &winner, &base_metaclass) {
+            continue
+        }
+        else if native_issubclass(&state.store, &base_metaclass, &winner) {
+            winner = base_metaclass
+        }
+        else {
+            let exc = state.primitive_objects.typeerror.clone();
+            raise(state, call_stack, exc, "metaclass conflict: the metaclass of a derived class must be a (non-strict) subclass of the metaclasses of all its bases".to_string());
+            return None
+        }
+    }
+    Some(winner)
+}
+
+fn type_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let metaclass = pop_cls!("type.__new__", state, call_stack, args);
+    let typeerror = state.primitive_objects.typeerror.clone();
+    if !native_issubclass(&state.store, &metaclass, &state.primitive_objects.type_) {
+        let metaclass_name = state.store.deref(&metaclass).name.clone().unwrap_or("?".to_string());
+        return raise(state, call_stack, typeerror, format!("type.__new__({}): {} is not a subtype of type", metaclass_name, metaclass_name))
+    }
+    if metaclass.is(&state.primitive_objects.type_) && args.len() == 1 && kwargs.len() == 0 {
+        // type(obj) returns the class of the object
+        let class = state.store.deref(&args[0]).class.clone();
+        return return_value(call_stack, class)
+    }
+    if args.len() != 3 {
+        return raise(state, call_stack, typeerror, "type() takes 1 or 3 arguments".to_string())
+    }
+    let name = match state.store.deref(&args[0]).content {
+        ObjectContent::String(ref s) => s.clone(),
+        _ => return raise(state, call_stack, typeerror, "type.__new__() argument 1 must be str".to_string()),
+    };
+    let bases = match state.store.deref(&args[1]).content {
+        ObjectContent::Tuple(ref v) => v.clone(),
+        _ => return raise(state, call_stack, typeerror, "type.__new__() argument 2 must be tuple".to_string()),
+    };
+    let items = match state.store.deref(&args[2]).content {
+        ObjectContent::Dict(ref items) => items.items(),
+        _ => return raise(state, call_stack, typeerror, "type.__new__() argument 3 must be dict".to_string()),
+    };
+
+    for (i, base) in bases.iter().enumerate() {
+        if state.store.deref(base).bases.is_none() {
+            return raise(state, call_stack, typeerror, "bases must be types".to_string())
+        }
+        if bases[..i].contains(base) {
+            let base_name = state.store.deref(base).name.clone().unwrap_or("?".to_string());
+            return raise(state, call_stack, typeerror, format!("duplicate base class {}", base_name))
+        }
+    }
+
+    // If a base has a more derived metaclass, it creates the class instead.
+    let winner = match most_derived_metaclass(state, call_stack, metaclass.clone(), &bases) {
+        Some(winner) => winner,
+        None => return,
+    };
+    if !winner.is(&metaclass) {
+        let winner_new = lookup_class_attribute(&state.store, &winner, "__new__").unwrap(); // object.__new__ always exists
+        let type_new = lookup_class_attribute(&state.store, &state.primitive_objects.type_, "__new__").unwrap();
+        if !winner_new.is(&type_new) {
+            let winner_new = bind_attribute(state, winner_new, None, winner.clone());
+            args.insert(0, winner);
+            return match call_function_sync(state, call_stack, &winner_new, args, kwargs) {
+                PyResult::Return(cls) => return_value(call_stack, cls),
+                PyResult::Raised => (),
+            }
+        }
+    }
+    let metaclass = winner;
+
     let bases = if bases.len() == 0 {
         vec![state.primitive_objects.object.clone()]
     }
@@ -82,68 +307,345 @@ fn build_class<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>,
         bases
     };
 
+    let mut attributes = HashMap::new();
+    let mut classcell = None;
+    let mut attribute_names = Vec::new();
+    for (key, value) in items {
+        let key = match state.store.deref(&key).content {
+            ObjectContent::String(ref s) => s.clone(),
+            _ => continue,
+        };
+        if key == "__classcell__" {
+            classcell = Some(value);
+            continue
+        }
+        // __init_subclass__ is implicitly a classmethod
+        let value = match (key.as_ref(), &state.store.deref(&value).content) {
+            ("__init_subclass__", &ObjectContent::Function(..)) => {
+                let classmethod_type = state.primitive_objects.classmethod_type.clone();
+                state.store.allocate(Object::new_instance(None, classmethod_type, ObjectContent::ClassMethod(value)))
+            },
+            _ => value,
+        };
+        attribute_names.push((key.clone(), value.clone()));
+        attributes.insert(key, value);
+    }
+    // Overriding __eq__ without __hash__ makes instances unhashable
+    if attributes.contains_key("__eq__") && !attributes.contains_key("__hash__") {
+        attributes.insert("__hash__".to_string(), state.primitive_objects.none.clone());
+    }
 
-    let attributes = Rc::new(RefCell::new(HashMap::new()));
-    let cls_ref = state.store.allocate(Object::new_class(name, Some(attributes.clone()), state.primitive_objects.type_.clone(), bases));
+    // Slots are stored after the ones of the bases, which must not conflict.
+    let mut layout_base: Option<ObjectRef> = None;
+    let mut first_slot = 0;
+    for base in bases.iter() {
+        let nb_slots = instance_layout(&state.store, base).0;
+        if nb_slots == 0 {
+            continue
+        }
+        match layout_base.clone() {
+            Some(ref other) if native_issubclass(&state.store, other, base) => (),
+            Some(ref other) if !native_issubclass(&state.store, base, other) => {
+                return raise(state, call_stack, typeerror, "multiple bases have instance lay-out conflict".to_string())
+            },
+            _ => {
+                layout_base = Some(base.clone());
+                first_slot = nb_slots;
+            },
+        }
+    }
+    let slots = attributes.get("__slots__").cloned();
+    if let Some(slots) = slots {
+        let slots = match slot_names(&state.store, &slots) {
+            Some(slots) => slots,
+            None => {
+                let repr = slots.repr(&state.store);
+                return raise(state, call_stack, typeerror, format!("{} object is not iterable", repr))
+            },
+        };
+        let mut index = first_slot;
+        for slot in slots {
+            let slot = match state.store.deref(&slot).content {
+                ObjectContent::String(ref s) => s.clone(),
+                _ => {
+                    let class_name = state.store.deref(&state.store.deref(&slot).class).name.clone().unwrap_or("?".to_string());
+                    return raise(state, call_stack, typeerror, format!("__slots__ items must be strings, not '{}'", class_name))
+                },
+            };
+            if slot == "__dict__" || slot == "__weakref__" {
+                continue
+            }
+            let member_descriptor_type = state.primitive_objects.member_descriptor_type.clone();
+            let descriptor = state.store.allocate(Object::new_builtin_instance(member_descriptor_type, ObjectContent::MemberDescriptor(slot.clone(), index)));
+            attributes.insert(slot, descriptor);
+            index += 1;
+        }
+    }
+
+    let cls = state.store.allocate(Object::new_class(name, Some(Rc::new(RefCell::new(attributes))), metaclass, bases.clone()));
+    match c3_linearization(&state.store, &cls) {
+        Some(mro) => state.store.deref_mut(&cls).mro = Some(mro),
+        None => {
+            let base_names = bases.iter().map(|base| state.store.deref(base).name.clone().unwrap_or("?".to_string())).collect::<Vec<_>>();
+            return raise(state, call_stack, typeerror, format!("Cannot create a consistent method resolution order (MRO) for bases {}", base_names.join(", ")))
+        },
+    }
+
+    // Methods using super() or __class__ get the class from the __class__ cell.
+    if let Some(classcell) = classcell {
+        state.store.deref_mut(&classcell).content = ObjectContent::Cell(Some(cls.clone()));
+    }
 
-    let mut instructions: Vec<Instruction> = InstructionDecoder::new(code.code.iter()).collect();
+    // Tell descriptors their name.
+    for (key, value) in attribute_names {
+        let value_class = state.store.deref(&value).class.clone();
+        if let Some(set_name) = lookup_class_attribute(&state.store, &value_class, "__set_name__") {
+            let set_name = bind_attribute(state, set_name, Some(value), value_class);
+            let key = state.store.allocate(state.primitive_objects.new_string(key));
+            if let PyResult::Raised = call_function_sync(state, call_stack, &set_name, vec![cls.clone(), key], vec![]) {
+                return
+            }
+        }
+    }
 
-    // Hack to made the class' code return the class instead of None
-    let mut last_instruction;
-    while {last_instruction = instructions.pop(); last_instruction == Some(Instruction::Nop)} {};
-    assert_eq!(last_instruction, Some(Instruction::ReturnValue));
-    instructions.pop(); // LoadConst None
-    instructions.push(Instruction::PushImmediate(cls_ref.clone()));
-    instructions.push(Instruction::ReturnValue);
+    // Call __init_subclass__ of the parent class.
+    let init_subclass = lookup_mro_attribute(&state.store, &class_mro(&state.store, &cls)[1..], "__init_subclass__").unwrap(); // object.__init_subclass__ always exists
+    let init_subclass = bind_attribute(state, init_subclass, None, cls.clone());
+    if let PyResult::Raised = call_function_sync(state, call_stack, &init_subclass, vec![], kwargs) {
+        return
+    }
 
-    let frame = Frame {
-        object: cls_ref,
-        var_stack: VectorVarStack::new(),
-        block_stack: vec![],
-        locals: attributes,
-        instructions: instructions,
-        code: (*code).clone(),
-        program_counter: 0,
-    };
-    call_stack.push(frame);
+    return_value(call_stack, cls)
 }
 
-pub fn native_issubclass(store: &ObjectStore, first: &ObjectRef, second: &ObjectRef) -> bool {
-    let mut visited = HashSet::new();
-    let mut to_visit = LinkedList::new();
-    to_visit.push_back(first.clone());
-    while let Some(candidate) = to_visit.pop_front() {
-        if !visited.insert(candidate.clone()) {
-            // Already visited
-            continue
-        };
-        if candidate.is(second) {
-            return true
+fn type_init<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    // Keyword arguments are for __init_subclass__, and were already used by type.__new__
+    if args.len() != 2 && args.len() != 4 {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, "type.__init__() takes 1 or 3 arguments".to_string())
+    }
+    if args.len() == 2 {
+        check_no_kwargs!("type.__init__", state, call_stack, kwargs);
+    }
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+fn type_call<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("type.__call__", state, call_stack, args);
+    processor::call_class(state, call_stack, &cls, args, kwargs)
+}
+
+fn type_prepare<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, _args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let namespace = state.store.allocate(state.primitive_objects.new_dict(DictContent::new()));
+    return_value(call_stack, namespace)
+}
+
+fn object_init_subclass<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, _args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("__init_subclass__", state, call_stack, kwargs);
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+/// Names in the __slots__ of a class (a string or a container of strings).
+fn slot_names(store: &ObjectStore, slots: &ObjectRef) -> Option<Vec<ObjectRef>> {
+    match store.deref(slots).content {
+        ObjectContent::String(_) => Some(vec![slots.clone()]),
+        ObjectContent::Tuple(ref v) | ObjectContent::List(ref v) => Some(v.clone()),
+        ObjectContent::Set(ref v) | ObjectContent::FrozenSet(ref v) | ObjectContent::Dict(ref v) => Some(v.keys()),
+        _ => None,
+    }
+}
+
+/// Number of slots of instances of a class, and whether they have a __dict__
+/// (ie. if a class of the MRO other than object has no __slots__, or has __dict__ in them).
+pub fn instance_layout(store: &ObjectStore, class: &ObjectRef) -> (usize, bool) {
+    let mut nb_slots = 0;
+    let mut has_dict = false;
+    for candidate in class_mro(store, class) {
+        let candidate = store.deref(candidate);
+        if candidate.bases.as_ref().is_none_or(|bases| bases.is_empty()) {
+            continue // object
+        }
+        let attributes = match candidate.attributes {
+            Some(ref attributes) => attributes.borrow(),
+            None => { has_dict = true; continue },
         };
-        match store.deref(&candidate).bases {
-            None => (),
-            Some(ref bases) => {
-                for base in bases.iter() {
-                    to_visit.push_back(base.clone())
+        match attributes.get("__slots__").and_then(|slots| slot_names(store, slots)) {
+            None => has_dict = true,
+            Some(slots) => {
+                for slot in slots {
+                    if let ObjectContent::String(ref slot) = store.deref(&slot).content {
+                        match attributes.get(slot).map(|descriptor| &store.deref(descriptor).content) {
+                            Some(&ObjectContent::MemberDescriptor(_, index)) => nb_slots = ::std::cmp::max(nb_slots, index+1),
+                            _ => has_dict |= slot == "__dict__",
+                        }
+                    }
                 }
+            },
+        }
+    }
+    (nb_slots, has_dict)
+}
+
+/// C3 linearization of a class and its bases, or None if the bases cannot be
+/// ordered consistently.
+fn c3_linearization(store: &ObjectStore, class: &ObjectRef) -> Option<Vec<ObjectRef>> {
+    let bases = store.deref(class).bases.clone().unwrap_or(vec![]);
+    let mut sequences = Vec::with_capacity(bases.len()+1);
+    for base in bases.iter() {
+        sequences.push(class_mro(store, base).to_vec());
+    }
+    sequences.push(bases);
+    let mut mro = vec![class.clone()];
+    loop {
+        sequences.retain(|sequence| sequence.len() != 0);
+        if sequences.len() == 0 {
+            return Some(mro)
+        }
+        // The next class is the first head that is not in the tail of any sequence.
+        let head = sequences.iter().map(|sequence| sequence[0].clone()).find(|candidate| {
+            sequences.iter().all(|sequence| !sequence[1..].contains(candidate))
+        })?;
+        for sequence in sequences.iter_mut() {
+            if sequence[0].is(&head) {
+                sequence.remove(0);
             }
-        };
+        }
+        mro.push(head);
     }
-    false
 }
 
-fn issubclass<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>) {
+/// Method resolution order of a class, computed with the C3 algorithm when
+/// the class is created.
+pub fn class_mro<'a>(store: &'a ObjectStore, class: &ObjectRef) -> &'a [ObjectRef] {
+    store.deref(class).mro.as_ref().expect("Class without MRO")
+}
+
+pub fn native_issubclass(store: &ObjectStore, first: &ObjectRef, second: &ObjectRef) -> bool {
+    class_mro(store, first).iter().any(|candidate| candidate.is(second))
+}
+
+/// Shared code of isinstance() and issubclass(), which accept a class or a nested
+/// tuple of classes, and call __instancecheck__/__subclasscheck__ of its metaclass
+/// if it defines one. Returns None if an exception was raised.
+pub fn class_check<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef, classinfo: &ObjectRef, is_instance_check: bool) -> Option<bool> {
+    if is_instance_check && state.store.deref(obj_ref).class.is(classinfo) {
+        return Some(true) // Fast path, like CPython
+    }
+    let classes = match state.store.deref(classinfo).content {
+        ObjectContent::Tuple(ref classes) => Some(classes.clone()),
+        _ => None,
+    };
+    if let Some(classes) = classes {
+        for class in classes.iter() {
+            if class_check(state, call_stack, obj_ref, class, is_instance_check)? {
+                return Some(true)
+            }
+        }
+        return Some(false)
+    }
+    let hook = if is_instance_check { "__instancecheck__" } else { "__subclasscheck__" };
+    if let Some(method) = processor::lookup_special_method(state, classinfo, hook) {
+        return match call_function_sync(state, call_stack, &method, vec![obj_ref.clone()], vec![]) {
+            PyResult::Return(res) => Some(native_bool(&state.store, &res)),
+            PyResult::Raised => None,
+        }
+    }
+    let typeerror = state.primitive_objects.typeerror.clone();
+    if is_instance_check {
+        if state.store.deref(classinfo).bases.is_none() {
+            raise(state, call_stack, typeerror, "isinstance() arg 2 must be a type or tuple of types".to_string());
+            return None
+        }
+        Some(native_isinstance(&state.store, obj_ref, classinfo))
+    }
+    else {
+        if state.store.deref(obj_ref).bases.is_none() {
+            raise(state, call_stack, typeerror, "issubclass() arg 1 must be a class".to_string());
+            return None
+        }
+        if state.store.deref(classinfo).bases.is_none() {
+            raise(state, call_stack, typeerror, "issubclass() arg 2 must be a class or tuple of classes".to_string());
+            return None
+        }
+        Some(native_issubclass(&state.store, obj_ref, classinfo))
+    }
+}
+
+fn issubclass<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("issubclass", state, call_stack, kwargs);
     if args.len() != 2 {
-        panic!(format!("__primitives__.issubclass takes 2 arguments, not {}", args.len()))
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, format!("issubclass expected 2 arguments, got {}", args.len()))
+    }
+    if let Some(res) = class_check(state, call_stack, &args[0], &args[1], false) {
+        let res = if res { state.primitive_objects.true_obj.clone() } else { state.primitive_objects.false_obj.clone() };
+        return_value(call_stack, res)
+    }
+}
+
+/// Whether an exception matches the class, or nested tuple of classes, of an except clause.
+/// Returns None if one of them is not a subclass of BaseException.
+pub fn exception_match(store: &ObjectStore, baseexception: &ObjectRef, exc_ref: &ObjectRef, pattern: &ObjectRef) -> Option<bool> {
+    let pattern_obj = store.deref(pattern);
+    match pattern_obj.content {
+        ObjectContent::Tuple(ref classes) => {
+            let mut res = false;
+            for class in classes.iter() {
+                res |= exception_match(store, baseexception, exc_ref, class)?;
+            }
+            Some(res)
+        },
+        _ if pattern_obj.bases.is_some() && native_issubclass(store, pattern, baseexception) => Some(native_isinstance(store, exc_ref, pattern)),
+        _ => None,
     }
-    let first = args.get(0).unwrap();
-    let second = args.get(1).unwrap();
-    let res = native_issubclass(&state.store, first, second);
-    if res {
-        return_value(call_stack, state.primitive_objects.true_obj.clone())
+}
+
+/// Look up a name in the classes of the MRO of a class.
+pub fn lookup_class_attribute(store: &ObjectStore, class: &ObjectRef, name: &str) -> Option<ObjectRef> {
+    lookup_mro_attribute(store, class_mro(store, class), name)
+}
+
+/// Look up a name in the attributes of a sequence of classes.
+pub fn lookup_mro_attribute(store: &ObjectStore, mro: &[ObjectRef], name: &str) -> Option<ObjectRef> {
+    for candidate in mro {
+        if let Some(ref attributes) = store.deref(candidate).attributes {
+            if let Some(value) = attributes.borrow().get(name) {
+                return Some(value.clone())
+            }
+        }
+    }
+    None
+}
+
+/// Resolves an index which may be negative; returns None if it is before the start.
+pub fn signed_index(index: i64, length: usize) -> Option<usize> {
+    if index >= 0 {
+        Some(index as usize)
     }
     else {
-        return_value(call_stack, state.primitive_objects.false_obj.clone())
+        length.checked_sub(index.unsigned_abs() as usize)
+    }
+}
+
+/// Resolves the start or end of a slice, like CPython: negative values count
+/// from the end, and values before the start are clamped to it.
+fn slice_index(index: i64, length: usize) -> usize {
+    signed_index(index, length).unwrap_or(0)
+}
+
+/// Truth value of builtin objects; instances of other classes are true.
+pub fn native_bool(store: &ObjectStore, obj_ref: &ObjectRef) -> bool {
+    match store.deref(obj_ref).content {
+        ObjectContent::None | ObjectContent::False => false,
+        ObjectContent::Int(i) => i != 0,
+        ObjectContent::Float(bits) => f64::from_bits(bits) != 0.,
+        ObjectContent::String(ref s) => s.len() != 0,
+        ObjectContent::Bytes(ref v) | ObjectContent::ByteArray(ref v) => v.len() != 0,
+        ObjectContent::Tuple(ref v) | ObjectContent::List(ref v) => v.len() != 0,
+        ObjectContent::Set(ref v) | ObjectContent::FrozenSet(ref v) | ObjectContent::Dict(ref v) => v.len() != 0,
+        ObjectContent::DictView(ref dict, _) => native_bool(store, dict),
+        ObjectContent::Range(start, stop, step) => iterators::range_len(start, stop, step) != 0,
+        _ => true,
     }
 }
 
@@ -151,59 +653,850 @@ pub fn native_isinstance(store: &ObjectStore, first: &ObjectRef, second: &Object
     native_issubclass(store, &store.deref(&first).class, second)
 }
 
-fn isinstance<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>) {
+fn isinstance<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("isinstance", state, call_stack, kwargs);
     if args.len() != 2 {
-        panic!(format!("__primitives__.isinstance takes 2 arguments, not {}", args.len()))
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, format!("isinstance expected 2 arguments, got {}", args.len()))
     }
-    let second = args.pop().unwrap();
-    let first = args.pop().unwrap();
-    let res = native_isinstance(&state.store, &first, &second);
-    if res {
-        return_value(call_stack, state.primitive_objects.true_obj.clone())
+    if let Some(res) = class_check(state, call_stack, &args[0], &args[1], true) {
+        let res = if res { state.primitive_objects.true_obj.clone() } else { state.primitive_objects.false_obj.clone() };
+        return_value(call_stack, res)
     }
-    else {
-        return_value(call_stack, state.primitive_objects.false_obj.clone())
+}
+
+/// Get an iterator on an object and push it on the stack, using its __iter__
+/// method if it has one, or the sequence protocol if it has a __getitem__ method.
+pub fn get_iter<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: ObjectRef) {
+    let typeerror = state.primitive_objects.typeerror.clone();
+    if let Some(method) = processor::lookup_special_method(state, &obj_ref, "__iter__") {
+        let iterator = match call_function_sync(state, call_stack, &method, vec![], vec![]) {
+            PyResult::Return(iterator) => iterator,
+            PyResult::Raised => return,
+        };
+        let is_iterator = is_native_iterator(&state.store, &iterator) || processor::lookup_special_method(state, &iterator, "__next__").is_some();
+        if !is_iterator {
+            let name = processor::type_name(&state.store, &iterator);
+            return raise(state, call_stack, typeerror, format!("iter() returned non-iterator of type '{}'", name))
+        }
+        return return_value(call_stack, iterator)
     }
+    let content = {
+        let obj = state.store.deref(&obj_ref);
+        match obj.content {
+            ObjectContent::List(_) | ObjectContent::Tuple(_) | ObjectContent::String(_) |
+            ObjectContent::Bytes(_) | ObjectContent::ByteArray(_) | ObjectContent::Range(..) =>
+                Some(ObjectContent::RandomAccessIterator(obj_ref.clone(), 0, obj.version)),
+            ObjectContent::Set(_) | ObjectContent::FrozenSet(_) | ObjectContent::Dict(_) => Some(ObjectContent::DictIterator(obj_ref.clone(), 0, obj.version, DictView::Keys)),
+            ObjectContent::DictView(ref dict_ref, view) => {
+                let version = state.store.deref(dict_ref).version;
+                Some(ObjectContent::DictIterator(dict_ref.clone(), 0, version, view))
+            },
+            _ => None,
+        }
+    };
+    let content = match content {
+        Some(content) => content,
+        None if is_native_iterator(&state.store, &obj_ref) => return return_value(call_stack, obj_ref),
+        None if processor::lookup_special_method(state, &obj_ref, "__getitem__").is_some() => ObjectContent::SequenceIterator(obj_ref, 0),
+        None => {
+            let name = processor::type_name(&state.store, &obj_ref);
+            return raise(state, call_stack, typeerror, format!("'{}' object is not iterable", name))
+        },
+    };
+    let iterator = state.store.allocate(Object::new_instance(None, state.primitive_objects.iterator_type.clone(), content));
+    return_value(call_stack, iterator)
 }
 
-fn iter<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>) {
-    if args.len() != 1 {
-        panic!(format!("__primitives__.iter takes 1 arguments, not {}", args.len()))
+fn is_native_iterator(store: &ObjectStore, obj_ref: &ObjectRef) -> bool {
+    match store.deref(obj_ref).content {
+        ObjectContent::RandomAccessIterator(..) | ObjectContent::DictIterator(..) | ObjectContent::SequenceIterator(..) |
+        ObjectContent::ReversedIterator(..) | ObjectContent::Enumerate(..) | ObjectContent::Zip(..) |
+        ObjectContent::Map(..) | ObjectContent::Filter(..) | ObjectContent::Generator => true,
+        _ => false,
+    }
+}
+
+fn iter<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some(obj_ref) = one_argument(state, call_stack, "iter", args, kwargs) {
+        get_iter(state, call_stack, obj_ref)
     }
-    let iterator_ref = args.last().unwrap();
-    let iterator = state.store.deref(iterator_ref).clone();
-    match iterator.content {
+}
+
+/// Advance a native iterator.
+/// Returns None if an exception was raised, and Some(None) if the iterator is exhausted.
+pub fn native_iterator_next<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, iterator_ref: &ObjectRef) -> Option<Option<ObjectRef>> {
+    let runtimeerror = state.primitive_objects.runtimeerror.clone();
+    let content = state.store.deref(iterator_ref).content.clone();
+    match content {
+        ObjectContent::Generator => processor::resume_generator(state, call_stack, iterator_ref),
         ObjectContent::RandomAccessIterator(container_ref, index, container_version) => {
-            let value = {
+            let (value, next_index) = {
                 let container = state.store.deref(&container_ref);
-                if container.version != container_version {
-                    panic!("Container changed while iterating.")
-                };
                 match container.content {
-                    ObjectContent::List(ref v) | ObjectContent::Tuple(ref v) => v.get(index).map(|r| r.clone()),
+                    // Like CPython, lists and bytearrays can be mutated while iterating;
+                    // the iterator sees their new content. Other sequences are immutable.
+                    ObjectContent::List(ref v) | ObjectContent::Tuple(ref v) => (v.get(index).cloned().map(Ok), index+1),
+                    ObjectContent::String(ref s) => {
+                        match s[index..].chars().next() {
+                            Some(c) => (Some(Err(state.primitive_objects.new_string(c.to_string()))), index+c.len_utf8()),
+                            None => (None, index),
+                        }
+                    },
+                    ObjectContent::Bytes(ref v) | ObjectContent::ByteArray(ref v) => (v.get(index).map(|b| Err(state.primitive_objects.new_int(*b as i64))), index+1),
+                    ObjectContent::Range(start, stop, step) => {
+                        let value = if (index as i64) < iterators::range_len(start, stop, step) { Some(Err(state.primitive_objects.new_int(start + (index as i64) * step))) } else { None };
+                        (value, index+1)
+                    },
                     _ => panic!(format!("RandomAccessIterator does not support {}", container_ref.repr(&state.store)))
                 }
             };
-            match value {
-                Some(value) => {
-                    let mut iterator = state.store.deref_mut(iterator_ref);
-                    iterator.content = ObjectContent::RandomAccessIterator(container_ref, index+1, container_version);
-                    return_value(call_stack, value.clone())
+            // Items of str and bytes are new objects, allocated once the container is released.
+            let value = match value {
+                None => return Some(None),
+                Some(Ok(value)) => value,
+                Some(Err(new_object)) => state.store.allocate(new_object),
+            };
+            state.store.deref_mut(iterator_ref).content = ObjectContent::RandomAccessIterator(container_ref, next_index, container_version);
+            Some(Some(value))
+        },
+        ObjectContent::DictIterator(dict_ref, index, dict_version, view) => {
+            let item = {
+                let dict = state.store.deref(&dict_ref);
+                if dict.version != dict_version {
+                    let message = match dict.content {
+                        ObjectContent::Set(_) => "Set changed size during iteration",
+                        _ => "dictionary changed size during iteration",
+                    };
+                    raise(state, call_stack, runtimeerror, message.to_string());
+                    return None
                 }
-                None => {
-                    let stopiteration = state.primitive_objects.stopiteration.clone();
+                match dict.content {
+                    ObjectContent::Set(ref items) | ObjectContent::FrozenSet(ref items) | ObjectContent::Dict(ref items) => items.next_entry(index).map(|(position, entry)| (position, entry.key.clone(), entry.value.clone())),
+                    _ => panic!(format!("DictIterator does not support {}", dict_ref.repr(&state.store)))
+                }
+            };
+            let (position, key, value) = match item {
+                Some(item) => item,
+                None => return Some(None),
+            };
+            state.store.deref_mut(iterator_ref).content = ObjectContent::DictIterator(dict_ref, position+1, dict_version, view);
+            match view {
+                DictView::Keys => Some(Some(key)),
+                DictView::Values => Some(Some(value)),
+                DictView::Items => Some(Some(state.store.allocate(state.primitive_objects.new_tuple(vec![key, value])))),
+            }
+        },
+        ObjectContent::SequenceIterator(container_ref, index) => {
+            let getitem = processor::lookup_special_method(state, &container_ref, "__getitem__").unwrap(); // Checked by get_iter
+            let index_ref = state.store.allocate(state.primitive_objects.new_int(index as i64));
+            let indexerror = state.primitive_objects.indexerror.clone();
+            match processor::call_function_catching(state, call_stack, &getitem, vec![index_ref], vec![], &indexerror) {
+                None => Some(None),
+                Some(PyResult::Raised) => None,
+                Some(PyResult::Return(value)) => {
+                    state.store.deref_mut(iterator_ref).content = ObjectContent::SequenceIterator(container_ref, index+1);
+                    Some(Some(value))
+                },
+            }
+        },
+        ObjectContent::ReversedIterator(..) | ObjectContent::Enumerate(..) | ObjectContent::Zip(..) |
+        ObjectContent::Map(..) | ObjectContent::Filter(..) => iterators::wrapper_next(state, call_stack, iterator_ref, content),
+        _ => {
+            let typeerror = state.primitive_objects.typeerror.clone();
+            let name = processor::type_name(&state.store, iterator_ref);
+            raise(state, call_stack, typeerror, format!("'{}' object is not an iterator", name));
+            None
+        },
+    }
+}
+
+/// next(), without the default value. Raises StopIteration if the iterator is exhausted.
+fn next<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let iterator_ref = match one_argument(state, call_stack, "next", args, kwargs) {
+        Some(iterator_ref) => iterator_ref,
+        None => return,
+    };
+    if let Some(method) = processor::lookup_special_method(state, &iterator_ref, "__next__") {
+        return processor::call_function(state, call_stack, &method, vec![], vec![])
+    }
+    match native_iterator_next(state, call_stack, &iterator_ref) {
+        None => (),
+        Some(Some(value)) => return_value(call_stack, value),
+        Some(None) => {
+            let stopiteration = state.primitive_objects.stopiteration.clone();
+            raise(state, call_stack, stopiteration, "".to_string())
+        },
+    }
+}
+
+fn one_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> Option<ObjectRef> {
+    let exc = state.primitive_objects.typeerror.clone();
+    if kwargs.len() != 0 {
+        raise(state, call_stack, exc, format!("{}() takes no keyword arguments", funcname));
+        return None
+    }
+    if args.len() != 1 {
+        raise(state, call_stack, exc, format!("{}() takes exactly one argument ({} given)", funcname, args.len()));
+        return None
+    }
+    args.pop()
+}
+
+fn len<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let obj_ref = match one_argument(state, call_stack, "len", args, kwargs) {
+        Some(obj_ref) => obj_ref,
+        None => return,
+    };
+    let typeerror = state.primitive_objects.typeerror.clone();
+    if let Some(method) = processor::lookup_special_method(state, &obj_ref, "__len__") {
+        let res = match call_function_sync(state, call_stack, &method, vec![], vec![]) {
+            PyResult::Return(res) => res,
+            PyResult::Raised => return,
+        };
+        return match state.store.deref(&res).content {
+            ObjectContent::Int(i) if i < 0 => {
+                let exc = state.primitive_objects.valueerror.clone();
+                raise(state, call_stack, exc, "__len__() should return >= 0".to_string())
+            },
+            ObjectContent::Int(_) => return_value(call_stack, res),
+            _ => {
+                let name = processor::type_name(&state.store, &res);
+                raise(state, call_stack, typeerror, format!("'{}' object cannot be interpreted as an integer", name))
+            },
+        }
+    }
+    let len = match native_len(&state.store, &obj_ref) {
+        Some(len) => len,
+        None => {
+            let name = processor::type_name(&state.store, &obj_ref);
+            return raise(state, call_stack, typeerror, format!("object of type '{}' has no len()", name))
+        },
+    };
+    let len = state.store.allocate(state.primitive_objects.new_int(len as i64));
+    return_value(call_stack, len)
+}
+
+/// Length of a builtin container, or None if the object is not one.
+fn native_len(store: &ObjectStore, obj_ref: &ObjectRef) -> Option<usize> {
+    match store.deref(obj_ref).content {
+        ObjectContent::String(ref s) => Some(s.chars().count()),
+        ObjectContent::Bytes(ref v) | ObjectContent::ByteArray(ref v) => Some(v.len()),
+        ObjectContent::Tuple(ref v) | ObjectContent::List(ref v) => Some(v.len()),
+        ObjectContent::Set(ref v) | ObjectContent::FrozenSet(ref v) | ObjectContent::Dict(ref v) => Some(v.len()),
+        ObjectContent::DictView(ref dict, _) => match store.deref(dict).content {
+            ObjectContent::Dict(ref v) => Some(v.len()),
+            _ => panic!("view of a non-dict object"),
+        },
+        ObjectContent::Range(start, stop, step) => Some(iterators::range_len(start, stop, step) as usize),
+        _ => None,
+    }
+}
+
+/// sum(), which adds numbers natively and other objects with `__add__` or `__radd__`.
+fn sum<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("sum", state, call_stack, kwargs);
+    let (iterable, start) = match bind_arguments(state, call_stack, "sum", &["iterable", "start"], 1, args, vec![]) {
+        Some(mut values) => (values.remove(0).unwrap(), values.remove(0)),
+        None => return,
+    };
+    let mut total = match start {
+        Some(start) => start,
+        None => state.store.allocate(state.primitive_objects.new_int(0)),
+    };
+    let message = match state.store.deref(&total).content {
+        ObjectContent::String(_) => Some("sum() can't sum strings [use ''.join(seq) instead]"),
+        ObjectContent::Bytes(_) => Some("sum() can't sum bytes [use b''.join(seq) instead]"),
+        ObjectContent::ByteArray(_) => Some("sum() can't sum bytearray [use b''.join(seq) instead]"),
+        _ => None,
+    };
+    if let Some(message) = message {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, message.to_string())
+    }
+    let items = match container_items(state, call_stack, &iterable) {
+        Some(items) => items,
+        None => return,
+    };
+    for item in items {
+        total = match numbers::add_numbers(state, call_stack, &total, &item) {
+            Some(Some(res)) => res,
+            Some(None) => return,
+            None => match processor::binary_operator_sync(state, call_stack, total, item, "__add__", "__radd__", "+") {
+                PyResult::Return(res) => res,
+                PyResult::Raised => return,
+            },
+        };
+    }
+    return_value(call_stack, total)
+}
+
+// FNV-1a, used for strings and bytes
+fn hash_bytes(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(2166136261, |hash, byte| (hash ^ (*byte as u32)).wrapping_mul(16777619))
+}
+
+// Does not depend on the order of the items, and reuses their hashes.
+fn frozenset_hash(items: &DictContent) -> u32 {
+    items.iter().fold(1927868237, |hash, entry| hash ^ entry.hash.wrapping_mul(3644798167))
+}
+
+/// Hash of an object of a builtin immutable type, computed without running Python code.
+/// Returns None for other objects. Agrees with object_hash.
+pub fn native_hash(store: &ObjectStore, obj_ref: &ObjectRef) -> Option<u32> {
+    match store.deref(obj_ref).content {
+        ObjectContent::None | ObjectContent::NotImplemented => Some(obj_ref.id() as u32),
+        ObjectContent::Int(i) => Some(i as u32),
+        ObjectContent::True => Some(1),
+        ObjectContent::False => Some(0),
+        ObjectContent::Float(bits) => Some(numbers::float_hash(f64::from_bits(bits))),
+        ObjectContent::String(ref s) => Some(hash_bytes(s.as_bytes())),
+        ObjectContent::Bytes(ref v) => Some(hash_bytes(v)),
+        ObjectContent::Tuple(ref v) => {
+            let mut hash: u32 = 0x345678;
+            for item in v.iter() {
+                hash = (hash ^ native_hash(store, item)?).wrapping_mul(1000003);
+            }
+            Some(hash)
+        },
+        ObjectContent::FrozenSet(ref v) => Some(frozenset_hash(v)),
+        _ => None,
+    }
+}
+
+/// Hash of an object, using the __hash__ method of its class if it has one.
+/// Returns None if an exception was raised.
+pub fn object_hash<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<u32> {
+    let typeerror = state.primitive_objects.typeerror.clone();
+    if let Some(method) = processor::lookup_special_method(state, obj_ref, "__hash__") {
+        if method.is(&state.primitive_objects.none) {
+            let name = processor::type_name(&state.store, obj_ref);
+            raise(state, call_stack, typeerror, format!("unhashable type: '{}'", name));
+            return None
+        }
+        let res = match call_function_sync(state, call_stack, &method, vec![], vec![]) {
+            PyResult::Return(res) => res,
+            PyResult::Raised => return None,
+        };
+        return match state.store.deref(&res).content {
+            ObjectContent::Int(i) => Some(i as u32),
+            ObjectContent::True => Some(1),
+            ObjectContent::False => Some(0),
+            _ => {
+                raise(state, call_stack, typeerror, "__hash__ method should return an integer".to_string());
+                None
+            },
+        }
+    }
+    let content = state.store.deref(obj_ref).content.clone(); // TODO: copy only if needed
+    match content {
+        ObjectContent::Int(i) => Some(i as u32),
+        ObjectContent::True => Some(1),
+        ObjectContent::False => Some(0),
+        ObjectContent::Float(bits) => Some(numbers::float_hash(f64::from_bits(bits))),
+        ObjectContent::String(ref s) => Some(hash_bytes(s.as_bytes())),
+        ObjectContent::Bytes(ref v) => Some(hash_bytes(v)),
+        ObjectContent::Tuple(ref v) => {
+            let mut hash: u32 = 0x345678;
+            for item in v.iter() {
+                hash = (hash ^ object_hash(state, call_stack, item)?).wrapping_mul(1000003);
+            }
+            Some(hash)
+        },
+        ObjectContent::FrozenSet(ref v) => Some(frozenset_hash(v)),
+        ObjectContent::List(_) | ObjectContent::Set(_) | ObjectContent::Dict(_) | ObjectContent::ByteArray(_) |
+        ObjectContent::DictView(_, DictView::Keys) | ObjectContent::DictView(_, DictView::Items) => {
+            let name = processor::type_name(&state.store, obj_ref);
+            raise(state, call_stack, typeerror, format!("unhashable type: '{}'", name));
+            None
+        },
+        _ => Some(obj_ref.id() as u32),
+    }
+}
 
-                    return raise(state, call_stack, stopiteration, "StopIteration instance".to_string())
+fn hash<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let obj_ref = match one_argument(state, call_stack, "hash", args, kwargs) {
+        Some(obj_ref) => obj_ref,
+        None => return,
+    };
+    if let Some(hash) = object_hash(state, call_stack, &obj_ref) {
+        // Hashes are 32 bits, so small negative ints are their own hash, except
+        // -1 which CPython reserves to signal errors.
+        let hash = match hash as i32 as i64 {
+            -1 => -2,
+            hash => hash,
+        };
+        let hash = state.store.allocate(state.primitive_objects.new_int(hash));
+        return_value(call_stack, hash)
+    }
+}
+
+fn repr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let obj_ref = match one_argument(state, call_stack, "repr", args, kwargs) {
+        Some(obj_ref) => obj_ref,
+        None => return,
+    };
+    if let Some(s) = processor::repr_of(state, call_stack, &obj_ref) {
+        let s = state.store.allocate(state.primitive_objects.new_string(s));
+        return_value(call_stack, s)
+    }
+}
+
+fn property_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("property.__new__", state, call_stack, args);
+    let mut values = match bind_arguments(state, call_stack, "property", &["fget", "fset", "fdel", "doc"], 0, args, kwargs) {
+        Some(values) => values.into_iter(),
+        None => return,
+    };
+    let none = state.primitive_objects.none.clone();
+    let fget = values.next().unwrap().unwrap_or(none.clone());
+    let fset = values.next().unwrap().unwrap_or(none.clone());
+    let fdel = values.next().unwrap().unwrap_or(none.clone());
+    let mut doc = values.next().unwrap().unwrap_or(none.clone());
+    if doc.is(&none) && !fget.is(&none) {
+        // Like CPython, the docstring defaults to the getter's
+        let attributeerror = state.primitive_objects.attributeerror.clone();
+        match processor::load_attr_catching(state, call_stack, &fget, &"__doc__".to_string(), &attributeerror) {
+            Some(PyResult::Return(fget_doc)) => doc = fget_doc,
+            Some(PyResult::Raised) => return,
+            None => (),
+        }
+    }
+    let property = state.store.allocate(Object::new_instance(None, cls, ObjectContent::Property(fget, fset, fdel, doc)));
+    return_value(call_stack, property)
+}
+
+/// Shared code of property.getter, property.setter, and property.deleter:
+/// returns a copy of the property with one of the functions replaced.
+fn property_replace<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!(funcname, state, call_stack, kwargs);
+    if args.len() != 2 {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, format!("{}() takes exactly one argument ({} given)", funcname, args.len().saturating_sub(1)))
+    }
+    let func = args.get(1).unwrap().clone();
+    let property = state.store.deref(args.get(0).unwrap()).clone();
+    let content = match property.content {
+        ObjectContent::Property(fget, fset, fdel, doc) => {
+            match funcname {
+                "getter" => ObjectContent::Property(func, fset, fdel, doc),
+                "setter" => ObjectContent::Property(fget, func, fdel, doc),
+                "deleter" => ObjectContent::Property(fget, fset, func, doc),
+                _ => panic!("Unknown property method {}", funcname),
+            }
+        },
+        _ => {
+            let exc = state.primitive_objects.typeerror.clone();
+            return raise(state, call_stack, exc, format!("descriptor '{}' requires a 'property' object", funcname))
+        }
+    };
+    let new_property = state.store.allocate(Object::new_instance(None, property.class, content));
+    return_value(call_stack, new_property)
+}
+
+fn property_getter<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    property_replace(state, call_stack, "getter", args, kwargs)
+}
+
+fn property_setter<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    property_replace(state, call_stack, "setter", args, kwargs)
+}
+
+fn property_deleter<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    property_replace(state, call_stack, "deleter", args, kwargs)
+}
+
+fn super_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("super.__new__", state, call_stack, args);
+    check_no_kwargs!("super", state, call_stack, kwargs);
+    let runtimeerror = state.primitive_objects.runtimeerror.clone();
+    let typeerror = state.primitive_objects.typeerror.clone();
+    let (this_class, instance) = match args.len() {
+        0 => {
+            // Use the __class__ cell and the first argument of the calling function.
+            let (this_class, instance) = {
+                let frame = call_stack.iter().rev().find(|frame| !frame.is_barrier()).unwrap();
+                let mut this_class = None;
+                let mut first_arg = frame.code.varnames.first().and_then(|name| frame.locals.borrow().get(name).cloned());
+                let cell_names = frame.code.cellvars.iter().chain(frame.code.freevars.iter());
+                for (cell_name, cell) in cell_names.zip(frame.cells.iter()) {
+                    let cell_name = match state.store.deref(cell_name).content {
+                        ObjectContent::String(ref s) => s.clone(),
+                        _ => continue,
+                    };
+                    let value = match state.store.deref(cell).content {
+                        ObjectContent::Cell(ref value) => value.clone(),
+                        _ => None,
+                    };
+                    if cell_name == "__class__" {
+                        this_class = Some(value);
+                    }
+                    else if frame.code.argcount > 0 && Some(&cell_name) == frame.code.varnames.first() {
+                        // The first argument is also used by a nested function
+                        first_arg = value;
+                    }
                 }
+                if frame.code.argcount == 0 {
+                    first_arg = None;
+                }
+                (this_class, first_arg)
+            };
+            match (this_class, instance) {
+                (_, None) => return raise(state, call_stack, runtimeerror, "super(): no arguments".to_string()),
+                (None, _) => return raise(state, call_stack, runtimeerror, "super(): __class__ cell not found".to_string()),
+                (Some(None), _) => return raise(state, call_stack, runtimeerror, "super(): empty __class__ cell".to_string()),
+                (Some(Some(this_class)), Some(instance)) => (this_class, instance),
             }
+        },
+        2 => {
+            let instance = args.pop().unwrap();
+            (args.pop().unwrap(), instance)
+        },
+        nb_args => {
+            return raise(state, call_stack, typeerror, format!("super() takes 0 or 2 arguments ({} given)", nb_args))
+        },
+    };
+    if state.store.deref(&this_class).bases.is_none() {
+        let class_name = state.store.deref(&state.store.deref(&this_class).class).name.clone().unwrap_or("?".to_string());
+        return raise(state, call_stack, typeerror, format!("super() argument 1 must be type, not {}", class_name))
+    }
+    let is_subclass = state.store.deref(&instance).bases.is_some() && native_issubclass(&state.store, &instance, &this_class);
+    if !is_subclass && !native_isinstance(&state.store, &instance, &this_class) {
+        return raise(state, call_stack, typeerror, "super(type, obj): obj must be an instance or subtype of type".to_string())
+    }
+    let obj = state.store.allocate(Object::new_instance(None, cls, ObjectContent::Super(this_class, instance)));
+    return_value(call_stack, obj)
+}
+
+fn type_mro<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("mro", state, call_stack, kwargs);
+    if args.len() != 1 || state.store.deref(&args[0]).bases.is_none() {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, "descriptor 'mro' of 'type' object needs a type argument".to_string())
+    }
+    let mro = class_mro(&state.store, &args[0]).to_vec();
+    let list = state.store.allocate(state.primitive_objects.new_list(mro));
+    return_value(call_stack, list)
+}
+
+fn staticmethod_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("staticmethod", state, call_stack, kwargs);
+    if args.len() != 2 {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, format!("staticmethod expected 1 arguments, got {}", args.len().saturating_sub(1)))
+    }
+    let mut args_iter = args.into_iter();
+    let cls = args_iter.next().unwrap();
+    let func = args_iter.next().unwrap();
+    let method = state.store.allocate(Object::new_instance(None, cls, ObjectContent::StaticMethod(func)));
+    return_value(call_stack, method)
+}
+
+fn classmethod_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("classmethod", state, call_stack, kwargs);
+    if args.len() != 2 {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, format!("classmethod expected 1 arguments, got {}", args.len().saturating_sub(1)))
+    }
+    let mut args_iter = args.into_iter();
+    let cls = args_iter.next().unwrap();
+    let func = args_iter.next().unwrap();
+    let method = state.store.allocate(Object::new_instance(None, cls, ObjectContent::ClassMethod(func)));
+    return_value(call_stack, method)
+}
+
+/// Whether `class` uses the implementation of `name` inherited from `object`.
+fn uses_object_method<EP: EnvProxy>(state: &State<EP>, class: &ObjectRef, name: &str) -> bool {
+    let default = lookup_class_attribute(&state.store, &state.primitive_objects.object, name);
+    match (lookup_class_attribute(&state.store, class, name), default) {
+        (Some(method), Some(default)) => method.is(&default),
+        _ => false,
+    }
+}
+
+/// Contents of builtin containers, used to build other containers from them.
+/// Raises a TypeError and returns None for other objects.
+/// Returns the items of an iterable, or None if an exception was raised.
+pub fn container_items<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<Vec<ObjectRef>> {
+    let items = match state.store.deref(obj_ref).content {
+        ObjectContent::Tuple(ref v) | ObjectContent::List(ref v) => Some(v.clone()),
+        ObjectContent::Set(ref v) | ObjectContent::FrozenSet(ref v) | ObjectContent::Dict(ref v) => Some(v.keys()),
+        _ => None,
+    };
+    if items.is_some() {
+        return items
+    }
+    // Not a native container, use the iterator protocol
+    let iter = processor::primitive_function(state, "iter");
+    let iterator = match call_function_sync(state, call_stack, &iter, vec![obj_ref.clone()], vec![]) {
+        PyResult::Return(iterator) => iterator,
+        PyResult::Raised => return None,
+    };
+    let mut items = Vec::new();
+    loop {
+        match processor::iterator_next_sync(state, call_stack, &iterator) {
+            None => return Some(items),
+            Some(PyResult::Return(item)) => items.push(item),
+            Some(PyResult::Raised) => return None,
         }
-        _ =>  {
-            let repr = iterator_ref.repr(&state.store);
-            let exc = Object::new_instance(None, state.primitive_objects.typeerror.clone(), ObjectContent::OtherObject);
-            let exc = state.store.allocate(exc);
-            raise(state, call_stack, exc, format!("{} is not an iterator", repr));
+    }
+}
+
+fn object_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("object.__new__", state, call_stack, args);
+    if args.len() != 0 || kwargs.len() != 0 {
+        // Like CPython, extra arguments are only accepted if they are meant for __init__.
+        if !uses_object_method(state, &cls, "__new__") || uses_object_method(state, &cls, "__init__") {
+            let exc = state.primitive_objects.typeerror.clone();
+            return raise(state, call_stack, exc, "object() takes no parameters".to_string())
+        }
+    }
+    let (nb_slots, has_dict) = instance_layout(&state.store, &cls);
+    let mut instance = match has_dict {
+        true => Object::new_instance(None, cls, ObjectContent::OtherObject),
+        false => Object::new_builtin_instance(cls, ObjectContent::OtherObject),
+    };
+    instance.slots = vec![None; nb_slots];
+    let instance = state.store.allocate(instance);
+    return_value(call_stack, instance)
+}
+
+fn object_init<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if args.len() > 1 || kwargs.len() != 0 {
+        let cls = state.store.deref(args.first().unwrap()).class.clone();
+        if !uses_object_method(state, &cls, "__init__") || uses_object_method(state, &cls, "__new__") {
+            let exc = state.primitive_objects.typeerror.clone();
+            return raise(state, call_stack, exc, "object.__init__() takes no parameters".to_string())
         }
     }
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+/// Checks the arguments of the attribute access functions, and returns the object
+/// and the attribute name.
+fn attribute_arguments<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, nb_args: usize, args: &[ObjectRef], kwargs: &[(ObjectRef, ObjectRef)]) -> Option<(ObjectRef, String)> {
+    let exc = state.primitive_objects.typeerror.clone();
+    if kwargs.len() != 0 {
+        raise(state, call_stack, exc, format!("{}() takes no keyword arguments", funcname));
+        return None
+    }
+    if args.len() != nb_args {
+        raise(state, call_stack, exc, format!("{}() takes exactly {} arguments ({} given)", funcname, nb_args, args.len()));
+        return None
+    }
+    let name = match state.store.deref(&args[1]).content {
+        ObjectContent::String(ref s) => s.clone(),
+        _ => {
+            let class_name = state.store.deref(&state.store.deref(&args[1]).class).name.clone().unwrap_or("?".to_string());
+            raise(state, call_stack, exc, format!("attribute name must be string, not '{}'", class_name));
+            return None
+        },
+    };
+    Some((args[0].clone(), name))
+}
+
+fn object_getattribute<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((obj, name)) = attribute_arguments(state, call_stack, "__getattribute__", 2, &args, &kwargs) {
+        processor::generic_getattr(state, call_stack, &obj, &name, false)
+    }
+}
+
+fn object_setattr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((obj, name)) = attribute_arguments(state, call_stack, "__setattr__", 3, &args, &kwargs) {
+        if processor::generic_setattr(state, call_stack, obj, name, args[2].clone()) {
+            return_value(call_stack, state.primitive_objects.none.clone())
+        }
+    }
+}
+
+fn object_delattr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((obj, name)) = attribute_arguments(state, call_stack, "__delattr__", 2, &args, &kwargs) {
+        if processor::generic_delattr(state, call_stack, obj, name) {
+            return_value(call_stack, state.primitive_objects.none.clone())
+        }
+    }
+}
+
+fn getattr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((obj, name)) = attribute_arguments(state, call_stack, "getattr", 2, &args, &kwargs) {
+        processor::load_attr(state, call_stack, &obj, &name)
+    }
+}
+
+fn setattr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((obj, name)) = attribute_arguments(state, call_stack, "setattr", 3, &args, &kwargs) {
+        if processor::store_attr(state, call_stack, obj, name, args[2].clone()) {
+            return_value(call_stack, state.primitive_objects.none.clone())
+        }
+    }
+}
+
+fn delattr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((obj, name)) = attribute_arguments(state, call_stack, "delattr", 2, &args, &kwargs) {
+        if processor::delete_attr(state, call_stack, obj, name) {
+            return_value(call_stack, state.primitive_objects.none.clone())
+        }
+    }
+}
+
+fn nonetype_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    pop_cls!("NoneType.__new__", state, call_stack, args);
+    if args.len() != 0 || kwargs.len() != 0 {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, "NoneType takes no arguments".to_string())
+    }
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+fn int_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("int.__new__", state, call_stack, args);
+    let (x, base) = match bind_arguments(state, call_stack, "int", &["x", "base"], 0, args, kwargs) {
+        Some(mut values) => (values.remove(0), values.remove(0)),
+        None => return,
+    };
+    let value = match numbers::int_value(state, call_stack, x, base) {
+        Some(value) => value,
+        None => return,
+    };
+    let obj = state.store.allocate(Object::new_instance(None, cls, ObjectContent::Int(value)));
+    return_value(call_stack, obj)
+}
+
+fn int_bit_length<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("bit_length", state, call_stack, kwargs);
+    let mut value = 0;
+    parse_arguments!("int.bit_length", state.store, args,
+        "self" "an integer": {
+            ObjectContent::Int(i) => { value = i; },
+            ObjectContent::True => { value = 1; },
+            ObjectContent::False => { value = 0; },
+        }
+    );
+    let res = state.store.allocate(state.primitive_objects.new_int(64 - value.unsigned_abs().leading_zeros() as i64));
+    return_value(call_stack, res)
+}
+
+fn bool_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    pop_cls!("bool.__new__", state, call_stack, args);
+    check_no_kwargs!("bool", state, call_stack, kwargs);
+    let value = match bind_arguments(state, call_stack, "bool", &["x"], 0, args, vec![]) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let value = match value {
+        Some(value) => match processor::truth_value(state, call_stack, &value) {
+            Some(value) => value,
+            None => return,
+        },
+        None => false,
+    };
+    let res = if value { state.primitive_objects.true_obj.clone() } else { state.primitive_objects.false_obj.clone() };
+    return_value(call_stack, res)
+}
+
+fn str_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("str.__new__", state, call_stack, args);
+    let value = match bind_arguments(state, call_stack, "str", &["object"], 0, args, kwargs) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let s = match value {
+        None => "".to_string(),
+        Some(value) => match processor::str_of(state, call_stack, &value) {
+            Some(s) => s,
+            None => return,
+        },
+    };
+    let obj = state.store.allocate(Object::new_instance(None, cls, ObjectContent::String(s)));
+    return_value(call_stack, obj)
+}
+
+fn bytes_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("bytes.__new__", state, call_stack, args);
+    let mut values = match bind_arguments(state, call_stack, "bytes", &["source", "encoding", "errors"], 0, args, kwargs) {
+        Some(values) => values,
+        None => return,
+    };
+    let bytes = match bytes::source_bytes(state, call_stack, values.remove(0), values.remove(0), values.remove(0)) {
+        Some(bytes) => bytes,
+        None => return,
+    };
+    let obj = state.store.allocate(Object::new_instance(None, cls, ObjectContent::Bytes(bytes)));
+    return_value(call_stack, obj)
+}
+
+fn tuple_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("tuple.__new__", state, call_stack, args);
+    let iterable = match bind_arguments(state, call_stack, "tuple", &["iterable"], 0, args, kwargs) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let items = match iterable {
+        None => vec![],
+        Some(iterable) => match container_items(state, call_stack, &iterable) {
+            Some(items) => items,
+            None => return,
+        },
+    };
+    let obj = state.store.allocate(Object::new_instance(None, cls, ObjectContent::Tuple(items)));
+    return_value(call_stack, obj)
+}
+
+/// __new__ of mutable containers, which are filled by __init__
+fn new_empty_container<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, content: ObjectContent) {
+    let cls = pop_cls!(funcname, state, call_stack, args);
+    let obj = state.store.allocate(Object::new_instance(None, cls, content));
+    return_value(call_stack, obj)
+}
+
+fn list_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    new_empty_container(state, call_stack, "list.__new__", args, ObjectContent::List(vec![]))
+}
+
+fn set_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    new_empty_container(state, call_stack, "set.__new__", args, ObjectContent::Set(DictContent::new()))
+}
+
+fn bytearray_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    new_empty_container(state, call_stack, "bytearray.__new__", args, ObjectContent::ByteArray(vec![]))
+}
+
+fn dict_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    new_empty_container(state, call_stack, "dict.__new__", args, ObjectContent::Dict(DictContent::new()))
+}
+
+fn list_init<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let list = args.remove(0);
+    check_no_kwargs!("list", state, call_stack, kwargs);
+    let iterable = match bind_arguments(state, call_stack, "list", &["iterable"], 0, args, vec![]) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let items = match iterable {
+        None => vec![],
+        Some(iterable) => match container_items(state, call_stack, &iterable) {
+            Some(items) => items,
+            None => return,
+        },
+    };
+    state.store.deref_mut(&list).content = ObjectContent::List(items);
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+fn baseexception_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("BaseException.__new__", state, call_stack, args);
+    let exc_args = state.store.allocate(state.primitive_objects.new_tuple(args));
+    let exc = state.store.allocate(Object::new_instance(None, cls, ObjectContent::OtherObject));
+    exc.setattr(&mut state.store, "args".to_string(), exc_args);
+    return_value(call_stack, exc)
+}
+
+fn baseexception_init<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let exc = args.remove(0);
+    check_no_kwargs!("BaseException", state, call_stack, kwargs);
+    let exc_args = state.store.allocate(state.primitive_objects.new_tuple(args));
+    exc.setattr(&mut state.store, "args".to_string(), exc_args);
+    return_value(call_stack, state.primitive_objects.none.clone())
 }
 
 
@@ -214,5 +1507,221 @@ pub fn get_default_primitives<EP: EnvProxy>() -> HashMap<String, PyFunction<EP>>
     builtins.insert("issubclass".to_string(), issubclass);
     builtins.insert("isinstance".to_string(), isinstance);
     builtins.insert("iter".to_string(), iter);
+    builtins.insert("next".to_string(), next);
+    builtins.insert("len".to_string(), len);
+    builtins.insert("sum".to_string(), sum);
+    builtins.insert("hash".to_string(), hash);
+    builtins.insert("repr".to_string(), repr);
+    builtins.insert("abs".to_string(), numbers::abs);
+    builtins.insert("min".to_string(), numbers::min);
+    builtins.insert("max".to_string(), numbers::max);
+    builtins.insert("divmod".to_string(), numbers::divmod);
+    builtins.insert("pow".to_string(), numbers::pow);
+    builtins.insert("round".to_string(), numbers::round);
+    builtins.insert("chr".to_string(), numbers::chr);
+    builtins.insert("ord".to_string(), numbers::ord);
+    builtins.insert("hex".to_string(), numbers::hex);
+    builtins.insert("oct".to_string(), numbers::oct);
+    builtins.insert("bin".to_string(), numbers::bin);
+    builtins.insert("format".to_string(), formatting::format);
+    builtins.insert("getattr".to_string(), getattr);
+    builtins.insert("setattr".to_string(), setattr);
+    builtins.insert("delattr".to_string(), delattr);
+    builtins.insert("object.__new__".to_string(), object_new);
+    builtins.insert("object.__init__".to_string(), object_init);
+    builtins.insert("object.__init_subclass__".to_string(), object_init_subclass);
+    builtins.insert("object.__getattribute__".to_string(), object_getattribute);
+    builtins.insert("object.__setattr__".to_string(), object_setattr);
+    builtins.insert("object.__delattr__".to_string(), object_delattr);
+    builtins.insert("object.__format__".to_string(), formatting::object_format);
+    builtins.insert("type.__new__".to_string(), type_new);
+    builtins.insert("type.__init__".to_string(), type_init);
+    builtins.insert("type.__prepare__".to_string(), type_prepare);
+    builtins.insert("type.__call__".to_string(), type_call);
+    builtins.insert("nonetype.__new__".to_string(), nonetype_new);
+    builtins.insert("int.__new__".to_string(), int_new);
+    builtins.insert("range.__new__".to_string(), iterators::range_new);
+    builtins.insert("reversed.__new__".to_string(), iterators::reversed_new);
+    builtins.insert("enumerate.__new__".to_string(), iterators::enumerate_new);
+    builtins.insert("zip.__new__".to_string(), iterators::zip_new);
+    builtins.insert("map.__new__".to_string(), iterators::map_new);
+    builtins.insert("filter.__new__".to_string(), iterators::filter_new);
+    builtins.insert("int.bit_length".to_string(), int_bit_length);
+    builtins.insert("int.__format__".to_string(), formatting::int_format);
+    builtins.insert("int.__sub__".to_string(), numbers::int_sub);
+    builtins.insert("int.__rsub__".to_string(), numbers::int_rsub);
+    builtins.insert("int.__and__".to_string(), numbers::int_and);
+    builtins.insert("int.__rand__".to_string(), numbers::int_and);
+    builtins.insert("int.__xor__".to_string(), numbers::int_xor);
+    builtins.insert("int.__rxor__".to_string(), numbers::int_xor);
+    builtins.insert("int.__or__".to_string(), numbers::int_or);
+    builtins.insert("int.__ror__".to_string(), numbers::int_or);
+    builtins.insert("float.__new__".to_string(), numbers::float_new);
+    builtins.insert("float.__format__".to_string(), formatting::float_format);
+    builtins.insert("float.__add__".to_string(), numbers::float_add);
+    builtins.insert("float.__radd__".to_string(), numbers::float_radd);
+    builtins.insert("float.__sub__".to_string(), numbers::float_sub);
+    builtins.insert("float.__rsub__".to_string(), numbers::float_rsub);
+    builtins.insert("float.__mul__".to_string(), numbers::float_mul);
+    builtins.insert("float.__rmul__".to_string(), numbers::float_rmul);
+    builtins.insert("bool.__new__".to_string(), bool_new);
+    builtins.insert("str.__new__".to_string(), str_new);
+    builtins.insert("str.__format__".to_string(), formatting::str_format);
+    builtins.insert("str.__mod__".to_string(), formatting::str_mod);
+    builtins.insert("str.format".to_string(), formatting::format_method);
+    builtins.insert("str.join".to_string(), strings::join);
+    builtins.insert("str.split".to_string(), strings::split);
+    builtins.insert("str.rsplit".to_string(), strings::rsplit);
+    builtins.insert("str.strip".to_string(), strings::strip);
+    builtins.insert("str.lstrip".to_string(), strings::lstrip);
+    builtins.insert("str.rstrip".to_string(), strings::rstrip);
+    builtins.insert("str.find".to_string(), strings::find);
+    builtins.insert("str.rfind".to_string(), strings::rfind);
+    builtins.insert("str.index".to_string(), strings::index);
+    builtins.insert("str.rindex".to_string(), strings::rindex);
+    builtins.insert("str.count".to_string(), strings::count);
+    builtins.insert("str.startswith".to_string(), strings::startswith);
+    builtins.insert("str.endswith".to_string(), strings::endswith);
+    builtins.insert("str.replace".to_string(), strings::replace);
+    builtins.insert("str.upper".to_string(), strings::upper);
+    builtins.insert("str.lower".to_string(), strings::lower);
+    builtins.insert("str.casefold".to_string(), strings::casefold);
+    builtins.insert("str.isdigit".to_string(), strings::isdigit);
+    builtins.insert("str.isdecimal".to_string(), strings::isdecimal);
+    builtins.insert("str.isnumeric".to_string(), strings::isnumeric);
+    builtins.insert("str.isalpha".to_string(), strings::isalpha);
+    builtins.insert("str.isalnum".to_string(), strings::isalnum);
+    builtins.insert("str.isspace".to_string(), strings::isspace);
+    builtins.insert("str.isupper".to_string(), strings::isupper);
+    builtins.insert("str.islower".to_string(), strings::islower);
+    builtins.insert("str.partition".to_string(), strings::partition);
+    builtins.insert("str.rpartition".to_string(), strings::rpartition);
+    builtins.insert("str.splitlines".to_string(), strings::splitlines);
+    builtins.insert("str.center".to_string(), strings::center);
+    builtins.insert("str.ljust".to_string(), strings::ljust);
+    builtins.insert("str.rjust".to_string(), strings::rjust);
+    builtins.insert("str.zfill".to_string(), strings::zfill);
+    builtins.insert("str.encode".to_string(), strings::encode);
+    builtins.insert("bytes.__new__".to_string(), bytes_new);
+    // Methods shared by bytes and bytearray
+    let bytes_methods: [(&str, PyFunction<EP>); 27] = [
+        ("decode", bytes::decode),
+        ("hex", bytes::hex),
+        ("fromhex", bytes::fromhex),
+        ("join", bytes::join),
+        ("split", bytes::split),
+        ("rsplit", bytes::rsplit),
+        ("strip", bytes::strip),
+        ("lstrip", bytes::lstrip),
+        ("rstrip", bytes::rstrip),
+        ("find", bytes::find),
+        ("rfind", bytes::rfind),
+        ("index", bytes::index),
+        ("rindex", bytes::rindex),
+        ("count", bytes::count),
+        ("startswith", bytes::startswith),
+        ("endswith", bytes::endswith),
+        ("replace", bytes::replace),
+        ("upper", bytes::upper),
+        ("lower", bytes::lower),
+        ("isdigit", bytes::isdigit),
+        ("isalpha", bytes::isalpha),
+        ("isalnum", bytes::isalnum),
+        ("isspace", bytes::isspace),
+        ("isupper", bytes::isupper),
+        ("islower", bytes::islower),
+        ("partition", bytes::partition),
+        ("rpartition", bytes::rpartition),
+    ];
+    for &(name, function) in bytes_methods.iter() {
+        builtins.insert(format!("bytes.{}", name), function);
+        builtins.insert(format!("bytearray.{}", name), function);
+    }
+    builtins.insert("bytearray.__new__".to_string(), bytearray_new);
+    builtins.insert("bytearray.__init__".to_string(), bytes::bytearray_init);
+    builtins.insert("bytearray.append".to_string(), bytes::append);
+    builtins.insert("bytearray.extend".to_string(), bytes::extend);
+    builtins.insert("bytearray.insert".to_string(), bytes::insert);
+    builtins.insert("bytearray.pop".to_string(), bytes::pop);
+    builtins.insert("bytearray.remove".to_string(), bytes::remove);
+    builtins.insert("bytearray.clear".to_string(), bytes::clear);
+    builtins.insert("bytearray.copy".to_string(), bytes::copy);
+    builtins.insert("bytearray.reverse".to_string(), bytes::reverse);
+    builtins.insert("tuple.__new__".to_string(), tuple_new);
+    builtins.insert("frozenset.__new__".to_string(), sets::frozenset_new);
+    builtins.insert("list.__new__".to_string(), list_new);
+    builtins.insert("list.__init__".to_string(), list_init);
+    builtins.insert("list.append".to_string(), lists::append);
+    builtins.insert("list.extend".to_string(), lists::extend);
+    builtins.insert("list.insert".to_string(), lists::insert);
+    builtins.insert("list.pop".to_string(), lists::pop);
+    builtins.insert("list.remove".to_string(), lists::remove);
+    builtins.insert("list.index".to_string(), lists::index);
+    builtins.insert("list.count".to_string(), lists::count);
+    builtins.insert("list.clear".to_string(), lists::clear);
+    builtins.insert("list.copy".to_string(), lists::copy);
+    builtins.insert("list.reverse".to_string(), lists::reverse);
+    builtins.insert("list.sort".to_string(), lists::sort);
+    builtins.insert("set.__new__".to_string(), set_new);
+    builtins.insert("set.__init__".to_string(), sets::set_init);
+    builtins.insert("set.add".to_string(), sets::add);
+    builtins.insert("set.discard".to_string(), sets::discard);
+    builtins.insert("set.remove".to_string(), sets::remove);
+    builtins.insert("set.pop".to_string(), sets::pop);
+    builtins.insert("set.clear".to_string(), sets::clear);
+    builtins.insert("set.update".to_string(), sets::update);
+    builtins.insert("set.intersection_update".to_string(), sets::intersection_update);
+    builtins.insert("set.difference_update".to_string(), sets::difference_update);
+    builtins.insert("set.symmetric_difference_update".to_string(), sets::symmetric_difference_update);
+    builtins.insert("set.__ior__".to_string(), sets::ior);
+    builtins.insert("set.__iand__".to_string(), sets::iand);
+    builtins.insert("set.__isub__".to_string(), sets::isub);
+    builtins.insert("set.__ixor__".to_string(), sets::ixor);
+    // Methods shared by set and frozenset
+    let set_methods: [(&str, PyFunction<EP>); 16] = [
+        ("copy", sets::copy),
+        ("union", sets::union),
+        ("intersection", sets::intersection),
+        ("difference", sets::difference),
+        ("symmetric_difference", sets::symmetric_difference),
+        ("issubset", sets::issubset),
+        ("issuperset", sets::issuperset),
+        ("isdisjoint", sets::isdisjoint),
+        ("__or__", sets::or),
+        ("__and__", sets::and),
+        ("__sub__", sets::sub),
+        ("__xor__", sets::xor),
+        ("__le__", sets::le),
+        ("__lt__", sets::lt),
+        ("__ge__", sets::ge),
+        ("__gt__", sets::gt),
+    ];
+    for &(name, function) in set_methods.iter() {
+        builtins.insert(format!("set.{}", name), function);
+        builtins.insert(format!("frozenset.{}", name), function);
+    }
+    builtins.insert("dict.__new__".to_string(), dict_new);
+    builtins.insert("dict.__init__".to_string(), dicts::dict_init);
+    builtins.insert("dict.get".to_string(), dicts::get);
+    builtins.insert("dict.setdefault".to_string(), dicts::setdefault);
+    builtins.insert("dict.pop".to_string(), dicts::pop);
+    builtins.insert("dict.popitem".to_string(), dicts::popitem);
+    builtins.insert("dict.update".to_string(), dicts::update);
+    builtins.insert("dict.keys".to_string(), dicts::keys);
+    builtins.insert("dict.values".to_string(), dicts::values);
+    builtins.insert("dict.items".to_string(), dicts::items);
+    builtins.insert("dict.fromkeys".to_string(), dicts::fromkeys);
+    builtins.insert("dict.copy".to_string(), dicts::copy);
+    builtins.insert("dict.clear".to_string(), dicts::clear);
+    builtins.insert("BaseException.__new__".to_string(), baseexception_new);
+    builtins.insert("BaseException.__init__".to_string(), baseexception_init);
+    builtins.insert("property.__new__".to_string(), property_new);
+    builtins.insert("property.getter".to_string(), property_getter);
+    builtins.insert("property.setter".to_string(), property_setter);
+    builtins.insert("property.deleter".to_string(), property_deleter);
+    builtins.insert("staticmethod.__new__".to_string(), staticmethod_new);
+    builtins.insert("super.__new__".to_string(), super_new);
+    builtins.insert("type.mro".to_string(), type_mro);
+    builtins.insert("classmethod.__new__".to_string(), classmethod_new);
     builtins
 }
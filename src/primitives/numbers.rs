@@ -0,0 +1,856 @@
+//! The `float` type, the operators of ints and floats, and the builtin functions
+//! which convert and combine numbers.
+//!
+//! Ints are signed 64-bit integers, so operations whose result is too large
+//! raise an OverflowError. Objects which are not numbers are
+//! handled by their special methods (`__abs__`, `__divmod__`, `__pow__`, ...).
+
+use super::super::sandbox::EnvProxy;
+use super::super::state::{State, PyResult, raise, return_value};
+use super::super::objects::{ObjectRef, ObjectContent, Object, ObjectStore};
+use super::super::processor::frame::Frame;
+use super::super::processor;
+use super::super::processor::call_function_sync;
+use super::{bind_arguments, container_items};
+use super::strings::return_bool;
+
+#[derive(Clone, Copy)]
+enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn to_float(self) -> f64 {
+        match self {
+            Number::Int(i) => i as f64,
+            Number::Float(f) => f,
+        }
+    }
+}
+
+fn number(store: &ObjectStore, obj_ref: &ObjectRef) -> Option<Number> {
+    match store.deref(obj_ref).content {
+        ObjectContent::Int(i) => Some(Number::Int(i)),
+        ObjectContent::True => Some(Number::Int(1)),
+        ObjectContent::False => Some(Number::Int(0)),
+        ObjectContent::Float(bits) => Some(Number::Float(f64::from_bits(bits))),
+        _ => None,
+    }
+}
+
+/// Hash of a float; integral floats have the same hash as the equal int.
+pub fn float_hash(f: f64) -> u32 {
+    if f.fract() == 0. && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        f as i64 as u32
+    }
+    else {
+        let bits = f.to_bits();
+        (bits ^ (bits >> 32)) as u32
+    }
+}
+
+pub fn raise_overflow<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) {
+    let exc = state.primitive_objects.overflowerror.clone();
+    raise(state, call_stack, exc, "int too large to convert to a 64-bit signed int".to_string())
+}
+
+fn return_int<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, i: i64) {
+    let obj_ref = state.store.allocate(state.primitive_objects.new_int(i));
+    return_value(call_stack, obj_ref)
+}
+
+fn return_float<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, f: f64) {
+    let obj_ref = state.store.allocate(state.primitive_objects.new_float(f));
+    return_value(call_stack, obj_ref)
+}
+
+fn return_string<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, s: String) {
+    let obj_ref = state.store.allocate(state.primitive_objects.new_string(s));
+    return_value(call_stack, obj_ref)
+}
+
+// Calls a special method which must return an instance of a builtin type.
+fn call_number_method<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, method: &ObjectRef, args: Vec<ObjectRef>, check: fn(&ObjectContent) -> bool) -> Option<Result<ObjectRef, String>> {
+    let res = match call_function_sync(state, call_stack, method, args, vec![]) {
+        PyResult::Return(res) => res,
+        PyResult::Raised => return None,
+    };
+    if check(&state.store.deref(&res).content) {
+        Some(Ok(res))
+    }
+    else {
+        Some(Err(processor::type_name(&state.store, &res)))
+    }
+}
+
+fn is_int(content: &ObjectContent) -> bool {
+    matches!(*content, ObjectContent::Int(_) | ObjectContent::True | ObjectContent::False)
+}
+
+fn is_float(content: &ObjectContent) -> bool {
+    matches!(*content, ObjectContent::Float(_))
+}
+
+/// Value of an object used as an integer (eg. by chr() and hex()), using its
+/// `__index__` method if it is not an int.
+fn index_value<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<i64> {
+    let exc = state.primitive_objects.typeerror.clone();
+    let obj_ref = match processor::lookup_special_method(state, obj_ref, "__index__") {
+        None => obj_ref.clone(),
+        Some(method) => match call_number_method(state, call_stack, &method, vec![], is_int)? {
+            Ok(res) => res,
+            Err(name) => {
+                raise(state, call_stack, exc, format!("__index__ returned non-int (type {})", name));
+                return None
+            },
+        },
+    };
+    match number(&state.store, &obj_ref) {
+        Some(Number::Int(i)) => Some(i),
+        _ => {
+            let name = processor::type_name(&state.store, &obj_ref);
+            raise(state, call_stack, exc, format!("'{}' object cannot be interpreted as an integer", name));
+            None
+        },
+    }
+}
+
+/// Converts a float to an int, truncating it.
+pub fn float_to_int<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, f: f64) -> Option<i64> {
+    if f.is_nan() {
+        let exc = state.primitive_objects.valueerror.clone();
+        raise(state, call_stack, exc, "cannot convert float NaN to integer".to_string());
+        return None
+    }
+    if f.is_infinite() {
+        let exc = state.primitive_objects.overflowerror.clone();
+        raise(state, call_stack, exc, "cannot convert float infinity to integer".to_string());
+        return None
+    }
+    let f = f.trunc();
+    if f < i64::MIN as f64 || f >= i64::MAX as f64 {
+        raise_overflow(state, call_stack);
+        None
+    }
+    else {
+        Some(f as i64)
+    }
+}
+
+enum ParseIntError {
+    Invalid,
+    Overflow,
+}
+
+/// Parses an int literal like int() does: with an optional sign and prefix,
+/// and underscores between digits. Base 0 means the base is given by the prefix.
+fn parse_int(s: &str, base: u32) -> Result<i64, ParseIntError> {
+    let s = s.trim();
+    let (negative, s) = match s.chars().next() {
+        Some('-') => (true, &s[1..]),
+        Some('+') => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let prefix_base = match s.get(..2).map(|prefix| prefix.to_lowercase()) {
+        Some(ref prefix) if prefix == "0x" => Some(16),
+        Some(ref prefix) if prefix == "0o" => Some(8),
+        Some(ref prefix) if prefix == "0b" => Some(2),
+        _ => None,
+    };
+    let (base, digits) = match (base, prefix_base) {
+        (0, Some(prefix_base)) => (prefix_base, &s[2..]),
+        (0, None) => {
+            // Decimal literals can't have leading zeros, unless they are all zeros.
+            if s.starts_with('0') && s.chars().any(|c| c != '0' && c != '_') {
+                return Err(ParseIntError::Invalid)
+            }
+            (10, s)
+        },
+        (base, Some(prefix_base)) if base == prefix_base => (base, &s[2..]),
+        (base, _) => {
+            if s.starts_with('_') {
+                return Err(ParseIntError::Invalid)
+            }
+            (base, s)
+        },
+    };
+    // An underscore may follow the prefix, then only separate digits.
+    let digits = if digits.len() != s.len() && digits.starts_with('_') { &digits[1..] } else { digits };
+    if digits.is_empty() || digits.ends_with('_') || digits.contains("__") || digits.starts_with('_') {
+        return Err(ParseIntError::Invalid)
+    }
+    // The magnitude of i64::MIN is one more than i64::MAX.
+    let max = i64::MAX as u64 + negative as u64;
+    let mut value: u64 = 0;
+    let mut overflow = false;
+    for c in digits.chars().filter(|c| *c != '_') {
+        let digit = match c.to_digit(base) {
+            Some(digit) => digit as u64,
+            None => return Err(ParseIntError::Invalid),
+        };
+        value = value * (base as u64) + digit;
+        if value > max {
+            overflow = true;
+            value = max; // Keep checking the other digits
+        }
+    }
+    if overflow {
+        Err(ParseIntError::Overflow)
+    }
+    else if negative {
+        Ok((value as i64).wrapping_neg())
+    }
+    else {
+        Ok(value as i64)
+    }
+}
+
+/// Value of int(x) or int(x, base).
+pub fn int_value<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, x: Option<ObjectRef>, base: Option<ObjectRef>) -> Option<i64> {
+    let typeerror = state.primitive_objects.typeerror.clone();
+    let x = match (x, base.is_some()) {
+        (None, false) => return Some(0),
+        (None, true) => {
+            raise(state, call_stack, typeerror, "int() missing string argument".to_string());
+            return None
+        },
+        (Some(x), _) => x,
+    };
+    let text = match state.store.deref(&x).content {
+        ObjectContent::String(ref s) => Some(s.clone()),
+        ObjectContent::Bytes(ref b) | ObjectContent::ByteArray(ref b) => Some(String::from_utf8_lossy(b).into_owned()),
+        _ => None,
+    };
+    let text = match (text, base) {
+        (Some(text), base) => {
+            let base = match base {
+                None => 10,
+                Some(base) => index_value(state, call_stack, &base)?,
+            };
+            if base < 0 || base == 1 || base > 36 {
+                let exc = state.primitive_objects.valueerror.clone();
+                raise(state, call_stack, exc, "int() base must be >= 2 and <= 36, or 0".to_string());
+                return None
+            }
+            (text, base as u32)
+        },
+        (None, Some(_)) => {
+            raise(state, call_stack, typeerror, "int() can't convert non-string with explicit base".to_string());
+            return None
+        },
+        (None, None) => {
+            match number(&state.store, &x) {
+                Some(Number::Int(i)) => return Some(i),
+                Some(Number::Float(f)) => return float_to_int(state, call_stack, f),
+                None => (),
+            }
+            if let Some(method) = processor::lookup_special_method(state, &x, "__int__") {
+                return match call_number_method(state, call_stack, &method, vec![], is_int)? {
+                    Ok(res) => match number(&state.store, &res) {
+                        Some(Number::Int(i)) => Some(i),
+                        _ => unreachable!(),
+                    },
+                    Err(name) => {
+                        raise(state, call_stack, typeerror, format!("__int__ returned non-int (type {})", name));
+                        None
+                    },
+                }
+            }
+            let name = processor::type_name(&state.store, &x);
+            raise(state, call_stack, typeerror, format!("int() argument must be a string, a bytes-like object or a number, not '{}'", name));
+            return None
+        },
+    };
+    let (text, base) = text;
+    match parse_int(&text, base) {
+        Ok(i) => Some(i),
+        Err(ParseIntError::Invalid) => {
+            let exc = state.primitive_objects.valueerror.clone();
+            let repr = x.repr(&state.store);
+            raise(state, call_stack, exc, format!("invalid literal for int() with base {}: {}", base, repr));
+            None
+        },
+        Err(ParseIntError::Overflow) => {
+            raise_overflow(state, call_stack);
+            None
+        },
+    }
+}
+
+pub fn float_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("float.__new__", state, call_stack, args);
+    let x = match bind_arguments(state, call_stack, "float", &["x"], 0, args, kwargs) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let value = match x {
+        None => 0.,
+        Some(x) => match float_value(state, call_stack, &x) {
+            Some(value) => value,
+            None => return,
+        },
+    };
+    let obj = state.store.allocate(Object::new_instance(None, cls, ObjectContent::Float(value.to_bits())));
+    return_value(call_stack, obj)
+}
+
+// Value of float(x)
+fn float_value<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, x: &ObjectRef) -> Option<f64> {
+    if let Some(n) = number(&state.store, x) {
+        return Some(n.to_float())
+    }
+    let text = match state.store.deref(x).content {
+        ObjectContent::String(ref s) => Some(s.clone()),
+        ObjectContent::Bytes(ref b) | ObjectContent::ByteArray(ref b) => Some(String::from_utf8_lossy(b).into_owned()),
+        _ => None,
+    };
+    if let Some(text) = text {
+        // Rust's syntax is the same, except for underscores, which it rejects.
+        return match text.trim().parse::<f64>() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                let exc = state.primitive_objects.valueerror.clone();
+                let repr = x.repr(&state.store);
+                raise(state, call_stack, exc, format!("could not convert string to float: {}", repr));
+                None
+            },
+        }
+    }
+    let typeerror = state.primitive_objects.typeerror.clone();
+    if let Some(method) = processor::lookup_special_method(state, x, "__float__") {
+        return match call_number_method(state, call_stack, &method, vec![], is_float)? {
+            Ok(res) => number(&state.store, &res).map(Number::to_float),
+            Err(name) => {
+                let class_name = processor::type_name(&state.store, x);
+                raise(state, call_stack, typeerror, format!("{}.__float__ returned non-float (type {})", class_name, name));
+                None
+            },
+        }
+    }
+    let name = processor::type_name(&state.store, x);
+    raise(state, call_stack, typeerror, format!("float() argument must be a string or a number, not '{}'", name));
+    None
+}
+
+/// Sum of two numbers, computed natively: exactly if both are ints, and as
+/// floats otherwise. Returns None if one of them is not a number, and
+/// Some(None) if an exception was raised.
+pub fn add_numbers<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, x: &ObjectRef, y: &ObjectRef) -> Option<Option<ObjectRef>> {
+    let sum = match (number(&state.store, x)?, number(&state.store, y)?) {
+        (Number::Int(i), Number::Int(j)) => match i.checked_add(j) {
+            Some(sum) => state.primitive_objects.new_int(sum),
+            None => {
+                raise_overflow(state, call_stack);
+                return Some(None)
+            },
+        },
+        (x, y) => state.primitive_objects.new_float(x.to_float() + y.to_float()),
+    };
+    Some(Some(state.store.allocate(sum)))
+}
+
+// Shared code of the arithmetic operators of float, which accept ints and floats
+// and return NotImplemented for other objects. Reflected operators swap their
+// operands.
+fn float_operator<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, reflected: bool, operator: fn(f64, f64) -> f64) {
+    let (receiver, other) = (&args[0], &args[1]); // Operators are always called with one argument
+    match (number(&state.store, receiver), number(&state.store, other)) {
+        (Some(x), Some(y)) => {
+            let (x, y) = if reflected { (y, x) } else { (x, y) };
+            return_float(state, call_stack, operator(x.to_float(), y.to_float()))
+        },
+        _ => return_value(call_stack, state.primitive_objects.not_implemented.clone()),
+    }
+}
+
+pub fn float_add<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    float_operator(state, call_stack, args, false, |x, y| x + y)
+}
+
+pub fn float_radd<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    float_operator(state, call_stack, args, true, |x, y| x + y)
+}
+
+pub fn float_sub<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    float_operator(state, call_stack, args, false, |x, y| x - y)
+}
+
+pub fn float_rsub<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    float_operator(state, call_stack, args, true, |x, y| x - y)
+}
+
+pub fn float_mul<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    float_operator(state, call_stack, args, false, |x, y| x * y)
+}
+
+pub fn float_rmul<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    float_operator(state, call_stack, args, true, |x, y| x * y)
+}
+
+// Shared code of the arithmetic operators of int, which return NotImplemented
+// unless both operands are ints. Reflected operators swap their operands, and
+// the operator returns None on overflow.
+fn int_operator<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, reflected: bool, operator: fn(i64, i64) -> Option<i64>) {
+    let (receiver, other) = (&args[0], &args[1]); // Operators are always called with one argument
+    match (number(&state.store, receiver), number(&state.store, other)) {
+        (Some(Number::Int(x)), Some(Number::Int(y))) => {
+            let (x, y) = if reflected { (y, x) } else { (x, y) };
+            match operator(x, y) {
+                Some(res) => return_int(state, call_stack, res),
+                None => raise_overflow(state, call_stack),
+            }
+        },
+        _ => return_value(call_stack, state.primitive_objects.not_implemented.clone()),
+    }
+}
+
+// Shared code of the bitwise operators of int. They are symmetric, so they are
+// their own reflected operators; on two bools they return a bool.
+fn int_bitwise<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, operator: fn(i64, i64) -> i64) {
+    let (receiver, other) = (&args[0], &args[1]); // Operators are always called with one argument
+    let is_bool = |obj_ref: &ObjectRef| matches!(state.store.deref(obj_ref).content, ObjectContent::True | ObjectContent::False);
+    let bools = is_bool(receiver) && is_bool(other);
+    match (number(&state.store, receiver), number(&state.store, other)) {
+        (Some(Number::Int(x)), Some(Number::Int(y))) if bools => return_bool(state, call_stack, operator(x, y) != 0),
+        (Some(Number::Int(x)), Some(Number::Int(y))) => return_int(state, call_stack, operator(x, y)),
+        _ => return_value(call_stack, state.primitive_objects.not_implemented.clone()),
+    }
+}
+
+pub fn int_sub<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    int_operator(state, call_stack, args, false, i64::checked_sub)
+}
+
+pub fn int_rsub<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    int_operator(state, call_stack, args, true, i64::checked_sub)
+}
+
+pub fn int_and<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    int_bitwise(state, call_stack, args, |x, y| x & y)
+}
+
+pub fn int_xor<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    int_bitwise(state, call_stack, args, |x, y| x ^ y)
+}
+
+pub fn int_or<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    int_bitwise(state, call_stack, args, |x, y| x | y)
+}
+
+pub fn abs<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("abs", state, call_stack, kwargs);
+    let x = match bind_arguments(state, call_stack, "abs", &["x"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    if let Some(method) = processor::lookup_special_method(state, &x, "__abs__") {
+        return processor::call_function(state, call_stack, &method, vec![], vec![])
+    }
+    match number(&state.store, &x) {
+        Some(Number::Int(i)) => match i.checked_abs() {
+            Some(res) => return_int(state, call_stack, res),
+            None => raise_overflow(state, call_stack),
+        },
+        Some(Number::Float(f)) => return_float(state, call_stack, f.abs()),
+        None => {
+            let exc = state.primitive_objects.typeerror.clone();
+            let name = processor::type_name(&state.store, &x);
+            raise(state, call_stack, exc, format!("bad operand type for abs(): '{}'", name))
+        },
+    }
+}
+
+// Floor division and modulo of floats, with the sign of the modulo following
+// the divisor's, like CPython.
+fn float_divmod(x: f64, y: f64) -> (f64, f64) {
+    let mut modulo = x % y;
+    let mut div = (x - modulo) / y;
+    if modulo != 0. {
+        if (y < 0.) != (modulo < 0.) {
+            modulo += y;
+            div -= 1.;
+        }
+    }
+    else {
+        modulo = 0f64.copysign(y);
+    }
+    let floor_div = if div != 0. {
+        let floor = div.floor();
+        if div - floor > 0.5 { floor + 1. } else { floor }
+    }
+    else {
+        0f64.copysign(x / y)
+    };
+    (floor_div, modulo)
+}
+
+// Floor division and modulo of ints, with the sign of the modulo following
+// the divisor's, like CPython. None on overflow (i64::MIN // -1).
+pub fn int_divmod(x: i64, y: i64) -> Option<(i64, i64)> {
+    let (mut div, mut modulo) = (x.checked_div(y)?, x % y);
+    if modulo != 0 && (modulo < 0) != (y < 0) {
+        modulo += y;
+        div -= 1;
+    }
+    Some((div, modulo))
+}
+
+pub fn divmod<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("divmod", state, call_stack, kwargs);
+    let (x, y) = match bind_arguments(state, call_stack, "divmod", &["x", "y"], 2, args, vec![]) {
+        Some(mut values) => (values.remove(0).unwrap(), values.remove(0).unwrap()),
+        None => return,
+    };
+    let (div, modulo) = match (number(&state.store, &x), number(&state.store, &y)) {
+        (Some(Number::Int(i)), Some(Number::Int(j))) => {
+            if j == 0 {
+                let exc = state.primitive_objects.zerodivisionerror.clone();
+                return raise(state, call_stack, exc, "integer division or modulo by zero".to_string())
+            }
+            match int_divmod(i, j) {
+                Some((div, modulo)) => (Number::Int(div), Number::Int(modulo)),
+                None => return raise_overflow(state, call_stack),
+            }
+        },
+        (Some(x), Some(y)) => {
+            if y.to_float() == 0. {
+                let exc = state.primitive_objects.zerodivisionerror.clone();
+                return raise(state, call_stack, exc, "float divmod()".to_string())
+            }
+            let (div, modulo) = float_divmod(x.to_float(), y.to_float());
+            (Number::Float(div), Number::Float(modulo))
+        },
+        _ => {
+            return match processor::binary_operator_sync(state, call_stack, x, y, "__divmod__", "__rdivmod__", "divmod()") {
+                PyResult::Return(res) => return_value(call_stack, res),
+                PyResult::Raised => (),
+            }
+        },
+    };
+    let div = match div {
+        Number::Int(i) => state.primitive_objects.new_int(i),
+        Number::Float(f) => state.primitive_objects.new_float(f),
+    };
+    let modulo = match modulo {
+        Number::Int(i) => state.primitive_objects.new_int(i),
+        Number::Float(f) => state.primitive_objects.new_float(f),
+    };
+    let (div, modulo) = (state.store.allocate(div), state.store.allocate(modulo));
+    let res = state.store.allocate(state.primitive_objects.new_tuple(vec![div, modulo]));
+    return_value(call_stack, res)
+}
+
+// base ** exp % modulo, without overflowing. The result has the sign of
+// the modulo, like the % operator.
+fn modular_pow(base: i64, mut exp: i64, modulo: i64) -> i64 {
+    let abs_modulo = modulo.unsigned_abs() as u128;
+    let mut base = (base as i128).rem_euclid(abs_modulo as i128) as u128;
+    let mut res = 1 % abs_modulo;
+    while exp != 0 {
+        if exp & 1 == 1 {
+            res = res * base % abs_modulo;
+        }
+        base = base * base % abs_modulo;
+        exp >>= 1;
+    }
+    if modulo < 0 && res != 0 { (res as i128 - abs_modulo as i128) as i64 } else { res as i64 }
+}
+
+pub fn pow<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("pow", state, call_stack, kwargs);
+    let (x, y, z) = match bind_arguments(state, call_stack, "pow", &["x", "y", "z"], 2, args, vec![]) {
+        Some(mut values) => (values.remove(0).unwrap(), values.remove(0).unwrap(), values.remove(0)),
+        None => return,
+    };
+    let z = z.and_then(|z| if z.is(&state.primitive_objects.none) { None } else { Some(z) });
+    let typeerror = state.primitive_objects.typeerror.clone();
+    let (x_number, y_number) = (number(&state.store, &x), number(&state.store, &y));
+    let z = match z {
+        None => None,
+        Some(z) => match (x_number, y_number, number(&state.store, &z)) {
+            (Some(Number::Int(i)), Some(Number::Int(j)), Some(Number::Int(k))) => {
+                if k == 0 {
+                    let exc = state.primitive_objects.valueerror.clone();
+                    return raise(state, call_stack, exc, "pow() 3rd argument cannot be 0".to_string())
+                }
+                if j < 0 {
+                    let exc = state.primitive_objects.valueerror.clone();
+                    return raise(state, call_stack, exc, "pow() 2nd argument cannot be negative when 3rd argument specified".to_string())
+                }
+                return return_int(state, call_stack, modular_pow(i, j, k))
+            },
+            (Some(_), Some(_), Some(_)) => {
+                return raise(state, call_stack, typeerror, "pow() 3rd argument not allowed unless all arguments are integers".to_string())
+            },
+            _ => Some(z),
+        },
+    };
+    if let Some(z) = z {
+        // Only the base's __pow__ takes a third argument.
+        if let Some(method) = processor::lookup_special_method(state, &x, "__pow__") {
+            return processor::call_function(state, call_stack, &method, vec![y, z], vec![])
+        }
+        let names = [&x, &y, &z].iter().map(|obj_ref| format!("'{}'", processor::type_name(&state.store, obj_ref))).collect::<Vec<_>>();
+        return raise(state, call_stack, typeerror, format!("unsupported operand type(s) for pow(): {}", names.join(", ")))
+    }
+    match (x_number, y_number) {
+        // A negative exponent gives a float, like CPython.
+        (Some(Number::Int(i)), Some(Number::Int(j))) if j >= 0 => {
+            let res = if j > u32::MAX as i64 { None } else { i.checked_pow(j as u32) };
+            match res {
+                Some(res) => return_int(state, call_stack, res),
+                None => raise_overflow(state, call_stack),
+            }
+        },
+        (Some(x), Some(y)) => {
+            let (x, y) = (x.to_float(), y.to_float());
+            if x == 0. && y < 0. {
+                let exc = state.primitive_objects.zerodivisionerror.clone();
+                return raise(state, call_stack, exc, "0.0 cannot be raised to a negative power".to_string())
+            }
+            if x < 0. && y.fract() != 0. {
+                // TODO: complex numbers
+                let exc = state.primitive_objects.valueerror.clone();
+                return raise(state, call_stack, exc, "negative number cannot be raised to a fractional power".to_string())
+            }
+            let res = x.powf(y);
+            if res.is_infinite() && x.is_finite() && y.is_finite() {
+                let exc = state.primitive_objects.overflowerror.clone();
+                return raise(state, call_stack, exc, "(34, 'Numerical result out of range')".to_string())
+            }
+            return_float(state, call_stack, res)
+        },
+        _ => {
+            match processor::binary_operator_sync(state, call_stack, x, y, "__pow__", "__rpow__", "** or pow()") {
+                PyResult::Return(res) => return_value(call_stack, res),
+                PyResult::Raised => (),
+            }
+        },
+    }
+}
+
+// Rounds half to even, like CPython.
+fn round_half_even(f: f64) -> f64 {
+    let rounded = f.round();
+    if (f - f.trunc()).abs() == 0.5 {
+        2. * (f / 2.).round()
+    }
+    else {
+        rounded
+    }
+}
+
+// Rounds an int to a multiple of 10**digits, half to even. None on overflow.
+fn round_int(i: i64, digits: u64) -> Option<i64> {
+    let factor = match 10i64.checked_pow(digits.min(19) as u32) {
+        Some(factor) => factor,
+        None => return Some(0), // No int is as large as half of the factor.
+    };
+    let (div, modulo) = int_divmod(i, factor)?;
+    let up = 2 * modulo > factor || (2 * modulo == factor && div % 2 != 0);
+    let div = if up { div + 1 } else { div };
+    div.checked_mul(factor)
+}
+
+pub fn round<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let (x, ndigits) = match bind_arguments(state, call_stack, "round", &["number", "ndigits"], 1, args, kwargs) {
+        Some(mut values) => (values.remove(0).unwrap(), values.remove(0)),
+        None => return,
+    };
+    let ndigits = ndigits.and_then(|n| if n.is(&state.primitive_objects.none) { None } else { Some(n) });
+    if let Some(method) = processor::lookup_special_method(state, &x, "__round__") {
+        return processor::call_function(state, call_stack, &method, ndigits.into_iter().collect(), vec![])
+    }
+    let ndigits = match ndigits {
+        None => None,
+        Some(ndigits) => match index_value(state, call_stack, &ndigits) {
+            Some(ndigits) => Some(ndigits),
+            None => return,
+        },
+    };
+    match (number(&state.store, &x), ndigits) {
+        // Ints have no fractional digits to round.
+        (Some(Number::Int(i)), None) => return_int(state, call_stack, i),
+        (Some(Number::Int(i)), Some(ndigits)) if ndigits >= 0 => return_int(state, call_stack, i),
+        (Some(Number::Int(i)), Some(ndigits)) => {
+            match round_int(i, ndigits.unsigned_abs()) {
+                Some(res) => return_int(state, call_stack, res),
+                None => raise_overflow(state, call_stack),
+            }
+        },
+        (Some(Number::Float(f)), None) => {
+            if let Some(i) = float_to_int(state, call_stack, round_half_even(f)) {
+                return_int(state, call_stack, i)
+            }
+        },
+        (Some(Number::Float(f)), Some(ndigits)) if ndigits < 0 => {
+            let factor = 10f64.powi(ndigits.unsigned_abs().min(400) as i32);
+            let res = if f.is_finite() { round_half_even(f / factor) * factor } else { f };
+            return_float(state, call_stack, res)
+        },
+        (Some(Number::Float(f)), Some(ndigits)) => {
+            // Rust rounds the exact decimal value of the float, like CPython.
+            let res = if f.is_finite() { format!("{:.*}", ndigits as usize, f).parse().unwrap() } else { f };
+            return_float(state, call_stack, res)
+        },
+        (None, _) => {
+            let exc = state.primitive_objects.typeerror.clone();
+            let name = processor::type_name(&state.store, &x);
+            raise(state, call_stack, exc, format!("type {} doesn't define __round__ method", name))
+        },
+    }
+}
+
+// Common implementation of min() and max().
+fn min_max<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, is_max: bool) {
+    let typeerror = state.primitive_objects.typeerror.clone();
+    let (mut key, mut default) = (None, None);
+    for (name, value) in kwargs {
+        let name = match state.store.deref(&name).content {
+            ObjectContent::String(ref s) => s.clone(),
+            _ => panic!("Keyword names should be strings."),
+        };
+        match name.as_str() {
+            "key" => key = Some(value),
+            "default" => default = Some(value),
+            _ => return raise(state, call_stack, typeerror, format!("'{}' is an invalid keyword argument for this function", name)),
+        }
+    }
+    let items = match args.len() {
+        0 => return raise(state, call_stack, typeerror, format!("{} expected 1 arguments, got 0", funcname)),
+        1 => match container_items(state, call_stack, &args.remove(0)) {
+            Some(items) => items,
+            None => return,
+        },
+        _ if default.is_some() => {
+            return raise(state, call_stack, typeerror, format!("Cannot specify a default for {}() with multiple positional arguments", funcname))
+        },
+        _ => args,
+    };
+    let key = key.and_then(|key| if key.is(&state.primitive_objects.none) { None } else { Some(key) });
+    let mut best: Option<(ObjectRef, ObjectRef)> = None; // item and its key
+    for item in items {
+        let item_key = match key {
+            None => item.clone(),
+            Some(ref key) => match call_function_sync(state, call_stack, key, vec![item.clone()], vec![]) {
+                PyResult::Return(item_key) => item_key,
+                PyResult::Raised => return,
+            },
+        };
+        let replace = match best {
+            None => true,
+            // Only a strictly smaller (or greater) item replaces the previous one.
+            Some((_, ref best_key)) => {
+                let res = if is_max {
+                    processor::less_than(state, call_stack, best_key, &item_key)
+                }
+                else {
+                    processor::less_than(state, call_stack, &item_key, best_key)
+                };
+                match res {
+                    Some(res) => res,
+                    None => return,
+                }
+            },
+        };
+        if replace {
+            best = Some((item, item_key));
+        }
+    }
+    match (best, default) {
+        (Some((item, _)), _) => return_value(call_stack, item),
+        (None, Some(default)) => return_value(call_stack, default),
+        (None, None) => {
+            let exc = state.primitive_objects.valueerror.clone();
+            raise(state, call_stack, exc, format!("{}() arg is an empty sequence", funcname))
+        },
+    }
+}
+
+pub fn min<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    min_max(state, call_stack, "min", args, kwargs, false)
+}
+
+pub fn max<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    min_max(state, call_stack, "max", args, kwargs, true)
+}
+
+pub fn chr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("chr", state, call_stack, kwargs);
+    let i = match bind_arguments(state, call_stack, "chr", &["i"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let code = match number(&state.store, &i) {
+        Some(Number::Int(code)) => code,
+        _ => {
+            let exc = state.primitive_objects.typeerror.clone();
+            let name = processor::type_name(&state.store, &i);
+            return raise(state, call_stack, exc, format!("an integer is required (got type {})", name))
+        },
+    };
+    // TODO: surrogates, which Rust strings can't contain
+    let c = if code >= 0 && code <= u32::MAX as i64 { ::std::char::from_u32(code as u32) } else { None };
+    match c {
+        Some(c) => return_string(state, call_stack, c.to_string()),
+        None => {
+            let exc = state.primitive_objects.valueerror.clone();
+            raise(state, call_stack, exc, "chr() arg not in range(0x110000)".to_string())
+        },
+    }
+}
+
+pub fn ord<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    check_no_kwargs!("ord", state, call_stack, kwargs);
+    let c = match bind_arguments(state, call_stack, "ord", &["c"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let res = match state.store.deref(&c).content {
+        ObjectContent::String(ref s) => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c as u32),
+                _ => Err(format!("ord() expected a character, but string of length {} found", s.chars().count())),
+            }
+        },
+        ObjectContent::Bytes(ref b) | ObjectContent::ByteArray(ref b) if b.len() == 1 => Ok(b[0] as u32),
+        ObjectContent::Bytes(ref b) | ObjectContent::ByteArray(ref b) => {
+            Err(format!("ord() expected a character, but string of length {} found", b.len()))
+        },
+        _ => Err(format!("ord() expected string of length 1, but {} found", processor::type_name(&state.store, &c))),
+    };
+    match res {
+        Ok(code) => return_int(state, call_stack, code as i64),
+        Err(message) => {
+            let exc = state.primitive_objects.typeerror.clone();
+            raise(state, call_stack, exc, message)
+        },
+    }
+}
+
+// Common implementation of hex(), oct() and bin().
+fn radix_string<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, format: fn(u64) -> String) {
+    check_no_kwargs!(funcname, state, call_stack, kwargs);
+    let x = match bind_arguments(state, call_stack, funcname, &["number"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    if let Some(i) = index_value(state, call_stack, &x) {
+        let sign = if i < 0 { "-" } else { "" };
+        return_string(state, call_stack, format!("{}{}", sign, format(i.unsigned_abs())))
+    }
+}
+
+pub fn hex<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    radix_string(state, call_stack, "hex", args, kwargs, |i| format!("0x{:x}", i))
+}
+
+pub fn oct<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    radix_string(state, call_stack, "oct", args, kwargs, |i| format!("0o{:o}", i))
+}
+
+pub fn bin<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    radix_string(state, call_stack, "bin", args, kwargs, |i| format!("0b{:b}", i))
+}
@@ -0,0 +1,492 @@
+//! Native methods of `set` and `frozenset`.
+//!
+//! Sets use the same hash table as dicts, with their items as keys. Methods
+//! shared by both types return an object of the same type as their receiver.
+//! Like dicts, sets bump their version only when their size changes.
+
+use super::super::sandbox::EnvProxy;
+use super::super::state::{State, raise, return_value};
+use super::super::objects::{ObjectRef, ObjectContent, Object, ObjectStore, DictContent};
+use super::super::processor::frame::Frame;
+use super::{bind_arguments, container_items, native_hash};
+use super::dicts::{lookup, lookup_hashed, lookup_in};
+use super::strings::return_bool;
+
+/// Copy of the content of a set or frozenset object.
+fn set_content(store: &ObjectStore, obj_ref: &ObjectRef) -> Option<DictContent> {
+    match store.deref(obj_ref).content {
+        ObjectContent::Set(ref s) | ObjectContent::FrozenSet(ref s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+// Applies a change to the content of a set; bumps its version if its size changed.
+fn update_content<EP: EnvProxy, T, F: FnOnce(&mut DictContent) -> T>(state: &mut State<EP>, set_ref: &ObjectRef, change: F) -> T {
+    let obj = state.store.deref_mut(set_ref);
+    let (result, resized) = match obj.content {
+        ObjectContent::Set(ref mut s) => {
+            let len = s.len();
+            let result = change(s);
+            (result, s.len() != len)
+        },
+        _ => panic!("set operation on a non-set object"),
+    };
+    if resized {
+        obj.bump_version();
+    }
+    result
+}
+
+fn add_to<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, set: &mut DictContent, item: ObjectRef) -> Option<()> {
+    if let (hash, None) = lookup(state, call_stack, set, &item)? {
+        set.push(hash, item.clone(), item);
+    }
+    Some(())
+}
+
+/// Content of a set with these items, without duplicates.
+pub fn set_from_items<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, items: Vec<ObjectRef>) -> Option<DictContent> {
+    let mut set = DictContent::new();
+    for item in items {
+        add_to(state, call_stack, &mut set, item)?;
+    }
+    Some(set)
+}
+
+/// Content of a set of constants, which are already distinct and hashed without
+/// running Python code (used by the marshal decoder).
+/// Returns None if one of the items is not a constant.
+pub fn constant_set(store: &ObjectStore, items: Vec<ObjectRef>) -> Option<DictContent> {
+    let mut set = DictContent::new();
+    for item in items {
+        let hash = native_hash(store, &item)?;
+        set.push(hash, item.clone(), item);
+    }
+    Some(set)
+}
+
+/// Content of the items of an iterable, as a set.
+fn iterable_content<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, iterable: &ObjectRef) -> Option<DictContent> {
+    if let Some(set) = set_content(&state.store, iterable) {
+        return Some(set)
+    }
+    let items = container_items(state, call_stack, iterable)?;
+    set_from_items(state, call_stack, items)
+}
+
+/// A new set or frozenset, depending on the type of the receiver of the method.
+fn new_like<EP: EnvProxy>(state: &mut State<EP>, receiver: &ObjectRef, content: DictContent) -> ObjectRef {
+    let obj = match state.store.deref(receiver).content {
+        ObjectContent::FrozenSet(_) => state.primitive_objects.new_frozenset(content),
+        _ => state.primitive_objects.new_set(content),
+    };
+    state.store.allocate(obj)
+}
+
+fn contains_entry<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, set: &DictContent, hash: u32, item: &ObjectRef) -> Option<bool> {
+    lookup_hashed(state, call_stack, set, hash, item).map(|position| position.is_some())
+}
+
+fn union_of<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, first: DictContent, second: &DictContent) -> Option<DictContent> {
+    let mut result = first;
+    for entry in second.iter() {
+        if !contains_entry(state, call_stack, &result, entry.hash, &entry.key)? {
+            result.push(entry.hash, entry.key.clone(), entry.key.clone());
+        }
+    }
+    Some(result)
+}
+
+/// Items of the first set which are (or are not) in the second one.
+fn filter_by<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, first: &DictContent, second: &DictContent, keep_common: bool) -> Option<DictContent> {
+    let mut result = DictContent::new();
+    for entry in first.iter() {
+        if contains_entry(state, call_stack, second, entry.hash, &entry.key)? == keep_common {
+            result.push(entry.hash, entry.key.clone(), entry.key.clone());
+        }
+    }
+    Some(result)
+}
+
+fn symmetric_difference_of<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, first: &DictContent, second: &DictContent) -> Option<DictContent> {
+    let mut result = filter_by(state, call_stack, first, second, false)?;
+    for entry in filter_by(state, call_stack, second, first, false)?.iter() {
+        result.push(entry.hash, entry.key.clone(), entry.key.clone());
+    }
+    Some(result)
+}
+
+fn is_subset<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, first: &DictContent, second: &DictContent) -> Option<bool> {
+    if first.len() > second.len() {
+        return Some(false)
+    }
+    for entry in first.iter() {
+        if !contains_entry(state, call_stack, second, entry.hash, &entry.key)? {
+            return Some(false)
+        }
+    }
+    Some(true)
+}
+
+/// The operations which combine sets.
+#[derive(Clone, Copy)]
+enum Operation {
+    Union,
+    Intersection,
+    Difference,
+    SymmetricDifference,
+}
+
+impl Operation {
+    fn apply<EP: EnvProxy>(self, state: &mut State<EP>, call_stack: &mut Vec<Frame>, first: DictContent, second: &DictContent) -> Option<DictContent> {
+        match self {
+            Operation::Union => union_of(state, call_stack, first, second),
+            Operation::Intersection => filter_by(state, call_stack, &first, second, true),
+            Operation::Difference => filter_by(state, call_stack, &first, second, false),
+            Operation::SymmetricDifference => symmetric_difference_of(state, call_stack, &first, second),
+        }
+    }
+}
+
+// Content of the receiver combined with all the iterables given as arguments.
+fn combine<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, receiver: &ObjectRef, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, operation: Operation) -> Option<DictContent> {
+    if !kwargs.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() takes no keyword arguments", funcname));
+        return None
+    }
+    let mut result = set_content(&state.store, receiver).unwrap();
+    for iterable in args {
+        let other = iterable_content(state, call_stack, &iterable)?;
+        result = operation.apply(state, call_stack, result, &other)?;
+    }
+    Some(result)
+}
+
+fn combine_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, operation: Operation) {
+    let receiver = args.remove(0);
+    if let Some(content) = combine(state, call_stack, funcname, &receiver, args, kwargs, operation) {
+        let set = new_like(state, &receiver, content);
+        return_value(call_stack, set)
+    }
+}
+
+fn combine_update_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, operation: Operation) {
+    let receiver = args.remove(0);
+    if let Some(content) = combine(state, call_stack, funcname, &receiver, args, kwargs, operation) {
+        update_content(state, &receiver, |s| *s = content);
+        return_value(call_stack, state.primitive_objects.none.clone())
+    }
+}
+
+pub fn union<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    combine_impl(state, call_stack, "union", args, kwargs, Operation::Union)
+}
+
+pub fn intersection<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    combine_impl(state, call_stack, "intersection", args, kwargs, Operation::Intersection)
+}
+
+pub fn difference<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    combine_impl(state, call_stack, "difference", args, kwargs, Operation::Difference)
+}
+
+pub fn update<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    combine_update_impl(state, call_stack, "update", args, kwargs, Operation::Union)
+}
+
+pub fn intersection_update<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    combine_update_impl(state, call_stack, "intersection_update", args, kwargs, Operation::Intersection)
+}
+
+pub fn difference_update<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    combine_update_impl(state, call_stack, "difference_update", args, kwargs, Operation::Difference)
+}
+
+// The receiver and the content of the only argument, an iterable.
+fn other_iterable<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> Option<(ObjectRef, DictContent)> {
+    let receiver = args.remove(0);
+    if !kwargs.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() takes no keyword arguments", funcname));
+        return None
+    }
+    let iterable = bind_arguments(state, call_stack, funcname, &["other"], 1, args, vec![])?.remove(0).unwrap();
+    let other = iterable_content(state, call_stack, &iterable)?;
+    Some((receiver, other))
+}
+
+pub fn symmetric_difference<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((receiver, other)) = other_iterable(state, call_stack, "symmetric_difference", args, kwargs) {
+        let content = set_content(&state.store, &receiver).unwrap();
+        if let Some(content) = symmetric_difference_of(state, call_stack, &content, &other) {
+            let set = new_like(state, &receiver, content);
+            return_value(call_stack, set)
+        }
+    }
+}
+
+pub fn symmetric_difference_update<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((receiver, other)) = other_iterable(state, call_stack, "symmetric_difference_update", args, kwargs) {
+        let content = set_content(&state.store, &receiver).unwrap();
+        if let Some(content) = symmetric_difference_of(state, call_stack, &content, &other) {
+            update_content(state, &receiver, |s| *s = content);
+            return_value(call_stack, state.primitive_objects.none.clone())
+        }
+    }
+}
+
+fn subset_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, superset: bool) {
+    if let Some((receiver, other)) = other_iterable(state, call_stack, funcname, args, kwargs) {
+        let content = set_content(&state.store, &receiver).unwrap();
+        let res = if superset { is_subset(state, call_stack, &other, &content) } else { is_subset(state, call_stack, &content, &other) };
+        if let Some(res) = res {
+            return_bool(state, call_stack, res)
+        }
+    }
+}
+
+pub fn issubset<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    subset_impl(state, call_stack, "issubset", args, kwargs, false)
+}
+
+pub fn issuperset<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    subset_impl(state, call_stack, "issuperset", args, kwargs, true)
+}
+
+pub fn isdisjoint<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((receiver, other)) = other_iterable(state, call_stack, "isdisjoint", args, kwargs) {
+        let content = set_content(&state.store, &receiver).unwrap();
+        if let Some(common) = filter_by(state, call_stack, &content, &other, true) {
+            return_bool(state, call_stack, common.len() == 0)
+        }
+    }
+}
+
+// Operands of set operators and comparisons, which return NotImplemented if the
+// other operand is not a set.
+fn operands<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>) -> Option<(ObjectRef, DictContent, DictContent)> {
+    let (receiver, other) = (args[0].clone(), args[1].clone()); // Operators are always called with one argument
+    let content = set_content(&state.store, &receiver).unwrap();
+    match set_content(&state.store, &other) {
+        Some(other_content) => Some((receiver, content, other_content)),
+        None => {
+            return_value(call_stack, state.primitive_objects.not_implemented.clone());
+            None
+        },
+    }
+}
+
+fn operator_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, operation: Operation, in_place: bool) {
+    let (receiver, content, other) = match operands(state, call_stack, args) {
+        Some(operands) => operands,
+        None => return,
+    };
+    if let Some(content) = operation.apply(state, call_stack, content, &other) {
+        if in_place {
+            update_content(state, &receiver, |s| *s = content);
+            return_value(call_stack, receiver)
+        }
+        else {
+            let set = new_like(state, &receiver, content);
+            return_value(call_stack, set)
+        }
+    }
+}
+
+pub fn or<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    operator_impl(state, call_stack, args, Operation::Union, false)
+}
+
+pub fn and<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    operator_impl(state, call_stack, args, Operation::Intersection, false)
+}
+
+pub fn sub<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    operator_impl(state, call_stack, args, Operation::Difference, false)
+}
+
+pub fn xor<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    operator_impl(state, call_stack, args, Operation::SymmetricDifference, false)
+}
+
+pub fn ior<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    operator_impl(state, call_stack, args, Operation::Union, true)
+}
+
+pub fn iand<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    operator_impl(state, call_stack, args, Operation::Intersection, true)
+}
+
+pub fn isub<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    operator_impl(state, call_stack, args, Operation::Difference, true)
+}
+
+pub fn ixor<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    operator_impl(state, call_stack, args, Operation::SymmetricDifference, true)
+}
+
+// Comparisons are inclusion tests; strict ones also require different sizes.
+fn comparison_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, superset: bool, strict: bool) {
+    let (_, content, other) = match operands(state, call_stack, args) {
+        Some(operands) => operands,
+        None => return,
+    };
+    if strict && content.len() == other.len() {
+        return return_bool(state, call_stack, false)
+    }
+    let res = if superset { is_subset(state, call_stack, &other, &content) } else { is_subset(state, call_stack, &content, &other) };
+    if let Some(res) = res {
+        return_bool(state, call_stack, res)
+    }
+}
+
+pub fn le<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    comparison_impl(state, call_stack, args, false, false)
+}
+
+pub fn lt<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    comparison_impl(state, call_stack, args, false, true)
+}
+
+pub fn ge<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    comparison_impl(state, call_stack, args, true, false)
+}
+
+pub fn gt<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, _kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    comparison_impl(state, call_stack, args, true, true)
+}
+
+// The receiver and the only argument of a method.
+fn item_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> Option<(ObjectRef, ObjectRef)> {
+    let receiver = args.remove(0);
+    if !kwargs.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() takes no keyword arguments", funcname));
+        return None
+    }
+    let item = bind_arguments(state, call_stack, funcname, &["elem"], 1, args, vec![])?.remove(0).unwrap();
+    Some((receiver, item))
+}
+
+/// Adds an item to a set object, unless it is already in it.
+pub fn add_item<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, set: &ObjectRef, item: ObjectRef) -> Option<()> {
+    if let (hash, None) = lookup_in(state, call_stack, set, &item)? {
+        update_content(state, set, |s| s.push(hash, item.clone(), item));
+    }
+    Some(())
+}
+
+pub fn add<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((set, item)) = item_argument(state, call_stack, "add", args, kwargs) {
+        if add_item(state, call_stack, &set, item).is_some() {
+            return_value(call_stack, state.primitive_objects.none.clone())
+        }
+    }
+}
+
+// Removes an item from a set; returns whether it was in the set.
+fn remove_item<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, set: &ObjectRef, item: &ObjectRef) -> Option<bool> {
+    match lookup_in(state, call_stack, set, item)? {
+        (_, Some(position)) => {
+            update_content(state, set, |s| s.remove(position));
+            Some(true)
+        },
+        (_, None) => Some(false),
+    }
+}
+
+pub fn discard<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((set, item)) = item_argument(state, call_stack, "discard", args, kwargs) {
+        if remove_item(state, call_stack, &set, &item).is_some() {
+            return_value(call_stack, state.primitive_objects.none.clone())
+        }
+    }
+}
+
+pub fn remove<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if let Some((set, item)) = item_argument(state, call_stack, "remove", args, kwargs) {
+        match remove_item(state, call_stack, &set, &item) {
+            None => (),
+            Some(true) => return_value(call_stack, state.primitive_objects.none.clone()),
+            Some(false) => {
+                let exc = state.primitive_objects.keyerror.clone();
+                let repr = item.repr(&state.store);
+                raise(state, call_stack, exc, repr)
+            },
+        }
+    }
+}
+
+pub fn pop<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let set = args.remove(0);
+    check_no_kwargs!("pop", state, call_stack, kwargs);
+    let _ = match bind_arguments(state, call_stack, "pop", &[], 0, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    // The first item, which is the first one in the iteration order
+    let item = update_content(state, &set, |s| {
+        let position = s.next_entry(0).map(|(position, _)| position);
+        position.and_then(|position| s.remove(position))
+    });
+    match item {
+        Some(entry) => return_value(call_stack, entry.key),
+        None => {
+            let exc = state.primitive_objects.keyerror.clone();
+            raise(state, call_stack, exc, "'pop from an empty set'".to_string())
+        },
+    }
+}
+
+pub fn clear<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let set = args.remove(0);
+    check_no_kwargs!("clear", state, call_stack, kwargs);
+    let _ = match bind_arguments(state, call_stack, "clear", &[], 0, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    update_content(state, &set, |s| s.clear());
+    return_value(call_stack, state.primitive_objects.none.clone())
+}
+
+pub fn copy<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let set = args.remove(0);
+    check_no_kwargs!("copy", state, call_stack, kwargs);
+    let _ = match bind_arguments(state, call_stack, "copy", &[], 0, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let content = set_content(&state.store, &set).unwrap();
+    let copy = new_like(state, &set, content);
+    return_value(call_stack, copy)
+}
+
+// The content of a new set, from the optional argument of set() and frozenset().
+fn constructor_content<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> Option<DictContent> {
+    if !kwargs.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() does not take keyword arguments", funcname));
+        return None
+    }
+    match bind_arguments(state, call_stack, funcname, &["iterable"], 0, args, vec![])?.remove(0) {
+        None => Some(DictContent::new()),
+        Some(iterable) => iterable_content(state, call_stack, &iterable),
+    }
+}
+
+pub fn set_init<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let set = args.remove(0);
+    if let Some(content) = constructor_content(state, call_stack, "set", args, kwargs) {
+        update_content(state, &set, |s| *s = content);
+        return_value(call_stack, state.primitive_objects.none.clone())
+    }
+}
+
+pub fn frozenset_new<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let cls = pop_cls!("frozenset.__new__", state, call_stack, args);
+    if let Some(content) = constructor_content(state, call_stack, "frozenset", args, kwargs) {
+        let obj = state.store.allocate(Object::new_instance(None, cls, ObjectContent::FrozenSet(content)));
+        return_value(call_stack, obj)
+    }
+}
@@ -0,0 +1,634 @@
+//! Native methods of `str`.
+//!
+//! `ObjectContent::String` is UTF-8, but indices and lengths seen by Python code
+//! are in code points.
+
+use super::super::sandbox::EnvProxy;
+use super::super::state::{State, raise, return_value};
+use super::super::objects::{ObjectRef, ObjectContent};
+use super::super::processor::frame::Frame;
+use super::super::processor;
+use super::{bind_arguments, container_items, slice_index};
+use super::bytes;
+
+/// Byte offset of the code point at `index`, or the length of the string if it
+/// is past the end.
+fn byte_offset(s: &str, index: usize) -> usize {
+    s.char_indices().nth(index).map(|(offset, _)| offset).unwrap_or(s.len())
+}
+
+/// Content of the receiver of a method; method descriptors already checked it is a str.
+fn receiver<EP: EnvProxy>(state: &State<EP>, args: &mut Vec<ObjectRef>) -> String {
+    let receiver = args.remove(0);
+    match state.store.deref(&receiver).content {
+        ObjectContent::String(ref s) => s.clone(),
+        _ => panic!("str method called on a non-str object"),
+    }
+}
+
+/// Content of a str argument; raises a TypeError and returns None if it is not a str.
+fn string_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<String> {
+    if let ObjectContent::String(ref s) = state.store.deref(obj_ref).content {
+        return Some(s.clone())
+    }
+    let exc = state.primitive_objects.typeerror.clone();
+    let name = processor::type_name(&state.store, obj_ref);
+    raise(state, call_stack, exc, format!("must be str, not {}", name));
+    None
+}
+
+/// Like string_argument, but None is the same as a missing argument.
+pub fn optional_string_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: Option<ObjectRef>) -> Option<Option<String>> {
+    match obj_ref {
+        Some(ref obj_ref) if !obj_ref.is(&state.primitive_objects.none) => string_argument(state, call_stack, obj_ref).map(Some),
+        _ => Some(None),
+    }
+}
+
+/// Value of an optional integer argument (a width, an index, a count, ...);
+/// None is the same as a missing argument.
+pub fn int_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: Option<ObjectRef>, default: i64) -> Option<i64> {
+    let obj_ref = match obj_ref {
+        Some(ref obj_ref) if !obj_ref.is(&state.primitive_objects.none) => obj_ref.clone(),
+        _ => return Some(default),
+    };
+    let value = match state.store.deref(&obj_ref).content {
+        ObjectContent::Int(i) => Some(i),
+        ObjectContent::True => Some(1),
+        ObjectContent::False => Some(0),
+        _ => None,
+    };
+    if value.is_none() {
+        let exc = state.primitive_objects.typeerror.clone();
+        let name = processor::type_name(&state.store, &obj_ref);
+        raise(state, call_stack, exc, format!("'{}' object cannot be interpreted as an integer", name));
+    }
+    value
+}
+
+/// Like int_argument, for a maximum number of splits or replacements; missing
+/// and negative values mean there is no limit.
+pub fn limit_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: Option<ObjectRef>) -> Option<usize> {
+    int_argument(state, call_stack, obj_ref, -1).map(|limit| if limit < 0 { usize::MAX } else { limit as usize })
+}
+
+/// Fill character of center, ljust and rjust.
+fn fill_char_argument<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: Option<ObjectRef>) -> Option<char> {
+    let fill = match obj_ref {
+        None => return Some(' '),
+        Some(obj_ref) => string_argument(state, call_stack, &obj_ref)?,
+    };
+    let mut chars = fill.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => {
+            let exc = state.primitive_objects.typeerror.clone();
+            raise(state, call_stack, exc, "The fill character must be exactly one character long".to_string());
+            None
+        },
+    }
+}
+
+fn return_string<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, s: String) {
+    let obj_ref = state.store.allocate(state.primitive_objects.new_string(s));
+    return_value(call_stack, obj_ref)
+}
+
+fn return_strings<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, strings: Vec<String>) {
+    let items = strings.into_iter().map(|s| state.store.allocate(state.primitive_objects.new_string(s))).collect();
+    let list = state.store.allocate(state.primitive_objects.new_list(items));
+    return_value(call_stack, list)
+}
+
+pub fn return_bool<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, b: bool) {
+    let obj_ref = if b { state.primitive_objects.true_obj.clone() } else { state.primitive_objects.false_obj.clone() };
+    return_value(call_stack, obj_ref)
+}
+
+pub fn raise_empty_separator<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) {
+    let exc = state.primitive_objects.valueerror.clone();
+    raise(state, call_stack, exc, "empty separator".to_string())
+}
+
+pub fn join<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let s = receiver(state, &mut args);
+    check_no_kwargs!("join", state, call_stack, kwargs);
+    let iterable = match bind_arguments(state, call_stack, "join", &["iterable"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let items = match container_items(state, call_stack, &iterable) {
+        Some(items) => items,
+        None => return,
+    };
+    let mut strings = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        match state.store.deref(item).content {
+            ObjectContent::String(ref item) => strings.push(item.clone()),
+            _ => {
+                let exc = state.primitive_objects.typeerror.clone();
+                let name = processor::type_name(&state.store, item);
+                return raise(state, call_stack, exc, format!("sequence item {}: expected str instance, {} found", i, name))
+            },
+        }
+    }
+    return_string(state, call_stack, strings.join(&s))
+}
+
+// Splits on runs of whitespace, ignoring leading and trailing whitespace.
+fn split_whitespace(s: &str, maxsplit: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        if parts.len() == maxsplit {
+            parts.push(rest.to_string());
+            break
+        }
+        match rest.find(char::is_whitespace) {
+            Some(end) => {
+                parts.push(rest[..end].to_string());
+                rest = rest[end..].trim_start();
+            },
+            None => {
+                parts.push(rest.to_string());
+                break
+            },
+        }
+    }
+    parts
+}
+
+fn rsplit_whitespace(s: &str, maxsplit: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = s.trim_end();
+    while !rest.is_empty() {
+        if parts.len() == maxsplit {
+            parts.push(rest.to_string());
+            break
+        }
+        match rest.rfind(char::is_whitespace) {
+            Some(start) => {
+                let start = start + rest[start..].chars().next().unwrap().len_utf8();
+                parts.push(rest[start..].to_string());
+                rest = rest[..start].trim_end();
+            },
+            None => {
+                parts.push(rest.to_string());
+                break
+            },
+        }
+    }
+    parts.reverse();
+    parts
+}
+
+fn split_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, reverse: bool) {
+    let s = receiver(state, &mut args);
+    let mut values = match bind_arguments(state, call_stack, funcname, &["sep", "maxsplit"], 0, args, kwargs) {
+        Some(values) => values,
+        None => return,
+    };
+    let sep = match optional_string_argument(state, call_stack, values.remove(0)) {
+        Some(sep) => sep,
+        None => return,
+    };
+    let maxsplit = match limit_argument(state, call_stack, values.remove(0)) {
+        Some(maxsplit) => maxsplit,
+        None => return,
+    };
+    let parts = match sep {
+        None if reverse => rsplit_whitespace(&s, maxsplit),
+        None => split_whitespace(&s, maxsplit),
+        Some(ref sep) if sep.is_empty() => return raise_empty_separator(state, call_stack),
+        Some(ref sep) if reverse => {
+            let mut parts: Vec<String> = s.rsplitn(maxsplit.saturating_add(1), sep.as_str()).map(str::to_string).collect();
+            parts.reverse();
+            parts
+        },
+        Some(ref sep) => s.splitn(maxsplit.saturating_add(1), sep.as_str()).map(str::to_string).collect(),
+    };
+    return_strings(state, call_stack, parts)
+}
+
+pub fn split<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    split_impl(state, call_stack, "split", args, kwargs, false)
+}
+
+pub fn rsplit<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    split_impl(state, call_stack, "rsplit", args, kwargs, true)
+}
+
+fn strip_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, left: bool, right: bool) {
+    let s = receiver(state, &mut args);
+    check_no_kwargs!(funcname, state, call_stack, kwargs);
+    let chars = match bind_arguments(state, call_stack, funcname, &["chars"], 0, args, vec![]) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let chars = match optional_string_argument(state, call_stack, chars) {
+        Some(chars) => chars,
+        None => return,
+    };
+    let is_stripped = |c: char| match chars {
+        Some(ref chars) => chars.contains(c),
+        None => c.is_whitespace(),
+    };
+    let mut stripped = s.as_str();
+    if left {
+        stripped = stripped.trim_start_matches(is_stripped);
+    }
+    if right {
+        stripped = stripped.trim_end_matches(is_stripped);
+    }
+    let stripped = stripped.to_string();
+    return_string(state, call_stack, stripped)
+}
+
+pub fn strip<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    strip_impl(state, call_stack, "strip", args, kwargs, true, true)
+}
+
+pub fn lstrip<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    strip_impl(state, call_stack, "lstrip", args, kwargs, true, false)
+}
+
+pub fn rstrip<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    strip_impl(state, call_stack, "rstrip", args, kwargs, false, true)
+}
+
+/// Parses the `sub[, start[, end]]` arguments of find, count, startswith, ...
+/// Returns the subject, the part of the string between start and end, and the
+/// index of its first code point; or None if the start is past the end of the
+/// string, or if an exception was raised (in which case the bool is true).
+fn substring_arguments<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, s: &str, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> Result<Option<(ObjectRef, String, usize)>, ()> {
+    if !kwargs.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() takes no keyword arguments", funcname));
+        return Err(())
+    }
+    let mut values = bind_arguments(state, call_stack, funcname, &["sub", "start", "end"], 1, args, vec![]).ok_or(())?;
+    let sub = values.remove(0).unwrap();
+    let length = s.chars().count();
+    let start = slice_index(int_argument(state, call_stack, values.remove(0), 0).ok_or(())?, length);
+    let end = slice_index(int_argument(state, call_stack, values.remove(0), length as i64).ok_or(())?, length);
+    if start > length {
+        return Ok(None)
+    }
+    let (start_offset, end_offset) = (byte_offset(s, start), byte_offset(s, end));
+    let window = if end_offset > start_offset { &s[start_offset..end_offset] } else { "" };
+    Ok(Some((sub, window.to_string(), start)))
+}
+
+fn find_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, reverse: bool, raise_if_missing: bool) {
+    let s = receiver(state, &mut args);
+    let position = match substring_arguments(state, call_stack, funcname, &s, args, kwargs) {
+        Err(()) => return,
+        Ok(None) => None,
+        Ok(Some((sub, window, start))) => {
+            let sub = match string_argument(state, call_stack, &sub) {
+                Some(sub) => sub,
+                None => return,
+            };
+            let offset = if reverse { window.rfind(sub.as_str()) } else { window.find(sub.as_str()) };
+            offset.map(|offset| start + window[..offset].chars().count())
+        },
+    };
+    match position {
+        Some(position) => {
+            let obj_ref = state.store.allocate(state.primitive_objects.new_int(position as i64));
+            return_value(call_stack, obj_ref)
+        },
+        None if raise_if_missing => {
+            let exc = state.primitive_objects.valueerror.clone();
+            raise(state, call_stack, exc, "substring not found".to_string())
+        },
+        None => {
+            let obj_ref = state.store.allocate(state.primitive_objects.new_int(-1));
+            return_value(call_stack, obj_ref)
+        },
+    }
+}
+
+pub fn find<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    find_impl(state, call_stack, "find", args, kwargs, false, false)
+}
+
+pub fn rfind<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    find_impl(state, call_stack, "rfind", args, kwargs, true, false)
+}
+
+pub fn index<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    find_impl(state, call_stack, "index", args, kwargs, false, true)
+}
+
+pub fn rindex<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    find_impl(state, call_stack, "rindex", args, kwargs, true, true)
+}
+
+pub fn count<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let s = receiver(state, &mut args);
+    let count = match substring_arguments(state, call_stack, "count", &s, args, kwargs) {
+        Err(()) => return,
+        Ok(None) => 0,
+        Ok(Some((sub, window, _))) => {
+            let sub = match string_argument(state, call_stack, &sub) {
+                Some(sub) => sub,
+                None => return,
+            };
+            window.matches(sub.as_str()).count()
+        },
+    };
+    let obj_ref = state.store.allocate(state.primitive_objects.new_int(count as i64));
+    return_value(call_stack, obj_ref)
+}
+
+fn affix_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, suffix: bool) {
+    let s = receiver(state, &mut args);
+    let (affix, window) = match substring_arguments(state, call_stack, funcname, &s, args, kwargs) {
+        Err(()) => return,
+        Ok(None) => return return_bool(state, call_stack, false),
+        Ok(Some((affix, window, _))) => (affix, window),
+    };
+    // The prefix (or suffix) may be a tuple of candidates
+    let candidates = match state.store.deref(&affix).content {
+        ObjectContent::Tuple(ref v) => v.clone(),
+        _ => vec![affix.clone()],
+    };
+    for candidate in candidates {
+        let candidate = match state.store.deref(&candidate).content {
+            ObjectContent::String(ref candidate) => candidate.clone(),
+            _ => {
+                let exc = state.primitive_objects.typeerror.clone();
+                let name = processor::type_name(&state.store, &candidate);
+                return raise(state, call_stack, exc, format!("{} first arg must be str or a tuple of str, not {}", funcname, name))
+            },
+        };
+        let found = if suffix { window.ends_with(candidate.as_str()) } else { window.starts_with(candidate.as_str()) };
+        if found {
+            return return_bool(state, call_stack, true)
+        }
+    }
+    return_bool(state, call_stack, false)
+}
+
+pub fn startswith<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    affix_impl(state, call_stack, "startswith", args, kwargs, false)
+}
+
+pub fn endswith<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    affix_impl(state, call_stack, "endswith", args, kwargs, true)
+}
+
+pub fn replace<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let s = receiver(state, &mut args);
+    check_no_kwargs!("replace", state, call_stack, kwargs);
+    let mut values = match bind_arguments(state, call_stack, "replace", &["old", "new", "count"], 2, args, vec![]) {
+        Some(values) => values,
+        None => return,
+    };
+    let old = match string_argument(state, call_stack, &values.remove(0).unwrap()) {
+        Some(old) => old,
+        None => return,
+    };
+    let new = match string_argument(state, call_stack, &values.remove(0).unwrap()) {
+        Some(new) => new,
+        None => return,
+    };
+    let count = match limit_argument(state, call_stack, values.remove(0)) {
+        Some(count) => count,
+        None => return,
+    };
+    return_string(state, call_stack, s.replacen(old.as_str(), new.as_str(), count))
+}
+
+// Methods that take no argument and return a transformation of the string
+fn transform_impl<EP: EnvProxy, F: Fn(&str) -> String>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, transform: F) {
+    let s = receiver(state, &mut args);
+    check_no_kwargs!(funcname, state, call_stack, kwargs);
+    if !args.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, format!("{}() takes no arguments ({} given)", funcname, args.len()))
+    }
+    return_string(state, call_stack, transform(&s))
+}
+
+pub fn upper<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    transform_impl(state, call_stack, "upper", args, kwargs, str::to_uppercase)
+}
+
+pub fn lower<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    transform_impl(state, call_stack, "lower", args, kwargs, str::to_lowercase)
+}
+
+pub fn casefold<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    // TODO: full case folding; this only handles the most common special case.
+    transform_impl(state, call_stack, "casefold", args, kwargs, |s| s.to_lowercase().replace('ß', "ss"))
+}
+
+// Methods that take no argument and return whether a predicate holds
+fn predicate_impl<EP: EnvProxy, F: Fn(&str) -> bool>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, predicate: F) {
+    let s = receiver(state, &mut args);
+    check_no_kwargs!(funcname, state, call_stack, kwargs);
+    if !args.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        return raise(state, call_stack, exc, format!("{}() takes no arguments ({} given)", funcname, args.len()))
+    }
+    return_bool(state, call_stack, predicate(&s))
+}
+
+fn all_chars<F: Fn(char) -> bool>(s: &str, predicate: F) -> bool {
+    !s.is_empty() && s.chars().all(predicate)
+}
+
+// The cased characters must all have the expected case, and there must be at least one.
+fn is_case<F: Fn(char) -> bool>(s: &str, has_case: F) -> bool {
+    let mut cased = s.chars().filter(|c| c.is_uppercase() || c.is_lowercase()).peekable();
+    cased.peek().is_some() && cased.all(has_case)
+}
+
+pub fn isdigit<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    // TODO: Unicode digits other than ASCII ones
+    predicate_impl(state, call_stack, "isdigit", args, kwargs, |s| all_chars(s, |c| c.is_ascii_digit()))
+}
+
+pub fn isdecimal<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    // TODO: Unicode decimals other than ASCII ones
+    predicate_impl(state, call_stack, "isdecimal", args, kwargs, |s| all_chars(s, |c| c.is_ascii_digit()))
+}
+
+pub fn isnumeric<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "isnumeric", args, kwargs, |s| all_chars(s, char::is_numeric))
+}
+
+pub fn isalpha<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "isalpha", args, kwargs, |s| all_chars(s, char::is_alphabetic))
+}
+
+pub fn isalnum<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "isalnum", args, kwargs, |s| all_chars(s, char::is_alphanumeric))
+}
+
+pub fn isspace<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "isspace", args, kwargs, |s| all_chars(s, char::is_whitespace))
+}
+
+pub fn isupper<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "isupper", args, kwargs, |s| is_case(s, char::is_uppercase))
+}
+
+pub fn islower<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    predicate_impl(state, call_stack, "islower", args, kwargs, |s| is_case(s, char::is_lowercase))
+}
+
+fn partition_impl<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, reverse: bool) {
+    let s = receiver(state, &mut args);
+    check_no_kwargs!(funcname, state, call_stack, kwargs);
+    let sep = match bind_arguments(state, call_stack, funcname, &["sep"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0).unwrap(),
+        None => return,
+    };
+    let sep = match string_argument(state, call_stack, &sep) {
+        Some(sep) => sep,
+        None => return,
+    };
+    if sep.is_empty() {
+        return raise_empty_separator(state, call_stack)
+    }
+    let offset = if reverse { s.rfind(sep.as_str()) } else { s.find(sep.as_str()) };
+    let parts = match offset {
+        Some(offset) => vec![s[..offset].to_string(), sep.clone(), s[offset+sep.len()..].to_string()],
+        None if reverse => vec![String::new(), String::new(), s.clone()],
+        None => vec![s.clone(), String::new(), String::new()],
+    };
+    let items = parts.into_iter().map(|s| state.store.allocate(state.primitive_objects.new_string(s))).collect();
+    let tuple = state.store.allocate(state.primitive_objects.new_tuple(items));
+    return_value(call_stack, tuple)
+}
+
+pub fn partition<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    partition_impl(state, call_stack, "partition", args, kwargs, false)
+}
+
+pub fn rpartition<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    partition_impl(state, call_stack, "rpartition", args, kwargs, true)
+}
+
+fn is_line_boundary(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\x0b' | '\x0c' | '\x1c' | '\x1d' | '\x1e' | '\u{85}' | '\u{2028}' | '\u{2029}')
+}
+
+pub fn splitlines<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let s = receiver(state, &mut args);
+    let keepends = match bind_arguments(state, call_stack, "splitlines", &["keepends"], 0, args, kwargs) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let keepends = match keepends {
+        Some(keepends) => super::native_bool(&state.store, &keepends),
+        None => false,
+    };
+    let mut lines = Vec::new();
+    let mut rest = s.as_str();
+    while let Some(end) = rest.find(is_line_boundary) {
+        // \r\n is a single line boundary
+        let boundary_length = if rest[end..].starts_with("\r\n") { 2 } else { rest[end..].chars().next().unwrap().len_utf8() };
+        let line_end = if keepends { end + boundary_length } else { end };
+        lines.push(rest[..line_end].to_string());
+        rest = &rest[end+boundary_length..];
+    }
+    if !rest.is_empty() {
+        lines.push(rest.to_string());
+    }
+    return_strings(state, call_stack, lines)
+}
+
+/// Parses the `width[, fillchar]` arguments of center, ljust and rjust, and returns
+/// the length of the padding and the fill character.
+fn padding_arguments<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, funcname: &str, s: &str, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> Option<(usize, char)> {
+    if !kwargs.is_empty() {
+        let exc = state.primitive_objects.typeerror.clone();
+        raise(state, call_stack, exc, format!("{}() takes no keyword arguments", funcname));
+        return None
+    }
+    let mut values = bind_arguments(state, call_stack, funcname, &["width", "fillchar"], 1, args, vec![])?;
+    let width = int_argument(state, call_stack, values.remove(0), 0)?;
+    let fill = fill_char_argument(state, call_stack, values.remove(0))?;
+    Some(((width.max(0) as usize).saturating_sub(s.chars().count()), fill))
+}
+
+fn repeat(c: char, n: usize) -> String {
+    c.to_string().repeat(n)
+}
+
+pub fn center<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let s = receiver(state, &mut args);
+    let (padding, fill) = match padding_arguments(state, call_stack, "center", &s, args, kwargs) {
+        Some(res) => res,
+        None => return,
+    };
+    // Same rounding as CPython when the padding is odd
+    let width = padding + s.chars().count();
+    let left = padding / 2 + (padding & width & 1);
+    return_string(state, call_stack, format!("{}{}{}", repeat(fill, left), s, repeat(fill, padding - left)))
+}
+
+pub fn ljust<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let s = receiver(state, &mut args);
+    let (padding, fill) = match padding_arguments(state, call_stack, "ljust", &s, args, kwargs) {
+        Some(res) => res,
+        None => return,
+    };
+    return_string(state, call_stack, format!("{}{}", s, repeat(fill, padding)))
+}
+
+pub fn rjust<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let s = receiver(state, &mut args);
+    let (padding, fill) = match padding_arguments(state, call_stack, "rjust", &s, args, kwargs) {
+        Some(res) => res,
+        None => return,
+    };
+    return_string(state, call_stack, format!("{}{}", repeat(fill, padding), s))
+}
+
+pub fn zfill<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let s = receiver(state, &mut args);
+    check_no_kwargs!("zfill", state, call_stack, kwargs);
+    let width = match bind_arguments(state, call_stack, "zfill", &["width"], 1, args, vec![]) {
+        Some(mut values) => values.remove(0),
+        None => return,
+    };
+    let width = match int_argument(state, call_stack, width, 0) {
+        Some(width) => width,
+        None => return,
+    };
+    let padding = repeat('0', (width.max(0) as usize).saturating_sub(s.chars().count()));
+    // The zeros go after the sign, if any
+    let res = match s.chars().next() {
+        Some(sign) if sign == '+' || sign == '-' => format!("{}{}{}", sign, padding, &s[1..]),
+        _ => format!("{}{}", padding, s),
+    };
+    return_string(state, call_stack, res)
+}
+
+
+pub fn encode<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    let s = receiver(state, &mut args);
+    let mut values = match bind_arguments(state, call_stack, "encode", &["encoding", "errors"], 0, args, kwargs) {
+        Some(values) => values,
+        None => return,
+    };
+    let (codec, errors) = match bytes::codec_arguments(state, call_stack, values.remove(0), values.remove(0)) {
+        Some(arguments) => arguments,
+        None => return,
+    };
+    match bytes::encode_str(&s, codec, errors) {
+        Ok(b) => {
+            let obj_ref = state.store.allocate(state.primitive_objects.new_bytes(b));
+            return_value(call_stack, obj_ref)
+        },
+        Err(message) => {
+            let exc = state.primitive_objects.unicodeencodeerror.clone();
+            raise(state, call_stack, exc, message)
+        },
+    }
+}
@@ -8,7 +8,8 @@ use super::instructions::{Instruction, InstructionDecoder};
 #[derive(Debug)]
 pub enum Block {
     Loop(usize, usize), // begin, end
-    TryExcept(usize, usize), // begin, end
+    TryExcept(usize, usize, usize), // begin, end, size of the stack when the block was set up
+    ExceptHandler(usize), // Replaces a TryExcept block while its except clauses run; size of the stack below the exception
     ExceptPopGoto(ObjectRef, usize, usize), // If an exception matchs the first arg matches, pop n elements from the stack and set the PC to the second arg
 }
 
@@ -18,9 +19,12 @@ pub struct Frame {
     pub var_stack: VectorVarStack<ObjectRef>,
     pub block_stack: Vec<Block>,
     pub locals: Rc<RefCell<HashMap<String, ObjectRef>>>,
+    pub namespace: Option<ObjectRef>, // Mapping of a class body, used instead of locals by the *_NAME instructions
+    pub cells: Vec<ObjectRef>, // cells of the code's cellvars, then of its freevars
     pub instructions: Vec<Instruction>,
     pub code: Code,
     pub program_counter: usize,
+    pub generator: Option<ObjectRef>, // generator object, if the frame runs a generator
 }
 
 impl Frame {
@@ -31,10 +35,40 @@ impl Frame {
             var_stack: VectorVarStack::new(),
             block_stack: Vec::new(),
             locals: locals,
+            namespace: None,
+            cells: Vec::new(),
             instructions: instructions,
             code: code,
             program_counter: 0,
+            generator: None,
         }
     }
+
+    /// A frame without instructions, pushed below a function called from native code
+    /// so the function's return value (or exception) stops there.
+    pub fn new_barrier(object: ObjectRef, lnotab: ObjectRef) -> Frame {
+        let code = Code {
+            argcount: 0,
+            kwonlyargcount: 0,
+            nlocals: 0,
+            stacksize: 1,
+            flags: 0,
+            code: Vec::new(),
+            consts: Vec::new(),
+            names: Vec::new(),
+            varnames: Vec::new(),
+            freevars: Vec::new(),
+            cellvars: Vec::new(),
+            filename: "<native>".to_string(),
+            name: "<native>".to_string(),
+            firstlineno: 0,
+            lnotab: lnotab,
+        };
+        Frame::new(object, code, Rc::new(RefCell::new(HashMap::new())))
+    }
+
+    pub fn is_barrier(&self) -> bool {
+        self.code.code.is_empty()
+    }
 }
 
@@ -45,20 +45,38 @@ pub enum Instruction {
     PopTop,
     DupTop,
     Nop,
+    BinaryModulo,
+    BinarySubtract,
     BinarySubscr,
+    BinaryAnd,
+    BinaryXor,
+    BinaryOr,
+    InplaceSubtract,
+    InplaceAnd,
+    InplaceXor,
+    InplaceOr,
+    StoreSubscr,
+    DeleteSubscr,
     GetIter,
     LoadBuildClass,
     ReturnValue,
+    YieldValue,
     PopBlock,
     EndFinally,
     PopExcept,
     StoreName(usize),
+    DeleteName(usize),
+    UnpackSequence(usize),
     ForIter(usize),
     StoreAttr(usize),
+    DeleteAttr(usize),
     StoreGlobal(usize),
+    DeleteGlobal(usize),
     LoadConst(usize),
     LoadName(usize),
     BuildTuple(usize),
+    BuildList(usize),
+    BuildSet(usize),
     LoadAttr(usize),
     SetupLoop(usize),
     SetupExcept(usize),
@@ -66,13 +84,29 @@ pub enum Instruction {
     JumpForward(usize),
     JumpAbsolute(usize),
     PopJumpIfFalse(usize),
+    PopJumpIfTrue(usize),
     LoadFast(usize),
     StoreFast(usize),
+    DeleteFast(usize),
     LoadGlobal(usize),
     CallFunction(usize, bool), // nb_args + nb_kwargs, has_kwargs
     RaiseVarargs(usize),
+    LoadClosure(usize),
+    LoadDeref(usize),
+    StoreDeref(usize),
+    LoadClassDeref(usize),
     MakeFunction { has_defaults: bool, has_kwdefaults: bool, has_annotations: bool, has_closure: bool },
     BuildConstKeyMap(usize),
+    BuildMap(usize),
+    BuildTupleUnpackWithCall(usize),
+    BuildMapUnpackWithCall(usize),
+    CallFunctionEx(bool), // has_kwargs
+    FormatValue { conversion: Option<char>, has_format_spec: bool },
+    BuildString(usize),
+    ListAppend(usize),
+    SetAdd(usize),
+    MapAdd(usize),
+    Unsupported(u8), // opcode the interpreter does not implement (eg. YIELD_FROM); raises a ProcessorError if run
 }
 
 #[derive(Debug)]
@@ -138,30 +172,51 @@ impl<'a, I> Iterator for InstructionDecoder<I> where I: Iterator<Item=&'a u8> {
         let inst = match opcode {
             1 => Instruction::PopTop,
             4 => Instruction::DupTop,
+            22 => Instruction::BinaryModulo,
+            24 => Instruction::BinarySubtract,
             25 => Instruction::BinarySubscr,
+            56 => Instruction::InplaceSubtract,
+            60 => Instruction::StoreSubscr,
+            61 => Instruction::DeleteSubscr,
+            64 => Instruction::BinaryAnd,
+            65 => Instruction::BinaryXor,
+            66 => Instruction::BinaryOr,
             68 => Instruction::GetIter,
             71 => Instruction::LoadBuildClass,
+            77 => Instruction::InplaceAnd,
+            78 => Instruction::InplaceXor,
+            79 => Instruction::InplaceOr,
             83 => Instruction::ReturnValue,
+            86 => Instruction::YieldValue,
             87 => Instruction::PopBlock,
             88 => Instruction::EndFinally,
             89 => Instruction::PopExcept,
             90 => Instruction::StoreName(oparg),
+            91 => Instruction::DeleteName(oparg),
+            92 => Instruction::UnpackSequence(oparg),
             93 => Instruction::ForIter(oparg),
             95 => Instruction::StoreAttr(oparg),
+            96 => Instruction::DeleteAttr(oparg),
             97 => Instruction::StoreGlobal(oparg),
+            98 => Instruction::DeleteGlobal(oparg),
             100 => Instruction::LoadConst(oparg),
             101 => Instruction::LoadName(oparg),
             102 => Instruction::BuildTuple(oparg),
+            103 => Instruction::BuildList(oparg),
+            104 => Instruction::BuildSet(oparg),
+            105 => Instruction::BuildMap(oparg),
             106 => Instruction::LoadAttr(oparg),
             107 => Instruction::CompareOp(CmpOperator::from_bytecode(oparg)),
             110 => Instruction::JumpForward(oparg),
             113 => Instruction::JumpAbsolute(oparg),
             114 => Instruction::PopJumpIfFalse(oparg),
+            115 => Instruction::PopJumpIfTrue(oparg),
             116 => Instruction::LoadGlobal(oparg),
             120 => Instruction::SetupLoop(oparg + 1),
             121 => Instruction::SetupExcept(oparg + 1),
             124 => Instruction::LoadFast(oparg),
             125 => Instruction::StoreFast(oparg),
+            126 => Instruction::DeleteFast(oparg),
             130 => Instruction::RaiseVarargs(oparg),
             131 => Instruction::CallFunction(oparg, false),
             132 => Instruction::MakeFunction {
@@ -170,10 +225,25 @@ impl<'a, I> Iterator for InstructionDecoder<I> where I: Iterator<Item=&'a u8> {
                 has_annotations: oparg & 0x04 != 0,
                 has_closure: oparg & 0x08 != 0,
             },
+            135 => Instruction::LoadClosure(oparg),
+            136 => Instruction::LoadDeref(oparg),
+            137 => Instruction::StoreDeref(oparg),
             141 => Instruction::CallFunction(oparg, true),
+            142 => Instruction::CallFunctionEx(oparg & 0x01 != 0),
+            145 => Instruction::ListAppend(oparg),
+            146 => Instruction::SetAdd(oparg),
+            147 => Instruction::MapAdd(oparg),
+            148 => Instruction::LoadClassDeref(oparg),
+            151 => Instruction::BuildMapUnpackWithCall(oparg),
+            155 => Instruction::FormatValue {
+                conversion: match oparg & 0x03 { 1 => Some('s'), 2 => Some('r'), 3 => Some('a'), _ => None },
+                has_format_spec: oparg & 0x04 != 0,
+            },
             156 => Instruction::BuildConstKeyMap(oparg),
+            157 => Instruction::BuildString(oparg),
+            158 => Instruction::BuildTupleUnpackWithCall(oparg),
             144 => panic!("The impossible happened."),
-            _ => panic!(format!("Opcode not supported: {:?}", (opcode, oparg))),
+            _ => Instruction::Unsupported(opcode),
         };
         Some(inst)
     }
@@ -186,3 +256,12 @@ fn test_load_read() {
     let instructions: Vec<Instruction> = reader.collect();
     assert_eq!(vec![Instruction::LoadFast(1), Instruction::ReturnValue], instructions);
 }
+
+#[test]
+fn test_unsupported_opcode() {
+    // YIELD_FROM, used by `yield from`
+    let bytes: Vec<u8> = vec![72, 0, 83, 0];
+    let reader = InstructionDecoder::new(bytes.iter());
+    let instructions: Vec<Instruction> = reader.collect();
+    assert_eq!(vec![Instruction::Unsupported(72), Instruction::ReturnValue], instructions);
+}
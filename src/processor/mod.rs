@@ -1,7 +1,7 @@
 pub mod instructions;
 pub mod frame;
 
-use super::objects::{ObjectRef, ObjectContent, Object};
+use super::objects::{ObjectRef, ObjectContent, Object, ObjectStore, Code, DictContent};
 use super::varstack::VarStack;
 use self::instructions::{CmpOperator, Instruction};
 use self::frame::{Block, Frame};
@@ -30,9 +30,11 @@ pub enum ProcessorError {
     InvalidName(String),
     InvalidNameIndex,
     InvalidVarnameIndex,
+    InvalidCellIndex,
     UnknownPrimitive(String),
     UnmarshalError(marshal::decode::UnmarshalError),
     InvalidModuleName(String),
+    YieldOutsideGenerator,
 }
 
 impl fmt::Display for ProcessorError {
@@ -77,45 +79,319 @@ macro_rules! top_stack {
 
 
 
-// Load a name from the namespace
-fn load_name<EP: EnvProxy>(state: &mut State<EP>, frame: &Frame, name: &String) -> Option<ObjectRef> {
+// Namespace of a module, which is the __dict__ of the module object
+fn module_namespace<EP: EnvProxy>(state: &State<EP>, module_name: &str) -> Option<Rc<RefCell<HashMap<String, ObjectRef>>>> {
+    state.modules.get(module_name).map(|module| state.store.deref(module).attributes.clone().unwrap()) // Modules always have a __dict__
+}
+
+// Namespace of the module a frame's code belongs to
+fn frame_globals<EP: EnvProxy>(state: &State<EP>, frame: &Frame) -> Rc<RefCell<HashMap<String, ObjectRef>>> {
+    module_namespace(state, &frame.object.module(&state.store)).unwrap()
+}
+
+// Load a name from the namespace: locals (if `use_locals`), then globals, then builtins
+fn load_name<EP: EnvProxy>(state: &mut State<EP>, frame: &Frame, name: &String, use_locals: bool) -> Option<ObjectRef> {
     if *name == "__primitives__" {
         return Some(state.store.allocate(Object::new_instance(Some("__primitives__".to_string()), state.primitive_objects.object.clone(), ObjectContent::PrimitiveNamespace)))
     }
-    if *name == "__name__" {
-        return Some(state.store.allocate(state.primitive_objects.new_string("<module>".to_string())))
+    if use_locals {
+        if let Some(obj_ref) = frame.locals.borrow().get(name) {
+            return Some(obj_ref.clone())
+        }
     }
-    if let Some(obj_ref) = frame.locals.borrow().get(name) {
+    if let Some(obj_ref) = frame_globals(state, frame).borrow().get(name) {
         return Some(obj_ref.clone())
     }
-    if let Some(m) = state.modules.get("builtins") {
+    if let Some(m) = module_namespace(state, "builtins") {
         if let Some(obj_ref) = m.borrow().get(name) {
             return Some(obj_ref.clone())
         }
     }
-    if let Some(m) = state.modules.get(&frame.object.module(&state.store)) {
-        if let Some(obj_ref) = m.borrow().get(name) {
-            return Some(obj_ref.clone())
+    None
+}
+
+// Value of a name in the namespace of a class body, which may be any mapping.
+// Returns None if an exception was raised, and Some(None) if the name is missing.
+fn namespace_get<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, namespace: &ObjectRef, name: &str) -> Option<Option<ObjectRef>> {
+    let key = state.store.allocate(state.primitive_objects.new_string(name.to_string()));
+    match lookup_special_method(state, namespace, "__getitem__") {
+        Some(method) => {
+            let keyerror = state.primitive_objects.keyerror.clone();
+            match call_function_catching(state, call_stack, &method, vec![key], vec![], &keyerror) {
+                None => Some(None),
+                Some(PyResult::Return(value)) => Some(Some(value)),
+                Some(PyResult::Raised) => None,
+            }
+        },
+        None => primitives::dicts::get_item(state, call_stack, namespace, &key),
+    }
+}
+
+// Removes a name from the namespace of a class body, like namespace_get.
+fn namespace_delete<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, namespace: &ObjectRef, name: &str) -> Option<Option<ObjectRef>> {
+    let key = state.store.allocate(state.primitive_objects.new_string(name.to_string()));
+    match lookup_special_method(state, namespace, "__delitem__") {
+        Some(method) => {
+            let keyerror = state.primitive_objects.keyerror.clone();
+            match call_function_catching(state, call_stack, &method, vec![key], vec![], &keyerror) {
+                None => Some(None),
+                Some(PyResult::Return(value)) => Some(Some(value)),
+                Some(PyResult::Raised) => None,
+            }
+        },
+        None => primitives::dicts::remove_item(state, call_stack, namespace, &key),
+    }
+}
+
+// Error raised when a local variable is used before being assigned
+fn raise_unbound_local<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, name: &str) {
+    let exc = state.primitive_objects.unboundlocalerror.clone();
+    raise(state, call_stack, exc, format!("local variable '{}' referenced before assignment", name))
+}
+
+// Name of the i-th cell of a frame running this code: cellvars come first, then freevars.
+fn cell_name(store: &ObjectStore, code: &Code, i: usize) -> Option<String> {
+    let name = if i < code.cellvars.len() {
+        code.cellvars.get(i)
+    }
+    else {
+        code.freevars.get(i - code.cellvars.len())
+    };
+    name.and_then(|name| match store.deref(name).content {
+        ObjectContent::String(ref s) => Some(s.clone()),
+        _ => None,
+    })
+}
+
+// Functions of a property: getter, setter, and deleter
+fn get_property(store: &ObjectStore, obj_ref: &ObjectRef) -> Option<(ObjectRef, ObjectRef, ObjectRef)> {
+    match store.deref(obj_ref).content {
+        ObjectContent::Property(ref fget, ref fset, ref fdel, _) => Some((fget.clone(), fset.clone(), fdel.clone())),
+        _ => None,
+    }
+}
+
+// Index of the slot of a member descriptor
+fn get_member(store: &ObjectStore, obj_ref: &ObjectRef) -> Option<usize> {
+    match store.deref(obj_ref).content {
+        ObjectContent::MemberDescriptor(_, index) => Some(index),
+        _ => None,
+    }
+}
+
+// Apply the descriptor protocol to a value found in a class: functions are bound to
+// the instance (if any), classmethods are bound to the class, and staticmethods are unwrapped.
+pub fn bind_attribute<EP: EnvProxy>(state: &mut State<EP>, value: ObjectRef, instance: Option<ObjectRef>, class: ObjectRef) -> ObjectRef {
+    let method = match state.store.deref(&value).content {
+        ObjectContent::Function(..) | ObjectContent::PrimitiveFunction(_) => {
+            match instance {
+                Some(instance) => state.primitive_objects.new_bound_method(value.clone(), instance),
+                None => return value,
+            }
+        },
+        ObjectContent::ClassMethod(ref func) => state.primitive_objects.new_bound_method(func.clone(), class),
+        ObjectContent::StaticMethod(ref func) => return func.clone(),
+        _ => return value,
+    };
+    state.store.allocate(method)
+}
+
+// Attributes of builtin objects that are stored in their content
+fn builtin_attribute(obj: &Object, name: &String) -> Option<ObjectRef> {
+    match (&obj.content, name.as_ref()) {
+        (&ObjectContent::Property(ref fget, _, _, _), "fget") => Some(fget.clone()),
+        (&ObjectContent::Property(_, ref fset, _, _), "fset") => Some(fset.clone()),
+        (&ObjectContent::Property(_, _, ref fdel, _), "fdel") => Some(fdel.clone()),
+        (&ObjectContent::Property(_, _, _, ref doc), "__doc__") => Some(doc.clone()),
+        (&ObjectContent::StaticMethod(ref func), "__func__") |
+        (&ObjectContent::ClassMethod(ref func), "__func__") |
+        (&ObjectContent::BoundMethod(ref func, _), "__func__") => Some(func.clone()),
+        (&ObjectContent::BoundMethod(_, ref instance), "__self__") |
+        (&ObjectContent::Super(_, ref instance), "__self__") => Some(instance.clone()),
+        (&ObjectContent::Super(ref class, _), "__thisclass__") => Some(class.clone()),
+        (&ObjectContent::Cell(Some(ref value)), "cell_contents") => Some(value.clone()),
+        _ => None,
+    }
+}
+
+// Copy of a namespace as a dict object, with names sorted for determinism.
+// TODO: return a view that reflects changes to the namespace
+fn namespace_dict<EP: EnvProxy>(state: &mut State<EP>, namespace: &Rc<RefCell<HashMap<String, ObjectRef>>>) -> ObjectRef {
+    let mut items: Vec<(String, ObjectRef)> = namespace.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+    items.sort_by(|&(ref k1, _), &(ref k2, _)| k1.cmp(k2));
+    primitives::dicts::new_string_dict(state, items)
+}
+
+// Attributes of functions and code objects that are computed from their content
+fn introspection_attribute<EP: EnvProxy>(state: &mut State<EP>, obj: &Object, name: &str) -> Option<ObjectRef> {
+    let new_int = |state: &mut State<EP>, i: usize| state.store.allocate(state.primitive_objects.new_int(i as i64));
+    let new_strings = |state: &mut State<EP>, strings: &[String]| {
+        let strings = strings.iter().map(|s| state.store.allocate(state.primitive_objects.new_string(s.clone()))).collect();
+        state.store.allocate(state.primitive_objects.new_tuple(strings))
+    };
+    match obj.content {
+        ObjectContent::Function(ref module, ref code_ref, ref defaults, ref closure) => {
+            let code = match state.store.deref(code_ref).content {
+                ObjectContent::Code(ref code) => code.clone(),
+                _ => return None,
+            };
+            match name {
+                "__code__" => Some(code_ref.clone()),
+                "__module__" => Some(state.store.allocate(state.primitive_objects.new_string(module.clone()))),
+                "__globals__" => module_namespace(state, module).map(|namespace| namespace_dict(state, &namespace)),
+                "__defaults__" => {
+                    let values: Vec<ObjectRef> = code.varnames[..code.argcount].iter().filter_map(|name| defaults.get(name).cloned()).collect();
+                    if values.len() == 0 {
+                        Some(state.primitive_objects.none.clone())
+                    }
+                    else {
+                        Some(state.store.allocate(state.primitive_objects.new_tuple(values)))
+                    }
+                },
+                "__kwdefaults__" => {
+                    let kwonlyargs = &code.varnames[code.argcount..code.argcount+code.kwonlyargcount];
+                    let items: Vec<(String, ObjectRef)> = kwonlyargs.iter().filter_map(|name| defaults.get(name).map(|value| (name.clone(), value.clone()))).collect();
+                    if items.len() == 0 {
+                        Some(state.primitive_objects.none.clone())
+                    }
+                    else {
+                        Some(primitives::dicts::new_string_dict(state, items))
+                    }
+                },
+                "__closure__" => {
+                    if closure.len() == 0 {
+                        Some(state.primitive_objects.none.clone())
+                    }
+                    else {
+                        Some(state.store.allocate(state.primitive_objects.new_tuple(closure.clone())))
+                    }
+                },
+                _ => None,
+            }
+        },
+        ObjectContent::Code(ref code) => {
+            match name {
+                "co_argcount" => Some(new_int(state, code.argcount)),
+                "co_kwonlyargcount" => Some(new_int(state, code.kwonlyargcount)),
+                "co_nlocals" => Some(new_int(state, code.nlocals as usize)),
+                "co_stacksize" => Some(new_int(state, code.stacksize as usize)),
+                "co_flags" => Some(new_int(state, code.flags as usize)),
+                "co_firstlineno" => Some(new_int(state, code.firstlineno as usize)),
+                "co_varnames" => Some(new_strings(state, &code.varnames)),
+                "co_names" => Some(new_strings(state, &code.names)),
+                "co_consts" => Some(state.store.allocate(state.primitive_objects.new_tuple(code.consts.clone()))),
+                "co_freevars" => Some(state.store.allocate(state.primitive_objects.new_tuple(code.freevars.clone()))),
+                "co_cellvars" => Some(state.store.allocate(state.primitive_objects.new_tuple(code.cellvars.clone()))),
+                "co_filename" => Some(state.store.allocate(state.primitive_objects.new_string(code.filename.clone()))),
+                "co_name" => Some(state.store.allocate(state.primitive_objects.new_string(code.name.clone()))),
+                "co_lnotab" => Some(code.lnotab.clone()),
+                "co_code" => Some(state.store.allocate(state.primitive_objects.new_bytes(code.code.clone()))),
+                _ => None,
+            }
+        },
+        _ => None,
+    }
+}
+
+// A method of a class that overrides the one of `object`, used for the attribute hooks.
+fn attribute_hook<EP: EnvProxy>(state: &State<EP>, class: &ObjectRef, name: &str) -> Option<ObjectRef> {
+    let default = primitives::lookup_class_attribute(&state.store, &state.primitive_objects.object, name);
+    match (primitives::lookup_class_attribute(&state.store, class, name), default) {
+        (Some(ref method), Some(ref default)) if method.is(default) => None,
+        (method, _) => method,
+    }
+}
+
+// Error raised when an attribute is not found
+fn raise_attribute_error<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef, name: &str) {
+    let exc = state.primitive_objects.attributeerror.clone();
+    let message = {
+        let obj = state.store.deref(obj_ref);
+        match obj.bases {
+            Some(_) => format!("type object '{}' has no attribute '{}'", obj.name.clone().unwrap_or("?".to_string()), name),
+            None => format!("'{}' object has no attribute '{}'", state.store.deref(&obj.class).name.clone().unwrap_or("?".to_string()), name),
         }
+    };
+    raise(state, call_stack, exc, message)
+}
+
+/// Load an attribute of an object and push it on the stack, using the
+/// __getattribute__ and __getattr__ methods of its class if it has some.
+pub fn load_attr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef, name: &String) {
+    let class = state.store.deref(obj_ref).class.clone();
+    match attribute_hook(state, &class, "__getattribute__") {
+        Some(getattribute) => {
+            let getattribute = bind_attribute(state, getattribute, Some(obj_ref.clone()), class.clone());
+            let name = state.store.allocate(state.primitive_objects.new_string(name.clone()));
+            match primitives::lookup_class_attribute(&state.store, &class, "__getattr__") {
+                Some(getattr) => {
+                    // Like CPython, __getattr__ is called if __getattribute__ raises an AttributeError
+                    let attributeerror = state.primitive_objects.attributeerror.clone();
+                    match call_function_catching(state, call_stack, &getattribute, vec![name.clone()], vec![], &attributeerror) {
+                        Some(PyResult::Return(value)) => return_value(call_stack, value),
+                        Some(PyResult::Raised) => (),
+                        None => {
+                            let getattr = bind_attribute(state, getattr, Some(obj_ref.clone()), class);
+                            call_function(state, call_stack, &getattr, vec![name], vec![])
+                        },
+                    }
+                },
+                None => call_function(state, call_stack, &getattribute, vec![name], vec![]),
+            }
+        },
+        None => generic_getattr(state, call_stack, obj_ref, name, true),
     }
-    None
 }
 
+// Copy of an object to look up its attributes. The items of containers are not
+// needed for that, and are not copied as there may be many of them.
+fn attribute_lookup_copy(obj: &Object) -> Object {
+    let content = match obj.content {
+        ObjectContent::String(_) | ObjectContent::Tuple(_) | ObjectContent::List(_) |
+        ObjectContent::Set(_) | ObjectContent::FrozenSet(_) | ObjectContent::Dict(_) |
+        ObjectContent::Bytes(_) | ObjectContent::ByteArray(_) => ObjectContent::OtherObject,
+        ref content => content.clone(),
+    };
+    Object {
+        version: obj.version,
+        name: obj.name.clone(),
+        content: content,
+        class: obj.class.clone(),
+        bases: obj.bases.clone(),
+        mro: obj.mro.clone(),
+        attributes: obj.attributes.clone(),
+        slots: obj.slots.clone(),
+    }
+}
 
-fn load_attr<EP: EnvProxy>(state: &mut State<EP>, obj: &Object, name: &String) -> Option<ObjectRef> {
-    match name.as_ref() {
+/// Load an attribute of an object and push it on the stack, like object.__getattribute__.
+/// Calls the getter if the attribute is a property, and __getattr__ (if `use_getattr` is
+/// true) when the attribute is not found.
+pub fn generic_getattr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef, name: &String, use_getattr: bool) {
+    let obj = attribute_lookup_copy(state.store.deref(obj_ref));
+    let is_module = match obj.content {
+        ObjectContent::Module(_) => true,
+        _ => false,
+    };
+    let res = match name.as_ref() {
         "__bases__" => {
             match obj.bases {
                 Some(ref v) => Some(state.store.allocate(state.primitive_objects.new_tuple(v.clone()))),
                 None => Some(state.primitive_objects.none.clone()),
             }
         },
-        "__name__" => {
+        "__dict__" if is_module => Some(namespace_dict(state, obj.attributes.as_ref().unwrap())),
+        "__name__" if !is_module => {
             match obj.name {
                 Some(ref s) => Some(state.store.allocate(state.primitive_objects.new_string(s.clone()))),
                 None => None,
             }
         },
+        "__class__" => Some(obj.class.clone()),
+        "__mro__" if obj.bases.is_some() => {
+            let mro = primitives::class_mro(&state.store, obj_ref).to_vec();
+            Some(state.store.allocate(state.primitive_objects.new_tuple(mro)))
+        },
         _ => {
             if let ObjectContent::PrimitiveNamespace = obj.content {
                 match state.primitive_objects.names_map.get(name) {
@@ -123,43 +399,948 @@ fn load_attr<EP: EnvProxy>(state: &mut State<EP>, obj: &Object, name: &String) -
                     None => Some(state.store.allocate(Object::new_instance(Some(name.clone()), state.primitive_objects.function_type.clone(), ObjectContent::PrimitiveFunction(name.clone())))),
                 }
             }
+            else if let Some(value) = builtin_attribute(&obj, name) {
+                Some(value)
+            }
+            else if let Some(value) = introspection_attribute(state, &obj, name) {
+                Some(value)
+            }
+            else if let ObjectContent::Super(ref this_class, ref instance) = obj.content {
+                return load_super_attr(state, call_stack, this_class, instance, name)
+            }
             else {
-                // TODO: special names
-                match obj.attributes {
-                    Some(ref attributes) => attributes.borrow().get(name).map(|r| r.clone()),
-                    None => None,
+                let type_attr = primitives::lookup_class_attribute(&state.store, &obj.class, name);
+                // Properties of the type take precedence over the object's own attributes
+                if let Some((fget, _, _)) = type_attr.as_ref().and_then(|attr| get_property(&state.store, attr)) {
+                    if fget.is(&state.primitive_objects.none) {
+                        let exc = state.primitive_objects.attributeerror.clone();
+                        return raise(state, call_stack, exc, "unreadable attribute".to_string())
+                    }
+                    return call_function(state, call_stack, &fget, vec![obj_ref.clone()], vec![])
+                }
+                // Then slots
+                if obj.bases.is_none() {
+                    if let Some(index) = type_attr.as_ref().and_then(|attr| get_member(&state.store, attr)) {
+                        return match obj.slots.get(index) {
+                            Some(&Some(ref value)) => return_value(call_stack, value.clone()),
+                            _ => {
+                                let exc = state.primitive_objects.attributeerror.clone();
+                                raise(state, call_stack, exc, name.clone())
+                            },
+                        }
+                    }
+                }
+                let own_attr = if obj.bases.is_some() {
+                    // obj is a class, its own attributes are those of its MRO.
+                    primitives::lookup_class_attribute(&state.store, obj_ref, name)
+                        .map(|value| bind_attribute(state, value, None, obj_ref.clone()))
+                }
+                else {
+                    match obj.attributes {
+                        Some(ref attributes) => attributes.borrow().get(name).cloned(),
+                        None => None,
+                    }
+                };
+                match (own_attr, type_attr) {
+                    (Some(value), _) => Some(value),
+                    (None, Some(type_attr)) => Some(bind_attribute(state, type_attr, Some(obj_ref.clone()), obj.class.clone())),
+                    (None, None) => None,
+                }
+            }
+        }
+    };
+    match res {
+        None => {
+            let getattr = match use_getattr {
+                true => primitives::lookup_class_attribute(&state.store, &obj.class, "__getattr__"),
+                false => None,
+            };
+            match getattr {
+                Some(getattr) => {
+                    let getattr = bind_attribute(state, getattr, Some(obj_ref.clone()), obj.class.clone());
+                    let name = state.store.allocate(state.primitive_objects.new_string(name.clone()));
+                    call_function(state, call_stack, &getattr, vec![name], vec![])
+                },
+                None => raise_attribute_error(state, call_stack, obj_ref, name),
+            }
+        },
+        Some(obj_ref) => {
+            let frame = call_stack.last_mut().unwrap();
+            frame.var_stack.push(obj_ref)
+        }
+    }
+}
+
+// Load an attribute through a super object: look it up in the MRO of the
+// instance's class (or of the class itself), after `this_class`.
+fn load_super_attr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, this_class: &ObjectRef, instance: &ObjectRef, name: &String) {
+    let (self_class, bound_instance) = {
+        let instance_obj = state.store.deref(instance);
+        if instance_obj.bases.is_some() && primitives::native_issubclass(&state.store, instance, this_class) {
+            (instance.clone(), None)
+        }
+        else {
+            (instance_obj.class.clone(), Some(instance.clone()))
+        }
+    };
+    let value = {
+        let mro = primitives::class_mro(&state.store, &self_class);
+        let start = mro.iter().position(|class| class.is(this_class)).map(|i| i+1).unwrap_or(mro.len());
+        primitives::lookup_mro_attribute(&state.store, &mro[start..], name)
+    };
+    match value {
+        Some(value) => {
+            if let (Some((fget, _, _)), &Some(ref bound_instance)) = (get_property(&state.store, &value), &bound_instance) {
+                return call_function(state, call_stack, &fget, vec![bound_instance.clone()], vec![])
+            }
+            let value = bind_attribute(state, value, bound_instance, self_class);
+            return_value(call_stack, value)
+        },
+        None => {
+            let exc = state.primitive_objects.attributeerror.clone();
+            raise(state, call_stack, exc, format!("'super' object has no attribute '{}'", name))
+        },
+    }
+}
+
+/// Set an attribute of an object, using the __setattr__ method of its class if it has one.
+/// Returns false if an exception was raised.
+pub fn store_attr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, owner: ObjectRef, name: String, value: ObjectRef) -> bool {
+    let class = state.store.deref(&owner).class.clone();
+    match attribute_hook(state, &class, "__setattr__") {
+        Some(setattr) => {
+            let setattr = bind_attribute(state, setattr, Some(owner), class);
+            let name = state.store.allocate(state.primitive_objects.new_string(name));
+            match call_function_sync(state, call_stack, &setattr, vec![name, value], vec![]) {
+                PyResult::Return(_) => true,
+                PyResult::Raised => false,
+            }
+        },
+        None => generic_setattr(state, call_stack, owner, name, value),
+    }
+}
+
+/// Set an attribute of an object, like object.__setattr__. Calls the setter if the
+/// attribute is a property. Returns false if an exception was raised.
+pub fn generic_setattr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, owner: ObjectRef, name: String, value: ObjectRef) -> bool {
+    let type_attr = {
+        let owner_class = &state.store.deref(&owner).class;
+        primitives::lookup_class_attribute(&state.store, owner_class, &name)
+    };
+    if let Some(index) = type_attr.as_ref().and_then(|attr| get_member(&state.store, attr)) {
+        let slots = &mut state.store.deref_mut(&owner).slots;
+        if slots.len() <= index {
+            slots.resize(index+1, None);
+        }
+        slots[index] = Some(value);
+        return true
+    }
+    let fset = type_attr.and_then(|attr| get_property(&state.store, &attr)).map(|(_, fset, _)| fset);
+    match fset {
+        Some(fset) => {
+            if fset.is(&state.primitive_objects.none) {
+                let exc = state.primitive_objects.attributeerror.clone();
+                raise(state, call_stack, exc, "can't set attribute".to_string());
+                return false
+            }
+            // The setter's return value is ignored.
+            match call_function_sync(state, call_stack, &fset, vec![owner, value], vec![]) {
+                PyResult::Return(_) => true,
+                PyResult::Raised => false,
+            }
+        },
+        None => {
+            if !owner.setattr(&mut state.store, name.clone(), value) {
+                raise_attribute_error(state, call_stack, &owner, &name);
+                return false
+            }
+            true
+        },
+    }
+}
+
+/// Delete an attribute of an object, using the __delattr__ method of its class if it has one.
+/// Returns false if an exception was raised.
+pub fn delete_attr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, owner: ObjectRef, name: String) -> bool {
+    let class = state.store.deref(&owner).class.clone();
+    match attribute_hook(state, &class, "__delattr__") {
+        Some(delattr) => {
+            let delattr = bind_attribute(state, delattr, Some(owner), class);
+            let name = state.store.allocate(state.primitive_objects.new_string(name));
+            match call_function_sync(state, call_stack, &delattr, vec![name], vec![]) {
+                PyResult::Return(_) => true,
+                PyResult::Raised => false,
+            }
+        },
+        None => generic_delattr(state, call_stack, owner, name),
+    }
+}
+
+/// Delete an attribute of an object, like object.__delattr__. Calls the deleter if the
+/// attribute is a property. Returns false if an exception was raised.
+pub fn generic_delattr<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, owner: ObjectRef, name: String) -> bool {
+    let type_attr = {
+        let owner_class = &state.store.deref(&owner).class;
+        primitives::lookup_class_attribute(&state.store, owner_class, &name)
+    };
+    if let Some(index) = type_attr.as_ref().and_then(|attr| get_member(&state.store, attr)) {
+        let value = state.store.deref_mut(&owner).slots.get_mut(index).and_then(|slot| slot.take());
+        if value.is_none() {
+            let exc = state.primitive_objects.attributeerror.clone();
+            raise(state, call_stack, exc, name);
+            return false
+        }
+        return true
+    }
+    let fdel = type_attr.and_then(|attr| get_property(&state.store, &attr)).map(|(_, _, fdel)| fdel);
+    match fdel {
+        Some(fdel) => {
+            if fdel.is(&state.primitive_objects.none) {
+                let exc = state.primitive_objects.attributeerror.clone();
+                raise(state, call_stack, exc, "can't delete attribute".to_string());
+                return false
+            }
+            match call_function_sync(state, call_stack, &fdel, vec![owner], vec![]) {
+                PyResult::Return(_) => true,
+                PyResult::Raised => false,
+            }
+        },
+        None => {
+            if owner.delattr(&mut state.store, &name).is_none() {
+                raise_attribute_error(state, call_stack, &owner, &name);
+                return false
+            }
+            true
+        },
+    }
+}
+
+// Calls a __str__ or __repr__ method, and checks it returns a str.
+fn call_string_method<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, method: &ObjectRef, method_name: &str) -> Option<String> {
+    let res = match call_function_sync(state, call_stack, method, vec![], vec![]) {
+        PyResult::Return(res) => res,
+        PyResult::Raised => return None,
+    };
+    if let ObjectContent::String(ref s) = state.store.deref(&res).content {
+        return Some(s.clone())
+    }
+    let exc = state.primitive_objects.typeerror.clone();
+    let name = type_name(&state.store, &res);
+    raise(state, call_stack, exc, format!("{} returned non-string (type {})", method_name, name));
+    None
+}
+
+/// str(obj) from native code, using the __str__ or __repr__ method of the object's
+/// class if it has one. Returns None if an exception was raised.
+pub fn str_of<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<String> {
+    if let ObjectContent::String(ref s) = state.store.deref(obj_ref).content {
+        return Some(s.clone())
+    }
+    match lookup_special_method(state, obj_ref, "__str__") {
+        Some(method) => call_string_method(state, call_stack, &method, "__str__"),
+        None => repr_of(state, call_stack, obj_ref),
+    }
+}
+
+/// repr(obj) from native code, using the __repr__ method of the object's class if
+/// it has one. Returns None if an exception was raised.
+pub fn repr_of<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<String> {
+    match lookup_special_method(state, obj_ref, "__repr__") {
+        Some(method) => call_string_method(state, call_stack, &method, "__repr__"),
+        None => Some(obj_ref.repr(&state.store)),
+    }
+}
+
+// Function object wrapping a primitive.
+pub fn primitive_function<EP: EnvProxy>(state: &mut State<EP>, name: &str) -> ObjectRef {
+    state.store.allocate(Object::new_instance(None, state.primitive_objects.function_type.clone(), ObjectContent::PrimitiveFunction(name.to_string())))
+}
+
+/// Look up a special method (eg. __len__) in the class of an object, and bind it to
+/// the object. Like CPython, this ignores the attributes of the object itself.
+pub fn lookup_special_method<EP: EnvProxy>(state: &mut State<EP>, obj_ref: &ObjectRef, name: &str) -> Option<ObjectRef> {
+    let class = state.store.deref(obj_ref).class.clone();
+    primitives::lookup_class_attribute(&state.store, &class, name).map(|method| bind_attribute(state, method, Some(obj_ref.clone()), class))
+}
+
+/// Get the next value of an iterator from native code.
+/// Returns None if the iterator is exhausted.
+pub fn iterator_next_sync<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, iterator: &ObjectRef) -> Option<PyResult> {
+    let (next, args) = match lookup_special_method(state, iterator, "__next__") {
+        Some(next) => (next, vec![]),
+        None => (primitive_function(state, "next"), vec![iterator.clone()]),
+    };
+    let stopiteration = state.primitive_objects.stopiteration.clone();
+    call_function_catching(state, call_stack, &next, args, vec![], &stopiteration)
+}
+
+fn to_number(content: &ObjectContent) -> Option<i64> {
+    match *content {
+        ObjectContent::Int(i) => Some(i),
+        ObjectContent::True => Some(1),
+        ObjectContent::False => Some(0),
+        _ => None,
+    }
+}
+
+// Numbers compared with floats are converted to floats.
+fn to_float(content: &ObjectContent) -> Option<f64> {
+    match *content {
+        ObjectContent::Float(bits) => Some(f64::from_bits(bits)),
+        _ => to_number(content).map(|i| i as f64),
+    }
+}
+
+/// Truth value of an object, as tested by `if`: calls `__bool__`, or `__len__`,
+/// and tests builtin objects natively.
+/// Returns None if an exception was raised.
+pub fn truth_value<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef) -> Option<bool> {
+    if let Some(method) = lookup_special_method(state, obj_ref, "__bool__") {
+        let res = match call_function_sync(state, call_stack, &method, vec![], vec![]) {
+            PyResult::Return(res) => res,
+            PyResult::Raised => return None,
+        };
+        return match state.store.deref(&res).content {
+            ObjectContent::True => Some(true),
+            ObjectContent::False => Some(false),
+            _ => {
+                let exc = state.primitive_objects.typeerror.clone();
+                let name = type_name(&state.store, &res);
+                raise(state, call_stack, exc, format!("__bool__ should return bool, returned {}", name));
+                None
+            },
+        }
+    }
+    if let Some(method) = lookup_special_method(state, obj_ref, "__len__") {
+        let res = match call_function_sync(state, call_stack, &method, vec![], vec![]) {
+            PyResult::Return(res) => res,
+            PyResult::Raised => return None,
+        };
+        return match state.store.deref(&res).content {
+            ObjectContent::Int(i) if i < 0 => {
+                let exc = state.primitive_objects.valueerror.clone();
+                raise(state, call_stack, exc, "__len__() should return >= 0".to_string());
+                None
+            },
+            ObjectContent::Int(i) => Some(i != 0),
+            ObjectContent::True => Some(true),
+            ObjectContent::False => Some(false),
+            _ => {
+                let exc = state.primitive_objects.typeerror.clone();
+                let name = type_name(&state.store, &res);
+                raise(state, call_stack, exc, format!("'{}' object cannot be interpreted as an integer", name));
+                None
+            },
+        }
+    }
+    Some(primitives::native_bool(&state.store, obj_ref))
+}
+
+/// Compare two objects with ==, using their __eq__ methods if they have some.
+/// Returns None if an exception was raised.
+pub fn objects_equal<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, first: &ObjectRef, second: &ObjectRef) -> Option<bool> {
+    if first.is(second) {
+        return Some(true)
+    }
+    match call_binary_method(state, call_stack, first, second, "__eq__", "__eq__")? {
+        Some(res) => Some(primitives::native_bool(&state.store, &res)),
+        None => native_equal(state, call_stack, first, second),
+    }
+}
+
+// Comparison of objects of native types with ==; other objects are only equal
+// to themselves. Returns None if an exception was raised.
+fn native_equal<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, first: &ObjectRef, second: &ObjectRef) -> Option<bool> {
+    if first.is(second) {
+        return Some(true)
+    }
+    let first_content = state.store.deref(first).content.clone(); // TODO: copy only if needed
+    let second_content = state.store.deref(second).content.clone();
+    if let (Some(i), Some(j)) = (to_number(&first_content), to_number(&second_content)) {
+        return Some(i == j)
+    }
+    if let (Some(x), Some(y)) = (to_float(&first_content), to_float(&second_content)) {
+        return Some(x == y)
+    }
+    match (first_content, second_content) {
+        (ObjectContent::None, ObjectContent::None) => Some(true),
+        (ObjectContent::String(ref s1), ObjectContent::String(ref s2)) => Some(s1 == s2),
+        (ObjectContent::Bytes(ref b1), ObjectContent::Bytes(ref b2)) |
+        (ObjectContent::Bytes(ref b1), ObjectContent::ByteArray(ref b2)) |
+        (ObjectContent::ByteArray(ref b1), ObjectContent::Bytes(ref b2)) |
+        (ObjectContent::ByteArray(ref b1), ObjectContent::ByteArray(ref b2)) => Some(b1 == b2),
+        (ObjectContent::Tuple(ref v1), ObjectContent::Tuple(ref v2)) |
+        (ObjectContent::List(ref v1), ObjectContent::List(ref v2)) => {
+            if v1.len() != v2.len() {
+                return Some(false)
+            }
+            for (item1, item2) in v1.iter().zip(v2.iter()) {
+                if !objects_equal(state, call_stack, item1, item2)? {
+                    return Some(false)
+                }
+            }
+            Some(true)
+        },
+        (ObjectContent::Set(ref v1), ObjectContent::Set(ref v2)) |
+        (ObjectContent::Set(ref v1), ObjectContent::FrozenSet(ref v2)) |
+        (ObjectContent::FrozenSet(ref v1), ObjectContent::Set(ref v2)) |
+        (ObjectContent::FrozenSet(ref v1), ObjectContent::FrozenSet(ref v2)) => {
+            if v1.len() != v2.len() {
+                return Some(false)
+            }
+            for entry in v1.iter() {
+                if primitives::dicts::lookup_hashed(state, call_stack, v2, entry.hash, &entry.key)?.is_none() {
+                    return Some(false)
+                }
+            }
+            Some(true)
+        },
+        (ObjectContent::Range(start1, stop1, step1), ObjectContent::Range(start2, stop2, step2)) => {
+            // Ranges are equal if they have the same items
+            let (len1, len2) = (primitives::iterators::range_len(start1, stop1, step1), primitives::iterators::range_len(start2, stop2, step2));
+            Some(len1 == len2 && (len1 == 0 || (start1 == start2 && (len1 == 1 || step1 == step2))))
+        },
+        (ObjectContent::Dict(ref items1), ObjectContent::Dict(ref items2)) => {
+            if items1.len() != items2.len() {
+                return Some(false)
+            }
+            for entry in items1.iter() {
+                match primitives::dicts::lookup(state, call_stack, items2, &entry.key)? {
+                    (_, Some(position)) => {
+                        let value2 = items2.get(position).unwrap().value.clone();
+                        if !objects_equal(state, call_stack, &entry.value, &value2)? {
+                            return Some(false)
+                        }
+                    },
+                    (_, None) => return Some(false),
+                }
+            }
+            Some(true)
+        },
+        _ => Some(false),
+    }
+}
+
+/// Index of the first item equal to the given object.
+/// Returns None if an exception was raised, and Some(None) if there is no such item.
+pub fn position_in<'a, EP: EnvProxy, I: Iterator<Item=&'a ObjectRef>>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, items: I, obj_ref: &ObjectRef) -> Option<Option<usize>> {
+    for (i, item) in items.enumerate() {
+        if objects_equal(state, call_stack, item, obj_ref)? {
+            return Some(Some(i))
+        }
+    }
+    Some(None)
+}
+
+/// Like Python's `<` operator, as used by list.sort: calls `__lt__`, or the
+/// reflected `__gt__`, and compares builtin objects natively.
+pub fn less_than<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, first: &ObjectRef, second: &ObjectRef) -> Option<bool> {
+    if let Some(res) = call_binary_method(state, call_stack, first, second, "__lt__", "__gt__")? {
+        return Some(primitives::native_bool(&state.store, &res))
+    }
+    match native_less_than(state, call_stack, first, second)? {
+        Some(res) => Some(res),
+        None => {
+            raise_unorderable(state, call_stack, first, second, "<");
+            None
+        },
+    }
+}
+
+// Comparison of objects of native types with <.
+// Returns None if an exception was raised, and Some(None) if they are not ordered.
+fn native_less_than<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, first: &ObjectRef, second: &ObjectRef) -> Option<Option<bool>> {
+    let first_content = state.store.deref(first).content.clone(); // TODO: copy only if needed
+    let second_content = state.store.deref(second).content.clone();
+    if let (Some(i), Some(j)) = (to_number(&first_content), to_number(&second_content)) {
+        return Some(Some(i < j))
+    }
+    if let (Some(x), Some(y)) = (to_float(&first_content), to_float(&second_content)) {
+        return Some(Some(x < y))
+    }
+    match (first_content, second_content) {
+        (ObjectContent::String(ref s1), ObjectContent::String(ref s2)) => Some(Some(s1 < s2)),
+        (ObjectContent::Bytes(ref b1), ObjectContent::Bytes(ref b2)) |
+        (ObjectContent::Bytes(ref b1), ObjectContent::ByteArray(ref b2)) |
+        (ObjectContent::ByteArray(ref b1), ObjectContent::Bytes(ref b2)) |
+        (ObjectContent::ByteArray(ref b1), ObjectContent::ByteArray(ref b2)) => Some(Some(b1 < b2)),
+        (ObjectContent::Tuple(ref v1), ObjectContent::Tuple(ref v2)) |
+        (ObjectContent::List(ref v1), ObjectContent::List(ref v2)) => {
+            // Lexicographic order: the first items which differ decide.
+            for (item1, item2) in v1.iter().zip(v2.iter()) {
+                if !objects_equal(state, call_stack, item1, item2)? {
+                    return less_than(state, call_stack, item1, item2).map(Some)
+                }
+            }
+            Some(Some(v1.len() < v2.len()))
+        },
+        _ => Some(None),
+    }
+}
+
+fn raise_unorderable<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, first: &ObjectRef, second: &ObjectRef, symbol: &str) {
+    let exc = state.primitive_objects.typeerror.clone();
+    let (first_name, second_name) = (type_name(&state.store, first), type_name(&state.store, second));
+    raise(state, call_stack, exc, format!("'{}' not supported between instances of '{}' and '{}'", symbol, first_name, second_name))
+}
+
+/// Implementation of the `in` operator, using the __contains__ or __iter__ method
+/// of the container if it has one.
+/// Returns None if an exception was raised.
+pub fn contains<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, container: &ObjectRef, item: &ObjectRef) -> Option<bool> {
+    if let Some(method) = lookup_special_method(state, container, "__contains__") {
+        return match call_function_sync(state, call_stack, &method, vec![item.clone()], vec![]) {
+            PyResult::Return(res) => Some(primitives::native_bool(&state.store, &res)),
+            PyResult::Raised => None,
+        }
+    }
+    // Hash tables are looked up without copying them
+    match state.store.deref(container).content {
+        ObjectContent::Set(_) | ObjectContent::FrozenSet(_) | ObjectContent::Dict(_) =>
+            return primitives::dicts::lookup_in(state, call_stack, container, item).map(|(_, position)| position.is_some()),
+        _ => (),
+    }
+    let content = state.store.deref(container).content.clone(); // TODO: copy only if needed
+    match content {
+        ObjectContent::String(ref s) => {
+            if let ObjectContent::String(ref substring) = state.store.deref(item).content {
+                return Some(s.contains(substring.as_str()))
+            }
+            let exc = state.primitive_objects.typeerror.clone();
+            let name = type_name(&state.store, item);
+            raise(state, call_stack, exc, format!("'in <string>' requires string as left operand, not {}", name));
+            None
+        },
+        ObjectContent::Bytes(ref b) | ObjectContent::ByteArray(ref b) => {
+            match state.store.deref(item).content {
+                ObjectContent::Int(i) if (0..256).contains(&i) => return Some(b.contains(&(i as u8))),
+                ObjectContent::Int(_) => {
+                    let exc = state.primitive_objects.valueerror.clone();
+                    raise(state, call_stack, exc, "byte must be in range(0, 256)".to_string());
+                    return None
+                },
+                ObjectContent::Bytes(ref sub) | ObjectContent::ByteArray(ref sub) =>
+                    return Some(sub.is_empty() || b.windows(sub.len()).any(|window| window == &sub[..])),
+                _ => (),
+            }
+            let exc = state.primitive_objects.typeerror.clone();
+            let name = type_name(&state.store, item);
+            raise(state, call_stack, exc, format!("a bytes-like object is required, not '{}'", name));
+            None
+        },
+        ObjectContent::Tuple(ref v) | ObjectContent::List(ref v) =>
+            position_in(state, call_stack, v.iter(), item).map(|pos| pos.is_some()),
+        ObjectContent::DictView(ref dict, ref view) =>
+            primitives::dicts::view_contains(state, call_stack, dict, *view, item),
+        ObjectContent::Range(start, stop, step) => {
+            let number = to_number(&state.store.deref(item).content);
+            match number {
+                Some(i) => Some(primitives::iterators::range_contains(start, stop, step, i)),
+                None => {
+                    // Other objects may be equal to an int
+                    for i in 0..primitives::iterators::range_len(start, stop, step) {
+                        let value = state.store.allocate(state.primitive_objects.new_int(start + i * step));
+                        if objects_equal(state, call_stack, &value, item)? {
+                            return Some(true)
+                        }
+                    }
+                    Some(false)
+                },
+            }
+        },
+        _ => {
+            let is_iterable = lookup_special_method(state, container, "__iter__").is_some() ||
+                lookup_special_method(state, container, "__getitem__").is_some();
+            if !is_iterable {
+                let exc = state.primitive_objects.typeerror.clone();
+                let name = type_name(&state.store, container);
+                raise(state, call_stack, exc, format!("argument of type '{}' is not iterable", name));
+                return None
+            }
+            let iter_func = primitive_function(state, "iter");
+            let iterator = match call_function_sync(state, call_stack, &iter_func, vec![container.clone()], vec![]) {
+                PyResult::Return(iterator) => iterator,
+                PyResult::Raised => return None,
+            };
+            loop {
+                match iterator_next_sync(state, call_stack, &iterator) {
+                    None => return Some(false),
+                    Some(PyResult::Raised) => return None,
+                    Some(PyResult::Return(value)) => {
+                        if objects_equal(state, call_stack, &value, item)? {
+                            return Some(true)
+                        }
+                    }
                 }
             }
         }
     }
 }
 
+// Native implementation of container[index]
+// Implementation of container[index], using __getitem__ if the container has one.
+fn getitem<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, container: ObjectRef, index: ObjectRef) {
+    match lookup_special_method(state, &container, "__getitem__") {
+        Some(method) => call_function(state, call_stack, &method, vec![index], vec![]),
+        None => native_getitem(state, call_stack, container, index),
+    }
+}
+
+// Calls the special method of the left operand, then the reflected one of the right
+// operand, until one of them exists and does not return NotImplemented.
+// Returns None if an exception was raised, and Some(None) if no method gave a result.
+fn call_binary_method<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, lhs: &ObjectRef, rhs: &ObjectRef, method: &str, reflected: &str) -> Option<Option<ObjectRef>> {
+    for &(left, right, method_name) in [(lhs, rhs, method), (rhs, lhs, reflected)].iter() {
+        if let Some(method) = lookup_special_method(state, left, method_name) {
+            match call_function_sync(state, call_stack, &method, vec![right.clone()], vec![]) {
+                PyResult::Return(ref res) if res.is(&state.primitive_objects.not_implemented) => (),
+                PyResult::Return(res) => return Some(Some(res)),
+                PyResult::Raised => return None,
+            }
+        }
+    }
+    Some(None)
+}
+
+// Implementation of binary operators, using the special method of the left operand,
+// or the reflected one of the right operand.
+fn binary_operator<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, lhs: ObjectRef, rhs: ObjectRef, method: &str, reflected: &str, symbol: &str) {
+    match call_binary_method(state, call_stack, &lhs, &rhs, method, reflected) {
+        None => return,
+        Some(Some(res)) => return return_value(call_stack, res),
+        Some(None) => (),
+    }
+    let exc = state.primitive_objects.typeerror.clone();
+    let (lhs_name, rhs_name) = (type_name(&state.store, &lhs), type_name(&state.store, &rhs));
+    raise(state, call_stack, exc, format!("unsupported operand type(s) for {}: '{}' and '{}'", symbol, lhs_name, rhs_name))
+}
+
+/// A binary operator from native code.
+pub fn binary_operator_sync<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, lhs: ObjectRef, rhs: ObjectRef, method: &str, reflected: &str, symbol: &str) -> PyResult {
+    let barrier = Frame::new_barrier(call_stack.last().unwrap().object.clone(), state.primitive_objects.none.clone());
+    run_with_barrier(state, call_stack, barrier, |state, call_stack| binary_operator(state, call_stack, lhs, rhs, method, reflected, symbol)).unwrap() // This barrier does not catch exceptions
+}
+
+/// container[index] from native code.
+pub fn getitem_sync<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, container: ObjectRef, index: ObjectRef) -> PyResult {
+    let barrier = Frame::new_barrier(call_stack.last().unwrap().object.clone(), state.primitive_objects.none.clone());
+    run_with_barrier(state, call_stack, barrier, |state, call_stack| getitem(state, call_stack, container, index)).unwrap() // This barrier does not catch exceptions
+}
+
+// An item of a builtin sequence, read while the sequence is borrowed and
+// allocated afterwards if needed.
+enum SequenceItem {
+    Object(ObjectRef),
+    Int(i64),
+    Char(char),
+}
+
+// Position of an item in a sequence of this length, from an index which may be
+// negative. Returns None if the index is out of range.
+fn item_position(index: i64, length: usize) -> Option<usize> {
+    primitives::signed_index(index, length).filter(|&position| position < length)
+}
+
+fn native_getitem<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, container_ref: ObjectRef, index_ref: ObjectRef) {
+    if let ObjectContent::Dict(_) = state.store.deref(&container_ref).content {
+        return dict_getitem(state, call_stack, container_ref, index_ref)
+    }
+    let index = match state.store.deref(&index_ref).content {
+        ObjectContent::Int(i) => Some(i),
+        _ => None,
+    };
+    let typeerror = state.primitive_objects.typeerror.clone();
+    // None if the container is not a sequence indexed by an int, Some(None) if
+    // the index is out of range.
+    let item = match (&state.store.deref(&container_ref).content, index) {
+        (&ObjectContent::Tuple(ref v), Some(i)) | (&ObjectContent::List(ref v), Some(i)) =>
+            Some(item_position(i, v.len()).map(|i| SequenceItem::Object(v[i].clone()))),
+        // Strings are indexed by code points
+        (&ObjectContent::String(ref s), Some(i)) =>
+            Some(item_position(i, s.chars().count()).and_then(|i| s.chars().nth(i)).map(SequenceItem::Char)),
+        (&ObjectContent::Bytes(ref v), Some(i)) | (&ObjectContent::ByteArray(ref v), Some(i)) =>
+            Some(item_position(i, v.len()).map(|i| SequenceItem::Int(v[i] as i64))),
+        (&ObjectContent::Range(start, stop, step), Some(i)) => {
+            let length = primitives::iterators::range_len(start, stop, step) as usize;
+            Some(item_position(i, length).map(|i| SequenceItem::Int(start + (i as i64) * step)))
+        },
+        _ => None,
+    };
+    match item {
+        Some(Some(item)) => {
+            let obj_ref = match item {
+                SequenceItem::Object(obj_ref) => obj_ref,
+                SequenceItem::Int(i) => state.store.allocate(state.primitive_objects.new_int(i)),
+                SequenceItem::Char(c) => state.store.allocate(state.primitive_objects.new_string(c.to_string())),
+            };
+            return_value(call_stack, obj_ref)
+        },
+        Some(None) => {
+            let exc = state.primitive_objects.indexerror.clone();
+            let message = match state.store.deref(&container_ref).content {
+                ObjectContent::String(_) => "string index out of range".to_string(),
+                ObjectContent::Bytes(_) => "index out of range".to_string(),
+                ObjectContent::Range(..) => "range object index out of range".to_string(),
+                _ => format!("{} index out of range", type_name(&state.store, &container_ref)),
+            };
+            raise(state, call_stack, exc, message)
+        },
+        None => {
+            match state.store.deref(&container_ref).content {
+                ObjectContent::Tuple(_) | ObjectContent::List(_) | ObjectContent::String(_) => {
+                    let (container_name, index_name) = (type_name(&state.store, &container_ref), type_name(&state.store, &index_ref));
+                    raise(state, call_stack, typeerror, format!("{} indices must be integers, not {}", container_name, index_name))
+                },
+                ObjectContent::Bytes(_) | ObjectContent::ByteArray(_) | ObjectContent::Range(..) => {
+                    let (container_name, index_name) = (type_name(&state.store, &container_ref), type_name(&state.store, &index_ref));
+                    let container_name = if container_name == "bytes" { "byte".to_string() } else { container_name };
+                    raise(state, call_stack, typeerror, format!("{} indices must be integers or slices, not {}", container_name, index_name))
+                },
+                _ => {
+                    let name = type_name(&state.store, &container_ref);
+                    raise(state, call_stack, typeerror, format!("'{}' object is not subscriptable", name))
+                },
+            }
+        },
+    }
+}
+
+// Native implementation of dict[key], which does not copy the dict's table.
+fn dict_getitem<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, dict_ref: ObjectRef, key_ref: ObjectRef) {
+    match primitives::dicts::get_item(state, call_stack, &dict_ref, &key_ref) {
+        None => (),
+        Some(Some(value)) => return_value(call_stack, value),
+        Some(None) => {
+            // Subclasses of dict may compute missing values
+            match lookup_special_method(state, &dict_ref, "__missing__") {
+                Some(method) => call_function(state, call_stack, &method, vec![key_ref], vec![]),
+                None => {
+                    let exc = state.primitive_objects.keyerror.clone();
+                    let repr = key_ref.repr(&state.store);
+                    raise(state, call_stack, exc, repr)
+                },
+            }
+        },
+    }
+}
+
+// Implementation of container[index] = value, using __setitem__ if the container has one.
+fn setitem<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, container: ObjectRef, index: ObjectRef, value: ObjectRef) {
+    match lookup_special_method(state, &container, "__setitem__") {
+        Some(method) => {
+            match call_function_sync(state, call_stack, &method, vec![index, value], vec![]) {
+                PyResult::Return(_) | PyResult::Raised => (),
+            }
+        },
+        None => native_setitem(state, call_stack, container, index, Some(value)),
+    }
+}
+
+// Native implementation of container[index] = value, or of del container[index]
+// if value is None.
+fn native_setitem<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, container_ref: ObjectRef, index_ref: ObjectRef, value: Option<ObjectRef>) {
+    if let ObjectContent::Dict(_) = state.store.deref(&container_ref).content {
+        // Dicts bump their version only when keys are added or removed
+        match value {
+            Some(value) => { primitives::dicts::set_item(state, call_stack, &container_ref, index_ref, value); },
+            None => {
+                if let Some(None) = primitives::dicts::remove_item(state, call_stack, &container_ref, &index_ref) {
+                    let exc = state.primitive_objects.keyerror.clone();
+                    let repr = index_ref.repr(&state.store);
+                    raise(state, call_stack, exc, repr)
+                }
+            },
+        }
+        return
+    }
+    let index = match state.store.deref(&index_ref).content {
+        ObjectContent::Int(i) => Some(i),
+        _ => None,
+    };
+    let typeerror = state.primitive_objects.typeerror.clone();
+    let (length, indices_message) = match state.store.deref(&container_ref).content {
+        ObjectContent::List(ref v) => (v.len(), "list indices must be integers"),
+        ObjectContent::ByteArray(ref v) => (v.len(), "bytearray indices must be integers or slices"),
+        _ => {
+            let name = type_name(&state.store, &container_ref);
+            let message = if value.is_some() { "does not support item assignment" } else { "doesn't support item deletion" };
+            return raise(state, call_stack, typeerror, format!("'{}' object {}", name, message))
+        },
+    };
+    let index = match index {
+        Some(index) => index,
+        None => {
+            let name = type_name(&state.store, &index_ref);
+            return raise(state, call_stack, typeerror, format!("{}, not {}", indices_message, name))
+        },
+    };
+    let is_list = match state.store.deref(&container_ref).content {
+        ObjectContent::List(_) => true,
+        _ => false,
+    };
+    let position = match item_position(index, length) {
+        Some(position) => position,
+        None => {
+            let exc = state.primitive_objects.indexerror.clone();
+            let message = match (is_list, value.is_some()) {
+                (true, true) => "list assignment index out of range",
+                (true, false) => "list index out of range",
+                (false, _) => "bytearray index out of range",
+            };
+            return raise(state, call_stack, exc, message.to_string())
+        },
+    };
+    // Bytearrays only hold ints in range(0, 256)
+    let byte = match value {
+        Some(ref value) if !is_list => {
+            match state.store.deref(value).content {
+                ObjectContent::Int(b) if (0..256).contains(&b) => Some(b as u8),
+                ObjectContent::Int(_) => {
+                    let exc = state.primitive_objects.valueerror.clone();
+                    return raise(state, call_stack, exc, "byte must be in range(0, 256)".to_string())
+                },
+                _ => {
+                    let name = type_name(&state.store, value);
+                    return raise(state, call_stack, typeerror, format!("an integer is required (got type {})", name))
+                },
+            }
+        },
+        _ => None,
+    };
+    let container = state.store.deref_mut(&container_ref);
+    match (&mut container.content, value, byte) {
+        (&mut ObjectContent::List(ref mut v), Some(value), _) => v[position] = value,
+        (&mut ObjectContent::ByteArray(ref mut v), Some(_), Some(byte)) => v[position] = byte,
+        (&mut ObjectContent::List(ref mut v), None, _) => { v.remove(position); },
+        (&mut ObjectContent::ByteArray(ref mut v), None, _) => { v.remove(position); },
+        _ => unreachable!(),
+    }
+    container.bump_version();
+}
+
+// Arguments given to a call with *args.
+// Raises a TypeError and returns None if one of them is not iterable.
+fn unpack_call_args<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, iterables: Vec<ObjectRef>) -> Option<Vec<ObjectRef>> {
+    let mut args = Vec::new();
+    for iterable in iterables {
+        match state.store.deref(&iterable).content {
+            ObjectContent::Tuple(ref v) | ObjectContent::List(ref v) => { args.extend(v.iter().cloned()); continue },
+            _ => (),
+        }
+        let exc = state.primitive_objects.typeerror.clone();
+        let repr = iterable.repr(&state.store);
+        raise(state, call_stack, exc, format!("argument after * must be an iterable, not {}", repr));
+        return None
+    }
+    Some(args)
+}
+
+// Keyword arguments given to a call with **kwargs.
+// Raises a TypeError and returns None if they are not dicts with string keys, or if
+// a keyword is given twice.
+fn unpack_call_kwargs<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, mappings: Vec<ObjectRef>) -> Option<Vec<(ObjectRef, ObjectRef)>> {
+    let exc = state.primitive_objects.typeerror.clone();
+    let mut kwargs: Vec<(ObjectRef, ObjectRef)> = Vec::new();
+    let mut names: Vec<String> = Vec::new();
+    for mapping in mappings {
+        let items = match state.store.deref(&mapping).content {
+            ObjectContent::Dict(ref items) => items.items(),
+            _ => {
+                let repr = mapping.repr(&state.store);
+                raise(state, call_stack, exc, format!("argument after ** must be a mapping, not {}", repr));
+                return None
+            },
+        };
+        for (key, value) in items {
+            let name = match state.store.deref(&key).content {
+                ObjectContent::String(ref s) => s.clone(),
+                _ => {
+                    raise(state, call_stack, exc, "keywords must be strings".to_string());
+                    return None
+                },
+            };
+            if names.contains(&name) {
+                raise(state, call_stack, exc, format!("got multiple values for keyword argument '{}'", name));
+                return None
+            }
+            names.push(name);
+            kwargs.push((key, value));
+        }
+    }
+    Some(kwargs)
+}
+
+// Primitives named `<class>.<method>` are the native methods of builtin classes.
+// Like CPython's method descriptors, they check they are called on an instance
+// of their class, so primitives can rely on the content of their receiver.
+fn native_method_receiver_error<EP: EnvProxy>(state: &State<EP>, name: &str, args: &[ObjectRef]) -> Option<String> {
+    let mut parts = name.splitn(2, '.');
+    let (class_name, method_name) = match (parts.next(), parts.next()) {
+        (Some(class_name), Some(method_name)) => (class_name, method_name),
+        _ => return None,
+    };
+    match method_name {
+        "__new__" | "__init_subclass__" | "__prepare__" | "fromhex" | "fromkeys" => return None, // Static and class methods
+        _ => (),
+    }
+    let class = state.primitive_objects.names_map.get(class_name)?;
+    match args.first() {
+        Some(receiver) if primitives::native_isinstance(&state.store, receiver, class) => None,
+        Some(receiver) => Some(format!("descriptor '{}' requires a '{}' object but received a '{}'", method_name, class_name, type_name(&state.store, receiver))),
+        None => Some(format!("descriptor '{}' of '{}' object needs an argument", method_name, class_name)),
+    }
+}
+
+// Name of the class of an object, for error messages.
+pub fn type_name(store: &ObjectStore, obj_ref: &ObjectRef) -> String {
+    store.deref(&store.deref(obj_ref).class).name.clone().unwrap_or("?".to_string())
+}
+
 // Call a primitive / function / code object, with arguments.
-fn call_function<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, func_ref: &ObjectRef, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+pub fn call_function<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, func_ref: &ObjectRef, mut args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
     // TODO: clone only if necessary
     match state.store.deref(func_ref).content.clone() {
         ObjectContent::Class => {
-            let frame = call_stack.last_mut().unwrap();
-            frame.var_stack.push(func_ref.new_instance(&mut state.store, args, kwargs))
+            // Calling a class calls the __call__ method of its metaclass, which is
+            // type.__call__ unless overridden.
+            let metaclass = state.store.deref(func_ref).class.clone();
+            let call = primitives::lookup_class_attribute(&state.store, &metaclass, "__call__").unwrap(); // type.__call__ always exists
+            let is_type_call = match state.store.deref(&call).content {
+                ObjectContent::PrimitiveFunction(ref name) => name == "type.__call__",
+                _ => false,
+            };
+            if is_type_call {
+                call_class(state, call_stack, func_ref, args, kwargs)
+            }
+            else {
+                let call = bind_attribute(state, call, Some(func_ref.clone()), metaclass);
+                call_function(state, call_stack, &call, args, kwargs)
+            }
+        },
+        ObjectContent::BoundMethod(ref func, ref instance) => {
+            args.insert(0, instance.clone());
+            call_function(state, call_stack, func, args, kwargs)
         },
-        ObjectContent::Function(ref _func_module, ref code_ref, ref defaults) => {
+        ObjectContent::Function(ref _func_module, ref code_ref, ref defaults, ref closure) => {
             let code = state.store.deref(code_ref).content.clone();
             if let ObjectContent::Code(code) = code {
 
                 let mut locals = defaults.clone();
 
                 if let Some(starargs_name) = code.get_varargs_name() { // If it has a *args argument
-                    if code.argcount > args.len() {
-                        panic!(format!("{}() takes at least {} arguments, but {} was/were given.", code.name, code.argcount, args.len()))
-                    };
-                    let to_vararg = args.drain(code.argcount..).collect();
+                    let to_vararg = if args.len() > code.argcount { args.drain(code.argcount..).collect() } else { Vec::new() };
                     let obj_ref = state.store.allocate(state.primitive_objects.new_tuple(to_vararg));
 
                     // Bind *args
                     assert_eq!(None, locals.insert(starargs_name.clone(), obj_ref));
                 }
-                else if code.argcount != args.len() { // If it has no *args argument
-                    panic!(format!("{}() takes {} arguments, but {} was/were given.", code.name, code.argcount, args.len()))
+                else if args.len() > code.argcount { // If it has no *args argument
+                    let exc = state.primitive_objects.typeerror.clone();
+                    let nb_required = code.varnames[..code.argcount].iter().filter(|name| !defaults.contains_key(*name)).count();
+                    let expected = if nb_required == code.argcount { code.argcount.to_string() } else { format!("from {} to {}", nb_required, code.argcount) };
+                    let plural = if code.argcount == 1 && nb_required == 1 { "" } else { "s" };
+                    let verb = if args.len() == 1 { "was" } else { "were" };
+                    return raise(state, call_stack, exc, format!("{}() takes {} positional argument{} but {} {} given", code.name, expected, plural, args.len(), verb))
                 };
 
                 // Handle keyword arguments
@@ -168,11 +1349,19 @@ fn call_function<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame
                     let explicit_keywords = code.keywords();
                     for (key, value) in kwargs.into_iter() {
                         let key_str = match state.store.deref(&key).content {
-                            ObjectContent::String(ref s) => s,
+                            ObjectContent::String(ref s) => s.clone(),
                             _ => panic!("Keyword names should be strings."),
                         };
-                        if explicit_keywords.contains(key_str) {
-                            locals.insert(key_str.clone(), value);
+                        if explicit_keywords.contains(&key_str) {
+                            locals.insert(key_str, value);
+                        }
+                        else if let Some(position) = code.varnames[..code.argcount].iter().position(|name| *name == key_str) {
+                            // Positional arguments can be given by keyword too
+                            if position < args.len() {
+                                let exc = state.primitive_objects.typeerror.clone();
+                                return raise(state, call_stack, exc, format!("{}() got multiple values for argument '{}'", code.name, key_str))
+                            }
+                            locals.insert(key_str, value);
                         }
                         else {
                             remaining_kwargs.push((key, value))
@@ -181,7 +1370,8 @@ fn call_function<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame
                 }
 
                 if let Some(starkwargs_name) = code.get_varkwargs_name() { // If it has a **kwargs argument
-                    let obj_ref = state.store.allocate(state.primitive_objects.new_dict(remaining_kwargs));
+                    let dict = primitives::dicts::string_keyed_dict(state, remaining_kwargs);
+                    let obj_ref = state.store.allocate(state.primitive_objects.new_dict(dict));
                     locals.insert(starkwargs_name.clone(), obj_ref);
                 }
                 else { // If it has no **kwargs argument
@@ -197,8 +1387,48 @@ fn call_function<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame
                     };
                 }
 
-                let new_frame = Frame::new(func_ref.clone(), *code, Rc::new(RefCell::new(locals)));
-                call_stack.push(new_frame);
+                // Arguments without default values must be given
+                for &(ref names, kind) in [(&code.varnames[..code.argcount], "positional"), (&code.varnames[code.argcount..code.argcount+code.kwonlyargcount], "keyword-only")].iter() {
+                    let missing: Vec<String> = names.iter().filter(|name| !locals.contains_key(*name)).map(|name| format!("'{}'", name)).collect();
+                    if missing.len() != 0 {
+                        let exc = state.primitive_objects.typeerror.clone();
+                        let plural = if missing.len() == 1 { "" } else { "s" };
+                        let names = match missing.len() {
+                            1 => missing[0].clone(),
+                            2 => format!("{} and {}", missing[0], missing[1]),
+                            n => format!("{}, and {}", missing[..n-1].join(", "), missing[n-1]),
+                        };
+                        return raise(state, call_stack, exc, format!("{}() missing {} required {} argument{}: {}", code.name, missing.len(), kind, plural, names))
+                    }
+                }
+
+                // Create cells of variables used by nested functions (arguments
+                // start in their cell), then add the cells of the closure.
+                let mut cells = Vec::with_capacity(code.cellvars.len() + closure.len());
+                for name in code.cellvars.iter() {
+                    let value = match state.store.deref(name).content {
+                        ObjectContent::String(ref name) => locals.get(name).cloned(),
+                        _ => None,
+                    };
+                    cells.push(state.store.allocate(state.primitive_objects.new_cell(value)));
+                }
+                cells.extend(closure.iter().cloned());
+
+                let is_generator = code.co_generator();
+                let name = code.name.clone();
+                let mut new_frame = Frame::new(func_ref.clone(), *code, Rc::new(RefCell::new(locals)));
+                new_frame.cells = cells;
+                if is_generator {
+                    // The function's code runs when the generator is iterated
+                    let generator_type = state.primitive_objects.generator_type.clone();
+                    let generator = state.store.allocate(Object::new_instance(Some(name), generator_type, ObjectContent::Generator));
+                    new_frame.generator = Some(generator.clone());
+                    state.generator_frames.insert(generator.clone(), new_frame);
+                    return_value(call_stack, generator)
+                }
+                else {
+                    call_stack.push(new_frame);
+                }
             }
             else {
                 let exc = state.primitive_objects.processorerror.clone();
@@ -214,22 +1444,157 @@ fn call_function<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame
                     raise(state, call_stack, exc, format!("Unknown primitive {}", name)); // Should have errored before
                 },
                 Some(function) => {
-                    function(state, call_stack, args); // Call the primitive
+                    if let Some(message) = native_method_receiver_error(state, name, &args) {
+                        let exc = state.primitive_objects.typeerror.clone();
+                        return raise(state, call_stack, exc, message)
+                    }
+                    function(state, call_stack, args, kwargs); // Call the primitive
+                }
+            }
+        },
+        _ => {
+            match lookup_special_method(state, func_ref, "__call__") {
+                Some(call) => call_function(state, call_stack, &call, args, kwargs),
+                None => {
+                    let exc = state.primitive_objects.typeerror.clone();
+                    let name = type_name(&state.store, func_ref);
+                    raise(state, call_stack, exc, format!("'{}' object is not callable", name));
+                }
+            }
+        }
+    }
+}
+
+/// Create an instance of a class with its __new__ method, then initialize it
+/// with __init__. This is the implementation of type.__call__.
+pub fn call_class<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, func_ref: &ObjectRef, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) {
+    if func_ref.is(&state.primitive_objects.type_) && args.len() == 1 && kwargs.len() == 0 {
+        // type(obj) returns the class of the object, without calling the __init__ of its metaclass
+        let class = state.store.deref(&args[0]).class.clone();
+        return return_value(call_stack, class)
+    }
+    let new = primitives::lookup_class_attribute(&state.store, func_ref, "__new__").unwrap(); // object.__new__ always exists
+    let new = bind_attribute(state, new, None, func_ref.clone());
+    let mut new_args = args.clone();
+    new_args.insert(0, func_ref.clone());
+    let instance = match call_function_sync(state, call_stack, &new, new_args, kwargs.clone()) {
+        PyResult::Return(instance) => instance,
+        PyResult::Raised => return,
+    };
+    if primitives::native_isinstance(&state.store, &instance, func_ref) {
+        let class = state.store.deref(&instance).class.clone();
+        let init = primitives::lookup_class_attribute(&state.store, &class, "__init__").unwrap(); // object.__init__ always exists
+        let init = bind_attribute(state, init, Some(instance.clone()), class);
+        match call_function_sync(state, call_stack, &init, args, kwargs) {
+            PyResult::Return(res) => {
+                if !res.is(&state.primitive_objects.none) {
+                    let exc = state.primitive_objects.typeerror.clone();
+                    let class_name = type_name(&state.store, &res);
+                    return raise(state, call_stack, exc, format!("__init__() should return None, not '{}'", class_name))
                 }
-            }
-        },
-        _ => {
-            let exc = state.primitive_objects.typeerror.clone();
-            let repr = func_ref.repr(&state.store);
-            raise(state, call_stack, exc, format!("Not a function object {:?}", repr));
+            },
+            PyResult::Raised => return,
+        }
+    }
+    return_value(call_stack, instance)
+}
+
+/// Call a function from native code, and run it until it returns.
+/// If it raises an exception, the call stack is unwound as usual and Raised is returned.
+pub fn call_function_sync<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, func_ref: &ObjectRef, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> PyResult {
+    let barrier = Frame::new_barrier(call_stack.last().unwrap().object.clone(), state.primitive_objects.none.clone());
+    call_with_barrier(state, call_stack, barrier, func_ref, args, kwargs).unwrap() // This barrier does not catch exceptions
+}
+
+/// Like call_function_sync, but returns None instead of unwinding the call stack if
+/// the function raises an instance of `exc_class`.
+pub fn call_function_catching<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, func_ref: &ObjectRef, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>, exc_class: &ObjectRef) -> Option<PyResult> {
+    let mut barrier = Frame::new_barrier(call_stack.last().unwrap().object.clone(), state.primitive_objects.none.clone());
+    barrier.block_stack.push(Block::ExceptPopGoto(exc_class.clone(), 0, 0));
+    call_with_barrier(state, call_stack, barrier, func_ref, args, kwargs)
+}
+
+/// Like load_attr from native code, but returns None instead of unwinding the call
+/// stack if the attribute lookup raises an instance of `exc_class`.
+pub fn load_attr_catching<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, obj_ref: &ObjectRef, name: &String, exc_class: &ObjectRef) -> Option<PyResult> {
+    let mut barrier = Frame::new_barrier(call_stack.last().unwrap().object.clone(), state.primitive_objects.none.clone());
+    barrier.block_stack.push(Block::ExceptPopGoto(exc_class.clone(), 0, 0));
+    run_with_barrier(state, call_stack, barrier, |state, call_stack| load_attr(state, call_stack, obj_ref, name))
+}
+
+// Push a barrier frame, then call the function above it and run it until it returns.
+// Returns None if the exception was caught by the barrier.
+fn call_with_barrier<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, barrier: Frame, func_ref: &ObjectRef, args: Vec<ObjectRef>, kwargs: Vec<(ObjectRef, ObjectRef)>) -> Option<PyResult> {
+    run_with_barrier(state, call_stack, barrier, |state, call_stack| call_function(state, call_stack, func_ref, args, kwargs))
+}
+
+// Push a barrier frame, then run an operation that pushes its result (or a
+// new frame) on the call stack, and run it until it returns.
+// Returns None if the exception was caught by the barrier.
+fn run_with_barrier<EP: EnvProxy, F>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, barrier: Frame, operation: F) -> Option<PyResult>
+        where F: FnOnce(&mut State<EP>, &mut Vec<Frame>) {
+    call_stack.push(barrier);
+    let depth = call_stack.len();
+    operation(state, call_stack);
+    if call_stack.len() > depth {
+        // A new frame was pushed, run it.
+        if let PyResult::Return(res) = run_code(state, call_stack, depth) {
+            call_stack.pop().unwrap(); // Remove the barrier
+            return Some(PyResult::Return(res))
         }
     }
+    if call_stack.len() == depth {
+        // Either the function returned its result on the barrier's stack, or the
+        // exception was caught by the barrier.
+        let mut barrier = call_stack.pop().unwrap();
+        barrier.var_stack.pop().map(PyResult::Return)
+    }
+    else {
+        // An exception was raised and unwound past the barrier.
+        Some(PyResult::Raised)
+    }
+}
+
+/// Run a generator until it yields a value, which is returned, or until its code
+/// returns, in which case Some(None) is returned.
+/// Returns None if an exception was raised.
+pub fn resume_generator<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, generator: &ObjectRef) -> Option<Option<ObjectRef>> {
+    let mut frame = match state.generator_frames.remove(generator) {
+        Some(frame) => frame,
+        None => return Some(None), // Exhausted
+    };
+    if frame.program_counter != 0 {
+        // Value of the yield expression the generator was suspended at
+        frame.var_stack.push(state.primitive_objects.none.clone());
+    }
+    match run_frame_sync(state, call_stack, frame) {
+        // The frame is kept only if the generator yielded
+        PyResult::Return(value) => Some(if state.generator_frames.contains_key(generator) { Some(value) } else { None }),
+        PyResult::Raised => None,
+    }
+}
+
+/// Run a frame from native code until it returns, like call_function_sync.
+pub fn run_frame_sync<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, frame: Frame) -> PyResult {
+    let barrier = Frame::new_barrier(frame.object.clone(), state.primitive_objects.none.clone());
+    call_stack.push(barrier);
+    let depth = call_stack.len();
+    call_stack.push(frame);
+    let res = run_code(state, call_stack, depth);
+    if let PyResult::Return(_) = res {
+        call_stack.pop().unwrap(); // Remove the barrier
+    }
+    res
 }
 
-// Main interpreter loop
+// Main interpreter loop. Returns when the frame above `depth` returns.
 // See https://docs.python.org/3/library/dis.html for a description of instructions
-fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) -> PyResult {
+fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, depth: usize) -> PyResult {
     loop {
+        if call_stack.len() <= depth {
+            // An exception was unwound below the frame we were running
+            return PyResult::Raised
+        }
         let instruction = {
             let frame = call_stack.last_mut().unwrap();
             let instruction = py_unwrap!(state, frame.instructions.get(frame.program_counter), ProcessorError::InvalidProgramCounter);
@@ -262,41 +1627,86 @@ fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) ->
                 frame.var_stack.push(val);
             }
             Instruction::Nop => (),
+            Instruction::BinaryModulo | Instruction::BinarySubtract | Instruction::BinaryAnd | Instruction::BinaryXor | Instruction::BinaryOr => {
+                let (lhs, rhs) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let rhs = pop_stack!(state, frame.var_stack);
+                    let lhs = pop_stack!(state, frame.var_stack);
+                    (lhs, rhs)
+                };
+                let (method, reflected, symbol) = match instruction {
+                    Instruction::BinaryModulo => ("__mod__", "__rmod__", "%"),
+                    Instruction::BinarySubtract => ("__sub__", "__rsub__", "-"),
+                    Instruction::BinaryAnd => ("__and__", "__rand__", "&"),
+                    Instruction::BinaryXor => ("__xor__", "__rxor__", "^"),
+                    _ => ("__or__", "__ror__", "|"),
+                };
+                binary_operator(state, call_stack, lhs, rhs, method, reflected, symbol)
+            }
+            Instruction::InplaceSubtract | Instruction::InplaceAnd | Instruction::InplaceXor | Instruction::InplaceOr => {
+                let (lhs, rhs) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let rhs = pop_stack!(state, frame.var_stack);
+                    let lhs = pop_stack!(state, frame.var_stack);
+                    (lhs, rhs)
+                };
+                let (inplace_method, method, reflected, symbol) = match instruction {
+                    Instruction::InplaceSubtract => ("__isub__", "__sub__", "__rsub__", "-="),
+                    Instruction::InplaceAnd => ("__iand__", "__and__", "__rand__", "&="),
+                    Instruction::InplaceXor => ("__ixor__", "__xor__", "__rxor__", "^="),
+                    _ => ("__ior__", "__or__", "__ror__", "|="),
+                };
+                // Immutable objects have no in-place method, and are replaced by the result of the operator.
+                if let Some(inplace_method) = lookup_special_method(state, &lhs, inplace_method) {
+                    match call_function_sync(state, call_stack, &inplace_method, vec![rhs.clone()], vec![]) {
+                        PyResult::Return(ref res) if res.is(&state.primitive_objects.not_implemented) => (),
+                        PyResult::Return(res) => { return_value(call_stack, res); continue },
+                        PyResult::Raised => continue,
+                    }
+                }
+                binary_operator(state, call_stack, lhs, rhs, method, reflected, symbol)
+            }
             Instruction::BinarySubscr => {
                 let (container, index) = {
                     let frame = call_stack.last_mut().unwrap();
-                    let index_ref = pop_stack!(state, frame.var_stack);
-                    let index = state.store.deref(&index_ref).content.clone();
-                    let container_ref = pop_stack!(state, frame.var_stack);
-                    let container = state.store.deref(&container_ref).content.clone();
+                    let index = pop_stack!(state, frame.var_stack);
+                    let container = pop_stack!(state, frame.var_stack);
                     (container, index)
                 };
-                let typeerror = state.primitive_objects.typeerror.clone();
-                match (container, index) {
-                    (ObjectContent::Tuple(v), ObjectContent::Int(i)) | (ObjectContent::List(v), ObjectContent::Int(i)) => {
-                        match v.get(i as usize) { // TODO: overflow check
-                            None => {
-                                let exc = state.primitive_objects.nameerror.clone();
-                                raise(state, call_stack, exc, format!("Index out of range"))
-                            },
-                            Some(obj_ref) => {
-                                let frame = call_stack.last_mut().unwrap();
-                                frame.var_stack.push(obj_ref.clone())
-                            },
+                getitem(state, call_stack, container, index)
+            }
+            Instruction::StoreSubscr => {
+                let (container, index, value) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let index = pop_stack!(state, frame.var_stack);
+                    let container = pop_stack!(state, frame.var_stack);
+                    let value = pop_stack!(state, frame.var_stack);
+                    (container, index, value)
+                };
+                setitem(state, call_stack, container, index, value)
+            }
+            Instruction::DeleteSubscr => {
+                let (container, index) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let index = pop_stack!(state, frame.var_stack);
+                    let container = pop_stack!(state, frame.var_stack);
+                    (container, index)
+                };
+                match lookup_special_method(state, &container, "__delitem__") {
+                    Some(method) => {
+                        match call_function_sync(state, call_stack, &method, vec![index], vec![]) {
+                            PyResult::Return(_) | PyResult::Raised => (),
                         }
                     },
-                    (ObjectContent::Tuple(_), index) =>
-                        raise(state, call_stack, typeerror, format!("tuple indices must be int, not {:?}", index)),
-                    (ObjectContent::List(_), index) =>
-                        raise(state, call_stack, typeerror, format!("list indices must be int, not {:?}", index)),
-                    (container, _index) =>
-                        raise(state, call_stack, typeerror, format!("{:?} object is not subscriptable", container)),
+                    None => native_setitem(state, call_stack, container, index, None),
                 }
             }
             Instruction::GetIter => {
-                let frame = call_stack.last_mut().unwrap();
-                let obj_ref = pop_stack!(state, frame.var_stack);
-                frame.var_stack.push(obj_ref.iter(state));
+                let obj_ref = {
+                    let frame = call_stack.last_mut().unwrap();
+                    pop_stack!(state, frame.var_stack)
+                };
+                primitives::get_iter(state, call_stack, obj_ref)
             }
             Instruction::LoadBuildClass => {
                 let frame = call_stack.last_mut().unwrap();
@@ -304,17 +1714,28 @@ fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) ->
                 frame.var_stack.push(state.store.allocate(obj));
             }
             Instruction::ReturnValue => {
-                if call_stack.len() == 1 {
-                    let mut frame = call_stack.pop().unwrap();
-                    let result = pop_stack!(state, frame.var_stack);
+                let mut frame = call_stack.pop().unwrap();
+                let result = pop_stack!(state, frame.var_stack);
+                if call_stack.len() == depth {
                     return PyResult::Return(result);
                 }
                 else {
-                    let mut frame = call_stack.pop().unwrap();
-                    let result = pop_stack!(state, frame.var_stack);
                     return_value(call_stack, result)
                 }
             }
+            Instruction::YieldValue => {
+                // Suspend the frame until the generator is resumed
+                let mut frame = call_stack.pop().unwrap();
+                let value = pop_stack!(state, frame.var_stack);
+                let generator = py_unwrap!(state, frame.generator.clone(), ProcessorError::YieldOutsideGenerator);
+                state.generator_frames.insert(generator, frame);
+                if call_stack.len() == depth {
+                    return PyResult::Return(value);
+                }
+                else {
+                    return_value(call_stack, value)
+                }
+            }
             Instruction::PopBlock => {
                 let frame = call_stack.last_mut().unwrap();
                 pop_stack!(state, frame.block_stack);
@@ -349,57 +1770,162 @@ fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) ->
             }
             Instruction::PopExcept => {
                 let frame = call_stack.last_mut().unwrap();
-                let mut three_last = frame.var_stack.pop_all_and_get_n_last(3).unwrap(); // TODO: check
-                let _exc_type = three_last.pop();
-                let _exc_value = three_last.pop();
-                let _exc_traceback = three_last.pop();
-                // TODO: do something with exc_*
-                pop_stack!(state, frame.block_stack);
+                match pop_stack!(state, frame.block_stack) {
+                    // Drop the exception saved below the except clauses
+                    // TODO: restore it as the current exception
+                    Block::ExceptHandler(stack_size) => frame.var_stack.truncate(stack_size),
+                    _ => panic!("PopExcept outside an except clause"),
+                }
             },
             Instruction::StoreName(i) => {
+                let (name, obj_ref, namespace) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let name = py_unwrap!(state, frame.code.names.get(i), ProcessorError::InvalidNameIndex).clone();
+                    let obj_ref = pop_stack!(state, frame.var_stack);
+                    match frame.namespace {
+                        Some(ref namespace) => (name, obj_ref, namespace.clone()),
+                        None => {
+                            frame.locals.borrow_mut().insert(name, obj_ref);
+                            continue
+                        },
+                    }
+                };
+                let name = state.store.allocate(state.primitive_objects.new_string(name));
+                setitem(state, call_stack, namespace, name, obj_ref)
+            }
+            Instruction::UnpackSequence(count) => {
+                let sequence = {
+                    let frame = call_stack.last_mut().unwrap();
+                    pop_stack!(state, frame.var_stack)
+                };
+                let items = match primitives::container_items(state, call_stack, &sequence) {
+                    Some(items) => items,
+                    None => continue, // Exception already raised
+                };
+                if items.len() != count {
+                    let exc = state.primitive_objects.valueerror.clone();
+                    let message = if items.len() < count {
+                        format!("not enough values to unpack (expected {}, got {})", count, items.len())
+                    }
+                    else {
+                        format!("too many values to unpack (expected {})", count)
+                    };
+                    raise(state, call_stack, exc, message);
+                    continue
+                }
+                // The first item ends on top of the stack
                 let frame = call_stack.last_mut().unwrap();
-                let name = py_unwrap!(state, frame.code.names.get(i), ProcessorError::InvalidNameIndex).clone();
-                let obj_ref = pop_stack!(state, frame.var_stack);
-                frame.locals.borrow_mut().insert(name, obj_ref);
+                for item in items.into_iter().rev() {
+                    frame.var_stack.push(item);
+                }
             }
             Instruction::ForIter(i) => {
                 let iterator = {
                     let frame = call_stack.last_mut().unwrap();
-                    frame.block_stack.push(Block::ExceptPopGoto(state.primitive_objects.stopiteration.clone(), 1, frame.program_counter+i/WORD_SIZE));
                     let iterator = top_stack!(state, frame.var_stack);
                     iterator.clone()
                 };
-                let iter_func = state.store.allocate(Object::new_instance(None, state.primitive_objects.function_type.clone(), ObjectContent::PrimitiveFunction("iter".to_string())));
-                call_function(state, call_stack, &iter_func, vec![iterator], vec![]);
+                // Native iterators are advanced directly; only user-defined __next__
+                // methods need StopIteration to signal the end of the loop.
+                let next_value = match lookup_special_method(state, &iterator, "__next__") {
+                    Some(next) => {
+                        let stopiteration = state.primitive_objects.stopiteration.clone();
+                        match call_function_catching(state, call_stack, &next, vec![], vec![], &stopiteration) {
+                            None => Some(None),
+                            Some(PyResult::Return(value)) => Some(Some(value)),
+                            Some(PyResult::Raised) => None,
+                        }
+                    },
+                    None => primitives::native_iterator_next(state, call_stack, &iterator),
+                };
+                match next_value {
+                    None => (), // Exception already raised
+                    Some(Some(value)) => call_stack.last_mut().unwrap().var_stack.push(value),
+                    Some(None) => {
+                        let frame = call_stack.last_mut().unwrap();
+                        pop_stack!(state, frame.var_stack);
+                        frame.program_counter += i / WORD_SIZE;
+                    },
+                }
             }
             Instruction::StoreAttr(i) => {
+                let (name, owner, value) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let name = py_unwrap!(state, frame.code.names.get(i), ProcessorError::InvalidNameIndex).clone();
+                    let owner = pop_stack!(state, frame.var_stack);
+                    let value = pop_stack!(state, frame.var_stack);
+                    (name, owner, value)
+                };
+                store_attr(state, call_stack, owner, name, value);
+            }
+            Instruction::DeleteAttr(i) => {
+                let (name, owner) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let name = py_unwrap!(state, frame.code.names.get(i), ProcessorError::InvalidNameIndex).clone();
+                    let owner = pop_stack!(state, frame.var_stack);
+                    (name, owner)
+                };
+                delete_attr(state, call_stack, owner, name);
+            }
+            Instruction::StoreGlobal(i) => {
                 let frame = call_stack.last_mut().unwrap();
                 let name = py_unwrap!(state, frame.code.names.get(i), ProcessorError::InvalidNameIndex).clone();
-                let owner = pop_stack!(state, frame.var_stack);
                 let value = pop_stack!(state, frame.var_stack);
-                owner.setattr(&mut state.store, name, value);
+                frame_globals(state, frame).borrow_mut().insert(name, value);
             }
-            Instruction::StoreGlobal(i) => {
-                let frame = call_stack.last_mut().unwrap();
-                let name = py_unwrap!(state, frame.code.varnames.get(i), ProcessorError::InvalidVarnameIndex).clone();
-                let mut globals = state.modules.get(&frame.object.module(&state.store)).unwrap().borrow_mut();
-                globals.insert(name, pop_stack!(state, frame.var_stack));
+            Instruction::DeleteName(i) | Instruction::DeleteGlobal(i) => {
+                let (name, class_namespace, res) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let name = py_unwrap!(state, frame.code.names.get(i), ProcessorError::InvalidNameIndex).clone();
+                    match (instruction, &frame.namespace) {
+                        (Instruction::DeleteName(_), &Some(ref namespace)) => (name, Some(namespace.clone()), None),
+                        (Instruction::DeleteName(_), &None) => {
+                            let res = frame.locals.borrow_mut().remove(&name);
+                            (name, None, res)
+                        },
+                        _ => {
+                            let res = frame_globals(state, frame).borrow_mut().remove(&name);
+                            (name, None, res)
+                        },
+                    }
+                };
+                let res = match class_namespace {
+                    Some(namespace) => match namespace_delete(state, call_stack, &namespace, &name) {
+                        Some(res) => res,
+                        None => continue, // Exception raised
+                    },
+                    None => res,
+                };
+                if res.is_none() {
+                    let exc = state.primitive_objects.nameerror.clone();
+                    raise(state, call_stack, exc, format!("name '{}' is not defined", name))
+                }
             }
             Instruction::LoadConst(i) => {
                 let frame = call_stack.last_mut().unwrap();
                 frame.var_stack.push(py_unwrap!(state, frame.code.consts.get(i), ProcessorError::InvalidConstIndex).clone())
             }
-            Instruction::LoadName(i) | Instruction::LoadGlobal(i) => { // TODO: LoadGlobal should look only in globals
-                let (name, res) = {
+            Instruction::LoadName(i) | Instruction::LoadGlobal(i) => {
+                let (name, class_namespace, use_locals) = {
                     let frame = call_stack.last_mut().unwrap();
-                    let name = py_unwrap!(state, frame.code.names.get(i), ProcessorError::InvalidNameIndex);
-                    let res = load_name(state, &frame, name);
-                    (name.clone(), res)
+                    let name = py_unwrap!(state, frame.code.names.get(i), ProcessorError::InvalidNameIndex).clone();
+                    match instruction {
+                        Instruction::LoadName(_) => (name, frame.namespace.clone(), true),
+                        _ => (name, None, false),
+                    }
+                };
+                let res = match class_namespace {
+                    Some(namespace) => match namespace_get(state, call_stack, &namespace, &name) {
+                        Some(Some(obj_ref)) => Some(obj_ref),
+                        Some(None) => load_name(state, call_stack.last().unwrap(), &name, false),
+                        None => continue, // Exception raised
+                    },
+                    None => load_name(state, call_stack.last().unwrap(), &name, use_locals),
                 };
                 match res {
                     None => {
                         let exc = state.primitive_objects.nameerror.clone();
-                        raise(state, call_stack, exc, format!("Unknown variable {}", name))
+                        raise(state, call_stack, exc, format!("name '{}' is not defined", name))
                     },
                     Some(obj_ref) => {
                         let frame = call_stack.last_mut().unwrap();
@@ -413,25 +1939,31 @@ fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) ->
                 let tuple = state.primitive_objects.new_tuple(content);
                 frame.var_stack.push(state.store.allocate(tuple));
             }
+            Instruction::BuildList(size) => {
+                let frame = call_stack.last_mut().unwrap();
+                let content = py_unwrap!(state, frame.var_stack.pop_many(size), ProcessorError::StackTooSmall);
+                let list = state.primitive_objects.new_list(content);
+                frame.var_stack.push(state.store.allocate(list));
+            }
+            Instruction::BuildSet(size) => {
+                let items = {
+                    let frame = call_stack.last_mut().unwrap();
+                    py_unwrap!(state, frame.var_stack.pop_many(size), ProcessorError::StackTooSmall)
+                };
+                if let Some(content) = primitives::sets::set_from_items(state, call_stack, items) {
+                    let set = state.primitive_objects.new_set(content);
+                    let frame = call_stack.last_mut().unwrap();
+                    frame.var_stack.push(state.store.allocate(set))
+                }
+            }
             Instruction::LoadAttr(i) => {
-                let (name, obj) = {
+                let (name, obj_ref) = {
                     let frame = call_stack.last_mut().unwrap();
                     let name = py_unwrap!(state, frame.code.names.get(i), ProcessorError::InvalidNameIndex).clone();
                     let obj_ref = py_unwrap!(state, frame.var_stack.pop(), ProcessorError::StackTooSmall);
-                    let obj = state.store.deref(&obj_ref).clone();
-                    (name, obj)
+                    (name, obj_ref)
                 };
-                let res = load_attr(state, &obj, &name);
-                match res {
-                    None => {
-                        let exc = state.primitive_objects.nameerror.clone();
-                        raise(state, call_stack, exc, format!("Unknown attribute {}", name))
-                    },
-                    Some(obj_ref) => {
-                        let frame = call_stack.last_mut().unwrap();
-                        frame.var_stack.push(obj_ref)
-                    }
-                }
+                load_attr(state, call_stack, &obj_ref, &name);
             },
             Instruction::SetupLoop(i) => {
                 let frame = call_stack.last_mut().unwrap();
@@ -439,32 +1971,121 @@ fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) ->
             }
             Instruction::SetupExcept(i) => {
                 let frame = call_stack.last_mut().unwrap();
-                frame.block_stack.push(Block::TryExcept(frame.program_counter, frame.program_counter+i/WORD_SIZE))
+                let stack_size = frame.var_stack.len();
+                frame.block_stack.push(Block::TryExcept(frame.program_counter, frame.program_counter+i/WORD_SIZE, stack_size))
             }
             Instruction::CompareOp(CmpOperator::Eq) => {
-                let frame = call_stack.last_mut().unwrap();
-                // TODO: enrich this (support __eq__)
-                let obj1 = state.store.deref(&pop_stack!(state, frame.var_stack));
-                let obj2 = state.store.deref(&pop_stack!(state, frame.var_stack));
-                if obj1.name == obj2.name && obj1.content == obj2.content {
-                    frame.var_stack.push(state.primitive_objects.true_obj.clone())
+                let (first, second) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let second = pop_stack!(state, frame.var_stack);
+                    let first = pop_stack!(state, frame.var_stack);
+                    (first, second)
+                };
+                // A user-defined __eq__ returns whatever it wants, the other case is native.
+                match call_binary_method(state, call_stack, &first, &second, "__eq__", "__eq__") {
+                    None => (),
+                    Some(Some(res)) => return_value(call_stack, res),
+                    Some(None) => {
+                        if let Some(res) = native_equal(state, call_stack, &first, &second) {
+                            let res = if res { state.primitive_objects.true_obj.clone() } else { state.primitive_objects.false_obj.clone() };
+                            return_value(call_stack, res)
+                        }
+                    }
                 }
-                else {
-                    frame.var_stack.push(state.primitive_objects.false_obj.clone())
+            }
+            Instruction::CompareOp(CmpOperator::Neq) => {
+                let (first, second) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let second = pop_stack!(state, frame.var_stack);
+                    let first = pop_stack!(state, frame.var_stack);
+                    (first, second)
                 };
+                // Without __ne__, the result is the opposite of ==.
+                match call_binary_method(state, call_stack, &first, &second, "__ne__", "__ne__") {
+                    None => (),
+                    Some(Some(res)) => return_value(call_stack, res),
+                    Some(None) => {
+                        if let Some(res) = objects_equal(state, call_stack, &first, &second) {
+                            let res = if res { state.primitive_objects.false_obj.clone() } else { state.primitive_objects.true_obj.clone() };
+                            return_value(call_stack, res)
+                        }
+                    }
+                }
             }
-            Instruction::CompareOp(CmpOperator::ExceptionMatch) => {
+            Instruction::CompareOp(CmpOperator::Lt) | Instruction::CompareOp(CmpOperator::Leq) |
+            Instruction::CompareOp(CmpOperator::Gt) | Instruction::CompareOp(CmpOperator::Geq) => {
+                let (first, second) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let second = pop_stack!(state, frame.var_stack);
+                    let first = pop_stack!(state, frame.var_stack);
+                    (first, second)
+                };
+                let (method, reflected, symbol) = match instruction {
+                    Instruction::CompareOp(CmpOperator::Lt) => ("__lt__", "__gt__", "<"),
+                    Instruction::CompareOp(CmpOperator::Leq) => ("__le__", "__ge__", "<="),
+                    Instruction::CompareOp(CmpOperator::Gt) => ("__gt__", "__lt__", ">"),
+                    _ => ("__ge__", "__le__", ">="),
+                };
+                // User-defined comparisons return whatever they want, the other case is native.
+                match call_binary_method(state, call_stack, &first, &second, method, reflected) {
+                    None => (),
+                    Some(Some(res)) => return_value(call_stack, res),
+                    Some(None) => {
+                        // Native types are totally ordered: a <= b is not b < a.
+                        let res = match instruction {
+                            Instruction::CompareOp(CmpOperator::Lt) => native_less_than(state, call_stack, &first, &second),
+                            Instruction::CompareOp(CmpOperator::Leq) => native_less_than(state, call_stack, &second, &first).map(|res| res.map(|res| !res)),
+                            Instruction::CompareOp(CmpOperator::Gt) => native_less_than(state, call_stack, &second, &first),
+                            _ => native_less_than(state, call_stack, &first, &second).map(|res| res.map(|res| !res)),
+                        };
+                        match res {
+                            None => (),
+                            Some(Some(res)) => {
+                                let res = if res { state.primitive_objects.true_obj.clone() } else { state.primitive_objects.false_obj.clone() };
+                                return_value(call_stack, res)
+                            },
+                            Some(None) => raise_unorderable(state, call_stack, &first, &second, symbol),
+                        }
+                    },
+                }
+            }
+            Instruction::CompareOp(CmpOperator::Is) | Instruction::CompareOp(CmpOperator::IsNot) => {
                 let frame = call_stack.last_mut().unwrap();
-                // TODO: add support for tuples
-                let pattern_ref = pop_stack!(state, frame.var_stack);
-                let exc_ref = pop_stack!(state, frame.var_stack);
-                let val = if primitives::native_isinstance(&state.store, &exc_ref, &pattern_ref) {
-                    state.primitive_objects.true_obj.clone()
+                let second = pop_stack!(state, frame.var_stack);
+                let first = pop_stack!(state, frame.var_stack);
+                let res = first.is(&second) == (instruction == Instruction::CompareOp(CmpOperator::Is));
+                frame.var_stack.push(if res { state.primitive_objects.true_obj.clone() } else { state.primitive_objects.false_obj.clone() })
+            }
+            Instruction::CompareOp(CmpOperator::In) | Instruction::CompareOp(CmpOperator::NotIn) => {
+                let (item, container) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let container = pop_stack!(state, frame.var_stack);
+                    let item = pop_stack!(state, frame.var_stack);
+                    (item, container)
+                };
+                if let Some(res) = contains(state, call_stack, &container, &item) {
+                    let res = res == (instruction == Instruction::CompareOp(CmpOperator::In));
+                    let res = if res { state.primitive_objects.true_obj.clone() } else { state.primitive_objects.false_obj.clone() };
+                    return_value(call_stack, res)
                 }
-                else {
-                    state.primitive_objects.false_obj.clone()
+            }
+            Instruction::CompareOp(CmpOperator::ExceptionMatch) => {
+                let (exc_ref, pattern_ref) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let pattern_ref = pop_stack!(state, frame.var_stack);
+                    let exc_ref = pop_stack!(state, frame.var_stack);
+                    (exc_ref, pattern_ref)
                 };
-                frame.var_stack.push(val)
+                match primitives::exception_match(&state.store, &state.primitive_objects.baseexception, &exc_ref, &pattern_ref) {
+                    Some(res) => {
+                        let res = if res { state.primitive_objects.true_obj.clone() } else { state.primitive_objects.false_obj.clone() };
+                        return_value(call_stack, res)
+                    },
+                    None => {
+                        let exc = state.primitive_objects.typeerror.clone();
+                        raise(state, call_stack, exc, "catching classes that do not inherit from BaseException is not allowed".to_string())
+                    },
+                }
             }
             Instruction::JumpAbsolute(target) => {
                 let frame = call_stack.last_mut().unwrap();
@@ -475,23 +2096,49 @@ fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) ->
                 frame.program_counter += delta / WORD_SIZE
             }
             Instruction::LoadFast(i) => {
-                let frame = call_stack.last_mut().unwrap();
-                let name = py_unwrap!(state, frame.code.varnames.get(i), ProcessorError::InvalidVarnameIndex).clone();
-                let obj_ref = py_unwrap!(state, frame.locals.borrow().get(&name), ProcessorError::InvalidName(name)).clone();
-                frame.var_stack.push(obj_ref)
+                let (name, res) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let name = py_unwrap!(state, frame.code.varnames.get(i), ProcessorError::InvalidVarnameIndex).clone();
+                    let res = frame.locals.borrow().get(&name).cloned();
+                    (name, res)
+                };
+                match res {
+                    Some(obj_ref) => call_stack.last_mut().unwrap().var_stack.push(obj_ref),
+                    None => raise_unbound_local(state, call_stack, &name),
+                }
             }
             Instruction::StoreFast(i) => {
                 let frame = call_stack.last_mut().unwrap();
                 let name = py_unwrap!(state, frame.code.varnames.get(i), ProcessorError::InvalidVarnameIndex).clone();
                 frame.locals.borrow_mut().insert(name, pop_stack!(state, frame.var_stack));
             }
-            Instruction::PopJumpIfFalse(target) => {
-                let frame = call_stack.last_mut().unwrap();
-                let obj = state.store.deref(&pop_stack!(state, frame.var_stack));
-                match obj.content {
-                    ObjectContent::True => (),
-                    ObjectContent::False => frame.program_counter = target / WORD_SIZE,
-                    _ => unimplemented!(),
+            Instruction::DeleteFast(i) => {
+                let (name, res) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let name = py_unwrap!(state, frame.code.varnames.get(i), ProcessorError::InvalidVarnameIndex).clone();
+                    let res = frame.locals.borrow_mut().remove(&name);
+                    (name, res)
+                };
+                if res.is_none() {
+                    raise_unbound_local(state, call_stack, &name)
+                }
+            }
+            Instruction::PopJumpIfFalse(target) | Instruction::PopJumpIfTrue(target) => {
+                let obj_ref = pop_stack!(state, call_stack.last_mut().unwrap().var_stack);
+                let value = match state.store.deref(&obj_ref).content {
+                    ObjectContent::True => true,
+                    ObjectContent::False => false,
+                    _ => match truth_value(state, call_stack, &obj_ref) {
+                        Some(value) => value,
+                        None => continue,
+                    },
+                };
+                let jump = match instruction {
+                    Instruction::PopJumpIfTrue(_) => value,
+                    _ => !value,
+                };
+                if jump {
+                    call_stack.last_mut().unwrap().program_counter = target / WORD_SIZE
                 }
             }
 
@@ -500,6 +2147,14 @@ fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) ->
             }
             Instruction::RaiseVarargs(1) => {
                 let exception = pop_stack!(state, call_stack.last_mut().unwrap().var_stack);
+                let exception = match state.store.deref(&exception).bases {
+                    // Raising a class raises an instance of it
+                    Some(_) => match call_function_sync(state, call_stack, &exception, vec![], vec![]) {
+                        PyResult::Return(exception) => exception,
+                        PyResult::Raised => continue,
+                    },
+                    None => exception,
+                };
                 let traceback = state.primitive_objects.none.clone();
                 let value = state.primitive_objects.none.clone();
                 unwind(state, call_stack, traceback, exception, value);
@@ -537,14 +2192,59 @@ fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) ->
                 }
                 call_function(state, call_stack, &func, args, kwargs)
             },
-            Instruction::MakeFunction { has_defaults: false, has_kwdefaults, has_annotations: false, has_closure: false } => {
-                // TODO: consume default arguments and annotations
+            Instruction::LoadClosure(i) => {
+                let frame = call_stack.last_mut().unwrap();
+                let cell = py_unwrap!(state, frame.cells.get(i), ProcessorError::InvalidCellIndex).clone();
+                frame.var_stack.push(cell)
+            }
+            Instruction::LoadDeref(i) | Instruction::LoadClassDeref(i) => {
+                let (name, cell, class_namespace) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let name = py_unwrap!(state, cell_name(&state.store, &frame.code, i), ProcessorError::InvalidCellIndex);
+                    let cell = py_unwrap!(state, frame.cells.get(i), ProcessorError::InvalidCellIndex).clone();
+                    let class_namespace = match instruction {
+                        Instruction::LoadClassDeref(_) => frame.namespace.clone(),
+                        _ => None,
+                    };
+                    (name, cell, class_namespace)
+                };
+                // Class bodies look in their namespace first.
+                let local = match class_namespace {
+                    Some(namespace) => match namespace_get(state, call_stack, &namespace, &name) {
+                        Some(local) => local,
+                        None => continue, // Exception raised
+                    },
+                    None => None,
+                };
+                let res = local.or_else(|| match state.store.deref(&cell).content {
+                    ObjectContent::Cell(ref value) => value.clone(),
+                    _ => panic!("Not a cell: {:?}", cell),
+                });
+                match res {
+                    None if i < call_stack.last().unwrap().code.cellvars.len() => raise_unbound_local(state, call_stack, &name),
+                    None => {
+                        let exc = state.primitive_objects.nameerror.clone();
+                        raise(state, call_stack, exc, format!("free variable '{}' referenced before assignment in enclosing scope", name))
+                    },
+                    Some(obj_ref) => {
+                        let frame = call_stack.last_mut().unwrap();
+                        frame.var_stack.push(obj_ref)
+                    }
+                }
+            }
+            Instruction::StoreDeref(i) => {
+                let frame = call_stack.last_mut().unwrap();
+                let cell = py_unwrap!(state, frame.cells.get(i), ProcessorError::InvalidCellIndex).clone();
+                let value = pop_stack!(state, frame.var_stack);
+                state.store.deref_mut(&cell).content = ObjectContent::Cell(Some(value));
+            }
+            Instruction::MakeFunction { has_defaults, has_kwdefaults, has_annotations, has_closure } => {
                 let obj = {
                     let frame = call_stack.last_mut().unwrap();
                     let obj = state.store.deref(&pop_stack!(state, frame.var_stack)).content.clone(); // TODO: clone only if necessary
                     obj
                 };
-                let func_name = match obj {
+                let qualname = match obj {
                     ObjectContent::String(ref s) => s.clone(),
                     name => {
                         let exc = state.primitive_objects.typeerror.clone();
@@ -553,25 +2253,102 @@ fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) ->
                     }
                 };
                 let frame = call_stack.last_mut().unwrap();
-                let code = pop_stack!(state, frame.var_stack);
-                let mut kwdefaults: HashMap<String, ObjectRef> = HashMap::new();
+                let code_ref = pop_stack!(state, frame.var_stack);
+                let code = match state.store.deref(&code_ref).content {
+                    ObjectContent::Code(ref code) => code.clone(),
+                    _ => panic!("bad type for function code"),
+                };
+                let closure = if has_closure {
+                    match state.store.deref(&pop_stack!(state, frame.var_stack)).content {
+                        ObjectContent::Tuple(ref cells) => cells.clone(),
+                        _ => panic!("bad type for closure"),
+                    }
+                }
+                else {
+                    Vec::new()
+                };
+                let annotations = if has_annotations {
+                    pop_stack!(state, frame.var_stack)
+                }
+                else {
+                    state.store.allocate(state.primitive_objects.new_dict(DictContent::new()))
+                };
+                // Default values of arguments are stored by argument name.
+                let mut defaults: HashMap<String, ObjectRef> = HashMap::new();
                 if has_kwdefaults {
                     let obj = state.store.deref(&pop_stack!(state, frame.var_stack)).content.clone(); // TODO: clone only if necessary
                     let raw_kwdefaults = match obj {
                         ObjectContent::Dict(ref d) => d,
                         _ => panic!("bad type for default kwd"),
                     };
-                    kwdefaults.reserve(raw_kwdefaults.len());
-                    for &(ref key, ref value) in raw_kwdefaults {
+                    defaults.reserve(raw_kwdefaults.len());
+                    for (key, value) in raw_kwdefaults.items() {
                         match state.store.deref(&key).content {
-                            ObjectContent::String(ref s) => { kwdefaults.insert(s.clone(), value.clone()); },
+                            ObjectContent::String(ref s) => { defaults.insert(s.clone(), value.clone()); },
                             _ => panic!("Defaults' keys must be strings."),
                         }
                     }
                 }
-                let func = state.primitive_objects.new_function(func_name, frame.object.module(&state.store), code, kwdefaults);
+                if has_defaults {
+                    let values = match state.store.deref(&pop_stack!(state, frame.var_stack)).content {
+                        ObjectContent::Tuple(ref values) => values.clone(),
+                        _ => panic!("bad type for defaults"),
+                    };
+                    // They are the values of the last positional arguments
+                    let first_arg = code.argcount.saturating_sub(values.len());
+                    for (name, value) in code.varnames[first_arg..code.argcount].iter().zip(values) {
+                        defaults.insert(name.clone(), value);
+                    }
+                }
+                // The docstring, if any, is the first constant (except for lambdas)
+                let doc = match code.consts.first() {
+                    Some(doc) if code.name != "<lambda>" => match state.store.deref(doc).content {
+                        ObjectContent::String(_) => doc.clone(),
+                        _ => state.primitive_objects.none.clone(),
+                    },
+                    _ => state.primitive_objects.none.clone(),
+                };
+                let func = state.primitive_objects.new_function(code.name.clone(), frame.object.module(&state.store), code_ref, defaults, closure);
+                {
+                    let mut attributes = func.attributes.as_ref().unwrap().borrow_mut();
+                    attributes.insert("__qualname__".to_string(), state.store.allocate(state.primitive_objects.new_string(qualname)));
+                    attributes.insert("__doc__".to_string(), doc);
+                    attributes.insert("__annotations__".to_string(), annotations);
+                }
                 frame.var_stack.push(state.store.allocate(func))
             },
+            Instruction::FormatValue { conversion, has_format_spec } => {
+                let (value, format_spec) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let format_spec = if has_format_spec { Some(pop_stack!(state, frame.var_stack)) } else { None };
+                    (pop_stack!(state, frame.var_stack), format_spec)
+                };
+                let value = match conversion {
+                    Some(conversion) => match primitives::formatting::convert(state, call_stack, &value, conversion) {
+                        Some(s) => state.store.allocate(state.primitive_objects.new_string(s)),
+                        None => continue, // Exception raised
+                    },
+                    None => value,
+                };
+                let format_spec = match format_spec {
+                    Some(format_spec) => format_spec,
+                    None => state.store.allocate(state.primitive_objects.new_string(String::new())),
+                };
+                let format = primitive_function(state, "format");
+                call_function(state, call_stack, &format, vec![value, format_spec], vec![])
+            }
+            Instruction::BuildString(size) => {
+                let frame = call_stack.last_mut().unwrap();
+                let parts = py_unwrap!(state, frame.var_stack.pop_many(size), ProcessorError::StackTooSmall);
+                let mut s = String::new();
+                for part in parts {
+                    match state.store.deref(&part).content {
+                        ObjectContent::String(ref part) => s.push_str(part),
+                        _ => panic!("BuildString with non-string {:?}", part),
+                    }
+                }
+                frame.var_stack.push(state.store.allocate(state.primitive_objects.new_string(s)))
+            }
             Instruction::BuildConstKeyMap(size) => {
                 let frame = call_stack.last_mut().unwrap();
                 let obj = state.store.deref(&pop_stack!(state, frame.var_stack)).content.clone(); // TODO: clone only if necessary
@@ -579,11 +2356,100 @@ fn run_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>) ->
                     ObjectContent::Tuple(ref v) => v.clone(),
                     _ => panic!("bad BuildConstKeyMap keys argument."),
                 };
-                let values: Vec<ObjectRef> = frame.var_stack.peek(size).unwrap().iter().map(|r| (*r).clone()).collect();
-                let dict = state.primitive_objects.new_dict(keys.into_iter().zip(values).collect());
-                frame.var_stack.push(state.store.allocate(dict))
+                let values = py_unwrap!(state, frame.var_stack.pop_many(size), ProcessorError::StackTooSmall);
+                if let Some(content) = primitives::dicts::dict_from_pairs(state, call_stack, keys.into_iter().zip(values).collect()) {
+                    let dict = state.primitive_objects.new_dict(content);
+                    let frame = call_stack.last_mut().unwrap();
+                    frame.var_stack.push(state.store.allocate(dict))
+                }
+            }
+            Instruction::BuildMap(size) => {
+                let pairs = {
+                    let frame = call_stack.last_mut().unwrap();
+                    py_unwrap!(state, frame.var_stack.pop_n_pairs(size), ProcessorError::StackTooSmall)
+                };
+                if let Some(content) = primitives::dicts::dict_from_pairs(state, call_stack, pairs) {
+                    let dict = state.primitive_objects.new_dict(content);
+                    let frame = call_stack.last_mut().unwrap();
+                    frame.var_stack.push(state.store.allocate(dict))
+                }
+            }
+            Instruction::BuildTupleUnpackWithCall(size) => {
+                let iterables = {
+                    let frame = call_stack.last_mut().unwrap();
+                    py_unwrap!(state, frame.var_stack.pop_many(size), ProcessorError::StackTooSmall)
+                };
+                if let Some(content) = unpack_call_args(state, call_stack, iterables) {
+                    let tuple = state.primitive_objects.new_tuple(content);
+                    let frame = call_stack.last_mut().unwrap();
+                    frame.var_stack.push(state.store.allocate(tuple))
+                }
+            }
+            Instruction::BuildMapUnpackWithCall(size) => {
+                let mappings = {
+                    let frame = call_stack.last_mut().unwrap();
+                    py_unwrap!(state, frame.var_stack.pop_many(size), ProcessorError::StackTooSmall)
+                };
+                if let Some(content) = unpack_call_kwargs(state, call_stack, mappings) {
+                    let dict = state.primitive_objects.new_dict(primitives::dicts::string_keyed_dict(state, content));
+                    let frame = call_stack.last_mut().unwrap();
+                    frame.var_stack.push(state.store.allocate(dict))
+                }
+            }
+            Instruction::CallFunctionEx(has_kwargs) => {
+                let (func, args, kwargs) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let kwargs = if has_kwargs { Some(pop_stack!(state, frame.var_stack)) } else { None };
+                    let args = pop_stack!(state, frame.var_stack);
+                    let func = pop_stack!(state, frame.var_stack);
+                    (func, args, kwargs)
+                };
+                let args = match unpack_call_args(state, call_stack, vec![args]) {
+                    Some(args) => args,
+                    None => continue,
+                };
+                let kwargs = match kwargs.map(|kwargs| unpack_call_kwargs(state, call_stack, vec![kwargs])) {
+                    None => vec![],
+                    Some(Some(kwargs)) => kwargs,
+                    Some(None) => continue,
+                };
+                call_function(state, call_stack, &func, args, kwargs)
+            }
+            // The comprehension opcodes add to the container at depth i once
+            // their operands are popped; the container stays on the stack.
+            Instruction::ListAppend(i) => {
+                let frame = call_stack.last_mut().unwrap();
+                let value = pop_stack!(state, frame.var_stack);
+                let list_ref = py_unwrap!(state, frame.var_stack.peek(i), ProcessorError::StackTooSmall)[0].clone();
+                let list = state.store.deref_mut(&list_ref);
+                match list.content {
+                    ObjectContent::List(ref mut items) => items.push(value),
+                    _ => panic!("ListAppend on a non-list object"),
+                }
+                list.bump_version();
+            }
+            Instruction::SetAdd(i) => {
+                let (set, value) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let value = pop_stack!(state, frame.var_stack);
+                    (py_unwrap!(state, frame.var_stack.peek(i), ProcessorError::StackTooSmall)[0].clone(), value)
+                };
+                primitives::sets::add_item(state, call_stack, &set, value);
+            }
+            Instruction::MapAdd(i) => {
+                let (dict, key, value) = {
+                    let frame = call_stack.last_mut().unwrap();
+                    let key = pop_stack!(state, frame.var_stack);
+                    let value = pop_stack!(state, frame.var_stack);
+                    (py_unwrap!(state, frame.var_stack.peek(i), ProcessorError::StackTooSmall)[0].clone(), key, value)
+                };
+                primitives::dicts::set_item(state, call_stack, &dict, key, value);
+            }
+            Instruction::Unsupported(opcode) => {
+                // Decoding succeeds so that only code which runs them fails.
+                let exc = state.primitive_objects.processorerror.clone();
+                raise(state, call_stack, exc, format!("Opcode not supported: {}", opcode))
             }
-            _ => panic!(format!("todo: instruction {:?}", instruction)),
         }
     };
 }
@@ -597,10 +2463,17 @@ fn call_module_code<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Fr
         ObjectContent::Code(ref code) => code.clone(),
         ref o => return state.raise_processor_error(ProcessorError::NotACodeObject(format!("file code {:?}", o))),
     };
-    let module_obj = state.store.allocate(state.primitive_objects.new_module(module_name.clone(), code_ref));
-    state.modules.insert(module_name.clone(), Rc::new(RefCell::new(HashMap::new())));
-    call_stack.push(Frame::new(module_obj, *code, state.modules.get(&module_name).unwrap().clone()));
-    let res = run_code(state, call_stack);
+    let namespace = state.store.deref(&module_ref).attributes.clone().unwrap(); // Modules always have a __dict__
+    {
+        let mut namespace = namespace.borrow_mut();
+        namespace.insert("__name__".to_string(), state.store.allocate(state.primitive_objects.new_string(module_name.clone())));
+        namespace.insert("__file__".to_string(), state.store.allocate(state.primitive_objects.new_string(code.filename.clone())));
+        namespace.insert("__doc__".to_string(), state.primitive_objects.none.clone()); // Set by the code if it has a docstring
+        namespace.insert("__spec__".to_string(), state.primitive_objects.none.clone()); // TODO: module specs
+    }
+    state.modules.insert(module_name, module_ref.clone());
+    call_stack.push(Frame::new(module_ref, *code, namespace));
+    let res = run_code(state, call_stack, 0);
     res // Do not raise exceptions before the pop()
 }
 
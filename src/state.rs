@@ -1,6 +1,4 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
-use std::rc::Rc;
 use super::sandbox::EnvProxy;
 use super::objects::{ObjectStore, ObjectRef, ObjectContent, PrimitiveObjects, Object};
 use super::processor::ProcessorError;
@@ -15,14 +13,15 @@ pub enum PyResult {
     Raised, // Should only be returned after unwinding the call stack
 }
 
-pub type PyFunction<EP> = fn(&mut State<EP>, &mut Vec<Frame>, Vec<ObjectRef>);
+pub type PyFunction<EP> = fn(&mut State<EP>, &mut Vec<Frame>, Vec<ObjectRef>, Vec<(ObjectRef, ObjectRef)>); // args, kwargs
 
 pub struct State<EP: EnvProxy> {
     pub envproxy: EP,
     pub store: ObjectStore,
     pub primitive_functions: HashMap<String, PyFunction<EP>>,
     pub primitive_objects: PrimitiveObjects,
-    pub modules: HashMap<String, Rc<RefCell<HashMap<String, ObjectRef>>>>,
+    pub modules: HashMap<String, ObjectRef>, // module objects, by name
+    pub generator_frames: HashMap<ObjectRef, Frame>, // frames of suspended generators
 }
 
 impl<EP: EnvProxy> State<EP> {
@@ -37,16 +36,23 @@ pub fn unwind<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>,
     let exc_type = exception.clone(); // Looks like that's how CPython does things…
     'outer: loop {
         match call_stack.pop() {
-            None => panic!("Exception {:?} reached bottom of call stack.", exception.repr(&state.store)),
+            None => {
+                let args = state.store.deref(&exception).attributes.as_ref().and_then(|attributes| attributes.borrow().get("args").cloned());
+                let args = args.map_or(String::new(), |args| args.repr(&state.store));
+                panic!("Exception {} {} reached bottom of call stack.", exception.repr(&state.store), args)
+            },
             Some(mut frame) => {
                 // Unwind block stack
                 while let Some(block) = frame.block_stack.pop() {
                     match block {
                         Block::Loop(_begin, _end) => { // Non-try…except block, exit it.
                         }
-                        Block::TryExcept(begin, end) => {
-                            // Found a try…except block
-                            frame.block_stack.push(Block::TryExcept(begin, end)); // Push it back, it will be poped by PopExcept.
+                        Block::ExceptHandler(_) => { // Exception raised in an except clause, exit it.
+                        }
+                        Block::TryExcept(_begin, end, stack_size) => {
+                            // Found a try…except block, drop what the try clause left on the stack
+                            frame.var_stack.truncate(stack_size);
+                            frame.block_stack.push(Block::ExceptHandler(stack_size)); // Will be poped by PopExcept.
                             frame.program_counter = end;
                             frame.var_stack.push(traceback.clone());
                             frame.var_stack.push(value.clone());
@@ -75,8 +81,10 @@ pub fn unwind<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>,
 }
 
 pub fn raise<EP: EnvProxy>(state: &mut State<EP>, call_stack: &mut Vec<Frame>, exc_class: ObjectRef, msg: String) {
-    let exc = Object::new_instance(None, exc_class, ObjectContent::String(msg));
-    let exc = state.store.allocate(exc);
+    let msg = state.store.allocate(state.primitive_objects.new_string(msg));
+    let args = state.store.allocate(state.primitive_objects.new_tuple(vec![msg]));
+    let exc = state.store.allocate(Object::new_instance(None, exc_class, ObjectContent::OtherObject));
+    exc.setattr(&mut state.store, "args".to_string(), args);
     // TODO: actual traceback
     let traceback = state.primitive_objects.none.clone();
     let value = state.primitive_objects.none.clone();
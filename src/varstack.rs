@@ -7,9 +7,10 @@ pub trait VarStack : Debug {
     fn pop(&mut self) -> Option<Self::Item>;
     fn pop_many(&mut self, count: usize) -> Option<Vec<Self::Item>>;
     fn push(&mut self, value: Self::Item);
-    fn pop_all_and_get_n_last(&mut self, nb: usize) -> Option<Vec<Self::Item>>;
     fn pop_n_pairs(&mut self, nb: usize) -> Option<Vec<(Self::Item, Self::Item)>>;
     fn peek(&self, nb: usize) -> Option<Vec<&Self::Item>>;
+    fn len(&self) -> usize;
+    fn truncate(&mut self, len: usize);
 }
 
 #[derive(Debug)]
@@ -54,16 +55,6 @@ impl<Item: Clone> VarStack for VectorVarStack<Item> where Item: Debug {
         self.vector.push(value)
     }
 
-    fn pop_all_and_get_n_last(&mut self, nb: usize) -> Option<Vec<Self::Item>> {
-        if self.vector.len() < nb {
-            None
-        }
-        else {
-            self.vector.truncate(nb);
-            Some(self.vector.drain(..).collect())
-        }
-    }
-
     fn pop_n_pairs(&mut self, nb: usize) -> Option<Vec<(Self::Item, Self::Item)>> {
         self.pop_many(nb*2).map(|values| {
             let mut pairs = Vec::<(Self::Item, Self::Item)>::new();
@@ -85,4 +76,10 @@ impl<Item: Clone> VarStack for VectorVarStack<Item> where Item: Debug {
             Some(self.vector[(length-nb)..length].iter().collect())
         }
     }
+    fn len(&self) -> usize {
+        self.vector.len()
+    }
+    fn truncate(&mut self, len: usize) {
+        self.vector.truncate(len)
+    }
 }